@@ -0,0 +1,2118 @@
+use aws_apis::{
+    load_credential_from_env, CredentInitialize, SesOps, SimpleMail, Simple_, TemplateMail,
+    Template_,
+};
+use aws_config::SdkConfig;
+use clap::{Args, Parser, Subcommand};
+use colored::Colorize;
+use dotenv::dotenv;
+use inquire::Confirm;
+use std::env::var;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::s3::checksum::ChecksumKind;
+use crate::s3::copy::{copy_objects, CopyOptions};
+use crate::s3::cors::{
+    delete_cors, get_cors, print_cors, prompt_cors_rule, put_cors, read_cors_file, write_cors_file,
+};
+use crate::s3::creation::{create_bucket, read_spec_file, BucketSpec, OWNERSHIP_SETTINGS};
+use crate::s3::deletion::{delete_selection, empty_and_delete_bucket, DeleteSelection};
+use crate::s3::download::{download_object, parse_range, ByteRange, DownloadOptions};
+use crate::s3::encryption::{
+    get_bucket_encryption, read_customer_key, set_bucket_encryption, Encryption,
+};
+use crate::s3::images::{
+    parse_width, scratch_dir, upload_image_variants, ImagePipeline, VariantFormat, Watermark,
+    WatermarkPosition,
+};
+use crate::s3::lifecycle::{
+    get_rules, merge_rules, preview_rules, print_rules, prompt_rule, read_rules_file,
+    review_and_put_rules, write_rules_file, RuleSpec,
+};
+use crate::s3::listing::{parse_size, print_listing, ListingOptions, ObjectFilter};
+use crate::s3::manifest::{prefix_manifest, read_manifest, verify_manifest, write_manifest};
+use crate::s3::metadata::{
+    head_object, parse_metadata_pair, parse_tag_pair, print_head, update_object, MetadataChanges,
+    ObjectMetadata,
+};
+use crate::s3::multipart::{
+    abort_uploads, list_incomplete_uploads, print_incomplete_uploads, saved_uploads, upload_file,
+    UploadSettings,
+};
+use crate::s3::parse_s3_uri;
+use crate::s3::pickers::bucket_names;
+use crate::s3::policy::{
+    apply_template, delete_policy, edit_policy, empty_policy, get_policy, get_public_access_block,
+    put_policy, put_public_access_block, validate_policy, PolicyTemplate, PublicAccessBlock,
+};
+use crate::s3::presign::{
+    deliver_post, deliver_url, parse_duration, presign_get, presign_post, presign_put,
+    PostConditions,
+};
+use crate::s3::sync::{sync, SyncDirection, SyncOptions, DEFAULT_JOBS};
+use crate::s3::thumbnails::{make_thumbnails, ThumbnailOptions};
+use crate::s3::usage::{print_usage, usage_report, write_report, PriceTable};
+use crate::s3::versioning::{
+    delete_key_versions, key_versions, print_versions, restore_version, set_versioning,
+    versioning_status,
+};
+use crate::s3::website::{
+    disable_website, print_website, publish_website, WebsiteSettings, DEFAULT_ERROR, DEFAULT_INDEX,
+};
+use crate::ses::configuration_sets::{
+    add_event_destination, create_configuration_set, delete_configuration_set,
+    delete_event_destination, list_configuration_sets, parse_event_types, print_event_destinations,
+    EventDestinationKind,
+};
+use crate::ses::dns_check::{check_domain, print_report, ExpectedRecords, RecordSource};
+use crate::ses::sending::{parse_tag, send_bulk_simple, send_bulk_templated, SendOptions};
+use crate::ses::template_lint::{inline_css, lint_template, print_issues};
+use crate::ses::template_repo::{backups, pull, push, rollback, template_dir};
+use crate::ses::template_variants::{check_family, print_families, read_and_resolve_template};
+
+/// Running the binary without any arguments starts the interactive menu
+#[derive(Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// AWS Simple Email Service(SES) operations
+    #[command(subcommand)]
+    Ses(SesCommands),
+    /// S3 bucket operations
+    #[command(subcommand)]
+    S3(S3Commands),
+}
+
+#[derive(Subcommand)]
+enum S3Commands {
+    /// Upload a file, in parallel parts when it is larger than the part size
+    Upload(UploadArgs),
+    /// List, resume and abort multipart uploads that didn't complete
+    #[command(subcommand)]
+    Uploads(UploadsCommands),
+    /// Make a local folder and an S3 prefix match, in either direction
+    Sync(SyncArgs),
+    /// Copy objects on the server side, also between buckets in different regions
+    Cp(CopyArgs),
+    /// Copy objects on the server side, then delete the sources
+    Mv(CopyArgs),
+    /// Stream an object to disk, resuming an unfinished download of the same object
+    Download(DownloadArgs),
+    /// List the folders and objects under a prefix with their size, date, storage class and ETag
+    Ls(LsArgs),
+    /// Delete objects by key, by prefix or by glob after showing what matches
+    Rm(RmArgs),
+    /// Create a bucket with its region, ownership, versioning, encryption, public access and tags
+    Mb(MakeBucketArgs),
+    /// Empty a bucket, including old versions, delete markers and incomplete uploads, then delete it
+    Rb {
+        bucket: String,
+        /// Skip typing the bucket name to confirm
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Enable, suspend or show the versioning of a bucket
+    #[command(subcommand)]
+    Versioning(VersioningCommands),
+    /// List, restore and permanently delete object versions; 'download --version-id' fetches one
+    #[command(subcommand)]
+    Versions(VersionsCommands),
+    /// View and change the expiration, transition and abort rules of a bucket
+    #[command(subcommand)]
+    Lifecycle(LifecycleCommands),
+    /// View, edit, validate and generate bucket policies
+    #[command(subcommand)]
+    Policy(PolicyCommands),
+    /// View and change the Block Public Access settings of a bucket
+    #[command(subcommand)]
+    PublicAccess(PublicAccessCommands),
+    /// View and set the default encryption of a bucket
+    #[command(subcommand)]
+    Encryption(EncryptionCommands),
+    /// Create presigned GET and PUT URLs and POST forms that work without AWS credentials
+    #[command(subcommand)]
+    Presign(PresignCommands),
+    /// Show the metadata and tags of an object, changing them first when options are given
+    Head(HeadArgs),
+    /// Resize, convert and watermark an image, then upload every variant as name@800w.webp
+    Image(ImageArgs),
+    /// Upload thumbnails of every image under a prefix, with contact sheets showing them all
+    Thumbnails(ThumbnailArgs),
+    /// Host a static website from a bucket
+    #[command(subcommand)]
+    Website(WebsiteCommands),
+    /// View and change the CORS rules that let browsers call the bucket from other sites
+    #[command(subcommand)]
+    Cors(CorsCommands),
+    /// Total the objects and bytes of buckets by storage class and prefix, with the monthly cost
+    Usage(UsageArgs),
+    /// Record the sizes and checksums of objects in a file and check the bucket against it later
+    #[command(subcommand)]
+    Manifest(ManifestCommands),
+}
+
+#[derive(Subcommand)]
+enum ManifestCommands {
+    /// Write the size, checksum and ETag of every object under a prefix to a JSON file
+    Create {
+        /// s3://bucket/prefix
+        location: String,
+        file: PathBuf,
+        /// Number of objects read at the same time
+        #[arg(long, default_value_t = DEFAULT_JOBS)]
+        jobs: usize,
+    },
+    /// Check that every object in the manifest still has its size and checksum
+    Verify {
+        file: PathBuf,
+        #[arg(long, default_value_t = DEFAULT_JOBS)]
+        jobs: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum PresignCommands {
+    /// A URL that downloads the object
+    Get {
+        /// The object in the form s3://bucket/key
+        location: String,
+        #[command(flatten)]
+        output: PresignOutputArgs,
+    },
+    /// A URL that uploads to the key with an HTTP PUT
+    Put {
+        /// The key to upload to in the form s3://bucket/key
+        location: String,
+        /// The Content-Type the upload has to send
+        #[arg(long)]
+        content_type: Option<String>,
+        #[command(flatten)]
+        output: PresignOutputArgs,
+    },
+    /// A form that a browser can upload to; --output form.html writes a ready page
+    Post {
+        /// s3://bucket/key, or s3://bucket/prefix/ to keep the file name of the upload
+        location: String,
+        /// The accepted Content-Type; 'image/*' accepts every type starting with 'image/'
+        #[arg(long)]
+        content_type: Option<String>,
+        /// The smallest accepted file, e.g. 1KB
+        #[arg(long, value_parser = parse_size)]
+        min_size: Option<u64>,
+        /// The largest accepted file, e.g. 10MB
+        #[arg(long, value_parser = parse_size)]
+        max_size: Option<u64>,
+        #[command(flatten)]
+        output: PresignOutputArgs,
+    },
+}
+
+#[derive(Args)]
+struct PresignOutputArgs {
+    /// How long the URL stays valid, e.g. 15m, 12h or 2d; at most 7d
+    #[arg(long, default_value = "1h", value_parser = parse_duration)]
+    expires: Duration,
+    /// Also write the URL (or the POST form as JSON or HTML) to this file
+    #[arg(long)]
+    output: Option<String>,
+    /// Show the URL as a QR code, or save the code as a PNG when a path is given
+    #[arg(long, num_args = 0..=1, default_missing_value = "-")]
+    qr: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum EncryptionCommands {
+    Show {
+        bucket: String,
+    },
+    /// Encrypt new objects with SSE-S3, or with SSE-KMS when --kms-key-id is given
+    Set {
+        bucket: String,
+        #[arg(long, conflicts_with = "kms_key_id")]
+        sse_s3: bool,
+        /// The KMS key id or ARN; 'aws/s3' for the AWS managed key
+        #[arg(long)]
+        kms_key_id: Option<String>,
+        /// Use an S3 Bucket Key to cut the number of KMS requests
+        #[arg(long, requires = "kms_key_id")]
+        bucket_key: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum PolicyCommands {
+    /// Print the policy, or write it to a file
+    Show {
+        bucket: String,
+        #[arg(long)]
+        output: Option<String>,
+    },
+    /// Replace the policy with the one in a JSON file, after showing the differences
+    Put {
+        bucket: String,
+        file: String,
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Edit the policy in $VISUAL or $EDITOR
+    Edit { bucket: String },
+    /// Check a policy file without applying it
+    Validate { bucket: String, file: String },
+    Delete {
+        bucket: String,
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Add a statement for a common case to the policy
+    Template {
+        bucket: String,
+        #[command(subcommand)]
+        template: PolicyTemplateCommands,
+        #[arg(long, global = true)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum PolicyTemplateCommands {
+    /// Let anyone read the objects under a prefix
+    PublicRead {
+        #[arg(long, default_value = "")]
+        prefix: String,
+    },
+    /// Let a CloudFront distribution read the bucket through Origin Access Control
+    CloudfrontOac {
+        /// e.g. arn:aws:cloudfront::111122223333:distribution/EDFDVBD6EXAMPLE
+        #[arg(long)]
+        distribution_arn: String,
+    },
+    /// Let another AWS account list the bucket and read the objects under a prefix
+    CrossAccountRead {
+        #[arg(long)]
+        account_id: String,
+        #[arg(long, default_value = "")]
+        prefix: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum PublicAccessCommands {
+    Show {
+        bucket: String,
+    },
+    /// Turn on all four settings
+    BlockAll {
+        bucket: String,
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Turn off all four settings
+    UnblockAll {
+        bucket: String,
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Change individual settings; the ones not given keep their current value
+    Set {
+        bucket: String,
+        #[arg(long)]
+        block_public_acls: Option<bool>,
+        #[arg(long)]
+        ignore_public_acls: Option<bool>,
+        #[arg(long)]
+        block_public_policy: Option<bool>,
+        #[arg(long)]
+        restrict_public_buckets: Option<bool>,
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum WebsiteCommands {
+    /// Show the index and error documents and the endpoint
+    Show { bucket: String },
+    /// Turn on hosting, upload a build folder to the bucket root and make it publicly readable
+    Publish {
+        bucket: String,
+        /// The folder with the built site, e.g. dist or public
+        dir: PathBuf,
+        #[arg(long, default_value = DEFAULT_INDEX)]
+        index: String,
+        /// Leave it empty for the S3 error page; single page apps often use index.html
+        #[arg(long, default_value = DEFAULT_ERROR)]
+        error: String,
+        /// Delete objects that aren't in the build folder
+        #[arg(long)]
+        delete: bool,
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Turn off hosting and remove the public-read statement; the objects are kept
+    Disable {
+        bucket: String,
+        /// Leave the bucket policy as it is
+        #[arg(long)]
+        keep_policy: bool,
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum CorsCommands {
+    /// Show the rules, or write them to a YAML or JSON file that 'put' reads back
+    Show {
+        bucket: String,
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Replace all the rules with those in a YAML or JSON file
+    Put {
+        bucket: String,
+        file: PathBuf,
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Build a rule with prompts and add it to the bucket's rules
+    Add { bucket: String },
+    /// Check a CORS file without applying it
+    Validate { file: PathBuf },
+    Delete {
+        bucket: String,
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum LifecycleCommands {
+    /// Show the rules, or write them to a YAML or JSON file that 'apply' reads back
+    Show {
+        bucket: String,
+        #[arg(long)]
+        output: Option<String>,
+    },
+    /// Replace all the rules with those in a YAML or JSON file
+    Apply {
+        bucket: String,
+        file: String,
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Add rules from a file, or one built from prompts when no file is given; rules with the same id are replaced
+    Add {
+        bucket: String,
+        file: Option<String>,
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Change a rule through prompts prefilled with its current settings
+    Edit { bucket: String, id: String },
+    /// Remove rules by id
+    Delete {
+        bucket: String,
+        #[arg(required = true)]
+        ids: Vec<String>,
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Count the objects the bucket's rules, or the rules in a file, would affect
+    Preview {
+        bucket: String,
+        file: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum VersioningCommands {
+    Enable {
+        bucket: String,
+    },
+    /// Keep the existing versions but stop creating new ones
+    Suspend {
+        bucket: String,
+    },
+    Status {
+        bucket: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum VersionsCommands {
+    /// Show the versions and delete markers of s3://bucket/key, or of every key under s3://bucket/prefix/
+    List { location: String },
+    /// Make an older version of s3://bucket/key the current one
+    Restore {
+        location: String,
+        version_id: String,
+    },
+    /// Permanently delete versions or delete markers of s3://bucket/key
+    Delete {
+        location: String,
+        #[arg(required = true)]
+        version_ids: Vec<String>,
+        /// Delete without asking for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+#[derive(Args)]
+struct RmArgs {
+    bucket: String,
+    /// The keys to delete
+    #[arg(conflicts_with_all = ["prefix", "glob", "from_file"])]
+    keys: Vec<String>,
+    /// Delete every object under this prefix
+    #[arg(long, conflicts_with_all = ["glob", "from_file"])]
+    prefix: Option<String>,
+    /// Delete the keys matching this glob, e.g. 'logs/**/*.gz'
+    #[arg(long, conflicts_with = "from_file")]
+    glob: Option<String>,
+    /// Read the keys to delete from a file, one per line
+    #[arg(long)]
+    from_file: Option<String>,
+    /// Delete without asking for confirmation
+    #[arg(long)]
+    yes: bool,
+}
+
+#[derive(Args)]
+struct LsArgs {
+    /// s3://bucket or s3://bucket/prefix
+    location: String,
+    /// List every object under the prefix instead of grouping them into folders
+    #[arg(long, short)]
+    recursive: bool,
+    /// Conditions such as 'size>10MB modified<30d class=GLACIER'
+    #[arg(long, value_parser = ObjectFilter::parse)]
+    filter: Option<ObjectFilter>,
+    /// Number of keys requested per page, at most 1000
+    #[arg(long, default_value_t = 1000)]
+    page_size: i32,
+    /// Continue a previous listing from the token it printed
+    #[arg(long)]
+    page_token: Option<String>,
+    /// Follow every page instead of stopping after the first
+    #[arg(long)]
+    all: bool,
+    /// Add a column with each object's encryption, read with one HEAD request per object
+    #[arg(long)]
+    show_encryption: bool,
+}
+
+#[derive(Args)]
+struct DownloadArgs {
+    /// The object in the form s3://bucket/key
+    source: String,
+    /// Destination file or directory; the current directory when omitted
+    #[arg(default_value = ".")]
+    destination: String,
+    /// Only download these bytes, e.g. 0-1023, 1024- or -500 for the last 500
+    #[arg(long, value_parser = parse_range)]
+    range: Option<ByteRange>,
+    /// Skip comparing the finished file with the object's checksum or ETag
+    #[arg(long)]
+    no_verify: bool,
+    /// Download an older version, as shown by 'versions list'
+    #[arg(long)]
+    version_id: Option<String>,
+    /// The file with the SSE-C key the object was uploaded with
+    #[arg(long)]
+    sse_c_key_file: Option<String>,
+}
+
+#[derive(Args)]
+struct SyncArgs {
+    /// A local folder or s3://bucket/prefix
+    source: String,
+    /// A local folder or s3://bucket/prefix; exactly one side must be an S3 URI
+    destination: String,
+    /// Only sync paths matching this glob, relative to the folder or prefix; can be repeated
+    #[arg(long)]
+    include: Vec<String>,
+    /// Skip paths matching this glob; can be repeated
+    #[arg(long)]
+    exclude: Vec<String>,
+    /// Delete destination files that don't exist in the source
+    #[arg(long)]
+    delete: bool,
+    /// Show what would be transferred and deleted without changing anything
+    #[arg(long)]
+    dry_run: bool,
+    /// Number of files transferred at the same time
+    #[arg(long, default_value_t = DEFAULT_JOBS)]
+    jobs: usize,
+    /// Afterwards, write the size and checksum of every synced object to this JSON file
+    #[arg(long)]
+    manifest: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct MakeBucketArgs {
+    /// The name of the bucket; replaces the one in --spec
+    #[arg(required_unless_present = "spec")]
+    bucket: Option<String>,
+    /// A YAML or JSON file with the settings; the options below replace its values
+    #[arg(long)]
+    spec: Option<PathBuf>,
+    /// The configured region when omitted
+    #[arg(long)]
+    region: Option<String>,
+    /// BucketOwnerEnforced (ACLs off), BucketOwnerPreferred or ObjectWriter
+    #[arg(long)]
+    object_ownership: Option<String>,
+    #[arg(long)]
+    versioning: bool,
+    /// The default encryption: AES256 (SSE-S3) or aws:kms (SSE-KMS)
+    #[arg(long)]
+    sse: Option<String>,
+    /// The KMS key id or ARN for SSE-KMS; the aws/s3 key when omitted
+    #[arg(long)]
+    kms_key_id: Option<String>,
+    /// Use an S3 Bucket Key to reduce the KMS requests
+    #[arg(long)]
+    bucket_key: bool,
+    /// Leave Block Public Access off, e.g. for a website bucket
+    #[arg(long)]
+    allow_public: bool,
+    /// A bucket tag as key=value; can be repeated
+    #[arg(long, value_parser = parse_tag_pair)]
+    tag: Vec<(String, String)>,
+}
+
+impl MakeBucketArgs {
+    fn into_spec(self) -> BucketSpec {
+        let mut spec = match &self.spec {
+            Some(path) => read_spec_file(path).unwrap_or_else(|message| panic!("{message}\n")),
+            None => BucketSpec {
+                name: String::new(),
+                region: None,
+                object_ownership: OWNERSHIP_SETTINGS[0].into(),
+                versioning: false,
+                encryption: None,
+                kms_key_id: None,
+                bucket_key: false,
+                block_public_access: true,
+                tags: Default::default(),
+            },
+        };
+        if let Some(bucket) = self.bucket {
+            spec.name = bucket;
+        }
+        spec.region = self.region.or(spec.region);
+        spec.object_ownership = self.object_ownership.unwrap_or(spec.object_ownership);
+        spec.versioning |= self.versioning;
+        spec.encryption = self.sse.or(spec.encryption);
+        spec.kms_key_id = self.kms_key_id.or(spec.kms_key_id);
+        spec.bucket_key |= self.bucket_key;
+        spec.block_public_access &= !self.allow_public;
+        spec.tags.extend(self.tag);
+        spec
+    }
+}
+
+#[derive(Args)]
+struct UsageArgs {
+    /// s3://bucket or s3://bucket/prefix; every bucket when none are given
+    locations: Vec<String>,
+    /// A YAML or JSON file of prices per GB-month by storage class; S3_PRICES_FILE in the .env
+    /// file when omitted, otherwise the us-east-1 prices
+    #[arg(long)]
+    prices: Option<PathBuf>,
+    /// Also write the report to a .csv file, or to a .json file with the totals
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct CopyArgs {
+    /// s3://bucket/key, or s3://bucket/prefix/ with --recursive
+    source: String,
+    /// s3://bucket/key; a trailing '/' keeps the source's name
+    destination: String,
+    /// Copy every object under the source prefix, keeping the names relative to it
+    #[arg(long, short)]
+    recursive: bool,
+    /// Give the copies the metadata and tags from the options below instead of the source's
+    #[arg(long)]
+    replace_metadata: bool,
+    /// Keeps the source's Content-Type when omitted
+    #[arg(long, requires = "replace_metadata")]
+    content_type: Option<String>,
+    /// Keeps the source's Cache-Control when omitted
+    #[arg(long, requires = "replace_metadata")]
+    cache_control: Option<String>,
+    /// User metadata as key=value; can be repeated
+    #[arg(long, requires = "replace_metadata", value_parser = parse_metadata_pair)]
+    metadata: Vec<(String, String)>,
+    /// An object tag as key=value; can be repeated up to 10 times
+    #[arg(long, requires = "replace_metadata", value_parser = parse_tag_pair)]
+    tag: Vec<(String, String)>,
+    #[command(flatten)]
+    encryption: EncryptionArgs,
+    /// The SSE-C key file the sources were uploaded with; the copies' SSE-C key when omitted
+    #[arg(long)]
+    source_sse_c_key_file: Option<String>,
+    /// Show what would be copied without copying
+    #[arg(long)]
+    dry_run: bool,
+    /// Number of objects copied at the same time
+    #[arg(long, default_value_t = DEFAULT_JOBS)]
+    jobs: usize,
+}
+
+/// Values given here take precedence over S3_PART_SIZE_MB, S3_CONCURRENCY, S3_CHECKSUM and the
+/// S3_SSE settings in the .env file
+#[derive(Args)]
+struct UploadSettingArgs {
+    /// Size of each part in MB, at least 5
+    #[arg(long)]
+    part_size_mb: Option<u64>,
+    /// Number of parts uploaded at the same time
+    #[arg(long)]
+    concurrency: Option<usize>,
+    /// The checksum sent with the upload: sha256, crc32c (the default) or none
+    #[arg(long)]
+    checksum: Option<String>,
+    #[command(flatten)]
+    encryption: EncryptionArgs,
+}
+
+impl UploadSettingArgs {
+    fn into_upload_settings(self) -> UploadSettings {
+        let mut settings =
+            UploadSettings::from_env().with_overrides(self.part_size_mb, self.concurrency);
+        if let Some(checksum) = self.checksum {
+            settings = settings.with_checksum(
+                ChecksumKind::parse(&checksum).unwrap_or_else(|message| panic!("{message}\n")),
+            );
+        }
+        match self.encryption.into_encryption() {
+            Some(encryption) => settings.with_encryption(encryption),
+            None => settings,
+        }
+    }
+}
+
+#[derive(Args)]
+struct EncryptionArgs {
+    /// Server-side encryption: AES256 (SSE-S3) or aws:kms (SSE-KMS)
+    #[arg(long)]
+    sse: Option<String>,
+    /// The KMS key id or ARN for SSE-KMS; the aws/s3 key when omitted
+    #[arg(long)]
+    sse_kms_key_id: Option<String>,
+    /// A file with the 32-byte SSE-C key, raw or base64; the same key is needed to download
+    #[arg(long, conflicts_with_all = ["sse", "sse_kms_key_id"])]
+    sse_c_key_file: Option<String>,
+}
+
+impl EncryptionArgs {
+    // None when nothing was given, so the .env settings apply
+    fn into_encryption(self) -> Option<Encryption> {
+        if self.sse.is_none() && self.sse_kms_key_id.is_none() && self.sse_c_key_file.is_none() {
+            return None;
+        }
+        let encryption = Encryption::from_args(
+            self.sse.as_deref(),
+            self.sse_kms_key_id,
+            self.sse_c_key_file.as_deref(),
+        )
+        .unwrap_or_else(|message| panic!("{message}\n"));
+        Some(encryption)
+    }
+}
+
+#[derive(Args)]
+struct UploadArgs {
+    file: String,
+    /// Destination in the form s3://bucket/key; a trailing '/' appends the file name
+    destination: String,
+    #[command(flatten)]
+    settings: UploadSettingArgs,
+    /// The Content-Type to store instead of the one detected from the extension and content
+    #[arg(long)]
+    content_type: Option<String>,
+    /// e.g. 'max-age=86400' or 'no-cache'
+    #[arg(long)]
+    cache_control: Option<String>,
+    /// User metadata as key=value; can be repeated
+    #[arg(long, value_parser = parse_metadata_pair)]
+    metadata: Vec<(String, String)>,
+    /// An object tag as key=value; can be repeated up to 10 times
+    #[arg(long, value_parser = parse_tag_pair)]
+    tag: Vec<(String, String)>,
+}
+
+#[derive(Args)]
+struct ImageArgs {
+    file: String,
+    /// s3://bucket/prefix/ to name the variants after the file, or s3://bucket/prefix/name
+    destination: Option<String>,
+    /// The widths to make, e.g. 320,800,1600; widths over the source's are skipped
+    #[arg(long, value_delimiter = ',', value_parser = parse_width, default_value = "640,800,1280")]
+    widths: Vec<u32>,
+    /// png, jpeg or webp, e.g. webp,jpeg
+    #[arg(long, value_delimiter = ',', value_parser = VariantFormat::parse, default_value = "webp,jpeg")]
+    formats: Vec<VariantFormat>,
+    /// JPEG and WebP quality from 1 to 100
+    #[arg(long, default_value_t = 82, value_parser = clap::value_parser!(u8).range(1..=100))]
+    quality: u8,
+    /// Text drawn over every variant with the bundled font
+    #[arg(long)]
+    watermark: Option<String>,
+    /// bottom-right, bottom-left, top-right, top-left or center
+    #[arg(long, default_value = "bottom-right", value_parser = WatermarkPosition::parse)]
+    watermark_position: WatermarkPosition,
+    /// From 0.0 to 1.0
+    #[arg(long, default_value_t = 0.6)]
+    watermark_opacity: f32,
+    /// Keep the variants in this directory; without a destination they are only saved here
+    #[arg(long)]
+    output_dir: Option<String>,
+    /// e.g. 'public, max-age=31536000, immutable' for variants that never change
+    #[arg(long)]
+    cache_control: Option<String>,
+    #[command(flatten)]
+    settings: UploadSettingArgs,
+}
+
+#[derive(Args)]
+struct ThumbnailArgs {
+    /// The images to read, as s3://bucket/prefix/
+    source: String,
+    /// Where the thumbnails and sheets go, as s3://bucket/prefix/
+    destination: String,
+    /// The longest side of a thumbnail in pixels
+    #[arg(long, default_value_t = 256)]
+    size: u32,
+    /// png, jpeg or webp
+    #[arg(long, default_value = "jpeg", value_parser = VariantFormat::parse)]
+    format: VariantFormat,
+    #[arg(long, default_value_t = 80, value_parser = clap::value_parser!(u8).range(1..=100))]
+    quality: u8,
+    /// Thumbnails per row on the contact sheet
+    #[arg(long, default_value_t = 6)]
+    columns: u32,
+    /// Thumbnails per contact sheet before another one is started
+    #[arg(long, default_value_t = 120)]
+    per_sheet: usize,
+    /// Only upload the thumbnails
+    #[arg(long)]
+    no_sheet: bool,
+    /// Also keep the thumbnails and sheets in this directory
+    #[arg(long)]
+    output_dir: Option<String>,
+}
+
+#[derive(Args)]
+struct HeadArgs {
+    /// The object in the form s3://bucket/key
+    location: String,
+    #[arg(long)]
+    content_type: Option<String>,
+    /// The new Cache-Control header; an empty value removes it
+    #[arg(long)]
+    cache_control: Option<String>,
+    /// Add or change user metadata as key=value; can be repeated
+    #[arg(long, value_parser = parse_metadata_pair)]
+    metadata: Vec<(String, String)>,
+    /// Remove the user metadata with this key; can be repeated
+    #[arg(long)]
+    remove_metadata: Vec<String>,
+    /// Add or change a tag as key=value; can be repeated
+    #[arg(long, value_parser = parse_tag_pair)]
+    tag: Vec<(String, String)>,
+    /// Remove the tag with this key; can be repeated
+    #[arg(long)]
+    remove_tag: Vec<String>,
+    /// The file with the SSE-C key the object was uploaded with
+    #[arg(long)]
+    sse_c_key_file: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum UploadsCommands {
+    /// Show the multipart uploads of a bucket that were never completed or aborted
+    List { bucket: String },
+    /// Resume the interrupted uploads that have a local state file
+    Resume {
+        #[command(flatten)]
+        settings: UploadSettingArgs,
+    },
+    /// Abort the incomplete uploads of a bucket, only those under --key when given
+    Abort {
+        bucket: String,
+        #[arg(long)]
+        key: Option<String>,
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum SesCommands {
+    /// Compare the published DKIM, SPF and DMARC records of a domain with what SES expects
+    DnsCheck(DnsCheckArgs),
+    /// Create, list and delete configuration sets and their event destinations
+    #[command(subcommand)]
+    ConfigSet(ConfigSetCommands),
+    /// Send a simple email to a single verified address
+    SendSimple(SendSimpleArgs),
+    /// Send a templated email to a single verified address
+    SendTemplated(SendTemplatedArgs),
+    /// Send a simple email to every contact of a contact list
+    SendBulkSimple(SendBulkSimpleArgs),
+    /// Send a templated email to every contact of a contact list, using the contact attributes as template data
+    SendBulkTemplated(SendBulkTemplatedArgs),
+    /// Sync email templates between SES and the local template directory
+    Templates(TemplatesArgs),
+    /// Check template HTML for constructs that break in mail clients, optionally inlining its CSS
+    LintTemplate(LintTemplateArgs),
+    /// Manage locale variants such as 'welcome__en' and 'welcome__de' as one template family
+    #[command(subcommand)]
+    TemplateFamily(TemplateFamilyCommands),
+}
+
+#[derive(Subcommand)]
+enum TemplateFamilyCommands {
+    /// List the template families and their locales
+    List,
+    /// Check that every variant of a family uses the same template variables as the default locale
+    Check { family: String },
+}
+
+#[derive(Args)]
+struct LintTemplateArgs {
+    /// An HTML file, or a template folder containing 'template.html' and 'text_body.txt'
+    path: String,
+    /// Text part of the template; read from the template folder when omitted
+    #[arg(long)]
+    text: Option<String>,
+    /// Fetch the size of remote images with a HEAD request
+    #[arg(long)]
+    check_remote_images: bool,
+    /// Write the template with its CSS inlined to this file
+    #[arg(long, value_name = "OUTPUT")]
+    inline_css: Option<String>,
+}
+
+#[derive(Args)]
+struct TemplatesArgs {
+    /// Local template directory; defaults to TEMPLATE_DIR in the .env file or './templates'
+    #[arg(long, global = true)]
+    dir: Option<String>,
+    #[command(subcommand)]
+    command: TemplatesCommands,
+}
+
+#[derive(Subcommand)]
+enum TemplatesCommands {
+    /// Create or update the SES templates from the local directory, showing a diff before each update
+    Push {
+        /// Template names; all local templates when omitted
+        names: Vec<String>,
+        /// Apply the updates without asking for confirmation
+        #[arg(long)]
+        yes: bool,
+        /// Inline the CSS of the <style> blocks before uploading; the local files are left unchanged
+        #[arg(long)]
+        inline_css: bool,
+    },
+    /// Write the SES templates into the local directory
+    Pull {
+        /// Template names; all remote templates when omitted
+        names: Vec<String>,
+        /// Overwrite local changes without asking for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+    /// List the backups kept for a template
+    History { name: String },
+    /// Restore a backup of a template, the most recent one unless --to is given
+    Rollback {
+        name: String,
+        /// Timestamp of the backup, as shown by 'history'
+        #[arg(long)]
+        to: Option<String>,
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+#[derive(Args)]
+struct DnsCheckArgs {
+    /// The domain identity to check
+    domain: String,
+    /// Read the records from a BIND-style zone file instead of the system resolver
+    #[arg(long)]
+    zone_file: Option<String>,
+    /// DKIM tokens to expect; when given, SES isn't contacted at all
+    #[arg(long = "dkim-token")]
+    dkim_tokens: Vec<String>,
+    /// Custom MAIL FROM domain to expect, used together with --dkim-token
+    #[arg(long)]
+    mail_from_domain: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum ConfigSetCommands {
+    /// Create a configuration set
+    Create { name: String },
+    /// List the configuration sets in your account
+    List,
+    /// Delete a configuration set along with its event destinations
+    Delete { name: String },
+    /// Publish the sending events of a configuration set to SNS, CloudWatch or Firehose
+    AddDestination(AddDestinationArgs),
+    /// Show the event destinations of a configuration set
+    Destinations { name: String },
+    /// Remove an event destination from a configuration set
+    RemoveDestination {
+        configuration_set: String,
+        destination_name: String,
+    },
+}
+
+#[derive(Args)]
+struct AddDestinationArgs {
+    configuration_set: String,
+    destination_name: String,
+    /// Comma separated event types, e.g. send,delivery,bounce,complaint
+    #[arg(long, default_value = "send,delivery,bounce,complaint")]
+    events: String,
+    /// Publish the events to this SNS topic
+    #[arg(long, conflicts_with_all = ["cloudwatch_dimension", "firehose_stream"])]
+    sns_topic: Option<String>,
+    /// Publish the events as CloudWatch metrics, using this message tag as the dimension
+    #[arg(long, conflicts_with = "firehose_stream")]
+    cloudwatch_dimension: Option<String>,
+    /// Dimension value used when a message doesn't carry the tag
+    #[arg(long, default_value = "none")]
+    cloudwatch_default: String,
+    /// Publish the events to this Kinesis Firehose delivery stream
+    #[arg(long, requires = "firehose_role")]
+    firehose_stream: Option<String>,
+    /// IAM role that lets SES write to the delivery stream
+    #[arg(long)]
+    firehose_role: Option<String>,
+}
+
+/// Values given here take precedence over CONFIGURATION_SET and MESSAGE_TAGS in the .env file
+#[derive(Args)]
+struct SendOptionArgs {
+    /// Configuration set used to route the sending events
+    #[arg(long)]
+    configuration_set: Option<String>,
+    /// Message tag in the form key=value; can be repeated
+    #[arg(long = "tag", value_parser = parse_tag)]
+    tags: Vec<(String, String)>,
+}
+
+impl SendOptionArgs {
+    fn into_send_options(self) -> SendOptions {
+        SendOptions::from_env().with_overrides(self.configuration_set, self.tags)
+    }
+}
+
+#[derive(Args)]
+struct SendSimpleArgs {
+    email: String,
+    #[arg(long)]
+    subject: String,
+    /// Path to the text or HTML body of the email
+    #[arg(long)]
+    body_file: String,
+    /// Defaults to FROM_ADDRESS in the .env file
+    #[arg(long)]
+    from: Option<String>,
+    #[command(flatten)]
+    send_options: SendOptionArgs,
+}
+
+#[derive(Args)]
+struct SendTemplatedArgs {
+    email: String,
+    /// Defaults to TEMPLATE_NAME in the .env file. A family name such as 'welcome' picks the
+    /// variant matching the locale field of the template data
+    #[arg(long)]
+    template: Option<String>,
+    /// Path to the template data in JSON format
+    #[arg(long)]
+    data_file: String,
+    /// Defaults to FROM_ADDRESS in the .env file
+    #[arg(long)]
+    from: Option<String>,
+    #[command(flatten)]
+    send_options: SendOptionArgs,
+}
+
+#[derive(Args)]
+struct SendBulkSimpleArgs {
+    #[arg(long)]
+    subject: String,
+    /// Path to the text or HTML body of the email
+    #[arg(long)]
+    body_file: String,
+    /// Defaults to LIST_NAME in the .env file
+    #[arg(long)]
+    list: Option<String>,
+    /// Defaults to FROM_ADDRESS in the .env file
+    #[arg(long)]
+    from: Option<String>,
+    #[command(flatten)]
+    send_options: SendOptionArgs,
+}
+
+#[derive(Args)]
+struct SendBulkTemplatedArgs {
+    /// Defaults to TEMPLATE_NAME in the .env file. A family name such as 'welcome' picks the
+    /// variant matching the locale field of the template data
+    #[arg(long)]
+    template: Option<String>,
+    /// Defaults to LIST_NAME in the .env file
+    #[arg(long)]
+    list: Option<String>,
+    /// Defaults to FROM_ADDRESS in the .env file
+    #[arg(long)]
+    from: Option<String>,
+    #[command(flatten)]
+    send_options: SendOptionArgs,
+}
+
+pub async fn run(cli: Cli) {
+    match cli.command {
+        Commands::Ses(command) => run_ses(command).await,
+        Commands::S3(command) => run_s3(command).await,
+    }
+}
+
+fn rules_from_file(file: &str) -> Vec<RuleSpec> {
+    read_rules_file(Path::new(file)).unwrap_or_else(|message| panic!("{message}\n"))
+}
+
+async fn run_lifecycle(config: &SdkConfig, command: LifecycleCommands) {
+    match command {
+        LifecycleCommands::Show { bucket, output } => {
+            let rules = get_rules(config, &bucket).await;
+            match output {
+                Some(output) => write_rules_file(Path::new(&output), &rules),
+                None => print_rules(&bucket, &rules),
+            }
+        }
+        LifecycleCommands::Apply { bucket, file, yes } => {
+            let rules = rules_from_file(&file);
+            review_and_put_rules(config, &bucket, &rules, yes).await;
+        }
+        LifecycleCommands::Add { bucket, file, yes } => {
+            let added = match file {
+                Some(file) => rules_from_file(&file),
+                None => match prompt_rule(None) {
+                    Some(rule) => vec![rule],
+                    None => return,
+                },
+            };
+            let rules = merge_rules(get_rules(config, &bucket).await, added);
+            review_and_put_rules(config, &bucket, &rules, yes).await;
+        }
+        LifecycleCommands::Edit { bucket, id } => {
+            let rules = get_rules(config, &bucket).await;
+            let current = rules
+                .iter()
+                .find(|rule| rule.id == id)
+                .unwrap_or_else(|| panic!("'{bucket}' has no lifecycle rule with the id '{id}'\n"));
+            if let Err(message) = current.check_editable() {
+                panic!("{message}\n");
+            }
+            let Some(edited) = prompt_rule(Some(current)) else {
+                return;
+            };
+            // An edited id replaces the old rule rather than adding a second one
+            let rules = rules
+                .iter()
+                .map(|rule| match rule.id == id {
+                    true => edited.clone(),
+                    false => rule.clone(),
+                })
+                .collect::<Vec<_>>();
+            review_and_put_rules(config, &bucket, &rules, false).await;
+        }
+        LifecycleCommands::Delete { bucket, ids, yes } => {
+            let rules = get_rules(config, &bucket).await;
+            let missing = ids
+                .iter()
+                .filter(|id| !rules.iter().any(|rule| &rule.id == *id))
+                .collect::<Vec<_>>();
+            if !missing.is_empty() {
+                panic!("'{bucket}' has no lifecycle rules with the ids {missing:?}\n");
+            }
+            let kept = rules
+                .into_iter()
+                .filter(|rule| !ids.contains(&rule.id))
+                .collect::<Vec<_>>();
+            review_and_put_rules(config, &bucket, &kept, yes).await;
+        }
+        LifecycleCommands::Preview { bucket, file } => {
+            let rules = match file {
+                Some(file) => rules_from_file(&file),
+                None => get_rules(config, &bucket).await,
+            };
+            print_rules(&bucket, &rules);
+            preview_rules(config, &bucket, &rules).await;
+        }
+    }
+}
+
+async fn run_presign(config: &SdkConfig, command: PresignCommands) {
+    let object_location = |location: &str| {
+        parse_s3_uri(location)
+            .filter(|(_, key)| !key.is_empty())
+            .expect("The location should be in the form s3://bucket/key\n")
+    };
+    match command {
+        PresignCommands::Get { location, output } => {
+            let (bucket, key) = object_location(&location);
+            let url = presign_get(config, &bucket, &key, output.expires).await;
+            deliver_url(
+                &url,
+                output.expires,
+                output.output.as_deref(),
+                output.qr.as_deref(),
+            );
+        }
+        PresignCommands::Put {
+            location,
+            content_type,
+            output,
+        } => {
+            let (bucket, key) = object_location(&location);
+            let url = presign_put(
+                config,
+                &bucket,
+                &key,
+                output.expires,
+                content_type.as_deref(),
+            )
+            .await;
+            deliver_url(
+                &url,
+                output.expires,
+                output.output.as_deref(),
+                output.qr.as_deref(),
+            );
+        }
+        PresignCommands::Post {
+            location,
+            content_type,
+            min_size,
+            max_size,
+            output,
+        } => {
+            let (bucket, key) = parse_s3_uri(&location).expect(
+                "The location should be in the form s3://bucket/key or s3://bucket/prefix/\n",
+            );
+            if output.qr.is_some() {
+                println!(
+                    "{}\n",
+                    "A POST form has several fields, so it can't be a QR code; use --output form.html"
+                        .yellow()
+                        .bold()
+                );
+            }
+            let conditions = PostConditions {
+                content_type,
+                min_size,
+                max_size,
+            };
+            let post = presign_post(config, &bucket, &key, output.expires, &conditions).await;
+            deliver_post(&post, output.expires, output.output.as_deref());
+        }
+    }
+}
+
+async fn run_copy(config: &SdkConfig, args: CopyArgs, delete_source: bool) {
+    let source = parse_s3_uri(&args.source)
+        .expect("The source should be in the form s3://bucket/key or s3://bucket/prefix/\n");
+    let destination = parse_s3_uri(&args.destination)
+        .expect("The destination should be in the form s3://bucket/key or s3://bucket/prefix/\n");
+    let metadata = match args.replace_metadata {
+        true => Some(
+            ObjectMetadata::new(
+                args.content_type,
+                args.cache_control,
+                args.metadata,
+                args.tag,
+            )
+            .unwrap_or_else(|message| panic!("{message}\n")),
+        ),
+        false => None,
+    };
+    let encryption = args
+        .encryption
+        .into_encryption()
+        .unwrap_or_else(Encryption::from_env);
+    let source_encryption = match args.source_sse_c_key_file {
+        Some(file) => Encryption::Customer {
+            key: read_customer_key(Path::new(&file))
+                .unwrap_or_else(|message| panic!("{message}\n")),
+        },
+        None => encryption.clone(),
+    };
+    let options = CopyOptions {
+        metadata,
+        encryption,
+        source_encryption,
+        recursive: args.recursive,
+        delete_source,
+        dry_run: args.dry_run,
+        jobs: args.jobs,
+    };
+    let completed = copy_objects(
+        config,
+        (&source.0, &source.1),
+        (&destination.0, &destination.1),
+        &options,
+    )
+    .await;
+    if !completed {
+        std::process::exit(1);
+    }
+}
+
+async fn run_policy(config: &SdkConfig, command: PolicyCommands) {
+    match command {
+        PolicyCommands::Show { bucket, output } => match get_policy(config, &bucket).await {
+            Some(policy) => match output {
+                Some(output) => {
+                    std::fs::write(&output, policy).expect("Error while writing the policy file\n");
+                    println!("The policy is written to {}\n", output.green().bold());
+                }
+                None => println!("{policy}\n"),
+            },
+            None => println!(
+                "{}\n",
+                format!("'{bucket}' has no bucket policy").yellow().bold()
+            ),
+        },
+        PolicyCommands::Put { bucket, file, yes } => {
+            let policy =
+                std::fs::read_to_string(&file).expect("Error while reading the policy file\n");
+            if !put_policy(config, &bucket, &policy, yes).await {
+                std::process::exit(1);
+            }
+        }
+        PolicyCommands::Edit { bucket } => {
+            let current = get_policy(config, &bucket)
+                .await
+                .unwrap_or_else(empty_policy);
+            if let Some(edited) = edit_policy(&bucket, &current) {
+                put_policy(config, &bucket, &edited, false).await;
+            }
+        }
+        PolicyCommands::Validate { bucket, file } => {
+            let policy =
+                std::fs::read_to_string(&file).expect("Error while reading the policy file\n");
+            match validate_policy(&policy, &bucket) {
+                Ok(warnings) => {
+                    for warning in warnings {
+                        println!("{} {warning}", "Warning:".yellow().bold());
+                    }
+                    println!("{}\n", "The policy is valid".green().bold());
+                }
+                Err(message) => {
+                    println!("{}\n", message.red().bold());
+                    std::process::exit(1);
+                }
+            }
+        }
+        PolicyCommands::Delete { bucket, yes } => delete_policy(config, &bucket, yes).await,
+        PolicyCommands::Template {
+            bucket,
+            template,
+            yes,
+        } => {
+            let template = match template {
+                PolicyTemplateCommands::PublicRead { prefix } => {
+                    PolicyTemplate::PublicRead { prefix }
+                }
+                PolicyTemplateCommands::CloudfrontOac { distribution_arn } => {
+                    PolicyTemplate::CloudFrontOac { distribution_arn }
+                }
+                PolicyTemplateCommands::CrossAccountRead { account_id, prefix } => {
+                    PolicyTemplate::CrossAccountRead { account_id, prefix }
+                }
+            };
+            apply_template(config, &bucket, &template, yes).await;
+        }
+    }
+}
+
+async fn run_s3(command: S3Commands) {
+    let config = load_sdk_config().await;
+    match command {
+        S3Commands::Upload(args) => {
+            let (bucket, key) = parse_s3_uri(&args.destination)
+                .expect("The destination should be in the form s3://bucket/key\n");
+            let file = Path::new(&args.file);
+            let key = match key.is_empty() || key.ends_with('/') {
+                true => format!("{key}{}", file.file_name().unwrap().to_string_lossy()),
+                false => key,
+            };
+            let metadata = ObjectMetadata::new(
+                args.content_type,
+                args.cache_control,
+                args.metadata,
+                args.tag,
+            )
+            .unwrap_or_else(|message| panic!("{message}\n"));
+            let settings = args.settings.into_upload_settings().with_metadata(metadata);
+            if !upload_file(&config, &bucket, file, &key, &settings).await {
+                std::process::exit(1);
+            }
+        }
+        S3Commands::Cp(args) => run_copy(&config, args, false).await,
+        S3Commands::Mv(args) => run_copy(&config, args, true).await,
+        S3Commands::Sync(args) => {
+            let (direction, local, (bucket, prefix)) =
+                match (parse_s3_uri(&args.source), parse_s3_uri(&args.destination)) {
+                    (None, Some(remote)) => (SyncDirection::Upload, args.source, remote),
+                    (Some(remote), None) => (SyncDirection::Download, args.destination, remote),
+                    _ => panic!(
+                        "Exactly one of the source and the destination should be an s3:// URI\n"
+                    ),
+                };
+            let options = SyncOptions {
+                include: args.include,
+                exclude: args.exclude,
+                delete: args.delete,
+                dry_run: args.dry_run,
+                jobs: args.jobs,
+                manifest: args.manifest,
+            };
+            if !sync(
+                &config,
+                direction,
+                Path::new(&local),
+                &bucket,
+                &prefix,
+                &options,
+            )
+            .await
+            {
+                std::process::exit(1);
+            }
+        }
+        S3Commands::Download(args) => {
+            let (bucket, key) = parse_s3_uri(&args.source)
+                .filter(|(_, key)| !key.is_empty())
+                .expect("The source should be in the form s3://bucket/key\n");
+            let options = DownloadOptions {
+                range: args.range,
+                verify: !args.no_verify,
+                version_id: args.version_id,
+                encryption: match args.sse_c_key_file {
+                    Some(file) => Encryption::Customer {
+                        key: read_customer_key(Path::new(&file))
+                            .unwrap_or_else(|message| panic!("{message}\n")),
+                    },
+                    None => Encryption::BucketDefault,
+                },
+            };
+            let destination = Path::new(&args.destination);
+            if !download_object(&config, &bucket, &key, destination, &options).await {
+                std::process::exit(1);
+            }
+        }
+        S3Commands::Ls(args) => {
+            let (bucket, prefix) = parse_s3_uri(&args.location)
+                .expect("The location should be in the form s3://bucket/prefix\n");
+            let options = ListingOptions {
+                recursive: args.recursive,
+                filter: args.filter.unwrap_or_default(),
+                page_size: args.page_size.clamp(1, 1000),
+                page_token: args.page_token,
+                all: args.all,
+                encryption: args.show_encryption,
+            };
+            print_listing(&config, &bucket, &prefix, options).await;
+        }
+        S3Commands::Rm(args) => {
+            let selection = match (args.prefix, args.glob, args.from_file) {
+                (Some(prefix), _, _) => DeleteSelection::Prefix(prefix),
+                (_, Some(glob), _) => DeleteSelection::Glob(glob),
+                (_, _, Some(file)) => DeleteSelection::Keys(
+                    std::fs::read_to_string(&file)
+                        .expect("Error while reading the file of keys\n")
+                        .lines()
+                        .map(|key| key.trim().to_string())
+                        .filter(|key| !key.is_empty())
+                        .collect(),
+                ),
+                _ if args.keys.is_empty() => {
+                    panic!("Give the keys to delete, or one of --prefix, --glob and --from-file\n")
+                }
+                _ => DeleteSelection::Keys(args.keys),
+            };
+            delete_selection(&config, &args.bucket, &selection, args.yes).await;
+        }
+        S3Commands::Mb(args) => {
+            let spec = args.into_spec();
+            spec.print(&spec.region_or_default(&config));
+            if !create_bucket(&config, &spec).await {
+                std::process::exit(1);
+            }
+        }
+        S3Commands::Rb { bucket, yes } => empty_and_delete_bucket(&config, &bucket, yes).await,
+        S3Commands::Versioning(command) => match command {
+            VersioningCommands::Enable { bucket } => set_versioning(&config, &bucket, true).await,
+            VersioningCommands::Suspend { bucket } => set_versioning(&config, &bucket, false).await,
+            VersioningCommands::Status { bucket } => {
+                let status = versioning_status(&config, &bucket).await;
+                println!(
+                    "Versioning of '{}': {}\n",
+                    bucket.green().bold(),
+                    status.bold()
+                );
+            }
+        },
+        S3Commands::Versions(command) => match command {
+            VersionsCommands::List { location } => {
+                let (bucket, key) = parse_s3_uri(&location)
+                    .expect("The location should be in the form s3://bucket/key\n");
+                let versions = key_versions(&config, &bucket, &key).await;
+                print_versions(&bucket, &versions);
+            }
+            VersionsCommands::Restore {
+                location,
+                version_id,
+            } => {
+                let (bucket, key) = parse_s3_uri(&location)
+                    .filter(|(_, key)| !key.is_empty())
+                    .expect("The location should be in the form s3://bucket/key\n");
+                restore_version(&config, &bucket, &key, &version_id).await;
+            }
+            VersionsCommands::Delete {
+                location,
+                version_ids,
+                yes,
+            } => {
+                let (bucket, key) = parse_s3_uri(&location)
+                    .filter(|(_, key)| !key.is_empty())
+                    .expect("The location should be in the form s3://bucket/key\n");
+                let versions = key_versions(&config, &bucket, &key)
+                    .await
+                    .into_iter()
+                    .filter(|version| version_ids.contains(&version.version_id))
+                    .collect::<Vec<_>>();
+                print_versions(&bucket, &versions);
+                if versions.len() < version_ids.len() {
+                    println!(
+                        "{}\n",
+                        "Some of the version ids don't belong to this key and are skipped"
+                            .yellow()
+                            .bold()
+                    );
+                }
+                let confirm = versions.is_empty()
+                    || yes
+                    || Confirm::new(&format!(
+                        "Permanently delete these {} version(s)?\n",
+                        versions.len()
+                    ))
+                    .with_default(false)
+                    .prompt()
+                    .unwrap();
+                if confirm && !versions.is_empty() {
+                    delete_key_versions(&config, &bucket, &versions).await;
+                }
+            }
+        },
+        S3Commands::Lifecycle(command) => run_lifecycle(&config, command).await,
+        S3Commands::Policy(command) => run_policy(&config, command).await,
+        S3Commands::PublicAccess(command) => match command {
+            PublicAccessCommands::Show { bucket } => get_public_access_block(&config, &bucket)
+                .await
+                .print(&bucket),
+            PublicAccessCommands::BlockAll { bucket, yes } => {
+                put_public_access_block(&config, &bucket, PublicAccessBlock::all(true), yes).await;
+            }
+            PublicAccessCommands::UnblockAll { bucket, yes } => {
+                put_public_access_block(&config, &bucket, PublicAccessBlock::all(false), yes).await;
+            }
+            PublicAccessCommands::Set {
+                bucket,
+                block_public_acls,
+                ignore_public_acls,
+                block_public_policy,
+                restrict_public_buckets,
+                yes,
+            } => {
+                let current = get_public_access_block(&config, &bucket).await;
+                let block = PublicAccessBlock {
+                    block_public_acls: block_public_acls.unwrap_or(current.block_public_acls),
+                    ignore_public_acls: ignore_public_acls.unwrap_or(current.ignore_public_acls),
+                    block_public_policy: block_public_policy.unwrap_or(current.block_public_policy),
+                    restrict_public_buckets: restrict_public_buckets
+                        .unwrap_or(current.restrict_public_buckets),
+                };
+                put_public_access_block(&config, &bucket, block, yes).await;
+            }
+        },
+        S3Commands::Presign(command) => run_presign(&config, command).await,
+        S3Commands::Image(args) => {
+            let file = Path::new(&args.file);
+            let stem = file.file_stem().unwrap().to_string_lossy().to_string();
+            let destination = args.destination.map(|destination| {
+                let (bucket, key) = parse_s3_uri(&destination)
+                    .expect("The destination should be in the form s3://bucket/prefix/\n");
+                match key.is_empty() || key.ends_with('/') {
+                    true => (bucket, key, stem.clone()),
+                    false => {
+                        let (prefix, name) = key.rsplit_once('/').unwrap_or(("", &key));
+                        let prefix = match prefix.is_empty() {
+                            true => String::new(),
+                            false => format!("{prefix}/"),
+                        };
+                        (bucket, prefix, name.to_string())
+                    }
+                }
+            });
+            if destination.is_none() && args.output_dir.is_none() {
+                panic!("Give an s3:// destination, an --output-dir, or both\n");
+            }
+            let name = destination
+                .as_ref()
+                .map_or(stem.clone(), |(_, _, name)| name.clone());
+            let pipeline = ImagePipeline {
+                widths: args.widths,
+                formats: args.formats,
+                quality: args.quality,
+                watermark: args.watermark.map(|text| Watermark {
+                    text,
+                    position: args.watermark_position,
+                    opacity: args.watermark_opacity,
+                }),
+            };
+            let output_dir = args
+                .output_dir
+                .map(PathBuf::from)
+                .unwrap_or_else(|| scratch_dir(&name));
+            let metadata = ObjectMetadata {
+                cache_control: args.cache_control,
+                ..Default::default()
+            };
+            let settings = args.settings.into_upload_settings().with_metadata(metadata);
+            let completed = upload_image_variants(
+                &config,
+                file,
+                destination
+                    .as_ref()
+                    .map(|(bucket, prefix, _)| (bucket.as_str(), prefix.as_str())),
+                &name,
+                &pipeline,
+                &output_dir,
+                &settings,
+            )
+            .await;
+            if !completed {
+                std::process::exit(1);
+            }
+        }
+        S3Commands::Thumbnails(args) => {
+            let as_prefix = |location: &str| {
+                let (bucket, prefix) = parse_s3_uri(location)
+                    .expect("The locations should be in the form s3://bucket/prefix/\n");
+                match prefix.is_empty() || prefix.ends_with('/') {
+                    true => (bucket, prefix),
+                    false => (bucket, format!("{prefix}/")),
+                }
+            };
+            let (bucket, prefix) = as_prefix(&args.source);
+            let (destination_bucket, destination_prefix) = as_prefix(&args.destination);
+            let options = ThumbnailOptions {
+                size: args.size.max(16),
+                format: args.format,
+                quality: args.quality,
+                columns: match args.no_sheet {
+                    true => 0,
+                    false => args.columns.max(1),
+                },
+                per_sheet: args.per_sheet,
+            };
+            let completed = make_thumbnails(
+                &config,
+                &bucket,
+                &prefix,
+                (&destination_bucket, &destination_prefix),
+                &options,
+                args.output_dir.as_deref().map(Path::new),
+                &UploadSettings::from_env(),
+            )
+            .await;
+            if !completed {
+                std::process::exit(1);
+            }
+        }
+        S3Commands::Cors(command) => match command {
+            CorsCommands::Show { bucket, output } => {
+                let rules = get_cors(&config, &bucket).await;
+                match output {
+                    Some(output) => write_cors_file(&output, &rules),
+                    None => print_cors(&bucket, &rules),
+                }
+            }
+            CorsCommands::Put { bucket, file, yes } => {
+                let rules = read_cors_file(&file).unwrap_or_else(|message| panic!("{message}\n"));
+                if !put_cors(&config, &bucket, &rules, yes).await {
+                    std::process::exit(1);
+                }
+            }
+            CorsCommands::Add { bucket } => {
+                if let Some(rule) = prompt_cors_rule(None) {
+                    let mut rules = get_cors(&config, &bucket).await;
+                    rules.push(rule);
+                    put_cors(&config, &bucket, &rules, false).await;
+                }
+            }
+            CorsCommands::Validate { file } => match read_cors_file(&file) {
+                Ok(rules) => println!(
+                    "{}\n",
+                    format!("The {} CORS rule(s) are valid", rules.len())
+                        .green()
+                        .bold()
+                ),
+                Err(message) => {
+                    println!("{}\n", message.red().bold());
+                    std::process::exit(1);
+                }
+            },
+            CorsCommands::Delete { bucket, yes } => {
+                delete_cors(&config, &bucket, yes).await;
+            }
+        },
+        S3Commands::Website(command) => match command {
+            WebsiteCommands::Show { bucket } => print_website(&config, &bucket).await,
+            WebsiteCommands::Publish {
+                bucket,
+                dir,
+                index,
+                error,
+                delete,
+                yes,
+            } => {
+                let settings = WebsiteSettings {
+                    index,
+                    error: Some(error).filter(|error| !error.is_empty()),
+                };
+                if !publish_website(&config, &bucket, &dir, &settings, delete, yes).await {
+                    std::process::exit(1);
+                }
+            }
+            WebsiteCommands::Disable {
+                bucket,
+                keep_policy,
+                yes,
+            } => disable_website(&config, &bucket, keep_policy, yes).await,
+        },
+        S3Commands::Usage(args) => {
+            let prices = PriceTable::load(args.prices.as_deref())
+                .unwrap_or_else(|message| panic!("{message}\n"));
+            let locations = match args.locations.is_empty() {
+                true => bucket_names(&config)
+                    .await
+                    .into_iter()
+                    .map(|bucket| (bucket, String::new()))
+                    .collect(),
+                false => args
+                    .locations
+                    .iter()
+                    .map(|location| {
+                        parse_s3_uri(location)
+                            .expect("Each location should be in the form s3://bucket/prefix\n")
+                    })
+                    .collect::<Vec<_>>(),
+            };
+            let report = usage_report(&config, &locations, &prices).await;
+            print_usage(&report);
+            if let Some(output) = args.output {
+                if let Err(message) = write_report(&output, &report) {
+                    panic!("{message}\n");
+                }
+            }
+        }
+        S3Commands::Manifest(command) => match command {
+            ManifestCommands::Create {
+                location,
+                file,
+                jobs,
+            } => {
+                let (bucket, prefix) = parse_s3_uri(&location)
+                    .expect("The location should be in the form s3://bucket/prefix\n");
+                let manifest = prefix_manifest(&config, &bucket, &prefix, jobs).await;
+                if let Err(message) = write_manifest(&file, &manifest) {
+                    panic!("{message}\n");
+                }
+            }
+            ManifestCommands::Verify { file, jobs } => {
+                let manifest = read_manifest(&file).unwrap_or_else(|message| panic!("{message}\n"));
+                if !verify_manifest(&config, &manifest, jobs).await {
+                    std::process::exit(1);
+                }
+            }
+        },
+        S3Commands::Head(args) => {
+            let (bucket, key) = parse_s3_uri(&args.location)
+                .filter(|(_, key)| !key.is_empty())
+                .expect("The location should be in the form s3://bucket/key\n");
+            let encryption = match args.sse_c_key_file {
+                Some(file) => Encryption::Customer {
+                    key: read_customer_key(Path::new(&file))
+                        .unwrap_or_else(|message| panic!("{message}\n")),
+                },
+                None => Encryption::BucketDefault,
+            };
+            let changes = MetadataChanges {
+                content_type: args.content_type,
+                cache_control: args.cache_control,
+                set_metadata: args.metadata,
+                remove_metadata: args.remove_metadata,
+                set_tags: args.tag,
+                remove_tags: args.remove_tag,
+            };
+            if !changes.is_empty()
+                && !update_object(&config, &bucket, &key, &changes, &encryption).await
+            {
+                std::process::exit(1);
+            }
+            match head_object(&config, &bucket, &key, &encryption).await {
+                Ok(head) => print_head(&bucket, &key, &head),
+                Err(error) => {
+                    println!(
+                        "{}: {error}\n",
+                        "Error while reading the object".red().bold()
+                    );
+                    std::process::exit(1);
+                }
+            }
+        }
+        S3Commands::Encryption(command) => match command {
+            EncryptionCommands::Show { bucket } => println!(
+                "The default encryption of '{}' is {}\n",
+                bucket.green().bold(),
+                get_bucket_encryption(&config, &bucket).await.green().bold()
+            ),
+            EncryptionCommands::Set {
+                bucket,
+                sse_s3,
+                kms_key_id,
+                bucket_key,
+            } => {
+                let encryption = match (sse_s3, kms_key_id) {
+                    (_, Some(key_id)) => Encryption::Kms {
+                        key_id: Some(key_id).filter(|key_id| key_id != "aws/s3"),
+                    },
+                    (true, None) => Encryption::S3,
+                    (false, None) => panic!("Give either --sse-s3 or --kms-key-id\n"),
+                };
+                set_bucket_encryption(&config, &bucket, &encryption, bucket_key).await;
+            }
+        },
+        S3Commands::Uploads(command) => match command {
+            UploadsCommands::List { bucket } => {
+                let uploads = list_incomplete_uploads(&config, &bucket).await;
+                print_incomplete_uploads(&bucket, &uploads);
+            }
+            UploadsCommands::Resume { settings } => {
+                let settings = settings.into_upload_settings();
+                let saved = saved_uploads();
+                if saved.is_empty() {
+                    println!(
+                        "{}\n",
+                        "There are no interrupted uploads to resume".green().bold()
+                    );
+                }
+                for state in saved {
+                    let settings = state.resume_settings(settings.clone());
+                    upload_file(&config, &state.bucket, &state.file, &state.key, &settings).await;
+                }
+            }
+            UploadsCommands::Abort { bucket, key, yes } => {
+                let uploads = list_incomplete_uploads(&config, &bucket)
+                    .await
+                    .into_iter()
+                    .filter(|upload| key.as_ref().map_or(true, |key| upload.key.starts_with(key)))
+                    .collect::<Vec<_>>();
+                print_incomplete_uploads(&bucket, &uploads);
+                let confirm = uploads.is_empty()
+                    || yes
+                    || Confirm::new(&format!("Abort these {} uploads?\n", uploads.len()))
+                        .with_default(false)
+                        .prompt()
+                        .unwrap();
+                if confirm {
+                    abort_uploads(&config, &bucket, &uploads).await;
+                }
+            }
+        },
+    }
+}
+
+async fn run_ses(command: SesCommands) {
+    match command {
+        SesCommands::DnsCheck(args) => {
+            let expected = match args.dkim_tokens.is_empty() {
+                false => {
+                    dotenv().ok();
+                    let region = var("AWS_DEFAULT_REGION").unwrap_or("us-east-1".into());
+                    ExpectedRecords::from_tokens(
+                        &region,
+                        &args.domain,
+                        args.dkim_tokens,
+                        args.mail_from_domain.as_deref(),
+                    )
+                }
+                true => {
+                    let config = load_sdk_config().await;
+                    ExpectedRecords::from_ses(&config, &args.domain).await
+                }
+            };
+            let source = match args.zone_file {
+                Some(path) => RecordSource::zone_file(&path),
+                None => RecordSource::system(),
+            };
+            print_report(&check_domain(&expected, &source).await);
+        }
+        SesCommands::ConfigSet(command) => {
+            let config = load_sdk_config().await;
+            match command {
+                ConfigSetCommands::Create { name } => {
+                    create_configuration_set(&config, &name).await
+                }
+                ConfigSetCommands::List => {
+                    for name in list_configuration_sets(&config).await {
+                        println!("    {}\n", name.green().bold());
+                    }
+                }
+                ConfigSetCommands::Delete { name } => {
+                    delete_configuration_set(&config, &name).await
+                }
+                ConfigSetCommands::AddDestination(args) => {
+                    let event_types = parse_event_types(&args.events).unwrap_or_else(|error| {
+                        panic!("{error}\n");
+                    });
+                    let kind = match (args.sns_topic, args.cloudwatch_dimension, args.firehose_stream) {
+                        (Some(topic_arn), _, _) => EventDestinationKind::Sns { topic_arn },
+                        (_, Some(dimension_name), _) => EventDestinationKind::CloudWatch {
+                            dimension_name,
+                            default_value: args.cloudwatch_default,
+                        },
+                        (_, _, Some(delivery_stream_arn)) => EventDestinationKind::Firehose {
+                            delivery_stream_arn,
+                            iam_role_arn: args.firehose_role.unwrap(),
+                        },
+                        _ => panic!("One of --sns-topic, --cloudwatch-dimension or --firehose-stream is required\n"),
+                    };
+                    add_event_destination(
+                        &config,
+                        &args.configuration_set,
+                        &args.destination_name,
+                        event_types,
+                        kind,
+                    )
+                    .await;
+                }
+                ConfigSetCommands::Destinations { name } => {
+                    print_event_destinations(&config, &name).await
+                }
+                ConfigSetCommands::RemoveDestination {
+                    configuration_set,
+                    destination_name,
+                } => delete_event_destination(&config, &configuration_set, &destination_name).await,
+            }
+        }
+        SesCommands::SendSimple(args) => {
+            let ses_ops = SesOps::build(load_sdk_config().await);
+            let send_options = args.send_options.into_send_options();
+            let body_data = std::fs::read_to_string(&args.body_file)
+                .expect("Error opening the simple email file path you specified\n");
+            let simple_email = SimpleMail::builder(&body_data, &args.subject).build();
+            ses_ops
+                .send_mono_email(&args.email, Simple_(simple_email), args.from.as_deref())
+                .await
+                .set_configuration_set_name(send_options.configuration_set_name())
+                .set_email_tags(send_options.email_tags())
+                .send()
+                .await
+                .map(|_| {
+                    println!(
+                        "A simple email has been successfully sent to '{}'\n",
+                        args.email.green().bold()
+                    )
+                })
+                .expect("Error while Sending Simple Email\n");
+        }
+        SesCommands::SendTemplated(args) => {
+            let config = load_sdk_config().await;
+            let ses_ops = SesOps::build(config.clone());
+            let send_options = args.send_options.into_send_options();
+            let template_name = args.template.unwrap_or(ses_ops.get_template_name().into());
+            let (template_name, template_data) =
+                read_and_resolve_template(&config, &template_name, &args.data_file).await;
+            let email_content = TemplateMail::builder(&template_name, &template_data).build();
+            ses_ops
+                .send_mono_email(&args.email, Template_(email_content), args.from.as_deref())
+                .await
+                .set_configuration_set_name(send_options.configuration_set_name())
+                .set_email_tags(send_options.email_tags())
+                .send()
+                .await
+                .map(|_| {
+                    println!(
+                        "The template email is send to: {}\n",
+                        args.email.green().bold()
+                    )
+                })
+                .expect("Error while sending template mail\n");
+        }
+        SesCommands::SendBulkSimple(args) => {
+            let config = load_sdk_config().await;
+            let ses_ops = SesOps::build(config.clone());
+            let send_options = args.send_options.into_send_options();
+            let body_data = std::fs::read_to_string(&args.body_file)
+                .expect("Error opening the simple email file path you specified\n");
+            send_bulk_simple(
+                &config,
+                &args.list.unwrap_or(ses_ops.get_list_name().into()),
+                &args.from.unwrap_or(ses_ops.get_from_address().into()),
+                &args.subject,
+                &body_data,
+                &send_options,
+            )
+            .await;
+        }
+        SesCommands::Templates(args) => {
+            let root = args.dir.map(Into::into).unwrap_or(template_dir());
+            match args.command {
+                TemplatesCommands::History { name } => {
+                    for timestamp in backups(&root, &name) {
+                        println!("    {}", timestamp.green().bold());
+                    }
+                    println!();
+                }
+                command => {
+                    let ses_ops = SesOps::build(load_sdk_config().await);
+                    match command {
+                        TemplatesCommands::Push {
+                            names,
+                            yes,
+                            inline_css,
+                        } => push(&ses_ops, &root, names, yes, inline_css).await,
+                        TemplatesCommands::Pull { names, yes } => {
+                            pull(&ses_ops, &root, names, yes).await
+                        }
+                        TemplatesCommands::Rollback { name, to, yes } => {
+                            rollback(&ses_ops, &root, &name, to, yes).await
+                        }
+                        TemplatesCommands::History { .. } => unreachable!(),
+                    }
+                }
+            }
+        }
+        SesCommands::LintTemplate(args) => {
+            let path = Path::new(&args.path);
+            let (html_path, base_dir) = match path.is_dir() {
+                true => (path.join("template.html"), path.to_path_buf()),
+                false => (
+                    path.to_path_buf(),
+                    path.parent().unwrap_or(Path::new(".")).to_path_buf(),
+                ),
+            };
+            let html = std::fs::read_to_string(&html_path)
+                .expect("Error opening the template file path you specified\n");
+            let text = match (args.text, path.is_dir()) {
+                (Some(text_path), _) => Some(
+                    std::fs::read_to_string(text_path)
+                        .expect("Error opening the text body file path you specified\n"),
+                ),
+                (None, true) => std::fs::read_to_string(path.join("text_body.txt")).ok(),
+                (None, false) => None,
+            };
+            let issues = lint_template(
+                &html,
+                text.as_deref(),
+                Some(&base_dir),
+                args.check_remote_images,
+            )
+            .await;
+            let passed = print_issues(&args.path, &issues);
+            if let Some(output) = args.inline_css {
+                std::fs::write(&output, inline_css(&html))
+                    .expect("Error while writing the inlined template\n");
+                println!(
+                    "The template with inlined CSS is written to {}\n",
+                    output.green().bold()
+                );
+            }
+            if !passed {
+                std::process::exit(1);
+            }
+        }
+        SesCommands::TemplateFamily(command) => {
+            let config = load_sdk_config().await;
+            match command {
+                TemplateFamilyCommands::List => print_families(&config).await,
+                TemplateFamilyCommands::Check { family } => {
+                    if !check_family(&config, &family).await {
+                        std::process::exit(1);
+                    }
+                }
+            }
+        }
+        SesCommands::SendBulkTemplated(args) => {
+            let config = load_sdk_config().await;
+            let ses_ops = SesOps::build(config.clone());
+            let send_options = args.send_options.into_send_options();
+            send_bulk_templated(
+                &config,
+                &args.list.unwrap_or(ses_ops.get_list_name().into()),
+                &args.from.unwrap_or(ses_ops.get_from_address().into()),
+                &args.template.unwrap_or(ses_ops.get_template_name().into()),
+                &send_options,
+            )
+            .await;
+        }
+    }
+}
+
+// The subcommands can't ask which credentials to use, so the .env values are preferred
+// and the shared credential file is the fallback
+async fn load_sdk_config() -> SdkConfig {
+    let mut credential = CredentInitialize::default();
+    dotenv().ok();
+    match (
+        var("AWS_ACCESS_KEY_ID"),
+        var("AWS_SECRET_ACCESS_KEY"),
+        var("AWS_DEFAULT_REGION"),
+    ) {
+        (Ok(access_key), Ok(secret_key), Ok(region)) => {
+            credential.update(&access_key, &secret_key, Some(&region));
+        }
+        _ => {
+            let (credentials, region) = load_credential_from_env().await;
+            credential.update(
+                credentials.access_key_id(),
+                credentials.secret_access_key(),
+                region.as_deref(),
+            );
+        }
+    }
+    credential.build()
+}
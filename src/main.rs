@@ -10,11 +10,61 @@ use aws_apis::{
     load_credential_from_env, CredentInitialize, MemDbOps, RdsOps, S3Ops, SesOps, SimpleMail,
     Simple_, TemplateMail, Template_,
 };
+use clap::Parser;
 use dotenv::dotenv;
 use reqwest::get;
+use s3::copy::{copy_objects, CopyOptions};
+use s3::cors::manage_cors;
+use s3::creation::create_bucket_interactively;
+use s3::deletion::{delete_selection, empty_and_delete_bucket, DeleteSelection};
+use s3::download::{download_object, parse_range, DownloadOptions};
+use s3::encryption::{manage_bucket_encryption, prompt_download_key, Encryption};
+use s3::images::{prompt_pipeline, scratch_dir, upload_image_variants};
+use s3::lifecycle::manage_rules;
+use s3::listing::{browse, ObjectFilter};
+use s3::pickers::{bucket_names, pick_bucket, pick_folder, pick_key, pick_object};
+use s3::manifest::{prefix_manifest, read_manifest, verify_manifest, write_manifest};
+use s3::metadata::{manage_metadata, prompt_object_metadata};
+use s3::policy::manage_policy;
+use s3::presign::{
+    deliver_post, deliver_url, presign_get, presign_post, presign_put, prompt_expiry,
+    prompt_post_conditions, prompt_url_output,
+};
+use s3::usage::{print_usage, usage_report, write_report, PriceTable};
+use s3::versioning::{manage_versions, set_versioning, versioning_status};
+use s3::multipart::{
+    abort_uploads, list_incomplete_uploads, print_incomplete_uploads, prompt_upload_settings,
+    saved_uploads, upload_file, UploadSettings,
+};
+use s3::sync::{sync, SyncDirection, SyncOptions, DEFAULT_JOBS};
+use s3::thumbnails::{make_thumbnails, ThumbnailOptions};
+use s3::website::manage_website;
+use ses::configuration_sets::{
+    add_event_destination, create_configuration_set, delete_configuration_set,
+    list_configuration_sets, parse_event_types, pick_configuration_set, print_event_destinations,
+    EventDestinationKind, EVENT_TYPES,
+};
+use ses::dns_check::{check_domain, print_report, ExpectedRecords, RecordSource};
+use ses::sending::{prompt_send_options, send_bulk_simple, send_bulk_templated};
+use ses::template_lint::review_before_upload;
+use ses::template_variants::{check_family, print_families, read_and_resolve_template};
+use ses::template_repo::{
+    backed_up_template_names, backups, local_template_names, pull, push, rollback, template_dir, update_with_backup,
+    TemplateFiles,
+};
 use std::env::var;
+
+mod cli;
+mod s3;
+mod ses;
+
 #[tokio::main]
 async fn main() {
+    //Any argument runs the matching subcommand instead of the interactive menu
+    if std::env::args().len() > 1 {
+        cli::run(cli::Cli::parse()).await;
+        return;
+    }
     inquire::set_global_render_config(global_render_config());
     let operations: Vec<&str> = vec![
         "Verify the Credential\n",
@@ -31,6 +81,8 @@ async fn main() {
     let mut ses_ops: SesOps = SesOps::build(credential.build());
     let mut rds_ops: RdsOps = RdsOps::build(credential.build());
     let mut memdb_ops: MemDbOps = MemDbOps::build(credential.build());
+    //Used for the operations that talk to the AWS SDK clients directly
+    let mut sdk_config = credential.build();
     'main: loop {
         let choice = Select::new(
             "Select the option to execute the operation\n",
@@ -64,6 +116,7 @@ async fn main() {
                         s3_ops = S3Ops::build(config.clone());
                         rds_ops = RdsOps::build(config.clone());
                         memdb_ops = MemDbOps::build(config.clone());
+                        sdk_config = config;
                         println!("{}\n","Please verify the credentials by printing the credential information before proceeding with any operations".blue().bold());
                     }
                     false => {
@@ -80,6 +133,7 @@ async fn main() {
                         s3_ops = S3Ops::build(config.clone());
                         rds_ops = RdsOps::build(config.clone());
                         memdb_ops = MemDbOps::build(config.clone());
+                        sdk_config = config;
                         println!("{}\n","Please verify the credentials by printing the credential information before proceeding with any operations".red().bold());
                     }
                 }
@@ -123,9 +177,19 @@ async fn main() {
                     "Create Email Identity\n",
                     "Email Verification\n",
                     "Get Email Identities\n",
+                    "Check DNS Records of a Domain Identity\n",
                     "Update Email Template\n",
+                    "Push Local Templates to SES\n",
+                    "Pull Templates from SES\n",
+                    "Rollback Email Template\n",
+                    "List Template Families\n",
+                    "Check Template Family Variables\n",
                     "Delete Template\n",
                     "Delete Contact List Name\n",
+                    "Create Configuration Set\n",
+                    "Add Event Destination to a Configuration Set\n",
+                    "List Configuration Sets\n",
+                    "Delete Configuration Set\n",
                     "Common Errors\n",
                     "Return to the Main Menu\n",
                 ];
@@ -204,7 +268,7 @@ async fn main() {
                                         .read_to_string(&mut subject_data)
                                         .expect("Error while reading data\n");
 
-                                    match text_path.is_empty() {
+                                    let text_data = match text_path.is_empty() {
                                         false => {
                                             let mut reading_text_data = OpenOptions::new()
                                                                  .read(true)
@@ -217,26 +281,26 @@ async fn main() {
                                                 .expect(
                                                     "Error opening the file path you specified\n",
                                                 );
-
-                                            ses_ops
-                                                .create_email_template(
-                                                    &template_name,
-                                                    &subject_data,
-                                                    &template_data,
-                                                    Some(text_data),
-                                                )
-                                                .await;
-                                        }
-                                        true => {
-                                            ses_ops
-                                                .create_email_template(
-                                                    &template_name,
-                                                    &subject_data,
-                                                    &template_data,
-                                                    None,
-                                                )
-                                                .await;
+                                            Some(text_data)
                                         }
+                                        true => None,
+                                    };
+                                    //The template is linted and optionally has its CSS inlined before uploading
+                                    if let Some(template_data) = review_before_upload(
+                                        &template_name,
+                                        &template_data,
+                                        text_data.as_deref(),
+                                    )
+                                    .await
+                                    {
+                                        ses_ops
+                                            .create_email_template(
+                                                &template_name,
+                                                &subject_data,
+                                                &template_data,
+                                                text_data,
+                                            )
+                                            .await;
                                     }
                                 }
                                 _ => {
@@ -265,6 +329,11 @@ async fn main() {
                                                 false,
                                             )
                                             .await;
+                                    let remote_version = TemplateFiles::new(
+                                        &current_subject,
+                                        &current_template_html,
+                                        Some(&current_text),
+                                    );
                                     let current_subject = format!(
                                         "Your current email template subject is:\n {}",
                                         current_subject
@@ -320,7 +389,7 @@ async fn main() {
                                         .read_to_string(&mut subject_data)
                                         .expect("Error while reading subject data\n");
 
-                                    match text_path.is_empty() {
+                                    let text = match text_path.is_empty() {
                                         false => {
                                             let mut read_text_data = OpenOptions::new()
                                     .read(true)
@@ -331,25 +400,32 @@ async fn main() {
                                             read_text_data
                                                 .read_to_string(&mut text)
                                                 .expect("Error While Reading to String ");
-                                            ses_ops
-                                                .update_template(
-                                                    &template_name,
-                                                    &subject_data,
-                                                    &template_data,
-                                                    Some(text),
-                                                )
-                                                .await;
-                                        }
-                                        true => {
-                                            ses_ops
-                                                .update_template(
-                                                    &template_name,
-                                                    &subject_data,
-                                                    &template_data,
-                                                    None,
-                                                )
-                                                .await;
+                                            Some(text)
                                         }
+                                        true => None,
+                                    };
+                                    if let Some(template_data) = review_before_upload(
+                                        &template_name,
+                                        &template_data,
+                                        text.as_deref(),
+                                    )
+                                    .await
+                                    {
+                                        //The diff is shown and the replaced version is kept as a backup before updating
+                                        let new_version = TemplateFiles::new(
+                                            &subject_data,
+                                            &template_data,
+                                            text.as_deref(),
+                                        );
+                                        update_with_backup(
+                                            &ses_ops,
+                                            &template_dir(),
+                                            &template_name,
+                                            &remote_version,
+                                            &new_version,
+                                            false,
+                                        )
+                                        .await;
                                     }
                                 }
                                 true => {
@@ -357,6 +433,109 @@ async fn main() {
                                 }
                             }
                         }
+                        "Push Local Templates to SES\n" => {
+                            let root = template_dir();
+                            let local_names = local_template_names(&root);
+                            let placeholder_info = format!(
+                                "Templates found in '{}':\n{:#?}",
+                                root.display(),
+                                local_names
+                            );
+                            let names = Text::new("Enter the template names to push, separated by commas, or press Enter to push all of them\n")
+                                .with_placeholder(&placeholder_info)
+                                .with_formatter(&|str| format!(".....{str}.....\n"))
+                                .with_help_message("Each template is a folder with 'subject.html', 'template.html' and an optional 'text_body.txt'")
+                                .prompt_skippable()
+                                .unwrap()
+                                .unwrap_or_default();
+                            let names = names
+                                .split(',')
+                                .map(|name| name.trim().to_string())
+                                .filter(|name| !name.is_empty())
+                                .collect();
+                            let inline = Confirm::new("Inline the CSS of the <style> blocks into style attributes before pushing?\n")
+                                .with_default(false)
+                                .with_help_message("The local files are left unchanged")
+                                .prompt()
+                                .unwrap();
+                            push(&ses_ops, &root, names, false, inline).await;
+                        }
+                        "Pull Templates from SES\n" => {
+                            let get_available_template_names = ses_ops.list_email_templates().await;
+                            let placeholder_info = format!(
+                                "Available Template Names in Your Credentials\n{:#?}",
+                                get_available_template_names
+                            );
+                            let names = Text::new("Enter the template names to pull, separated by commas, or press Enter to pull all of them\n")
+                                .with_placeholder(&placeholder_info)
+                                .with_formatter(&|str| format!(".....{str}.....\n"))
+                                .prompt_skippable()
+                                .unwrap()
+                                .unwrap_or_default();
+                            let names = names
+                                .split(',')
+                                .map(|name| name.trim().to_string())
+                                .filter(|name| !name.is_empty())
+                                .collect();
+                            pull(&ses_ops, &template_dir(), names, false).await;
+                        }
+                        "Rollback Email Template\n" => {
+                            let root = template_dir();
+                            let backed_up_names = backed_up_template_names(&root);
+                            if backed_up_names.is_empty() {
+                                println!(
+                                    "{}\n",
+                                    format!("There are no template backups in '{}'", root.display())
+                                        .yellow()
+                                        .bold()
+                                );
+                                continue;
+                            }
+                            let template_name = Select::new(
+                                "Select the template to roll back\n",
+                                backed_up_names,
+                            )
+                            .with_help_message("Templates deleted from SES are created again from the backup")
+                            .prompt()
+                            .unwrap();
+                            let available_backups = backups(&root, &template_name);
+                            match available_backups.is_empty() {
+                                false => {
+                                    let timestamp = Select::new(
+                                        "Select the backup to restore\n",
+                                        available_backups,
+                                    )
+                                    .with_help_message("Backups are named after the time they were taken (UTC)")
+                                    .prompt()
+                                    .unwrap();
+                                    rollback(&ses_ops, &root, &template_name, Some(timestamp), false)
+                                        .await;
+                                }
+                                true => println!(
+                                    "{}\n",
+                                    "There are no backups for this template yet".yellow().bold()
+                                ),
+                            }
+                        }
+                        "List Template Families\n" => {
+                            print_families(&sdk_config).await;
+                        }
+                        "Check Template Family Variables\n" => {
+                            let family_name = Text::new("Enter the template family name, e.g. 'welcome' for 'welcome__en' and 'welcome__de'\n")
+                                .with_formatter(&|str| format!(".....{str}.....\n"))
+                                .with_help_message("Every variant is compared with the DEFAULT_LOCALE variant ('en' unless set in the .env file)")
+                                .prompt()
+                                .unwrap();
+                            match family_name.is_empty() {
+                                false => {
+                                    check_family(&sdk_config, &family_name).await;
+                                }
+                                true => println!(
+                                    "{}\n",
+                                    "Template family name can't be empty".red().bold()
+                                ),
+                            }
+                        }
                         "Get Email Template\n" => {
                             let get_available_template_names = ses_ops.list_email_templates().await;
                             let placeholder_info = format!(
@@ -693,6 +872,7 @@ async fn main() {
                                             true => None,
                                             false => Some(from_address.as_str()),
                                         };
+                                        let send_options = prompt_send_options();
                                         match (subject.is_empty(), body_info) {
                                             (false, true) => {
                                                 let body_path = Text::new("Please provide the path to the body of a simple email content file\n")
@@ -717,6 +897,8 @@ async fn main() {
                                                 ses_ops
                                 .send_mono_email(&email, Simple_(simple_email),from_address)
                                 .await
+                                .set_configuration_set_name(send_options.configuration_set_name())
+                                .set_email_tags(send_options.email_tags())
                                 .send()
                                 .await
                                 .map(|_|{
@@ -746,6 +928,8 @@ async fn main() {
                                  ses_ops
                                 .send_mono_email(&email, Simple_(simple_email),from_address)
                                 .await
+                                .set_configuration_set_name(send_options.configuration_set_name())
+                                .set_email_tags(send_options.email_tags())
                                 .send()
                                 .await
                                 .map(|_|{
@@ -773,6 +957,31 @@ async fn main() {
                             ses_ops.writing_email_identies_details_as_text_pdf().await;
                             println!("{}\n","This option only returns the emails that are created either via the 'Create Email Identity' option or\nby choosing 'yes' in the 'Add an Email to the list' option when asked to send a verification email".yellow().bold());
                         }
+                        "Check DNS Records of a Domain Identity\n" => {
+                            let domain = Text::new("Enter the domain identity to check\n")
+                                .with_placeholder("The DKIM tokens are read from SES, so the domain must be created through the 'Create Email Identity' option\n")
+                                .with_formatter(&|str| format!(".....{str}.....\n"))
+                                .prompt()
+                                .unwrap();
+                            let zone_file = Text::new("Please provide the path to a zone file, or press Enter to use the system resolver\n")
+                                .with_placeholder("A BIND-style zone file lets you check the records before publishing them\n")
+                                .with_formatter(&|str| format!(".....{str}.....\n"))
+                                .prompt_skippable()
+                                .unwrap()
+                                .unwrap();
+                            match domain.is_empty() {
+                                false => {
+                                    let expected =
+                                        ExpectedRecords::from_ses(&sdk_config, &domain).await;
+                                    let source = match zone_file.is_empty() {
+                                        false => RecordSource::zone_file(&zone_file),
+                                        true => RecordSource::system(),
+                                    };
+                                    print_report(&check_domain(&expected, &source).await);
+                                }
+                                true => println!("{}\n", "Domain can't be empty".red().bold()),
+                            }
+                        }
                         "Send a Single Templated Email\n" => {
                             let get_from_address = ses_ops.get_from_address();
                             let get_template_name = ses_ops.get_template_name();
@@ -817,6 +1026,7 @@ async fn main() {
                             .with_placeholder(&placeholder_info)
                             .prompt()
                             .unwrap();
+                                        let send_options = prompt_send_options();
 
                                         match (
                                             template_name.is_empty(),
@@ -824,20 +1034,10 @@ async fn main() {
                                             template_path.is_empty(),
                                         ) {
                                             (false, false, false) => {
-                                                let mut reading_template_data = OpenOptions::new()
-                                                    .read(true)
-                                                    .write(true)
-                                                    .open(&template_path)
-                                                    .expect(
-                                                        "Error opening the file path you specified",
-                                                    );
-                                                let mut template_data = String::new();
-                                                reading_template_data
-                                                    .read_to_string(&mut template_data)
-                                                    .expect("Error while reading data\n");
-
+                                                let (resolved_template, template_data) =
+                                                    read_and_resolve_template(&sdk_config, &template_name, &template_path).await;
                                                 let email_content = TemplateMail::builder(
-                                                    &template_name,
+                                                    &resolved_template,
                                                     &template_data,
                                                 )
                                                 .build();
@@ -848,6 +1048,8 @@ async fn main() {
                                                         Some(&from_address),
                                                     )
                                                     .await
+                                                    .set_configuration_set_name(send_options.configuration_set_name())
+                                                    .set_email_tags(send_options.email_tags())
                                                     .send()
                                                     .await
                                                     .map(|_| {
@@ -861,19 +1063,10 @@ async fn main() {
                                             }
                                             (false, true, false) => {
                                                 if email_contacts.contains(&email) {
-                                                    let mut reading_template_data = OpenOptions::new()
-                                    .read(true)
-                                    .write(true)
-                                    .open(&template_path)
-                                    .expect(
-                                        "Error opening the Template file path you specified",
-                                    );
-                                                    let mut template_data = String::new();
-                                                    reading_template_data
-                                                        .read_to_string(&mut template_data)
-                                                        .expect("Error while reading data\n");
+                                                    let (resolved_template, template_data) =
+                                                        read_and_resolve_template(&sdk_config, &template_name, &template_path).await;
                                                     let email_content = TemplateMail::builder(
-                                                        &template_name,
+                                                        &resolved_template,
                                                         &template_data,
                                                     )
                                                     .build();
@@ -884,6 +1077,8 @@ async fn main() {
                                                             None,
                                                         )
                                                         .await
+                                                        .set_configuration_set_name(send_options.configuration_set_name())
+                                                        .set_email_tags(send_options.email_tags())
                                                         .send()
                                                         .await
                                                         .map(|_| {
@@ -902,19 +1097,10 @@ async fn main() {
                                                 }
                                             }
                                             (true, true, false) => {
-                                                let mut reading_template_data = OpenOptions::new()
-                                                    .read(true)
-                                                    .write(true)
-                                                    .open(&template_path)
-                                                    .expect(
-                                                        "Error opening the file path you specified",
-                                                    );
-                                                let mut template_data = String::new();
-                                                reading_template_data
-                                                    .read_to_string(&mut template_data)
-                                                    .expect("Error while reading data\n");
+                                                let (resolved_template, template_data) =
+                                                    read_and_resolve_template(&sdk_config, &get_template_name, &template_path).await;
                                                 let email_content = TemplateMail::builder(
-                                                    &get_template_name,
+                                                    &resolved_template,
                                                     &template_data,
                                                 )
                                                 .build();
@@ -925,6 +1111,8 @@ async fn main() {
                                                         None,
                                                     )
                                                     .await
+                                                    .set_configuration_set_name(send_options.configuration_set_name())
+                                                    .set_email_tags(send_options.email_tags())
                                                     .send()
                                                     .await
                                                     .map(|_| {
@@ -937,19 +1125,10 @@ async fn main() {
                                                     .expect("Error while sending template mail\n");
                                             }
                                             (true, false, false) => {
-                                                let mut reading_template_data = OpenOptions::new()
-                                                    .read(true)
-                                                    .write(true)
-                                                    .open(&template_path)
-                                                    .expect(
-                                                        "Error opening the file path you specified",
-                                                    );
-                                                let mut template_data = String::new();
-                                                reading_template_data
-                                                    .read_to_string(&mut template_data)
-                                                    .expect("Error while reading data\n");
+                                                let (resolved_template, template_data) =
+                                                    read_and_resolve_template(&sdk_config, &get_template_name, &template_path).await;
                                                 let email_content = TemplateMail::builder(
-                                                    &get_template_name,
+                                                    &resolved_template,
                                                     &template_data,
                                                 )
                                                 .build();
@@ -960,6 +1139,8 @@ async fn main() {
                                                         Some(&from_address),
                                                     )
                                                     .await
+                                                    .set_configuration_set_name(send_options.configuration_set_name())
+                                                    .set_email_tags(send_options.email_tags())
                                                     .send()
                                                     .await
                                                     .map(|_| {
@@ -1015,6 +1196,15 @@ async fn main() {
                                 .prompt_skippable()
                                 .unwrap()
                                 .unwrap();
+                            let list_name = match list_name.is_empty() {
+                                false => list_name,
+                                true => get_list_name.to_string(),
+                            };
+                            let from_address = match from_address.is_empty() {
+                                false => from_address,
+                                true => get_from_address.to_string(),
+                            };
+                            let send_options = prompt_send_options();
 
                             match (subject.is_empty(), body_info) {
                                 (false, true) => {
@@ -1027,46 +1217,15 @@ async fn main() {
                                     let body_data = std::fs::read_to_string(&body_path).expect(
                                         "Error Opening the simple email file path you specified\n",
                                     );
-                                    let simple_data = SimpleMail::builder(&body_data, &subject);
-
-                                    match (list_name.is_empty(), from_address.is_empty()) {
-                                        (false, false) => {
-                                            ses_ops
-                                                .send_multi_email_with_simple(
-                                                    simple_data,
-                                                    Some(&from_address),
-                                                    Some(&list_name),
-                                                )
-                                                .await;
-                                        }
-                                        (true, true) => {
-                                            ses_ops
-                                                .send_multi_email_with_simple(
-                                                    simple_data,
-                                                    None,
-                                                    None,
-                                                )
-                                                .await;
-                                        }
-                                        (false, true) => {
-                                            ses_ops
-                                                .send_multi_email_with_simple(
-                                                    simple_data,
-                                                    None,
-                                                    Some(&list_name),
-                                                )
-                                                .await;
-                                        }
-                                        (true, false) => {
-                                            ses_ops
-                                                .send_multi_email_with_simple(
-                                                    simple_data,
-                                                    Some(&from_address),
-                                                    None,
-                                                )
-                                                .await;
-                                        }
-                                    }
+                                    send_bulk_simple(
+                                        &sdk_config,
+                                        &list_name,
+                                        &from_address,
+                                        &subject,
+                                        &body_data,
+                                        &send_options,
+                                    )
+                                    .await;
                                 }
                                 (false, false) => {
                                     let body_link = Text::new("Please provide the link to the body of a simple email content file\n")
@@ -1080,30 +1239,8 @@ async fn main() {
                                 let body_data = body.text().await.expect("Error while getting text data\n");
                                 let x: &[_] = &['\n','\r',' ','\x1b','\u{20}','\u{7f}','\u{80}'];
                                 let body_data = body_data.trim_matches(x);
-                                let simple_data = SimpleMail::builder(&body_data, &subject);
-                                match (list_name.is_empty(),from_address.is_empty()){
-                                    (false,false) => {
-                                         ses_ops
-                                        .send_multi_email_with_simple(simple_data, Some(&from_address), Some(&list_name))
-                                        .await; 
-                                    }
-                                    (true,true) =>{
-                                        ses_ops
-                                        .send_multi_email_with_simple(simple_data, None, None)
-                                        .await;
-                                    }
-                                    (false,true) =>{
-                                        ses_ops
-                                        .send_multi_email_with_simple(simple_data, None, Some(&list_name))
-                                        .await;
-                                    }
-                                    (true,false) =>{
-                                       ses_ops
-                                        .send_multi_email_with_simple(simple_data, Some(&from_address), None)
-                                        .await;
-                                    }
-                                  }
-
+                                send_bulk_simple(&sdk_config, &list_name, &from_address, &subject, body_data, &send_options)
+                                .await;
                             }
                             Err(_) => println!("{}\n","The provided link doesn't seem to be working. Could you please check the link and try again?".red().bold())
                         }
@@ -1131,7 +1268,15 @@ async fn main() {
                                         get_list_name.green().bold(),
                                         get_from_address.green().bold()
                                     );
-                                    ses_ops.send_bulk_templated_emails().await;
+                                    let send_options = prompt_send_options();
+                                    send_bulk_templated(
+                                        &sdk_config,
+                                        &get_list_name,
+                                        &get_from_address,
+                                        &get_template_name,
+                                        &send_options,
+                                    )
+                                    .await;
                                 }
                                 _ => {
                                     println!(
@@ -1149,6 +1294,125 @@ async fn main() {
                                 }
                             }
                         }
+                        "Create Configuration Set\n" => {
+                            let available_configuration_sets =
+                                list_configuration_sets(&sdk_config).await;
+                            let placeholder_info = format!(
+                                "These configuration sets are already in your account:\n{:#?}",
+                                available_configuration_sets
+                            );
+                            let name = Text::new("Please provide the name of the new configuration set\n")
+                                .with_placeholder(&placeholder_info)
+                                .with_formatter(&|str| format!(".....{str}.....\n"))
+                                .with_help_message("Set 'CONFIGURATION_SET' in the .env file to attach it to every email by default")
+                                .prompt()
+                                .unwrap();
+                            match name.is_empty() {
+                                false => create_configuration_set(&sdk_config, &name).await,
+                                true => println!(
+                                    "{}\n",
+                                    "Configuration set name can't be empty".red().bold()
+                                ),
+                            }
+                        }
+                        "Add Event Destination to a Configuration Set\n" => {
+                            let Some(configuration_set) = pick_configuration_set(&sdk_config, "Select the configuration set\n").await else {
+                                continue;
+                            };
+                            print_event_destinations(&sdk_config, &configuration_set).await;
+                            let destination_name =
+                                Text::new("Please provide a name for the event destination\n")
+                                    .with_placeholder("The name must be unique within the configuration set\n")
+                                    .with_formatter(&|str| format!(".....{str}.....\n"))
+                                    .prompt()
+                                    .unwrap();
+                            let event_types = Text::new("Enter the event types to publish, separated by commas\n")
+                                .with_placeholder(&EVENT_TYPES.join(","))
+                                .with_formatter(&|str| format!(".....{str}.....\n"))
+                                .with_validator(|input: &str| match parse_event_types(input) {
+                                    Ok(_) => Ok(inquire::validator::Validation::Valid),
+                                    Err(message) => {
+                                        Ok(inquire::validator::Validation::Invalid(message.into()))
+                                    }
+                                })
+                                .prompt()
+                                .unwrap();
+                            let destination_type = Select::new(
+                                "Where should the events be published?\n",
+                                vec!["SNS Topic", "CloudWatch Metrics", "Kinesis Firehose"],
+                            )
+                            .prompt()
+                            .unwrap();
+                            let kind = match destination_type {
+                                "SNS Topic" => {
+                                    let topic_arn = Text::new("Enter the SNS topic ARN\n")
+                                        .with_formatter(&|str| format!(".....{str}.....\n"))
+                                        .prompt()
+                                        .unwrap();
+                                    EventDestinationKind::Sns { topic_arn }
+                                }
+                                "CloudWatch Metrics" => {
+                                    let dimension_name = Text::new("Enter the message tag name to use as the CloudWatch dimension\n")
+                                        .with_placeholder("Messages without this tag are counted under the default value\n")
+                                        .with_formatter(&|str| format!(".....{str}.....\n"))
+                                        .prompt()
+                                        .unwrap();
+                                    let default_value = Text::new("Enter the default dimension value\n")
+                                        .with_formatter(&|str| format!(".....{str}.....\n"))
+                                        .prompt()
+                                        .unwrap();
+                                    EventDestinationKind::CloudWatch {
+                                        dimension_name,
+                                        default_value,
+                                    }
+                                }
+                                _ => {
+                                    let delivery_stream_arn = Text::new("Enter the Firehose delivery stream ARN\n")
+                                        .with_formatter(&|str| format!(".....{str}.....\n"))
+                                        .prompt()
+                                        .unwrap();
+                                    let iam_role_arn = Text::new("Enter the IAM role ARN that allows SES to write to the stream\n")
+                                        .with_formatter(&|str| format!(".....{str}.....\n"))
+                                        .prompt()
+                                        .unwrap();
+                                    EventDestinationKind::Firehose {
+                                        delivery_stream_arn,
+                                        iam_role_arn,
+                                    }
+                                }
+                            };
+                            match destination_name.is_empty() {
+                                false => {
+                                    add_event_destination(
+                                        &sdk_config,
+                                        &configuration_set,
+                                        &destination_name,
+                                        parse_event_types(&event_types).unwrap(),
+                                        kind,
+                                    )
+                                    .await;
+                                }
+                                true => println!("{}\n", "Fields should not be left empty".red().bold()),
+                            }
+                        }
+                        "List Configuration Sets\n" => {
+                            for name in list_configuration_sets(&sdk_config).await {
+                                println!("{}\n", name.blue().bold());
+                                print_event_destinations(&sdk_config, &name).await;
+                            }
+                        }
+                        "Delete Configuration Set\n" => {
+                            let Some(configuration_set) = pick_configuration_set(&sdk_config, "Select the configuration set to delete\n").await else {
+                                continue;
+                            };
+                            let confirm = Confirm::new(&format!("Are you sure you want to delete '{configuration_set}' along with its event destinations?\n"))
+                                .with_default(false)
+                                .prompt()
+                                .unwrap();
+                            if confirm {
+                                delete_configuration_set(&sdk_config, &configuration_set).await;
+                            }
+                        }
                         "Common Errors\n" => {
                             let possible_errors = include_str!("./possible_errors.txt")
                                 .yellow()
@@ -1168,13 +1432,31 @@ async fn main() {
                     "Create Bucket\n",
                     "Default Region Name\n",
                     "Put object in a Bucket\n",
+                    "Resume Interrupted Uploads\n",
+                    "Abort Incomplete Multipart Uploads\n",
+                    "Sync a Local Folder with a Bucket\n",
                     "Modifying Object Visibility\n",
                     "List objects from a Bucket\n",
                     "Download object from bucket\n",
-                    "Retrieve a presigned URL for an object\n",
+                    "Presigned URLs and Upload Forms\n",
                     "Get Bucket Lists\n",
                     "Delete object from a bucket\n",
+                    "Delete Many Objects by Keys, Prefix or Glob\n",
                     "Delete Bucket\n",
+                    "Empty and Delete a Bucket\n",
+                    "Bucket Versioning\n",
+                    "Object Versions\n",
+                    "Lifecycle Rules\n",
+                    "Bucket Policy and Block Public Access\n",
+                    "Default Bucket Encryption\n",
+                    "Object Metadata and Tags\n",
+                    "Upload an Image with Resized Variants\n",
+                    "Thumbnails and Contact Sheets for Images\n",
+                    "Copy or Move Objects\n",
+                    "Static Website Hosting\n",
+                    "CORS Rules\n",
+                    "Storage Usage and Cost Report\n",
+                    "Checksum Manifests\n",
                     "Return to the Main Menu\n",
                 ];
 
@@ -1183,35 +1465,12 @@ async fn main() {
                         "Select the option to execute the operation\n",
                         s3_operations.clone(),
                     )
-                    .with_page_size(10)
+                    .with_page_size(14)
                     .prompt()
                     .unwrap();
                     match s3_choices {
                         "Create Bucket\n" => {
-                            let get_bucket_lists = s3_ops.get_buckets().await;
-                            let existing_buckets = format!(
-                                "These buckets are already in your account: {:#?}",
-                                get_bucket_lists
-                            );
-                            let bucket_name = Text::new("Please input the name of the bucket\n")
-                                .with_placeholder(
-                                    &existing_buckets
-                                )
-                                .with_help_message("The name must begin with a lowercase letter and should be unique\nAn AWS bucket is a type of object storage designed for storing objects")
-                                .with_formatter(&|str| format!("The Bucket name is: {str} and assumes that region name is provided"))
-                                .prompt()
-                                .unwrap();
-                            match bucket_name.is_empty() {
-                                false => {
-                                    s3_ops.create_bucket(&bucket_name).await;
-                                }
-                                true => println!(
-                                    "{}\n",
-                                    "The bucket name can't be empty; please try again"
-                                        .red()
-                                        .bold()
-                                ),
-                            }
+                            create_bucket_interactively(&sdk_config).await;
                         }
                         "Default Region Name\n" => {
                             let default_region_name = var("REGION").unwrap_or("The region value is read from the .env file in the current directory if it is not provided in the credential file".into());
@@ -1225,102 +1484,345 @@ async fn main() {
                         }
 
                         "List objects from a Bucket\n" => {
-                            let get_bucket_name = s3_ops.get_buckets().await;
-                            let bucket_names =
-                                format!("Available buckets are:\n{:#?}\n", get_bucket_name);
-
-                            let bucket_name = Text::new("Please input the name of the bucket\n")
-                                .with_placeholder(&bucket_names)
+                            let Some(bucket_name) = pick_bucket(&sdk_config, "Select the bucket to list\n").await else {
+                                continue 's3_ops;
+                            };
+                            let prefix = Text::new("Start browsing from this prefix, or leave it empty for the top level\n")
+                                .with_placeholder("photos/2024/")
                                 .with_formatter(&|str| format!(".....{str}.....\n"))
                                 .prompt()
                                 .unwrap();
+                            let filter = Text::new("Only show objects matching these conditions, or leave it empty to show all\n")
+                                .with_placeholder("size>10MB modified<30d class=STANDARD_IA")
+                                .with_validator(|input: &str| match ObjectFilter::parse(input) {
+                                    Ok(_) => Ok(inquire::validator::Validation::Valid),
+                                    Err(error) => Ok(inquire::validator::Validation::Invalid(error.into())),
+                                })
+                                .with_formatter(&|str| format!(".....{str}.....\n"))
+                                .prompt()
+                                .unwrap();
+                            let filter = ObjectFilter::parse(&filter).unwrap();
+                            browse(&sdk_config, &bucket_name, &prefix, &filter, 100).await;
+                        }
 
-                            match bucket_name.is_empty() {
-                                false => {
-                                    let object_names =
-                                        s3_ops.retrieve_keys_in_a_bucket(&bucket_name).await;
-                                    for object in object_names {
-                                        let colored_key_name = object.green().bold();
-                                        println!("    {}\n", colored_key_name);
+                        "Delete object from a bucket\n" => {
+                            let Some((bucket_name, object_name)) = pick_object(&sdk_config, "Select the object to delete\n").await else {
+                                continue 's3_ops;
+                            };
+                            let confirm = Confirm::new(&format!("Delete s3://{bucket_name}/{object_name}?\n"))
+                                .with_default(false)
+                                .prompt()
+                                .unwrap();
+                            if confirm {
+                                s3_ops
+                                    .delete_content_in_a_bucket(&bucket_name, &object_name)
+                                    .await;
+                            }
+                        }
+
+                        "Delete Many Objects by Keys, Prefix or Glob\n" => {
+                            let Some(bucket_name) = pick_bucket(&sdk_config, "Select the bucket to delete the objects from\n").await else {
+                                continue 's3_ops;
+                            };
+                            let mode = Select::new(
+                                "How do you want to choose the objects?\n",
+                                vec!["A list of keys", "Everything under a prefix", "Keys matching a glob"],
+                            )
+                            .prompt()
+                            .unwrap();
+                            let input = Text::new("Enter the keys, the prefix or the glob\n")
+                                .with_placeholder(match mode {
+                                    "A list of keys" => "Separate the keys with commas, e.g. logs/a.txt,logs/b.txt",
+                                    "Everything under a prefix" => "e.g. tmp/2023/",
+                                    _ => "e.g. logs/**/*.gz",
+                                })
+                                .with_formatter(&|str| format!(".....{str}.....\n"))
+                                .prompt()
+                                .unwrap();
+                            let selection = match mode {
+                                "A list of keys" => DeleteSelection::Keys(
+                                    input
+                                        .split(',')
+                                        .map(|key| key.trim().to_string())
+                                        .filter(|key| !key.is_empty())
+                                        .collect(),
+                                ),
+                                "Everything under a prefix" => DeleteSelection::Prefix(input.trim().to_string()),
+                                _ => DeleteSelection::Glob(input.trim().to_string()),
+                            };
+                            delete_selection(&sdk_config, &bucket_name, &selection, false).await;
+                        }
+                        "Empty and Delete a Bucket\n" => {
+                            let Some(bucket_name) = pick_bucket(&sdk_config, "Select the bucket to empty and delete\n").await else {
+                                continue 's3_ops;
+                            };
+                            empty_and_delete_bucket(&sdk_config, &bucket_name, false).await;
+                        }
+                        "Bucket Versioning\n" => {
+                            let Some(bucket_name) = pick_bucket(&sdk_config, "Select the bucket\n").await else {
+                                continue 's3_ops;
+                            };
+                            let status = versioning_status(&sdk_config, &bucket_name).await;
+                            println!("Versioning of '{}': {}\n", bucket_name.green().bold(), status.bold());
+                            let choice = Select::new(
+                                "Change the versioning?\n",
+                                vec!["Enable", "Suspend", "Leave it as it is"],
+                            )
+                            .prompt()
+                            .unwrap();
+                            match choice {
+                                "Enable" => set_versioning(&sdk_config, &bucket_name, true).await,
+                                "Suspend" => set_versioning(&sdk_config, &bucket_name, false).await,
+                                _ => {}
+                            }
+                        }
+                        "Object Versions\n" => {
+                            let Some(bucket_name) = pick_bucket(&sdk_config, "Select the bucket\n").await else {
+                                continue 's3_ops;
+                            };
+                            let typed = Text::new("Enter the key or prefix, or press Enter to choose from the current objects\n")
+                                .with_placeholder("Type the key of a deleted object to bring it back")
+                                .with_formatter(&|str| format!(".....{str}.....\n"))
+                                .prompt()
+                                .unwrap();
+                            let key = match typed.trim().is_empty() {
+                                false => typed.trim().to_string(),
+                                true => match pick_key(&sdk_config, &bucket_name, "Select the object\n").await {
+                                    Some(key) => key,
+                                    None => continue 's3_ops,
+                                },
+                            };
+                            manage_versions(&sdk_config, &bucket_name, &key).await;
+                        }
+                        "Lifecycle Rules\n" => {
+                            let Some(bucket_name) = pick_bucket(&sdk_config, "Select the bucket\n").await else {
+                                continue 's3_ops;
+                            };
+                            manage_rules(&sdk_config, &bucket_name).await;
+                        }
+                        "Bucket Policy and Block Public Access\n" => {
+                            let Some(bucket_name) = pick_bucket(&sdk_config, "Select the bucket\n").await else {
+                                continue 's3_ops;
+                            };
+                            manage_policy(&sdk_config, &bucket_name).await;
+                        }
+                        "Upload an Image with Resized Variants\n" => {
+                            let image_path = Text::new("Enter the path of the image\n")
+                                .with_placeholder("PNG, JPEG, WebP, GIF, BMP or TIFF")
+                                .with_formatter(&|str| format!(".....{str}.....\n"))
+                                .prompt()
+                                .unwrap();
+                            let path = std::path::Path::new(image_path.trim());
+                            let Some(stem) = path.file_stem().map(|stem| stem.to_string_lossy().to_string()) else {
+                                println!("{}\n", "The image path can't be empty".red().bold());
+                                continue 's3_ops;
+                            };
+                            let Some(bucket_name) = pick_bucket(&sdk_config, "Select the bucket for the variants\n").await else {
+                                continue 's3_ops;
+                            };
+                            let Some(folder) = pick_folder(&sdk_config, &bucket_name, "Select the folder for the variants\n").await else {
+                                continue 's3_ops;
+                            };
+                            let name = Text::new("Enter the name the variants start with\n")
+                                .with_default(&stem)
+                                .with_help_message("The variants are named like name@800w.webp")
+                                .with_formatter(&|str| format!(".....{str}.....\n"))
+                                .prompt()
+                                .unwrap();
+                            let pipeline = prompt_pipeline();
+                            upload_image_variants(
+                                &sdk_config,
+                                path,
+                                Some((&bucket_name, &folder)),
+                                name.trim(),
+                                &pipeline,
+                                &scratch_dir(name.trim()),
+                                &UploadSettings::from_env(),
+                            )
+                            .await;
+                        }
+                        "Thumbnails and Contact Sheets for Images\n" => {
+                            let Some(bucket_name) = pick_bucket(&sdk_config, "Select the bucket with the images\n").await else {
+                                continue 's3_ops;
+                            };
+                            let Some(prefix) = pick_folder(&sdk_config, &bucket_name, "Select the folder with the images\n").await else {
+                                continue 's3_ops;
+                            };
+                            let Some(destination) = pick_folder(&sdk_config, &bucket_name, "Select the folder for the thumbnails\n").await else {
+                                continue 's3_ops;
+                            };
+                            let destination = Text::new("Enter the folder for the thumbnails and contact sheets\n")
+                                .with_initial_value(&match destination.is_empty() {
+                                    true => format!("{prefix}thumbnails/"),
+                                    false => destination,
+                                })
+                                .with_formatter(&|str| format!(".....{str}.....\n"))
+                                .prompt()
+                                .unwrap();
+                            let destination = match destination.trim().trim_end_matches('/') {
+                                "" => String::new(),
+                                folder => format!("{folder}/"),
+                            };
+                            make_thumbnails(
+                                &sdk_config,
+                                &bucket_name,
+                                &prefix,
+                                (&bucket_name, &destination),
+                                &ThumbnailOptions::default(),
+                                None,
+                                &UploadSettings::from_env(),
+                            )
+                            .await;
+                        }
+                        "CORS Rules\n" => {
+                            let Some(bucket_name) = pick_bucket(&sdk_config, "Select the bucket\n").await else {
+                                continue 's3_ops;
+                            };
+                            manage_cors(&sdk_config, &bucket_name).await;
+                        }
+                        "Checksum Manifests\n" => {
+                            let choice = Select::new(
+                                "What do you want to do?\n",
+                                vec!["Write a manifest of a folder", "Verify the bucket against a manifest"],
+                            )
+                            .prompt()
+                            .unwrap();
+                            match choice {
+                                "Write a manifest of a folder" => {
+                                    let Some(bucket_name) = pick_bucket(&sdk_config, "Select the bucket\n").await else {
+                                        continue 's3_ops;
+                                    };
+                                    let Some(folder) = pick_folder(&sdk_config, &bucket_name, "Select the folder to record\n").await else {
+                                        continue 's3_ops;
+                                    };
+                                    let file = Text::new("Enter the manifest file to write\n")
+                                        .with_default("manifest.json")
+                                        .with_formatter(&|str| format!(".....{str}.....\n"))
+                                        .prompt()
+                                        .unwrap();
+                                    let manifest = prefix_manifest(&sdk_config, &bucket_name, &folder, DEFAULT_JOBS).await;
+                                    if let Err(message) = write_manifest(std::path::Path::new(file.trim()), &manifest) {
+                                        println!("{}\n", message.red().bold());
                                     }
                                 }
-                                true => {
-                                    println!(
-                                        "{}\n",
-                                        "The bucket name can't be empty; please try again"
-                                            .red()
-                                            .bold()
-                                    )
+                                _ => {
+                                    let file = Text::new("Enter the manifest file to verify\n")
+                                        .with_default("manifest.json")
+                                        .with_formatter(&|str| format!(".....{str}.....\n"))
+                                        .prompt()
+                                        .unwrap();
+                                    match read_manifest(std::path::Path::new(file.trim())) {
+                                        Ok(manifest) => {
+                                            verify_manifest(&sdk_config, &manifest, DEFAULT_JOBS).await;
+                                        }
+                                        Err(message) => println!("{}\n", message.red().bold()),
+                                    }
                                 }
                             }
                         }
-                        "Delete object from a bucket\n" => {
-                            let get_bucket_lists = s3_ops.get_buckets().await;
-                            let existing_buckets = format!(
-                                "These buckets are already in your account:\n{:#?}\n",
-                                get_bucket_lists
-                            );
-                            let bucket_name = Text::new("Please input the name of the bucket\n")
-                                .with_placeholder(&existing_buckets)
-                                .with_formatter(&|str| format!(".....{str}....."))
+                        "Storage Usage and Cost Report\n" => {
+                            let every_bucket = Confirm::new("Report on every bucket?\n")
+                                .with_default(true)
+                                .with_help_message("Choose no to report on one bucket or folder")
                                 .prompt()
                                 .unwrap();
-
-                            match bucket_name.is_empty() {
+                            let locations = match every_bucket {
+                                true => bucket_names(&sdk_config)
+                                    .await
+                                    .into_iter()
+                                    .map(|bucket| (bucket, String::new()))
+                                    .collect(),
                                 false => {
-                                    let object_names =
-                                        s3_ops.retrieve_keys_in_a_bucket(&bucket_name).await;
-                                    let available_object_names = format!(
-                                        "The object names are in the {bucket_name} bucket:\n{}\n",
-                                        object_names.join("\n")
-                                    );
-                                    let object_name =
-                                        Text::new("Enter the object/key name to delete\n")
-                                            .with_placeholder(&available_object_names)
-                                            .with_formatter(&|str| format!(".....{str}.....\n"))
-                                            .prompt()
-                                            .unwrap();
-                                    s3_ops
-                                        .delete_content_in_a_bucket(&bucket_name, &object_name)
-                                        .await;
+                                    let Some(bucket_name) = pick_bucket(&sdk_config, "Select the bucket\n").await else {
+                                        continue 's3_ops;
+                                    };
+                                    let Some(folder) = pick_folder(&sdk_config, &bucket_name, "Select the folder to report on\n").await else {
+                                        continue 's3_ops;
+                                    };
+                                    vec![(bucket_name, folder)]
                                 }
-                                true => {
-                                    println!(
-                                        "{}\n",
-                                        "The bucket name can't be empty; please try again"
-                                            .red()
-                                            .bold()
-                                    )
+                            };
+                            let prices = match PriceTable::load(None) {
+                                Ok(prices) => prices,
+                                Err(message) => {
+                                    println!("{}\n", message.red().bold());
+                                    continue 's3_ops;
                                 }
-                            }
-                        }
-
-                        "Delete Bucket\n" => {
-                            let get_bucket_name = s3_ops.get_buckets().await;
-                            let bucket_names = format!(
-                                "Below, you'll find the buckets in your account:\n{:?}\n",
-                                get_bucket_name
-                            );
-                            let bucket_name = Text::new("Enter the bucket name to delete")
-                                .with_placeholder(&bucket_names)
+                            };
+                            let report = usage_report(&sdk_config, &locations, &prices).await;
+                            print_usage(&report);
+                            let output = Text::new("Enter a .csv or .json file to export the report to, or leave it empty\n")
+                                .with_placeholder("usage.csv")
                                 .with_formatter(&|str| format!(".....{str}.....\n"))
-                                .with_help_message("These are the buckets within your AWS services")
                                 .prompt()
                                 .unwrap();
-                            match bucket_name.is_empty() {
-                                false => {
-                                    s3_ops.delete_bucket(&bucket_name).await;
-                                }
-                                true => {
-                                    println!(
-                                        "{}\n",
-                                        "The bucket name can't be empty; please try again"
-                                            .red()
-                                            .bold()
-                                    )
+                            if !output.trim().is_empty() {
+                                if let Err(message) = write_report(std::path::Path::new(output.trim()), &report) {
+                                    println!("{}\n", message.red().bold());
                                 }
                             }
                         }
+                        "Copy or Move Objects\n" => {
+                            let Some((source_bucket, source_key)) = pick_object(&sdk_config, "Select the object to copy\n").await else {
+                                continue 's3_ops;
+                            };
+                            let Some(destination_bucket) = pick_bucket(&sdk_config, "Select the bucket to copy to\n").await else {
+                                continue 's3_ops;
+                            };
+                            let Some(folder) = pick_folder(&sdk_config, &destination_bucket, "Select the folder to copy to\n").await else {
+                                continue 's3_ops;
+                            };
+                            let name = source_key.rsplit('/').next().unwrap_or(&source_key).to_string();
+                            let destination_key = Text::new("Enter the key of the copy\n")
+                                .with_initial_value(&format!("{folder}{name}"))
+                                .with_formatter(&|str| format!(".....{str}.....\n"))
+                                .prompt()
+                                .unwrap();
+                            let delete_source = Confirm::new("Delete the source after copying, making it a move?\n")
+                                .with_default(false)
+                                .prompt()
+                                .unwrap();
+                            let options = CopyOptions {
+                                metadata: None,
+                                encryption: Encryption::from_env(),
+                                source_encryption: prompt_download_key(),
+                                recursive: false,
+                                delete_source,
+                                dry_run: false,
+                                jobs: DEFAULT_JOBS,
+                            };
+                            copy_objects(
+                                &sdk_config,
+                                (&source_bucket, &source_key),
+                                (&destination_bucket, destination_key.trim()),
+                                &options,
+                            )
+                            .await;
+                        }
+                        "Static Website Hosting\n" => {
+                            let Some(bucket_name) = pick_bucket(&sdk_config, "Select the bucket for the website\n").await else {
+                                continue 's3_ops;
+                            };
+                            manage_website(&sdk_config, &bucket_name).await;
+                        }
+                        "Object Metadata and Tags\n" => {
+                            let Some((bucket_name, object_name)) = pick_object(&sdk_config, "Select the object\n").await else {
+                                continue 's3_ops;
+                            };
+                            manage_metadata(&sdk_config, &bucket_name, &object_name, &prompt_download_key()).await;
+                        }
+                        "Default Bucket Encryption\n" => {
+                            let Some(bucket_name) = pick_bucket(&sdk_config, "Select the bucket\n").await else {
+                                continue 's3_ops;
+                            };
+                            manage_bucket_encryption(&sdk_config, &bucket_name).await;
+                        }
+                        "Delete Bucket\n" => {
+                            let Some(bucket_name) = pick_bucket(&sdk_config, "Select the bucket to delete\n").await else {
+                                continue 's3_ops;
+                            };
+                            s3_ops.delete_bucket(&bucket_name).await;
+                        }
                         "Put object in a Bucket\n" => {
                             let object = Text::new("Enter the object/data path\n")
                                 .with_placeholder(
@@ -1329,265 +1831,314 @@ async fn main() {
                                 .with_formatter(&|str| format!(".....{str}.....\n"))
                                 .prompt()
                                 .unwrap();
-
-                            let get_bucket_name = s3_ops.get_buckets().await;
-                            let available_bucket_name = format!(
-                                "Available bucket names in your account:\n {:#?}\n",
-                                get_bucket_name
-                            );
-                            let bucket_name = Text::new("Enter the bucket name\n")
-                                .with_placeholder(&available_bucket_name)
-                                .with_formatter(&|str| format!(".....{str}.....\n"))
-                                .with_help_message("This is where we put the actual data")
-                                .prompt()
-                                .unwrap();
-
-                            let key = Text::new("Enter the key or the identifier\n")
-                                .with_placeholder("This is what used to retreive the content later")
+                            let path = std::path::Path::new(object.trim());
+                            let Some(file_name) = path.file_name().map(|name| name.to_string_lossy().to_string()) else {
+                                println!("{}\n", "The data path can't be empty".red().bold());
+                                continue 's3_ops;
+                            };
+                            let Some(bucket_name) = pick_bucket(&sdk_config, "Select the bucket to put the data in\n").await else {
+                                continue 's3_ops;
+                            };
+                            let Some(folder) = pick_folder(&sdk_config, &bucket_name, "Select the folder for the object\n").await else {
+                                continue 's3_ops;
+                            };
+                            let name = Text::new("Enter the name of the object in that folder\n")
+                                .with_default(&file_name)
+                                .with_help_message("This is what used to retreive the content later")
                                 .with_formatter(&|str| format!(".....{str}.....\n"))
                                 .prompt()
                                 .unwrap();
 
-                            match (object.is_empty(), bucket_name.is_empty(), key.is_empty()) {
-                                (false, false, false) => {
-                                    use filesize::PathExt;
-                                    use std::path::Path;
-                                    let path = Path::new(&object);
-                                    let file_size = match path.symlink_metadata() {
-                                        Ok(metadata) => match path.size_on_disk_fast(&metadata) {
-                                            Ok(realsize) => Some(realsize),
-                                            Err(_) => None,
-                                        },
-                                        Err(_) => None,
-                                    };
-                                    match file_size {
-                                        Some(size) => {
-                                            let msg = format!(
-                                                "Uploading the file '{}' and its size is: {} Mb\n",
-                                                object.green().bold(),
-                                                (size / (1024 * 1024)).to_string().green().bold()
-                                            );
-                                            println!("{msg}");
-                                            let size_in_mb = size / (1024 * 1024);
-                                            if size_in_mb < 50 {
-                                                println!(
-                                                    "{}\n",
-                                                    "It will take less than a minute to upload the content"
-                                                        .yellow()
-                                                        .bold()
-                                                );
-                                            } else if size_in_mb * 1024 < 1048576 {
-                                                let guessed_minutes = size_in_mb / (50 * 50);
-                                                println!("It will take approximately '{}' minutes to upload content, so please be patient\n",guessed_minutes.to_string().yellow().bold());
-                                            } else if size_in_mb * 1024 > 1048576 {
-                                                let guessed_hours = size_in_mb / (50 * 50);
-                                                if guessed_hours == 0 | 1 {
-                                                    println!("It will take approximately '{}' hour to upload content, so you can accomplish other tasks while it's uploading\n",guessed_hours.to_string().yellow().bold());
-                                                } else {
-                                                    println!("It will take approximately '{}' hours to upload content, so you can accomplish other tasks while it's uploading\n",guessed_hours.to_string().yellow().bold());
-                                                }
-                                            }
-                                        }
-                                        None => {
-                                            let msg = format!(
-                                                "Uploading the file'{}'\n",
-                                                object.green().bold()
-                                            );
-                                            println!("{msg}");
-                                            println!("{}\n","No file size information is available; you can either wait or engage in other tasks while the uploading process is in progress".yellow().bold());
-                                        }
-                                    };
-                                    let start_time = std::time::SystemTime::now();
-                                    s3_ops
-                                        .upload_content_to_a_bucket(&bucket_name, &object, &key)
+                            match name.trim().is_empty() {
+                                false => {
+                                    let key = format!("{folder}{}", name.trim());
+                                    let settings = prompt_upload_settings()
+                                        .with_metadata(prompt_object_metadata(path));
+                                    upload_file(&sdk_config, &bucket_name, path, &key, &settings)
                                         .await;
-                                    let end_time = start_time.elapsed().expect(
-                                        "Error while converting to duration from system time\n",
-                                    );
-                                    if end_time.as_secs() < 60 {
-                                        println!(
-                                            "It took '{}' seconds to update the file\n",
-                                            end_time.as_secs().to_string().yellow().bold()
-                                        );
-                                    } else if end_time.as_secs() < 36000 {
-                                        println!(
-                                            "Uploading the provided content required '{}' minutes\n",
-                                            (end_time.as_secs() / 60).to_string().yellow().bold()
-                                        );
-                                    } else {
-                                        println!(
-                                            "Uploading the provided content required '{}' hours\n",
-                                            (end_time.as_secs() / (60 * 60))
-                                                .to_string()
-                                                .yellow()
-                                                .bold()
-                                        );
-                                    };
                                 }
-
-                                _ => {
-                                    println!("{}\n", "Data path,the key/object name or the bucket name can't be empty".red().bold())
+                                true => {
+                                    println!("{}\n", "The key/object name can't be empty".red().bold())
                                 }
                             }
                         }
-                        "Modifying Object Visibility\n" => {
-                            let get_bucket_name = s3_ops.get_buckets().await;
-                            let available_bucket_name = format!(
-                                "Available bucket names in your account:\n {:#?}\n",
-                                get_bucket_name
-                            );
-                            let bucket_name = Text::new("Enter bucket name that contains the object to which you want to attach the ACL or Permission\n")
-                                .with_placeholder(&available_bucket_name)
-                                .with_formatter(&|str| format!(".....{str}.....\n"))
-                                .with_help_message("This is where we put the actual data")
+                        "Resume Interrupted Uploads\n" => {
+                            let saved = saved_uploads();
+                            match saved.is_empty() {
+                                false => {
+                                    let labels = saved
+                                        .iter()
+                                        .map(|state| {
+                                            format!(
+                                                "{} -> s3://{}/{} ({} of {} parts uploaded)",
+                                                state.file.display(),
+                                                state.bucket,
+                                                state.key,
+                                                state.parts.len(),
+                                                state.file_size.div_ceil(state.part_size)
+                                            )
+                                        })
+                                        .collect::<Vec<_>>();
+                                    let choice = Select::new(
+                                        "Select the upload to resume\n",
+                                        labels.clone(),
+                                    )
+                                    .prompt()
+                                    .unwrap();
+                                    let state = &saved[labels
+                                        .iter()
+                                        .position(|label| *label == choice)
+                                        .unwrap()];
+                                    upload_file(
+                                        &sdk_config,
+                                        &state.bucket,
+                                        &state.file,
+                                        &state.key,
+                                        &state.resume_settings(UploadSettings::from_env()),
+                                    )
+                                    .await;
+                                }
+                                true => println!(
+                                    "{}\n",
+                                    "There are no interrupted uploads to resume".green().bold()
+                                ),
+                            }
+                        }
+                        "Abort Incomplete Multipart Uploads\n" => {
+                            let Some(bucket_name) = pick_bucket(&sdk_config, "Select the bucket to look for incomplete uploads\n").await else {
+                                continue 's3_ops;
+                            };
+                            let uploads =
+                                list_incomplete_uploads(&sdk_config, &bucket_name).await;
+                            print_incomplete_uploads(&bucket_name, &uploads);
+                            if !uploads.is_empty() {
+                                let confirm = Confirm::new(&format!(
+                                    "Abort these {} uploads? Their uploaded parts are deleted\n",
+                                    uploads.len()
+                                ))
+                                .with_default(false)
+                                .with_help_message("Incomplete uploads keep being billed for their parts until they are aborted")
                                 .prompt()
                                 .unwrap();
-                            match bucket_name.is_empty() {
-                                false => {
-                                    let object_names =
-                                        s3_ops.retrieve_keys_in_a_bucket(&bucket_name).await;
-                                    let available_object_names = format!(
-                                        "The object names are in the {bucket_name} bucket:\n{:#?}\n",
-                                        object_names
-                                    );
-                                    let object_name =
-                                        Text::new("Please enter the object name for which you'd like to modify permissions\n")
-                                            .with_placeholder(&available_object_names)
-                                            .with_formatter(&|str| format!(".....{str}.....\n"))
-                                            .prompt()
-                                            .unwrap();
-                                    let possible_acl_values ="private | public-read | public-read-write | authenticated-read";
-                                    let permission_string =
-                                        Text::new("Enter the ACL permission strings\n")
-                                            .with_placeholder(possible_acl_values)
-                                            .with_formatter(&|str| format!(".....{str}.....\n"))
-                                            .prompt()
-                                            .unwrap();
-                                    match (object_name.is_empty(), permission_string.is_empty()) {
-                                        (false, false) => {
-                                            s3_ops
-                                                .put_object_acl(
-                                                    &bucket_name,
-                                                    &object_name,
-                                                    &permission_string,
-                                                )
-                                                .await;
-                                        }
-                                        _ => println!("{}\n", "Fields can't be empty".red().bold()),
-                                    }
+                                if confirm {
+                                    abort_uploads(&sdk_config, &bucket_name, &uploads).await;
                                 }
-                                true => println!("{}\n", "Bucket Name Can't be empty".red().bold()),
                             }
                         }
-
-                        "Download object from bucket\n" => {
-                            let get_buckets = s3_ops.get_buckets().await;
-                            let available_buckets =
-                                format!("Available buckets in your account:\n{:#?}\n", get_buckets);
-
-                            let bucket_name = Text::new("Input the bucket name\n")
-                                .with_placeholder(&available_buckets)
+                        "Sync a Local Folder with a Bucket\n" => {
+                            let direction = Select::new(
+                                "Select the direction of the sync\n",
+                                vec!["Local folder to bucket", "Bucket to local folder"],
+                            )
+                            .prompt()
+                            .unwrap();
+                            let local = Text::new("Enter the local folder path\n")
+                                .with_placeholder("You can copy the path and ctrl+shift+v to paste it here without quotation around it")
+                                .with_formatter(&|str| format!(".....{str}.....\n"))
+                                .prompt()
+                                .unwrap();
+                            let Some(bucket_name) = pick_bucket(&sdk_config, "Select the bucket\n").await else {
+                                continue 's3_ops;
+                            };
+                            let prefix = Text::new("Enter the prefix to sync, or press Enter for the whole bucket\n")
+                                .with_placeholder("e.g. site/assets")
+                                .with_formatter(&|str| format!(".....{str}.....\n"))
+                                .prompt_skippable()
+                                .unwrap()
+                                .unwrap_or_default();
+                            let split_patterns = |input: String| {
+                                input
+                                    .split(',')
+                                    .map(|pattern| pattern.trim().to_string())
+                                    .filter(|pattern| !pattern.is_empty())
+                                    .collect::<Vec<_>>()
+                            };
+                            let include = Text::new("Enter the glob patterns to include, separated by commas, or press Enter to include everything\n")
+                                .with_placeholder("e.g. **/*.html,img/**")
                                 .with_formatter(&|str| format!(".....{str}.....\n"))
+                                .prompt_skippable()
+                                .unwrap()
+                                .unwrap_or_default();
+                            let exclude = Text::new("Enter the glob patterns to exclude, separated by commas, or press Enter to skip\n")
+                                .with_placeholder("e.g. **/.DS_Store,*.tmp")
+                                .with_formatter(&|str| format!(".....{str}.....\n"))
+                                .prompt_skippable()
+                                .unwrap()
+                                .unwrap_or_default();
+                            let delete = Confirm::new("Delete destination files that don't exist in the source?\n")
+                                .with_default(false)
                                 .prompt()
                                 .unwrap();
-                            match bucket_name.is_empty() {
+                            match local.is_empty() {
                                 false => {
-                                    let get_objects =
-                                        s3_ops.retrieve_keys_in_a_bucket(&bucket_name).await;
-                                    let available_objects = format!(
-                                        "Available keys in {bucket_name}\n{:#?}\n",
-                                        get_objects
-                                    );
-                                    let object = Text::new("Input the object/key to download\n")
-                                        .with_placeholder(&available_objects)
-                                        .with_formatter(&|str| format!(".....{str}.....\n"))
+                                    let direction = match direction {
+                                        "Local folder to bucket" => SyncDirection::Upload,
+                                        _ => SyncDirection::Download,
+                                    };
+                                    let mut options = SyncOptions {
+                                        include: split_patterns(include),
+                                        exclude: split_patterns(exclude),
+                                        delete,
+                                        dry_run: true,
+                                        jobs: DEFAULT_JOBS,
+                                        manifest: None,
+                                    };
+                                    let local = std::path::Path::new(&local);
+                                    //The plan is shown as a dry run first and only applied after confirming
+                                    if !sync(&sdk_config, direction, local, &bucket_name, &prefix, &options).await {
+                                        continue 's3_ops;
+                                    }
+                                    let proceed = Confirm::new("Apply these changes?\n")
+                                        .with_default(false)
                                         .prompt()
                                         .unwrap();
-                                    match object.is_empty() {
-                                        false => {
-                                            s3_ops
-                                                .download_content_from_bcuket(
-                                                    &bucket_name,
-                                                    &object,
-                                                    None,
-                                                    true,
-                                                )
-                                                .await;
-                                        }
-                                        true => {
-                                            println!(
-                                                "{}\n",
-                                                "key/object name can't be empty".red().bold()
-                                            )
-                                        }
+                                    if proceed {
+                                        let manifest = Text::new("Enter a file to write a checksum manifest of the synced objects to, or press Enter to skip\n")
+                                            .with_placeholder("e.g. manifest.json")
+                                            .with_formatter(&|str| format!(".....{str}.....\n"))
+                                            .prompt_skippable()
+                                            .unwrap()
+                                            .unwrap_or_default();
+                                        options.dry_run = false;
+                                        options.manifest = match manifest.trim() {
+                                            "" => None,
+                                            path => Some(path.into()),
+                                        };
+                                        sync(&sdk_config, direction, local, &bucket_name, &prefix, &options).await;
                                     }
                                 }
-                                true => {
-                                    println!("{}\n", "Bucket name can't be empty".red().bold())
-                                }
+                                true => println!("{}\n", "The local folder path can't be empty".red().bold()),
                             }
                         }
-
-                        "Retrieve a presigned URL for an object\n" => {
-                            let get_bucket_name = s3_ops.get_buckets().await;
-                            let available_bucket_name = format!(
-                                "Available buckets in your account:\n {:?}\n",
-                                get_bucket_name
-                            );
-                            let bucket_name = Text::new("Enter the bucket name\n")
-                                .with_placeholder(&available_bucket_name)
-                                .with_formatter(&|str| format!(".....{str}.....\n"))
-                                .with_help_message("This is where we put the actual data")
+                        "Modifying Object Visibility\n" => {
+                            let Some((bucket_name, object_name)) = pick_object(&sdk_config, "Select the object to which you want to attach the ACL or Permission\n").await else {
+                                continue 's3_ops;
+                            };
+                            let possible_acl_values = vec!["private", "public-read", "public-read-write", "authenticated-read"];
+                            let permission_string = Select::new("Select the ACL permission\n", possible_acl_values)
                                 .prompt()
                                 .unwrap();
+                            s3_ops
+                                .put_object_acl(&bucket_name, &object_name, permission_string)
+                                .await;
+                        }
 
-                            match bucket_name.is_empty() {
-                                false => {
-                                    let get_objects =
-                                        s3_ops.retrieve_keys_in_a_bucket(&bucket_name).await;
-                                    let available_objects = format!(
-                                        "Available keys in {bucket_name}\n{:#?}\n",
-                                        get_objects
-                                    );
-                                    let object_name = Text::new("Enter the key or object for which you require a pre-signed URL\n")
-                        .with_placeholder(&available_objects)
-                        .with_formatter(&|str| format!(".....{str}.....\n"))
-                        .prompt()
-                        .unwrap();
+                        "Download object from bucket\n" => {
+                            let Some((bucket_name, object_name)) = pick_object(&sdk_config, "Select the object to download\n").await else {
+                                continue 's3_ops;
+                            };
+                            let destination = Text::new("Enter the destination file or directory, or press Enter for the current directory\n")
+                                .with_placeholder("An unfinished download of the same object to the same place continues where it stopped")
+                                .with_formatter(&|str| format!(".....{str}.....\n"))
+                                .prompt_skippable()
+                                .unwrap()
+                                .unwrap_or_default();
+                            let range = Text::new("Enter a byte range to download only part of the object, or press Enter for the whole object\n")
+                                .with_placeholder("e.g. 0-1023, 1024- or -500 for the last 500 bytes")
+                                .with_formatter(&|str| format!(".....{str}.....\n"))
+                                .with_validator(|input: &str| match input.trim().is_empty() {
+                                    true => Ok(inquire::validator::Validation::Valid),
+                                    false => match parse_range(input) {
+                                        Ok(_) => Ok(inquire::validator::Validation::Valid),
+                                        Err(message) => Ok(inquire::validator::Validation::Invalid(message.into())),
+                                    },
+                                })
+                                .prompt_skippable()
+                                .unwrap()
+                                .unwrap_or_default();
+                            let options = DownloadOptions {
+                                range: match range.trim().is_empty() {
+                                    false => Some(parse_range(&range).unwrap()),
+                                    true => None,
+                                },
+                                verify: true,
+                                version_id: None,
+                                encryption: prompt_download_key(),
+                            };
+                            download_object(
+                                &sdk_config,
+                                &bucket_name,
+                                &object_name,
+                                std::path::Path::new(destination.trim()),
+                                &options,
+                            )
+                            .await;
+                        }
 
-                                    match object_name.is_empty() {
-                                        false => {
-                                            let choosing_hour = Text::new("Enter the expiration time for the url in hour\n")
-                                    .with_placeholder("Integer values should always be non-negative and should not contain any characters\n")
-                                    .with_formatter(&|str| format!(".....{str}.....\n"))
-                                    .prompt()
-                                    .unwrap();
-                                            match choosing_hour.is_empty() {
-                                                false => {
-                                                    let end_time =
-                                                        choosing_hour.parse::<u64>().unwrap();
-                                                    s3_ops
-                                                        .get_presigned_url_for_an_object(
-                                                            &bucket_name,
-                                                            &object_name,
-                                                            end_time,
-                                                        )
-                                                        .await;
-                                                }
-                                                true => println!(
-                                                    "{}\n",
-                                                    "the hour can't be empty".red().bold()
-                                                ),
-                                            }
-                                        }
-                                        true => println!(
-                                            "{}\n",
-                                            "key/object name can't be empty".red().bold()
-                                        ),
+                        "Presigned URLs and Upload Forms\n" => {
+                            let kind = Select::new(
+                                "What should the presigned request allow?\n",
+                                vec![
+                                    "Download an object (GET URL)",
+                                    "Upload an object (PUT URL)",
+                                    "Upload from a browser (POST form)",
+                                ],
+                            )
+                            .prompt()
+                            .unwrap();
+                            let (bucket_name, key) = match kind {
+                                "Download an object (GET URL)" => {
+                                    let Some(object) = pick_object(&sdk_config, "Select the object for which you require a pre-signed URL\n").await else {
+                                        continue 's3_ops;
+                                    };
+                                    object
+                                }
+                                _ => {
+                                    let Some(bucket_name) = pick_bucket(&sdk_config, "Select the bucket to upload to\n").await else {
+                                        continue 's3_ops;
+                                    };
+                                    let Some(folder) = pick_folder(&sdk_config, &bucket_name, "Select the folder for the upload\n").await else {
+                                        continue 's3_ops;
+                                    };
+                                    let name = Text::new("Enter the name of the object in that folder\n")
+                                        .with_help_message("For a POST form, leave it empty to keep the name of the uploaded file")
+                                        .with_formatter(&|str| format!(".....{str}.....\n"))
+                                        .prompt()
+                                        .unwrap();
+                                    (bucket_name, format!("{folder}{}", name.trim()))
+                                }
+                            };
+                            match kind {
+                                "Download an object (GET URL)" => {
+                                    let expires_in = prompt_expiry();
+                                    let (output, qr) = prompt_url_output();
+                                    let url = presign_get(&sdk_config, &bucket_name, &key, expires_in).await;
+                                    deliver_url(&url, expires_in, output.as_deref(), qr.as_deref());
+                                }
+                                "Upload an object (PUT URL)" => {
+                                    if key.is_empty() || key.ends_with('/') {
+                                        println!("{}\n", "A PUT URL needs the full key of the object".red().bold());
+                                        continue 's3_ops;
                                     }
+                                    let content_type = Text::new("Enter the Content-Type the upload has to send, or press Enter to leave it open\n")
+                                        .with_formatter(&|str| format!(".....{str}.....\n"))
+                                        .prompt_skippable()
+                                        .unwrap()
+                                        .filter(|content_type| !content_type.trim().is_empty());
+                                    let expires_in = prompt_expiry();
+                                    let (output, qr) = prompt_url_output();
+                                    let url = presign_put(
+                                        &sdk_config,
+                                        &bucket_name,
+                                        &key,
+                                        expires_in,
+                                        content_type.as_deref().map(str::trim),
+                                    )
+                                    .await;
+                                    deliver_url(&url, expires_in, output.as_deref(), qr.as_deref());
                                 }
-                                true => {
-                                    println!("{}\n", "bucket name can't be empty".red().bold())
+                                _ => {
+                                    let conditions = prompt_post_conditions();
+                                    let expires_in = prompt_expiry();
+                                    let output = Text::new("Enter a file to save the form to, or press Enter to only print it\n")
+                                        .with_help_message("A path ending with .html gets an upload page, anything else gets JSON")
+                                        .with_formatter(&|str| format!(".....{str}.....\n"))
+                                        .prompt_skippable()
+                                        .unwrap()
+                                        .map(|output| output.trim().to_string())
+                                        .filter(|output| !output.is_empty());
+                                    let post = presign_post(&sdk_config, &bucket_name, &key, expires_in, &conditions).await;
+                                    deliver_post(&post, expires_in, output.as_deref());
                                 }
                             }
                         }
@@ -0,0 +1,367 @@
+use aws_sdk_s3::types::ChecksumAlgorithm;
+use aws_sdk_s3::Client;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::env::var;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use super::encryption::Encryption;
+
+const MB: u64 = 1024 * 1024;
+
+pub fn file_md5(path: &Path) -> Option<String> {
+    let mut file = fs::File::open(path).ok()?;
+    let mut context = md5::Context::new();
+    let mut buffer = vec![0; MB as usize];
+    loop {
+        let read = file.read(&mut buffer).ok()?;
+        if read == 0 {
+            break Some(format!("{:x}", context.compute()));
+        }
+        context.consume(&buffer[..read]);
+    }
+}
+
+// A multipart ETag is the MD5 of the concatenated part MD5s followed by '-<part count>'
+fn multipart_etag(path: &Path, part_size: u64) -> Option<String> {
+    let mut file = fs::File::open(path).ok()?;
+    let mut digests = Vec::new();
+    let mut buffer = vec![0; part_size as usize];
+    let mut parts = 0;
+    loop {
+        let mut filled = 0;
+        while filled < buffer.len() {
+            let read = file.read(&mut buffer[filled..]).ok()?;
+            if read == 0 {
+                break;
+            }
+            filled += read;
+        }
+        if filled == 0 {
+            break;
+        }
+        digests.extend_from_slice(&md5::compute(&buffer[..filled]).0);
+        parts += 1;
+        if filled < buffer.len() {
+            break;
+        }
+    }
+    Some(format!("{:x}-{parts}", md5::compute(&digests)))
+}
+
+/// Compares a downloaded file with the object's ETag. Returns None when the ETag can't be
+/// reproduced locally, e.g. for SSE-KMS objects or multipart uploads with an unusual part size
+pub fn matches_etag(path: &Path, e_tag: &str) -> Option<bool> {
+    let e_tag = e_tag.trim_matches('"');
+    match e_tag.split_once('-') {
+        None if e_tag.len() == 32 => Some(file_md5(path)?.eq_ignore_ascii_case(e_tag)),
+        None => None,
+        Some((_, parts)) => {
+            let parts: u64 = parts.parse().ok().filter(|parts| *parts > 0)?;
+            let size = fs::metadata(path).ok()?.len();
+            // The part size isn't recorded, so the usual choices are tried: whole megabytes
+            // just large enough for the part count, and the common 8MB and 16MB defaults
+            let mut candidates = vec![size.div_ceil(parts).div_ceil(MB) * MB, 8 * MB, 16 * MB];
+            candidates.dedup();
+            let found = candidates
+                .into_iter()
+                .filter(|part_size| *part_size > 0 && size.div_ceil(*part_size) == parts)
+                .any(|part_size| multipart_etag(path, part_size).as_deref() == Some(e_tag));
+            // A mismatch only proves corruption when the part size can't be the reason
+            found.then_some(true)
+        }
+    }
+}
+
+/// The additional checksums S3 stores with an object and checks the upload against
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum ChecksumKind {
+    #[serde(rename = "SHA256")]
+    Sha256,
+    #[serde(rename = "CRC32C")]
+    Crc32c,
+}
+
+impl ChecksumKind {
+    /// 'sha256' or 'crc32c'; 'none' uploads without a checksum
+    pub fn parse(input: &str) -> Result<Option<Self>, String> {
+        match input.trim().to_lowercase().replace('-', "").as_str() {
+            "sha256" => Ok(Some(ChecksumKind::Sha256)),
+            "crc32c" => Ok(Some(ChecksumKind::Crc32c)),
+            "none" | "off" => Ok(None),
+            other => Err(format!(
+                "Unknown checksum '{other}'; use sha256, crc32c or none"
+            )),
+        }
+    }
+
+    /// The checksum set by S3_CHECKSUM in the .env file, CRC32C when it isn't set
+    pub fn from_env() -> Option<Self> {
+        dotenv::dotenv().ok();
+        match var("S3_CHECKSUM") {
+            Ok(value) => Self::parse(&value).unwrap_or_else(|message| panic!("{message}\n")),
+            Err(_) => Some(ChecksumKind::Crc32c),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChecksumKind::Sha256 => "SHA256",
+            ChecksumKind::Crc32c => "CRC32C",
+        }
+    }
+
+    pub fn algorithm(&self) -> ChecksumAlgorithm {
+        match self {
+            ChecksumKind::Sha256 => ChecksumAlgorithm::Sha256,
+            ChecksumKind::Crc32c => ChecksumAlgorithm::Crc32C,
+        }
+    }
+
+    fn hasher(&self) -> Hasher {
+        match self {
+            ChecksumKind::Sha256 => Hasher::Sha256(Sha256::new()),
+            ChecksumKind::Crc32c => Hasher::Crc32c(0),
+        }
+    }
+}
+
+enum Hasher {
+    Sha256(Sha256),
+    Crc32c(u32),
+}
+
+impl Hasher {
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha256(hasher) => hasher.update(data),
+            Hasher::Crc32c(crc) => *crc = crc32c::crc32c_append(*crc, data),
+        }
+    }
+
+    // S3 sends the digest bytes base64 encoded, the CRC in big-endian order
+    fn finish(self) -> Vec<u8> {
+        match self {
+            Hasher::Sha256(hasher) => hasher.finalize().to_vec(),
+            Hasher::Crc32c(crc) => crc.to_be_bytes().to_vec(),
+        }
+    }
+}
+
+/// A checksum the way S3 reports it: the base64 digest of the whole object, or for multipart
+/// uploads the digest of the part digests followed by '-<part count>'
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Checksum {
+    pub algorithm: ChecksumKind,
+    pub value: String,
+}
+
+impl Checksum {
+    pub fn of(algorithm: ChecksumKind, data: &[u8]) -> Self {
+        let mut hasher = algorithm.hasher();
+        hasher.update(data);
+        Self {
+            algorithm,
+            value: BASE64.encode(hasher.finish()),
+        }
+    }
+
+    /// Combines the checksums of the parts, in part order, into the object's checksum
+    pub fn composite(algorithm: ChecksumKind, parts: &[Checksum]) -> Self {
+        let mut hasher = algorithm.hasher();
+        for part in parts {
+            hasher.update(&BASE64.decode(&part.value).unwrap_or_default());
+        }
+        Self {
+            algorithm,
+            value: format!("{}-{}", BASE64.encode(hasher.finish()), parts.len()),
+        }
+    }
+
+    /// The checksum from the headers of a response, if the object has one
+    pub fn from_headers(sha256: Option<&str>, crc32c: Option<&str>) -> Option<Self> {
+        match (sha256, crc32c) {
+            (Some(value), _) => Some(Self {
+                algorithm: ChecksumKind::Sha256,
+                value: value.into(),
+            }),
+            (None, Some(value)) => Some(Self {
+                algorithm: ChecksumKind::Crc32c,
+                value: value.into(),
+            }),
+            (None, None) => None,
+        }
+    }
+
+    /// The value for the x-amz-checksum-sha256 header
+    pub fn sha256(&self) -> Option<String> {
+        (self.algorithm == ChecksumKind::Sha256).then(|| self.value.clone())
+    }
+
+    /// The value for the x-amz-checksum-crc32c header
+    pub fn crc32c(&self) -> Option<String> {
+        (self.algorithm == ChecksumKind::Crc32c).then(|| self.value.clone())
+    }
+
+    /// The part count of a multipart checksum; None for whole-object checksums
+    pub fn parts(&self) -> Option<u64> {
+        self.value.rsplit_once('-')?.1.parse().ok()
+    }
+}
+
+pub fn file_checksum(path: &Path, algorithm: ChecksumKind) -> Option<Checksum> {
+    let mut file = fs::File::open(path).ok()?;
+    let mut hasher = algorithm.hasher();
+    let mut buffer = vec![0; MB as usize];
+    loop {
+        let read = file.read(&mut buffer).ok()?;
+        if read == 0 {
+            break Some(Checksum {
+                algorithm,
+                value: BASE64.encode(hasher.finish()),
+            });
+        }
+        hasher.update(&buffer[..read]);
+    }
+}
+
+// The checksum a multipart upload of the file in 'part_size' parts ends up with
+fn multipart_checksum(path: &Path, algorithm: ChecksumKind, part_size: u64) -> Option<Checksum> {
+    let mut file = fs::File::open(path).ok()?;
+    let mut parts = Vec::new();
+    let mut buffer = vec![0; part_size as usize];
+    loop {
+        let mut filled = 0;
+        while filled < buffer.len() {
+            let read = file.read(&mut buffer[filled..]).ok()?;
+            if read == 0 {
+                break;
+            }
+            filled += read;
+        }
+        if filled == 0 {
+            break;
+        }
+        parts.push(Checksum::of(algorithm, &buffer[..filled]));
+        if filled < buffer.len() {
+            break;
+        }
+    }
+    Some(Checksum::composite(algorithm, &parts))
+}
+
+/// Compares a file with the checksum S3 stores for the object. A multipart checksum needs the
+/// part size the object was uploaded with; None means the file couldn't be compared
+pub fn matches_checksum(path: &Path, checksum: &Checksum, part_size: Option<u64>) -> Option<bool> {
+    let local = match checksum.parts() {
+        None => file_checksum(path, checksum.algorithm)?,
+        Some(parts) => {
+            let part_size = part_size.filter(|part_size| *part_size > 0)?;
+            // Parts of different sizes can't be reproduced from the first one
+            if fs::metadata(path).ok()?.len().div_ceil(part_size) != parts {
+                return None;
+            }
+            multipart_checksum(path, checksum.algorithm, part_size)?
+        }
+    };
+    Some(local.value == checksum.value)
+}
+
+/// The size of the first part of an object, which is the part size it was uploaded with
+pub async fn first_part_size(
+    client: &Client,
+    bucket: &str,
+    key: &str,
+    version_id: Option<String>,
+    encryption: &Encryption,
+) -> Option<u64> {
+    let output = client
+        .head_object()
+        .bucket(bucket)
+        .key(key)
+        .set_version_id(version_id)
+        .part_number(1)
+        .set_sse_customer_algorithm(encryption.customer_algorithm())
+        .set_sse_customer_key(encryption.customer_key())
+        .set_sse_customer_key_md5(encryption.customer_key_md5())
+        .send()
+        .await
+        .ok()?;
+    Some(output.content_length().max(0) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn whole_object_checksums() {
+        assert_eq!(
+            Checksum::of(ChecksumKind::Crc32c, b"123456789").value,
+            "4waSgw=="
+        );
+        assert_eq!(
+            Checksum::of(ChecksumKind::Sha256, b"abc").value,
+            "ungWv48Bz+pBQUDeXa4iI7ADYaOWF3qctBD/YfIAFa0="
+        );
+    }
+
+    #[test]
+    fn composite_checksums() {
+        let parts = |algorithm| {
+            [b"hello ".as_slice(), b"world".as_slice()].map(|part| Checksum::of(algorithm, part))
+        };
+        let crc32c = Checksum::composite(ChecksumKind::Crc32c, &parts(ChecksumKind::Crc32c));
+        assert_eq!(crc32c.value, "vUZpoA==-2");
+        assert_eq!(crc32c.parts(), Some(2));
+        let sha256 = Checksum::composite(ChecksumKind::Sha256, &parts(ChecksumKind::Sha256));
+        assert_eq!(
+            sha256.value,
+            "Zhie15keHg/OBlOZxcoF/BXCgYZaeimRvdZnwUZqkaQ=-2"
+        );
+    }
+
+    #[test]
+    fn files_match_multipart_checksums() {
+        let path = std::env::temp_dir().join(format!("checksum-test-{}", std::process::id()));
+        fs::write(&path, "hello world").unwrap();
+        let checksum = Checksum {
+            algorithm: ChecksumKind::Crc32c,
+            value: "vUZpoA==-2".into(),
+        };
+        assert_eq!(matches_checksum(&path, &checksum, Some(6)), Some(true));
+        // Another part size gives a different part count, so the file can't be compared
+        assert_eq!(matches_checksum(&path, &checksum, Some(4)), None);
+        assert_eq!(matches_checksum(&path, &checksum, None), None);
+        let whole = Checksum::of(ChecksumKind::Sha256, b"hello world");
+        assert_eq!(matches_checksum(&path, &whole, None), Some(true));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn checksum_names() {
+        assert_eq!(
+            ChecksumKind::parse("SHA-256"),
+            Ok(Some(ChecksumKind::Sha256))
+        );
+        assert_eq!(
+            ChecksumKind::parse("crc32c"),
+            Ok(Some(ChecksumKind::Crc32c))
+        );
+        assert_eq!(ChecksumKind::parse("none"), Ok(None));
+        assert!(ChecksumKind::parse("md5").is_err());
+    }
+
+    #[test]
+    fn sha256_headers_win() {
+        let checksum = Checksum::from_headers(Some("a"), Some("b")).unwrap();
+        assert_eq!(checksum.algorithm, ChecksumKind::Sha256);
+        assert_eq!(checksum.sha256().as_deref(), Some("a"));
+        assert_eq!(checksum.crc32c(), None);
+        assert_eq!(Checksum::from_headers(None, None), None);
+    }
+}
@@ -0,0 +1,565 @@
+use aws_config::SdkConfig;
+use aws_sdk_s3::types::{
+    CompletedMultipartUpload, CompletedPart, MetadataDirective, StorageClass, TaggingDirective,
+};
+use aws_sdk_s3::Client;
+use colored::Colorize;
+use std::collections::{HashMap, HashSet};
+use tokio::task::JoinSet;
+
+use super::encryption::Encryption;
+use super::metadata::ObjectMetadata;
+use super::multipart::abort_upload;
+use super::objects::{delete_keys, list_objects, RemoteObject};
+use super::{copy_source, format_size, regional_client, transfer_bar};
+
+/// CopyObject handles objects up to 5GB; larger ones are copied in parts with UploadPartCopy
+const MAX_SINGLE_COPY: u64 = 5 * 1024 * 1024 * 1024;
+const COPY_PART_SIZE: u64 = 512 * 1024 * 1024;
+const MAX_PARTS: u64 = 10_000;
+const PARALLEL_PARTS: usize = 8;
+
+pub struct CopyOptions {
+    /// New headers, user metadata and tags for the copies; None keeps those of the source
+    pub metadata: Option<ObjectMetadata>,
+    pub encryption: Encryption,
+    /// How the sources are encrypted; only an SSE-C key matters, since S3 needs it to read them
+    pub source_encryption: Encryption,
+    /// Copy every object under the source prefix instead of one object
+    pub recursive: bool,
+    /// Delete each source once it is copied, which makes it a move
+    pub delete_source: bool,
+    pub dry_run: bool,
+    pub jobs: usize,
+}
+
+// The headers that have to be given again when a copy replaces the metadata or uses parts
+struct SourceHeaders {
+    content_type: Option<String>,
+    cache_control: Option<String>,
+    content_disposition: Option<String>,
+    content_encoding: Option<String>,
+    content_language: Option<String>,
+    metadata: Option<HashMap<String, String>>,
+    tagging: Option<String>,
+}
+
+// Everything a spawned task needs to copy objects from one bucket to another
+#[derive(Clone)]
+struct CopyJob {
+    source: Client,
+    destination: Client,
+    source_bucket: String,
+    destination_bucket: String,
+    metadata: Option<ObjectMetadata>,
+    encryption: Encryption,
+    source_encryption: Encryption,
+}
+
+fn with_slash(prefix: &str) -> String {
+    match prefix.is_empty() || prefix.ends_with('/') {
+        true => prefix.to_string(),
+        false => format!("{prefix}/"),
+    }
+}
+
+async fn head_source(
+    client: &Client,
+    bucket: &str,
+    key: &str,
+    encryption: &Encryption,
+) -> Result<RemoteObject, String> {
+    let head = client
+        .head_object()
+        .bucket(bucket)
+        .key(key)
+        .set_sse_customer_algorithm(encryption.customer_algorithm())
+        .set_sse_customer_key(encryption.customer_key())
+        .set_sse_customer_key_md5(encryption.customer_key_md5())
+        .send()
+        .await
+        .map_err(|error| format!("s3://{bucket}/{key}: {}", error.into_service_error()))?;
+    Ok(RemoteObject {
+        key: key.to_string(),
+        size: head.content_length().max(0) as u64,
+        last_modified: head.last_modified().map_or(0, |date| date.secs()),
+        e_tag: head
+            .e_tag()
+            .unwrap_or_default()
+            .trim_matches('"')
+            .to_string(),
+        storage_class: head
+            .storage_class()
+            .map_or("STANDARD", |class| class.as_str())
+            .to_string(),
+    })
+}
+
+impl CopyJob {
+    // The source's headers, or the replacements with the source's type and caching as fallbacks
+    async fn headers(&self, key: &str) -> Result<SourceHeaders, String> {
+        let source_encryption = &self.source_encryption;
+        let head = self
+            .source
+            .head_object()
+            .bucket(&self.source_bucket)
+            .key(key)
+            .set_sse_customer_algorithm(source_encryption.customer_algorithm())
+            .set_sse_customer_key(source_encryption.customer_key())
+            .set_sse_customer_key_md5(source_encryption.customer_key_md5())
+            .send()
+            .await
+            .map_err(|error| error.into_service_error().to_string())?;
+        let mut headers = SourceHeaders {
+            content_type: head.content_type().map(str::to_string),
+            cache_control: head.cache_control().map(str::to_string),
+            content_disposition: head.content_disposition().map(str::to_string),
+            content_encoding: head.content_encoding().map(str::to_string),
+            content_language: head.content_language().map(str::to_string),
+            metadata: head.metadata().cloned(),
+            tagging: None,
+        };
+        match &self.metadata {
+            Some(replacement) => {
+                headers.content_type = replacement.content_type.clone().or(headers.content_type);
+                headers.cache_control = replacement.cache_control.clone().or(headers.cache_control);
+                headers.metadata = replacement.user_metadata();
+                headers.tagging = replacement.tagging();
+            }
+            None => {
+                let tagging = self
+                    .source
+                    .get_object_tagging()
+                    .bucket(&self.source_bucket)
+                    .key(key)
+                    .send()
+                    .await
+                    .map_err(|error| error.into_service_error().to_string())?;
+                let tags = tagging
+                    .tag_set()
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|tag| {
+                        (
+                            tag.key().unwrap_or_default().to_string(),
+                            tag.value().unwrap_or_default().to_string(),
+                        )
+                    })
+                    .collect();
+                headers.tagging = ObjectMetadata {
+                    tags,
+                    ..Default::default()
+                }
+                .tagging();
+            }
+        }
+        Ok(headers)
+    }
+
+    async fn copy_single(&self, object: &RemoteObject, key: &str) -> Result<(), String> {
+        let (encryption, source_encryption) = (&self.encryption, &self.source_encryption);
+        let request = self
+            .destination
+            .copy_object()
+            .bucket(&self.destination_bucket)
+            .key(key)
+            .copy_source(copy_source(&self.source_bucket, &object.key, None))
+            .storage_class(StorageClass::from(object.storage_class.as_str()))
+            .set_server_side_encryption(encryption.server_side_encryption())
+            .set_ssekms_key_id(encryption.kms_key_id())
+            .set_sse_customer_algorithm(encryption.customer_algorithm())
+            .set_sse_customer_key(encryption.customer_key())
+            .set_sse_customer_key_md5(encryption.customer_key_md5())
+            .set_copy_source_sse_customer_algorithm(source_encryption.customer_algorithm())
+            .set_copy_source_sse_customer_key(source_encryption.customer_key())
+            .set_copy_source_sse_customer_key_md5(source_encryption.customer_key_md5());
+        // Without directives S3 copies the headers, user metadata and tags as they are
+        let request = match &self.metadata {
+            Some(_) => {
+                let headers = self.headers(&object.key).await?;
+                request
+                    .metadata_directive(MetadataDirective::Replace)
+                    .tagging_directive(TaggingDirective::Replace)
+                    .set_content_type(headers.content_type)
+                    .set_cache_control(headers.cache_control)
+                    .set_content_disposition(headers.content_disposition)
+                    .set_content_encoding(headers.content_encoding)
+                    .set_content_language(headers.content_language)
+                    .set_metadata(headers.metadata)
+                    .set_tagging(headers.tagging)
+            }
+            None => request,
+        };
+        request
+            .send()
+            .await
+            .map_err(|error| error.into_service_error().to_string())?;
+        Ok(())
+    }
+
+    // Multipart copies don't carry anything over, so the headers and tags are given explicitly
+    async fn copy_parts(&self, object: &RemoteObject, key: &str) -> Result<(), String> {
+        let headers = self.headers(&object.key).await?;
+        let (encryption, source_encryption) = (&self.encryption, &self.source_encryption);
+        let output = self
+            .destination
+            .create_multipart_upload()
+            .bucket(&self.destination_bucket)
+            .key(key)
+            .storage_class(StorageClass::from(object.storage_class.as_str()))
+            .set_content_type(headers.content_type)
+            .set_cache_control(headers.cache_control)
+            .set_content_disposition(headers.content_disposition)
+            .set_content_encoding(headers.content_encoding)
+            .set_content_language(headers.content_language)
+            .set_metadata(headers.metadata)
+            .set_tagging(headers.tagging)
+            .set_server_side_encryption(encryption.server_side_encryption())
+            .set_ssekms_key_id(encryption.kms_key_id())
+            .set_sse_customer_algorithm(encryption.customer_algorithm())
+            .set_sse_customer_key(encryption.customer_key())
+            .set_sse_customer_key_md5(encryption.customer_key_md5())
+            .send()
+            .await
+            .map_err(|error| error.into_service_error().to_string())?;
+        let upload_id = output.upload_id().unwrap_or_default().to_string();
+
+        let part_size = COPY_PART_SIZE.max(object.size.div_ceil(MAX_PARTS));
+        let part_count = object.size.div_ceil(part_size) as i32;
+        let bar = transfer_bar(object.size, &format!("Copying {}", object.key));
+        let mut pending = 1..=part_count;
+        let mut tasks = JoinSet::new();
+        let mut parts = Vec::new();
+        let mut failure = None;
+        loop {
+            while failure.is_none() && tasks.len() < PARALLEL_PARTS {
+                let Some(part_number) = pending.next() else {
+                    break;
+                };
+                let first = (part_number as u64 - 1) * part_size;
+                let last = (first + part_size).min(object.size) - 1;
+                let request = self
+                    .destination
+                    .upload_part_copy()
+                    .bucket(&self.destination_bucket)
+                    .key(key)
+                    .upload_id(&upload_id)
+                    .part_number(part_number)
+                    .copy_source(copy_source(&self.source_bucket, &object.key, None))
+                    .copy_source_range(format!("bytes={first}-{last}"))
+                    .set_sse_customer_algorithm(encryption.customer_algorithm())
+                    .set_sse_customer_key(encryption.customer_key())
+                    .set_sse_customer_key_md5(encryption.customer_key_md5())
+                    .set_copy_source_sse_customer_algorithm(source_encryption.customer_algorithm())
+                    .set_copy_source_sse_customer_key(source_encryption.customer_key())
+                    .set_copy_source_sse_customer_key_md5(source_encryption.customer_key_md5());
+                tasks.spawn(async move { (part_number, last - first + 1, request.send().await) });
+            }
+            let Some(joined) = tasks.join_next().await else {
+                break;
+            };
+            let (part_number, length, result) = joined.expect("The part copy task panicked\n");
+            match result {
+                Ok(output) => {
+                    bar.inc(length);
+                    let e_tag = output
+                        .copy_part_result()
+                        .and_then(|result| result.e_tag())
+                        .unwrap_or_default();
+                    parts.push(
+                        CompletedPart::builder()
+                            .part_number(part_number)
+                            .e_tag(e_tag)
+                            .build(),
+                    );
+                }
+                Err(error) => {
+                    failure = Some(format!(
+                        "part {part_number}: {}",
+                        error.into_service_error()
+                    ))
+                }
+            }
+        }
+        if let Some(error) = failure {
+            bar.abandon();
+            abort_upload(&self.destination, &self.destination_bucket, key, &upload_id).await;
+            return Err(error);
+        }
+        bar.finish();
+        parts.sort_by_key(|part| part.part_number());
+        self.destination
+            .complete_multipart_upload()
+            .bucket(&self.destination_bucket)
+            .key(key)
+            .upload_id(&upload_id)
+            .set_sse_customer_algorithm(encryption.customer_algorithm())
+            .set_sse_customer_key(encryption.customer_key())
+            .set_sse_customer_key_md5(encryption.customer_key_md5())
+            .multipart_upload(
+                CompletedMultipartUpload::builder()
+                    .set_parts(Some(parts))
+                    .build(),
+            )
+            .send()
+            .await
+            .map_err(|error| error.into_service_error().to_string())?;
+        Ok(())
+    }
+}
+
+/// Copies on the server side, so nothing is downloaded, from 'source' to 'destination', both
+/// as (bucket, key). With 'recursive' the source is a prefix and every object under it keeps
+/// its relative name under the destination prefix. A destination ending with '/' keeps the
+/// source's name. The buckets may be in different regions. Returns whether everything was copied
+pub async fn copy_objects(
+    config: &SdkConfig,
+    source: (&str, &str),
+    destination: (&str, &str),
+    options: &CopyOptions,
+) -> bool {
+    let (source_bucket, source_key) = source;
+    let (destination_bucket, destination_key) = destination;
+    let source_client = regional_client(config, source_bucket).await;
+    let destination_client = match source_bucket == destination_bucket {
+        true => source_client.clone(),
+        false => regional_client(config, destination_bucket).await,
+    };
+
+    let mut copies = Vec::new();
+    if options.recursive {
+        let source_prefix = with_slash(source_key);
+        let destination_prefix = with_slash(destination_key);
+        for object in list_objects(&source_client, source_bucket, &source_prefix).await {
+            // Copies into a folder inside the source would otherwise be copied again
+            let inside_destination = source_bucket == destination_bucket
+                && !destination_prefix.is_empty()
+                && object.key.starts_with(&destination_prefix);
+            if object.key.ends_with('/') || inside_destination {
+                continue;
+            }
+            let key = format!("{destination_prefix}{}", &object.key[source_prefix.len()..]);
+            copies.push((object, key));
+        }
+    } else {
+        if source_key.is_empty() || source_key.ends_with('/') {
+            println!(
+                "{}\n",
+                "The source is a prefix; give --recursive to copy everything under it"
+                    .red()
+                    .bold()
+            );
+            return false;
+        }
+        let object = match head_source(
+            &source_client,
+            source_bucket,
+            source_key,
+            &options.source_encryption,
+        )
+        .await
+        {
+            Ok(object) => object,
+            Err(error) => {
+                println!(
+                    "{}: {error}\n",
+                    "Error while reading the source".red().bold()
+                );
+                return false;
+            }
+        };
+        let key = match destination_key.is_empty() || destination_key.ends_with('/') {
+            true => format!(
+                "{destination_key}{}",
+                source_key.rsplit('/').next().unwrap_or(source_key)
+            ),
+            false => destination_key.to_string(),
+        };
+        copies.push((object, key));
+    }
+    if source_bucket == destination_bucket {
+        if let Some((object, _)) = copies.iter().find(|(object, key)| object.key == *key) {
+            println!(
+                "{}\n",
+                format!(
+                    "'{}' would be copied onto itself; 'head' changes an object's metadata in place",
+                    object.key
+                )
+                .red()
+                .bold()
+            );
+            return false;
+        }
+    }
+    // A destination that is also a source, e.g. 'a/a/f' to 'a/f' while 'a/f' is copied too,
+    // races with the copy of that source, and a move would then delete the copied data
+    let overlapping = match source_bucket == destination_bucket {
+        true => {
+            let sources = copies
+                .iter()
+                .map(|(object, _)| object.key.as_str())
+                .collect::<HashSet<_>>();
+            copies
+                .iter()
+                .map(|(_, key)| key.clone())
+                .filter(|key| sources.contains(key.as_str()))
+                .collect::<HashSet<_>>()
+        }
+        false => HashSet::new(),
+    };
+    if !overlapping.is_empty() {
+        let mut keys = overlapping.iter().cloned().collect::<Vec<_>>();
+        keys.sort();
+        let sample = keys.iter().take(5).cloned().collect::<Vec<_>>().join(", ");
+        if options.delete_source {
+            println!(
+                "{}\n",
+                format!(
+                    "{} destination key(s) are also moved from, e.g. {sample}; move into a prefix outside the source instead",
+                    keys.len()
+                )
+                .red()
+                .bold()
+            );
+            return false;
+        }
+        println!(
+            "{} {} destination key(s) are also copied from and will be overwritten while copying, e.g. {sample}",
+            "Warning:".yellow().bold(),
+            keys.len()
+        );
+    }
+    // Archived objects have to be restored before S3 can read them
+    let (archived, copies): (Vec<_>, Vec<_>) = copies.into_iter().partition(|(object, _)| {
+        ["GLACIER", "DEEP_ARCHIVE"].contains(&object.storage_class.as_str())
+    });
+    for (object, _) in &archived {
+        println!(
+            "{} {} is in {} and needs to be restored first",
+            "Skipped".yellow().bold(),
+            object.key,
+            object.storage_class
+        );
+    }
+    if copies.is_empty() {
+        println!(
+            "{}\n",
+            format!("Nothing to copy from s3://{source_bucket}/{source_key}")
+                .yellow()
+                .bold()
+        );
+        return archived.is_empty();
+    }
+
+    let total_bytes: u64 = copies.iter().map(|(object, _)| object.size).sum();
+    let verb = match options.delete_source {
+        true => "move",
+        false => "copy",
+    };
+    if options.dry_run {
+        for (object, key) in &copies {
+            println!(
+                "{} {verb} s3://{source_bucket}/{} to s3://{destination_bucket}/{key}",
+                "(dry run)".yellow().bold(),
+                object.key
+            );
+        }
+    }
+    println!(
+        "{} object(s) to {verb} ({})\n",
+        copies.len().to_string().green().bold(),
+        format_size(total_bytes)
+    );
+    if options.dry_run {
+        return true;
+    }
+
+    let job = CopyJob {
+        source: source_client.clone(),
+        destination: destination_client,
+        source_bucket: source_bucket.to_string(),
+        destination_bucket: destination_bucket.to_string(),
+        metadata: options.metadata.clone(),
+        encryption: options.encryption.clone(),
+        source_encryption: options.source_encryption.clone(),
+    };
+    // Objects over 5GB are copied one at a time after the rest, each with parallel parts
+    let (large, small): (Vec<_>, Vec<_>) = copies
+        .into_iter()
+        .partition(|(object, _)| object.size > MAX_SINGLE_COPY);
+    let mut copied = Vec::new();
+    let mut failures = 0;
+    let bar = transfer_bar(small.iter().map(|(object, _)| object.size).sum(), "Copying");
+    let mut pending = small.into_iter();
+    let mut tasks = JoinSet::new();
+    loop {
+        while tasks.len() < options.jobs.max(1) {
+            let Some((object, key)) = pending.next() else {
+                break;
+            };
+            let job = job.clone();
+            tasks.spawn(async move {
+                let result = job.copy_single(&object, &key).await;
+                (object, key, result)
+            });
+        }
+        let Some(joined) = tasks.join_next().await else {
+            break;
+        };
+        let (object, key, result) = joined.expect("The copy task panicked\n");
+        bar.inc(object.size);
+        match result {
+            Ok(()) => copied.push(object.key),
+            Err(error) => {
+                failures += 1;
+                bar.println(format!(
+                    "{} {} to {key}: {error}",
+                    "Error while copying".red().bold(),
+                    object.key
+                ));
+            }
+        }
+    }
+    bar.finish();
+    for (object, key) in large {
+        match job.copy_parts(&object, &key).await {
+            Ok(()) => copied.push(object.key),
+            Err(error) => {
+                failures += 1;
+                println!(
+                    "{} {} to {key}: {error}",
+                    "Error while copying".red().bold(),
+                    object.key
+                );
+            }
+        }
+    }
+
+    println!(
+        "\n{} object(s) copied to {}",
+        copied.len().to_string().green().bold(),
+        format!("s3://{destination_bucket}/{destination_key}")
+            .green()
+            .bold()
+    );
+    // Only the sources that were copied are deleted, so a failed copy never loses an object,
+    // and never one that is also a destination
+    copied.retain(|key| !overlapping.contains(key));
+    if options.delete_source && !copied.is_empty() {
+        let deleted = delete_keys(&source_client, source_bucket, &copied).await;
+        println!(
+            "{} source object(s) deleted",
+            deleted.to_string().green().bold()
+        );
+    }
+    if failures > 0 {
+        println!(
+            "{}",
+            format!("{failures} object(s) couldn't be copied")
+                .red()
+                .bold()
+        );
+    }
+    println!();
+    failures == 0 && archived.is_empty()
+}
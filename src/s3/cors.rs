@@ -0,0 +1,660 @@
+use aws_config::SdkConfig;
+use aws_sdk_s3::error::ProvideErrorMetadata;
+use aws_sdk_s3::types::{CorsConfiguration, CorsRule};
+use aws_sdk_s3::Client;
+use colored::Colorize;
+use inquire::{Confirm, MultiSelect, Select, Text};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+pub const CORS_METHODS: [&str; 5] = ["GET", "PUT", "POST", "DELETE", "HEAD"];
+/// S3 accepts at most 100 rules in a CORS configuration
+const MAX_RULES: usize = 100;
+
+/// A CORS rule as written in a CORS file, e.g.
+///
+/// ```yaml
+/// rules:
+///   - id: uploads
+///     allowed_origins: [https://app.example.com, "https://*.example.com"]
+///     allowed_methods: [GET, PUT, POST]
+///     allowed_headers: ["*"]
+///     expose_headers: [ETag]
+///     max_age_seconds: 3000
+/// ```
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct CorsRuleSpec {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_headers: Vec<String>,
+    /// Response headers the browser lets scripts read, e.g. ETag for multipart uploads
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub expose_headers: Vec<String>,
+    /// How long browsers may cache the preflight response
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age_seconds: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CorsFile {
+    rules: Vec<CorsRuleSpec>,
+}
+
+// Header names are HTTP tokens: letters, digits and a few symbols, without spaces
+fn is_header_name(header: &str) -> bool {
+    !header.is_empty()
+        && header
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c))
+}
+
+fn check_origin(origin: &str) -> Result<(), String> {
+    if origin == "*" {
+        return Ok(());
+    }
+    if origin.matches('*').count() > 1 {
+        return Err(format!(
+            "The origin '{origin}' has more than one '*', S3 allows one"
+        ));
+    }
+    let Some((scheme, host)) = origin.split_once("://") else {
+        return Err(format!(
+            "The origin '{origin}' should start with http:// or https://, as browsers send it"
+        ));
+    };
+    if !["http", "https"].contains(&scheme) {
+        return Err(format!(
+            "The origin '{origin}' should use http or https, not '{scheme}'"
+        ));
+    }
+    // The Origin header never has a path, so such an origin would never match
+    if host.is_empty() || host.contains('/') {
+        return Err(format!(
+            "The origin '{origin}' should be only a scheme and a host, e.g. https://app.example.com"
+        ));
+    }
+    Ok(())
+}
+
+impl CorsRuleSpec {
+    pub fn validate(&self) -> Result<(), String> {
+        let name = self
+            .id
+            .as_deref()
+            .map_or("A CORS rule".to_string(), |id| format!("The rule '{id}'"));
+        if self.id.as_ref().is_some_and(|id| id.len() > 255) {
+            return Err(format!("{name} has an id longer than 255 characters"));
+        }
+        if self.allowed_origins.is_empty() {
+            return Err(format!("{name} has no allowed origin"));
+        }
+        for origin in &self.allowed_origins {
+            check_origin(origin)?;
+        }
+        if self.allowed_methods.is_empty() {
+            return Err(format!("{name} has no allowed method"));
+        }
+        if let Some(method) = self
+            .allowed_methods
+            .iter()
+            .find(|method| !CORS_METHODS.contains(&method.as_str()))
+        {
+            return Err(format!(
+                "{name} allows '{method}', which S3 doesn't support; use {}",
+                CORS_METHODS.join(", ")
+            ));
+        }
+        for header in &self.allowed_headers {
+            if !is_header_name(header) || header.matches('*').count() > 1 {
+                return Err(format!(
+                    "The allowed header '{header}' isn't a header name with at most one '*'"
+                ));
+            }
+        }
+        for header in &self.expose_headers {
+            if !is_header_name(header) || header.contains('*') {
+                return Err(format!(
+                    "The exposed header '{header}' should be a full header name, without '*'"
+                ));
+            }
+        }
+        if self.max_age_seconds.is_some_and(|seconds| seconds < 0) {
+            return Err(format!("{name} has a negative max age"));
+        }
+        Ok(())
+    }
+
+    fn to_rule(&self) -> CorsRule {
+        let list = |values: &[String]| (!values.is_empty()).then(|| values.to_vec());
+        CorsRule::builder()
+            .set_id(self.id.clone())
+            .set_allowed_origins(Some(self.allowed_origins.clone()))
+            .set_allowed_methods(Some(self.allowed_methods.clone()))
+            .set_allowed_headers(list(&self.allowed_headers))
+            .set_expose_headers(list(&self.expose_headers))
+            .set_max_age_seconds(self.max_age_seconds)
+            .build()
+    }
+
+    fn from_rule(rule: &CorsRule) -> Self {
+        let list = |values: Option<&[String]>| values.unwrap_or_default().to_vec();
+        Self {
+            id: rule.id().map(str::to_string),
+            allowed_origins: list(rule.allowed_origins()),
+            allowed_methods: list(rule.allowed_methods()),
+            allowed_headers: list(rule.allowed_headers()),
+            expose_headers: list(rule.expose_headers()),
+            // The SDK reports an unset max age as 0
+            max_age_seconds: Some(rule.max_age_seconds()).filter(|seconds| *seconds > 0),
+        }
+    }
+}
+
+/// Checks every rule and the limits of the whole configuration
+pub fn validate_cors(rules: &[CorsRuleSpec]) -> Result<(), String> {
+    if rules.len() > MAX_RULES {
+        return Err(format!(
+            "S3 accepts at most {MAX_RULES} CORS rules, not {}",
+            rules.len()
+        ));
+    }
+    for rule in rules {
+        rule.validate()?;
+    }
+    Ok(())
+}
+
+/// Reads CORS rules from a .json file, or from YAML otherwise. Both a bare list of rules and a
+/// 'rules:' key holding the list are accepted, and JSON files also in the CORSRules format of
+/// 'aws s3api get-bucket-cors'
+pub fn read_cors_file(path: &Path) -> Result<Vec<CorsRuleSpec>, String> {
+    let content =
+        fs::read_to_string(path).map_err(|error| format!("{}: {error}", path.display()))?;
+    let is_json = path
+        .extension()
+        .is_some_and(|extension| extension == "json");
+    let rules = match is_json {
+        true => serde_json::from_str::<CorsFile>(&content)
+            .map(|file| file.rules)
+            .or_else(|_| serde_json::from_str::<Vec<CorsRuleSpec>>(&content))
+            .or_else(|error| from_s3api(&content).ok_or(error))
+            .map_err(|error| format!("{}: {error}", path.display()))?,
+        false => serde_yaml::from_str::<CorsFile>(&content)
+            .map(|file| file.rules)
+            .or_else(|_| serde_yaml::from_str::<Vec<CorsRuleSpec>>(&content))
+            .map_err(|error| format!("{}: {error}", path.display()))?,
+    };
+    validate_cors(&rules)?;
+    Ok(rules)
+}
+
+// The AWS CLI and console use { "CORSRules": [{ "AllowedOrigins": [...], ... }] }
+fn from_s3api(content: &str) -> Option<Vec<CorsRuleSpec>> {
+    let document = serde_json::from_str::<serde_json::Value>(content).ok()?;
+    let rules = document.get("CORSRules").unwrap_or(&document).as_array()?;
+    let strings = |rule: &serde_json::Value, field: &str| -> Vec<String> {
+        rule.get(field)
+            .and_then(|values| values.as_array())
+            .map(|values| {
+                values
+                    .iter()
+                    .filter_map(|value| value.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    rules
+        .iter()
+        .map(|rule| {
+            rule.get("AllowedOrigins")?;
+            Some(CorsRuleSpec {
+                id: rule
+                    .get("ID")
+                    .and_then(|id| id.as_str())
+                    .map(str::to_string),
+                allowed_origins: strings(rule, "AllowedOrigins"),
+                allowed_methods: strings(rule, "AllowedMethods"),
+                allowed_headers: strings(rule, "AllowedHeaders"),
+                expose_headers: strings(rule, "ExposeHeaders"),
+                max_age_seconds: rule
+                    .get("MaxAgeSeconds")
+                    .and_then(|seconds| seconds.as_i64())
+                    .map(|seconds| seconds as i32),
+            })
+        })
+        .collect()
+}
+
+/// Writes the rules in the same format 'read_cors_file' reads, so they can be edited and applied again
+pub fn write_cors_file(path: &Path, rules: &[CorsRuleSpec]) {
+    let file = CorsFile {
+        rules: rules.to_vec(),
+    };
+    let content = match path
+        .extension()
+        .is_some_and(|extension| extension == "json")
+    {
+        true => serde_json::to_string_pretty(&file).unwrap(),
+        false => serde_yaml::to_string(&file).unwrap(),
+    };
+    fs::write(path, content).expect("Error while writing the CORS file\n");
+    println!(
+        "The CORS rules are written to {}\n",
+        path.display().to_string().green().bold()
+    );
+}
+
+/// The CORS rules of a bucket; a bucket without a CORS configuration has none
+pub async fn get_cors(config: &SdkConfig, bucket: &str) -> Vec<CorsRuleSpec> {
+    let client = Client::new(config);
+    match client.get_bucket_cors().bucket(bucket).send().await {
+        Ok(output) => output
+            .cors_rules()
+            .unwrap_or_default()
+            .iter()
+            .map(CorsRuleSpec::from_rule)
+            .collect(),
+        Err(error) if error.code() == Some("NoSuchCORSConfiguration") => Vec::new(),
+        Err(error) => panic!(
+            "Error while getting the CORS rules: {}\n",
+            error.into_service_error()
+        ),
+    }
+}
+
+pub fn print_cors(bucket: &str, rules: &[CorsRuleSpec]) {
+    if rules.is_empty() {
+        println!(
+            "{}\n",
+            format!("'{bucket}' has no CORS rules").yellow().bold()
+        );
+        return;
+    }
+    for (index, rule) in rules.iter().enumerate() {
+        let name = rule
+            .id
+            .clone()
+            .unwrap_or_else(|| format!("Rule {}", index + 1));
+        println!(
+            "{} allows {} from {}",
+            name.green().bold(),
+            rule.allowed_methods.join(", ").bold(),
+            rule.allowed_origins.join(", ").bold()
+        );
+        if !rule.allowed_headers.is_empty() {
+            println!("    request headers: {}", rule.allowed_headers.join(", "));
+        }
+        if !rule.expose_headers.is_empty() {
+            println!("    exposed headers: {}", rule.expose_headers.join(", "));
+        }
+        if let Some(seconds) = rule.max_age_seconds {
+            println!("    preflight cached for {seconds} seconds");
+        }
+        println!();
+    }
+}
+
+/// Validates the rules, shows them and replaces the bucket's after a confirmation that
+/// 'assume_yes' skips. Returns whether the rules were saved
+pub async fn put_cors(
+    config: &SdkConfig,
+    bucket: &str,
+    rules: &[CorsRuleSpec],
+    assume_yes: bool,
+) -> bool {
+    if let Err(message) = validate_cors(rules) {
+        println!("{}\n", message.red().bold());
+        return false;
+    }
+    if rules.is_empty() {
+        return delete_cors(config, bucket, assume_yes).await;
+    }
+    println!("{}\n", "The bucket will have these CORS rules:".bold());
+    print_cors(bucket, rules);
+    let confirm = assume_yes
+        || Confirm::new("Save these CORS rules?\n")
+            .with_default(false)
+            .prompt()
+            .unwrap();
+    if !confirm {
+        println!("{}\n", "The CORS rules weren't changed".yellow().bold());
+        return false;
+    }
+    let client = Client::new(config);
+    let configuration = CorsConfiguration::builder()
+        .set_cors_rules(Some(rules.iter().map(CorsRuleSpec::to_rule).collect()))
+        .build();
+    match client
+        .put_bucket_cors()
+        .bucket(bucket)
+        .cors_configuration(configuration)
+        .send()
+        .await
+    {
+        Ok(_) => {
+            println!(
+                "{} CORS rule(s) saved on '{}'\n",
+                rules.len().to_string().green().bold(),
+                bucket.green().bold()
+            );
+            true
+        }
+        Err(error) => {
+            println!(
+                "{}: {}\n",
+                "Error while saving the CORS rules".red().bold(),
+                error.into_service_error()
+            );
+            false
+        }
+    }
+}
+
+pub async fn delete_cors(config: &SdkConfig, bucket: &str, assume_yes: bool) -> bool {
+    let current = get_cors(config, bucket).await;
+    if current.is_empty() {
+        println!(
+            "{}\n",
+            format!("'{bucket}' has no CORS rules").yellow().bold()
+        );
+        return false;
+    }
+    print_cors(bucket, &current);
+    let confirm = assume_yes
+        || Confirm::new("Delete these CORS rules?\n")
+            .with_default(false)
+            .prompt()
+            .unwrap();
+    if !confirm {
+        return false;
+    }
+    let client = Client::new(config);
+    client
+        .delete_bucket_cors()
+        .bucket(bucket)
+        .send()
+        .await
+        .expect("Error while deleting the CORS rules\n");
+    println!(
+        "The CORS rules of '{}' are deleted\n",
+        bucket.green().bold()
+    );
+    true
+}
+
+fn split_list(input: &str) -> Vec<String> {
+    input
+        .split([',', ' '])
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Builds a rule from prompts, starting from 'current' when a rule is edited.
+/// Returns None when the rule isn't valid and the user stops editing it
+pub fn prompt_cors_rule(current: Option<&CorsRuleSpec>) -> Option<CorsRuleSpec> {
+    loop {
+        let id = Text::new("Enter an id for the rule, or leave it empty\n")
+            .with_initial_value(current.and_then(|rule| rule.id.as_deref()).unwrap_or(""))
+            .with_formatter(&|str| format!(".....{str}.....\n"))
+            .prompt()
+            .unwrap();
+        let origins = Text::new("Enter the allowed origins, separated by commas\n")
+            .with_initial_value(
+                &current.map_or(String::new(), |rule| rule.allowed_origins.join(", ")),
+            )
+            .with_placeholder("https://app.example.com, https://*.example.com")
+            .with_help_message("'*' allows every site")
+            .with_formatter(&|str| format!(".....{str}.....\n"))
+            .prompt()
+            .unwrap();
+        let defaults = CORS_METHODS
+            .iter()
+            .enumerate()
+            .filter(|(_, method)| {
+                current.map_or(**method == "GET", |rule| {
+                    rule.allowed_methods
+                        .iter()
+                        .any(|allowed| allowed == *method)
+                })
+            })
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        let methods = MultiSelect::new("Select the allowed methods\n", CORS_METHODS.to_vec())
+            .with_default(&defaults)
+            .with_help_message("Direct browser uploads need PUT, or POST for upload forms")
+            .prompt()
+            .unwrap();
+        let headers = Text::new("Enter the request headers to allow, separated by commas\n")
+            .with_initial_value(
+                &current.map_or("*".to_string(), |rule| rule.allowed_headers.join(", ")),
+            )
+            .with_help_message("'*' allows any header, such as Content-Type and x-amz-*")
+            .with_formatter(&|str| format!(".....{str}.....\n"))
+            .prompt()
+            .unwrap();
+        let exposed =
+            Text::new("Enter the response headers scripts may read, separated by commas\n")
+                .with_initial_value(
+                    &current.map_or("ETag".to_string(), |rule| rule.expose_headers.join(", ")),
+                )
+                .with_help_message("Multipart uploads from the browser need ETag")
+                .with_formatter(&|str| format!(".....{str}.....\n"))
+                .prompt()
+                .unwrap();
+        let max_age = Text::new("Cache the preflight response for how many seconds?\n")
+            .with_initial_value(&current.map_or("3000".to_string(), |rule| {
+                rule.max_age_seconds
+                    .map(|seconds| seconds.to_string())
+                    .unwrap_or_default()
+            }))
+            .with_placeholder("Leave empty to let the browser decide")
+            .with_validator(|input: &str| match input.trim() {
+                "" => Ok(inquire::validator::Validation::Valid),
+                seconds => match seconds.parse::<i32>() {
+                    Ok(seconds) if seconds >= 0 => Ok(inquire::validator::Validation::Valid),
+                    _ => Ok(inquire::validator::Validation::Invalid(
+                        "Enter a number of seconds".into(),
+                    )),
+                },
+            })
+            .with_formatter(&|str| format!(".....{str}.....\n"))
+            .prompt()
+            .unwrap();
+        let rule = CorsRuleSpec {
+            id: Some(id.trim().to_string()).filter(|id| !id.is_empty()),
+            allowed_origins: split_list(&origins),
+            allowed_methods: methods.into_iter().map(str::to_string).collect(),
+            allowed_headers: split_list(&headers),
+            expose_headers: split_list(&exposed),
+            max_age_seconds: max_age.trim().parse().ok(),
+        };
+        match rule.validate() {
+            Ok(()) => break Some(rule),
+            Err(message) => {
+                println!("{}\n", message.red().bold());
+                let retry = Confirm::new("Edit the rule again?\n")
+                    .with_default(true)
+                    .prompt()
+                    .unwrap();
+                if !retry {
+                    break None;
+                }
+            }
+        }
+    }
+}
+
+/// The interactive menu for the CORS rules of a bucket
+pub async fn manage_cors(config: &SdkConfig, bucket: &str) {
+    loop {
+        let rules = get_cors(config, bucket).await;
+        print_cors(bucket, &rules);
+        let mut choices = vec!["Add a rule", "Apply rules from a YAML or JSON file"];
+        if !rules.is_empty() {
+            choices.extend(["Edit a rule", "Delete rules", "Delete all CORS rules"]);
+        }
+        choices.push("Done");
+        let choice = Select::new("What do you want to do with the CORS rules?\n", choices)
+            .prompt()
+            .unwrap();
+        let names = rules
+            .iter()
+            .enumerate()
+            .map(|(index, rule)| match &rule.id {
+                Some(id) => format!("{}. {id}", index + 1),
+                None => format!("{}. {}", index + 1, rule.allowed_origins.join(", ")),
+            })
+            .collect::<Vec<_>>();
+        let position = |name: &str| names.iter().position(|candidate| candidate == name);
+        let updated = match choice {
+            "Add a rule" => match prompt_cors_rule(None) {
+                Some(rule) => rules.into_iter().chain([rule]).collect(),
+                None => continue,
+            },
+            "Apply rules from a YAML or JSON file" => {
+                let path = Text::new("Enter the path of the CORS file\n")
+                    .with_placeholder("cors.yaml")
+                    .with_formatter(&|str| format!(".....{str}.....\n"))
+                    .prompt()
+                    .unwrap();
+                match read_cors_file(Path::new(path.trim())) {
+                    Ok(rules) => rules,
+                    Err(message) => {
+                        println!("{}\n", message.red().bold());
+                        continue;
+                    }
+                }
+            }
+            "Edit a rule" => {
+                let name = Select::new("Select the rule to edit\n", names.clone())
+                    .prompt()
+                    .unwrap();
+                let index = position(&name).unwrap();
+                let Some(edited) = prompt_cors_rule(Some(&rules[index])) else {
+                    continue;
+                };
+                let mut rules = rules;
+                rules[index] = edited;
+                rules
+            }
+            "Delete rules" => {
+                let removed = MultiSelect::new("Select the rules to delete\n", names.clone())
+                    .prompt()
+                    .unwrap()
+                    .iter()
+                    .filter_map(|name| position(name))
+                    .collect::<Vec<_>>();
+                rules
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(index, _)| !removed.contains(index))
+                    .map(|(_, rule)| rule)
+                    .collect()
+            }
+            "Delete all CORS rules" => {
+                delete_cors(config, bucket, false).await;
+                continue;
+            }
+            _ => return,
+        };
+        put_cors(config, bucket, &updated, false).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(yaml: &str) -> CorsRuleSpec {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    fn error(yaml: &str) -> String {
+        rule(yaml).validate().unwrap_err()
+    }
+
+    #[test]
+    fn valid_rules() {
+        let uploads = rule(
+            r#"id: uploads
+allowed_origins: [https://app.example.com, "https://*.example.com", "http://localhost:3000"]
+allowed_methods: [GET, PUT, POST]
+allowed_headers: ["*", "x-amz-*"]
+expose_headers: [ETag]
+max_age_seconds: 3000"#,
+        );
+        assert_eq!(uploads.validate(), Ok(()));
+        assert_eq!(
+            rule(r#"{ allowed_origins: ["*"], allowed_methods: [HEAD] }"#).validate(),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn invalid_origins() {
+        for origin in [
+            "https://*.*.example.com",
+            "app.example.com",
+            "ftp://app.example.com",
+            "https://",
+            "https://app.example.com/path",
+        ] {
+            let yaml = format!("{{ allowed_origins: ['{origin}'], allowed_methods: [GET] }}");
+            assert!(error(&yaml).contains(origin), "{origin}");
+        }
+        assert!(
+            error("{ allowed_origins: [], allowed_methods: [GET] }").contains("no allowed origin")
+        );
+    }
+
+    #[test]
+    fn invalid_methods() {
+        assert!(
+            error("{ allowed_origins: ['*'], allowed_methods: [] }").contains("no allowed method")
+        );
+        assert!(
+            error("{ id: api, allowed_origins: ['*'], allowed_methods: [PATCH] }")
+                .contains("The rule 'api' allows 'PATCH'")
+        );
+    }
+
+    #[test]
+    fn invalid_headers() {
+        for yaml in [
+            "{ allowed_origins: ['*'], allowed_methods: [GET], allowed_headers: ['x-*-*'] }",
+            "{ allowed_origins: ['*'], allowed_methods: [GET], allowed_headers: ['Content Type'] }",
+        ] {
+            assert!(error(yaml).contains("allowed header"), "{yaml}");
+        }
+        assert!(error(
+            "{ allowed_origins: ['*'], allowed_methods: [GET], expose_headers: ['x-amz-*'] }"
+        )
+        .contains("exposed header"));
+    }
+
+    #[test]
+    fn id_length_and_max_age() {
+        let long = format!(
+            "{{ id: {}, allowed_origins: ['*'], allowed_methods: [GET] }}",
+            "a".repeat(256)
+        );
+        assert!(error(&long).contains("longer than 255"));
+        assert!(
+            error("{ allowed_origins: ['*'], allowed_methods: [GET], max_age_seconds: -1 }")
+                .contains("negative max age")
+        );
+    }
+
+    #[test]
+    fn too_many_rules() {
+        let rules = vec![rule("{ allowed_origins: ['*'], allowed_methods: [GET] }"); MAX_RULES + 1];
+        assert!(validate_cors(&rules).unwrap_err().contains("at most 100"));
+        assert_eq!(validate_cors(&rules[..MAX_RULES]), Ok(()));
+    }
+}
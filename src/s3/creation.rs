@@ -0,0 +1,527 @@
+use aws_config::SdkConfig;
+use aws_sdk_s3::error::ProvideErrorMetadata;
+use aws_sdk_s3::types::{
+    BucketLocationConstraint, BucketVersioningStatus, CreateBucketConfiguration, ObjectOwnership,
+    PublicAccessBlockConfiguration, Tag, Tagging, VersioningConfiguration,
+};
+use colored::Colorize;
+use inquire::{Confirm, Select, Text};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use super::client_for_region;
+use super::encryption::{default_encryption, prompt_bucket_encryption, Encryption};
+use super::metadata::{parse_pairs, parse_tag_pair};
+
+pub const OWNERSHIP_SETTINGS: [&str; 3] = [
+    "BucketOwnerEnforced",
+    "BucketOwnerPreferred",
+    "ObjectWriter",
+];
+/// A bucket can have at most 50 tags
+const MAX_BUCKET_TAGS: usize = 50;
+// Prefixes and suffixes S3 keeps for access points, Object Lambda and directory buckets
+const RESERVED_PREFIXES: [&str; 4] = ["xn--", "sthree-", "sthree-configurator", "amzn-s3-demo-"];
+const RESERVED_SUFFIXES: [&str; 3] = ["-s3alias", "--ol-s3", "--x-s3"];
+
+/// Everything a bucket gets at creation, as written in a spec file, e.g.
+///
+/// ```yaml
+/// name: example-site-assets
+/// region: eu-west-1
+/// object_ownership: BucketOwnerEnforced
+/// versioning: true
+/// encryption: aws:kms
+/// kms_key_id: alias/site-assets
+/// bucket_key: true
+/// block_public_access: true
+/// tags:
+///   team: web
+/// ```
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BucketSpec {
+    pub name: String,
+    /// The configured region when omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    /// BucketOwnerEnforced turns ACLs off, which S3 recommends
+    #[serde(default = "default_ownership")]
+    pub object_ownership: String,
+    #[serde(default)]
+    pub versioning: bool,
+    /// AES256 or aws:kms; new buckets use SSE-S3 when omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encryption: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kms_key_id: Option<String>,
+    #[serde(default)]
+    pub bucket_key: bool,
+    /// Turns on all four Block Public Access settings, as S3 does for new buckets
+    #[serde(default = "block_by_default")]
+    pub block_public_access: bool,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<String, String>,
+}
+
+fn default_ownership() -> String {
+    OWNERSHIP_SETTINGS[0].into()
+}
+
+fn block_by_default() -> bool {
+    true
+}
+
+/// Checks the name against the S3 naming rules, which keep it usable as a DNS label
+pub fn validate_bucket_name(name: &str) -> Result<(), String> {
+    if name.len() < 3 || name.len() > 63 {
+        return Err(format!(
+            "'{name}' has {} characters; bucket names have 3 to 63",
+            name.len()
+        ));
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| !(c.is_ascii_lowercase() || c.is_ascii_digit() || *c == '.' || *c == '-'))
+    {
+        return Err(format!(
+            "'{name}' contains '{c}'; use lowercase letters, digits, dots and hyphens"
+        ));
+    }
+    let alphanumeric = |c: Option<char>| c.is_some_and(|c| c.is_ascii_alphanumeric());
+    if !alphanumeric(name.chars().next()) || !alphanumeric(name.chars().last()) {
+        return Err(format!(
+            "'{name}' should begin and end with a letter or a digit"
+        ));
+    }
+    if name.contains("..") || name.contains(".-") || name.contains("-.") {
+        return Err(format!(
+            "'{name}' has an empty label; dots can't be next to each other or to a hyphen"
+        ));
+    }
+    let parts = name.split('.').collect::<Vec<_>>();
+    if parts.len() == 4 && parts.iter().all(|part| part.parse::<u8>().is_ok()) {
+        return Err(format!(
+            "'{name}' looks like an IP address, which S3 doesn't allow"
+        ));
+    }
+    if let Some(prefix) = RESERVED_PREFIXES
+        .iter()
+        .find(|prefix| name.starts_with(*prefix))
+    {
+        return Err(format!("Bucket names can't start with '{prefix}'"));
+    }
+    if let Some(suffix) = RESERVED_SUFFIXES
+        .iter()
+        .find(|suffix| name.ends_with(*suffix))
+    {
+        return Err(format!("Bucket names can't end with '{suffix}'"));
+    }
+    Ok(())
+}
+
+impl BucketSpec {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_bucket_name(&self.name)?;
+        if let Some(region) = &self.region {
+            let valid = !region.is_empty()
+                && region
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+            if !valid {
+                return Err(format!("'{region}' isn't a region name such as eu-west-1"));
+            }
+        }
+        if !OWNERSHIP_SETTINGS.contains(&self.object_ownership.as_str()) {
+            return Err(format!(
+                "'{}' isn't an object ownership setting; use {}",
+                self.object_ownership,
+                OWNERSHIP_SETTINGS.join(", ")
+            ));
+        }
+        let encryption = self.encryption()?;
+        if self.bucket_key && !matches!(encryption, Encryption::Kms { .. }) {
+            return Err("A Bucket Key only applies to SSE-KMS".into());
+        }
+        if self.tags.len() > MAX_BUCKET_TAGS {
+            return Err(format!(
+                "A bucket can have at most {MAX_BUCKET_TAGS} tags, not {}",
+                self.tags.len()
+            ));
+        }
+        for (key, value) in &self.tags {
+            parse_tag_pair(&format!("{key}={value}"))?;
+        }
+        Ok(())
+    }
+
+    /// The region of the spec, or else the configured one
+    pub fn region_or_default(&self, config: &SdkConfig) -> String {
+        self.region
+            .clone()
+            .or(config.region().map(|region| region.to_string()))
+            .unwrap_or("us-east-1".into())
+    }
+
+    fn encryption(&self) -> Result<Encryption, String> {
+        Encryption::from_args(self.encryption.as_deref(), self.kms_key_id.clone(), None)
+    }
+
+    pub fn print(&self, region: &str) {
+        let encryption = self.encryption().unwrap_or_default();
+        let rows = [
+            ("Name", self.name.clone()),
+            ("Region", region.to_string()),
+            ("Object ownership", self.object_ownership.clone()),
+            (
+                "Versioning",
+                match self.versioning {
+                    true => "enabled".into(),
+                    false => "off".into(),
+                },
+            ),
+            (
+                "Default encryption",
+                match (&encryption, self.bucket_key) {
+                    (Encryption::BucketDefault, _) => "SSE-S3".into(),
+                    (_, true) => format!("{} with an S3 Bucket Key", encryption.describe()),
+                    (_, false) => encryption.describe(),
+                },
+            ),
+            (
+                "Block Public Access",
+                match self.block_public_access {
+                    true => "all on".into(),
+                    false => "all off".into(),
+                },
+            ),
+            (
+                "Tags",
+                match self.tags.is_empty() {
+                    true => "-".into(),
+                    false => self
+                        .tags
+                        .iter()
+                        .map(|(key, value)| format!("{key}={value}"))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                },
+            ),
+        ];
+        for (name, value) in rows {
+            println!("{:<20} {}", format!("{name}:").bold(), value);
+        }
+        println!();
+    }
+}
+
+/// Reads a bucket spec from a .json file, or from YAML otherwise
+pub fn read_spec_file(path: &Path) -> Result<BucketSpec, String> {
+    let content =
+        fs::read_to_string(path).map_err(|error| format!("{}: {error}", path.display()))?;
+    let spec = match path
+        .extension()
+        .is_some_and(|extension| extension == "json")
+    {
+        true => serde_json::from_str::<BucketSpec>(&content)
+            .map_err(|error| format!("{}: {error}", path.display()))?,
+        false => serde_yaml::from_str::<BucketSpec>(&content)
+            .map_err(|error| format!("{}: {error}", path.display()))?,
+    };
+    spec.validate()?;
+    Ok(spec)
+}
+
+/// Creates the bucket, then applies the Block Public Access, versioning, encryption and tags
+/// of the spec. Requests go to the bucket's region, so it may differ from the configured one.
+/// Returns whether every step succeeded
+pub async fn create_bucket(config: &SdkConfig, spec: &BucketSpec) -> bool {
+    if let Err(message) = spec.validate() {
+        println!("{}\n", message.red().bold());
+        return false;
+    }
+    let region = spec.region_or_default(config);
+    let client = client_for_region(config, &region);
+    let name = spec.name.as_str();
+
+    // us-east-1 is the default location and S3 refuses it as an explicit constraint
+    let location = (region != "us-east-1").then(|| {
+        CreateBucketConfiguration::builder()
+            .location_constraint(BucketLocationConstraint::from(region.as_str()))
+            .build()
+    });
+    let result = client
+        .create_bucket()
+        .bucket(name)
+        .set_create_bucket_configuration(location)
+        .object_ownership(ObjectOwnership::from(spec.object_ownership.as_str()))
+        .send()
+        .await;
+    if let Err(error) = result {
+        let message = match error.code() {
+            Some("BucketAlreadyOwnedByYou") => format!("You already own a bucket named '{name}'"),
+            Some("BucketAlreadyExists") => {
+                format!("'{name}' is taken by another account; bucket names are shared by everyone")
+            }
+            _ => format!(
+                "Error while creating the bucket: {}",
+                error.into_service_error()
+            ),
+        };
+        println!("{}\n", message.red().bold());
+        return false;
+    }
+    println!(
+        "The bucket '{}' is created in {}",
+        name.green().bold(),
+        region.green().bold()
+    );
+
+    let blocked = spec.block_public_access;
+    let steps = [
+        (
+            "Block Public Access",
+            client
+                .put_public_access_block()
+                .bucket(name)
+                .public_access_block_configuration(
+                    PublicAccessBlockConfiguration::builder()
+                        .block_public_acls(blocked)
+                        .ignore_public_acls(blocked)
+                        .block_public_policy(blocked)
+                        .restrict_public_buckets(blocked)
+                        .build(),
+                )
+                .send()
+                .await
+                .map(|_| ())
+                .map_err(|error| error.into_service_error().to_string()),
+        ),
+        (
+            "versioning",
+            match spec.versioning {
+                true => client
+                    .put_bucket_versioning()
+                    .bucket(name)
+                    .versioning_configuration(
+                        VersioningConfiguration::builder()
+                            .status(BucketVersioningStatus::Enabled)
+                            .build(),
+                    )
+                    .send()
+                    .await
+                    .map(|_| ())
+                    .map_err(|error| error.into_service_error().to_string()),
+                false => Ok(()),
+            },
+        ),
+        (
+            "default encryption",
+            match spec.encryption() {
+                Ok(encryption) => match encryption.server_side_encryption() {
+                    Some(algorithm) => client
+                        .put_bucket_encryption()
+                        .bucket(name)
+                        .server_side_encryption_configuration(default_encryption(
+                            algorithm,
+                            &encryption,
+                            spec.bucket_key,
+                        ))
+                        .send()
+                        .await
+                        .map(|_| ())
+                        .map_err(|error| error.into_service_error().to_string()),
+                    None => Ok(()),
+                },
+                Err(message) => Err(message),
+            },
+        ),
+        (
+            "tags",
+            match spec.tags.is_empty() {
+                true => Ok(()),
+                false => {
+                    let tag_set = spec
+                        .tags
+                        .iter()
+                        .map(|(key, value)| Tag::builder().key(key).value(value).build())
+                        .collect();
+                    client
+                        .put_bucket_tagging()
+                        .bucket(name)
+                        .tagging(Tagging::builder().set_tag_set(Some(tag_set)).build())
+                        .send()
+                        .await
+                        .map(|_| ())
+                        .map_err(|error| error.into_service_error().to_string())
+                }
+            },
+        ),
+    ];
+    let mut completed = true;
+    for (step, result) in steps {
+        if let Err(error) = result {
+            completed = false;
+            println!("{} {step}: {error}", "Error while setting".red().bold());
+        }
+    }
+    match completed {
+        true => println!("{}\n", "Every setting is applied".green().bold()),
+        false => println!(
+            "{}\n",
+            "The bucket exists, but the settings above weren't applied"
+                .yellow()
+                .bold()
+        ),
+    }
+    completed
+}
+
+/// Asks for the name and every setting, validating the name as it is typed
+pub fn prompt_bucket_spec(config: &SdkConfig) -> BucketSpec {
+    let name = Text::new("Enter the name of the bucket\n")
+        .with_help_message(
+            "3 to 63 lowercase letters, digits, dots and hyphens, unique across all AWS accounts",
+        )
+        .with_validator(|input: &str| match validate_bucket_name(input.trim()) {
+            Ok(()) => Ok(inquire::validator::Validation::Valid),
+            Err(message) => Ok(inquire::validator::Validation::Invalid(message.into())),
+        })
+        .with_formatter(&|str| format!(".....{str}.....\n"))
+        .prompt()
+        .unwrap();
+    if name.contains('.') {
+        println!(
+            "{} dots in the name break HTTPS with virtual-hosted URLs and Transfer Acceleration\n",
+            "Warning:".yellow().bold()
+        );
+    }
+    let configured = config
+        .region()
+        .map(|region| region.to_string())
+        .unwrap_or("us-east-1".into());
+    let region = Text::new("Enter the region of the bucket\n")
+        .with_default(&configured)
+        .with_formatter(&|str| format!(".....{str}.....\n"))
+        .prompt()
+        .unwrap();
+    let object_ownership =
+        Select::new("Select the object ownership\n", OWNERSHIP_SETTINGS.to_vec())
+            .with_help_message("BucketOwnerEnforced turns ACLs off and is what S3 recommends")
+            .prompt()
+            .unwrap();
+    let versioning = Confirm::new("Enable versioning?\n")
+        .with_default(false)
+        .prompt()
+        .unwrap();
+    let (encryption, bucket_key) = prompt_bucket_encryption(None).unwrap_or_default();
+    let block_public_access = Confirm::new("Block all public access?\n")
+        .with_default(true)
+        .with_help_message(
+            "Turn it off only for buckets that serve public content, such as websites",
+        )
+        .prompt()
+        .unwrap();
+    let tags = Text::new("Enter the tags as key=value separated by commas, or leave it empty\n")
+        .with_placeholder("team=web, env=prod")
+        .with_validator(|input: &str| match parse_pairs(input, parse_tag_pair) {
+            Ok(_) => Ok(inquire::validator::Validation::Valid),
+            Err(message) => Ok(inquire::validator::Validation::Invalid(message.into())),
+        })
+        .with_formatter(&|str| format!(".....{str}.....\n"))
+        .prompt()
+        .unwrap();
+    BucketSpec {
+        name: name.trim().to_string(),
+        region: Some(region.trim().to_string()),
+        object_ownership: object_ownership.to_string(),
+        versioning,
+        encryption: encryption
+            .server_side_encryption()
+            .map(|algorithm| algorithm.as_str().to_string()),
+        kms_key_id: encryption.kms_key_id(),
+        bucket_key,
+        block_public_access,
+        tags: parse_pairs(&tags, parse_tag_pair)
+            .unwrap_or_default()
+            .into_iter()
+            .collect(),
+    }
+}
+
+/// Creates a bucket from prompts or a spec file, after showing what it will have
+pub async fn create_bucket_interactively(config: &SdkConfig) {
+    let source = Select::new(
+        "How do you want to set up the bucket?\n",
+        vec!["Answer the prompts", "Read a YAML or JSON spec file"],
+    )
+    .prompt()
+    .unwrap();
+    let spec = match source {
+        "Answer the prompts" => prompt_bucket_spec(config),
+        _ => {
+            let path = Text::new("Enter the path of the spec file\n")
+                .with_placeholder("bucket.yaml")
+                .with_formatter(&|str| format!(".....{str}.....\n"))
+                .prompt()
+                .unwrap();
+            match read_spec_file(Path::new(path.trim())) {
+                Ok(spec) => spec,
+                Err(message) => {
+                    println!("{}\n", message.red().bold());
+                    return;
+                }
+            }
+        }
+    };
+    let region = spec.region_or_default(config);
+    spec.print(&region);
+    let confirm = Confirm::new("Create this bucket?\n")
+        .with_default(true)
+        .prompt()
+        .unwrap();
+    if confirm {
+        create_bucket(config, &spec).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_bucket_names() {
+        for name in ["abc", "my-bucket.logs", "1bucket9", &"a".repeat(63)] {
+            assert_eq!(
+                validate_bucket_name(name),
+                Ok(()),
+                "'{name}' should be valid"
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_bucket_names() {
+        for name in [
+            "ab",
+            &"a".repeat(64),
+            "My-Bucket",
+            "my_bucket",
+            "-bucket",
+            "bucket.",
+            "my..bucket",
+            "my.-bucket",
+            "192.168.1.1",
+            "xn--bucket",
+            "sthree-bucket",
+            "bucket-s3alias",
+            "bucket--ol-s3",
+        ] {
+            assert!(
+                validate_bucket_name(name).is_err(),
+                "'{name}' should be rejected"
+            );
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use aws_config::SdkConfig;
+use aws_sdk_s3::Client;
+use colored::Colorize;
+use globset::Glob;
+use inquire::{Confirm, Text};
+
+use super::format_size;
+use super::multipart::{abort_uploads, list_incomplete_uploads};
+use super::objects::{delete_keys, delete_versions, list_objects, list_versions};
+
+/// How many keys are shown before the preview is cut short
+const PREVIEW_LINES: usize = 20;
+
+/// What to delete: exact keys, everything under a prefix, or the keys matching a glob
+pub enum DeleteSelection {
+    Keys(Vec<String>),
+    Prefix(String),
+    Glob(String),
+}
+
+impl DeleteSelection {
+    /// Resolves the selection into the existing keys with their sizes. Exact keys that don't
+    /// exist are left out, so the preview shows what will really disappear
+    async fn resolve(&self, client: &Client, bucket: &str) -> Vec<(String, u64)> {
+        match self {
+            DeleteSelection::Keys(keys) => {
+                let mut found = Vec::new();
+                for key in keys {
+                    match client.head_object().bucket(bucket).key(key).send().await {
+                        Ok(head) => found.push((key.clone(), head.content_length().max(0) as u64)),
+                        Err(_) => println!(
+                            "{} {}",
+                            "Skipping the missing key".yellow().bold(),
+                            key.yellow()
+                        ),
+                    }
+                }
+                found
+            }
+            DeleteSelection::Prefix(prefix) => list_objects(client, bucket, prefix)
+                .await
+                .into_iter()
+                .map(|object| (object.key, object.size))
+                .collect(),
+            DeleteSelection::Glob(pattern) => {
+                let matcher = Glob::new(pattern)
+                    .unwrap_or_else(|error| panic!("Invalid glob '{pattern}': {error}\n"))
+                    .compile_matcher();
+                // Only the part before the first wildcard needs to be listed
+                let literal = pattern
+                    .find(['*', '?', '[', '{'])
+                    .map_or(pattern.as_str(), |position| &pattern[..position]);
+                list_objects(client, bucket, literal)
+                    .await
+                    .into_iter()
+                    .filter(|object| matcher.is_match(&object.key))
+                    .map(|object| (object.key, object.size))
+                    .collect()
+            }
+        }
+    }
+}
+
+fn print_preview(bucket: &str, keys: &[(String, u64)]) {
+    for (key, size) in keys.iter().take(PREVIEW_LINES) {
+        println!(
+            "    {:>10}  s3://{bucket}/{}",
+            format_size(*size),
+            key.red()
+        );
+    }
+    if keys.len() > PREVIEW_LINES {
+        println!("    ... and {} more", keys.len() - PREVIEW_LINES);
+    }
+    let total: u64 = keys.iter().map(|(_, size)| size).sum();
+    println!(
+        "\n{} object(s), {} in total\n",
+        keys.len().to_string().red().bold(),
+        format_size(total).red().bold()
+    );
+}
+
+/// Shows the objects the selection matches and deletes them after a confirmation, which
+/// 'assume_yes' skips. In a versioned bucket this adds delete markers; the older versions stay
+pub async fn delete_selection(
+    config: &SdkConfig,
+    bucket: &str,
+    selection: &DeleteSelection,
+    assume_yes: bool,
+) {
+    let client = Client::new(config);
+    let keys = selection.resolve(&client, bucket).await;
+    if keys.is_empty() {
+        println!(
+            "{}\n",
+            "Nothing matches; no object was deleted".yellow().bold()
+        );
+        return;
+    }
+    print_preview(bucket, &keys);
+    let confirm = assume_yes
+        || Confirm::new(&format!("Delete these {} object(s)?\n", keys.len()))
+            .with_default(false)
+            .prompt()
+            .unwrap();
+    if !confirm {
+        println!("{}\n", "Nothing was deleted".yellow().bold());
+        return;
+    }
+    let keys = keys.into_iter().map(|(key, _)| key).collect::<Vec<_>>();
+    let deleted = delete_keys(&client, bucket, &keys).await;
+    println!(
+        "{} of {} object(s) deleted\n",
+        deleted.to_string().green().bold(),
+        keys.len()
+    );
+}
+
+/// Deletes every object version, delete marker and incomplete multipart upload, then the bucket.
+/// Unless 'assume_yes' is given, the bucket name has to be typed to go ahead
+pub async fn empty_and_delete_bucket(config: &SdkConfig, bucket: &str, assume_yes: bool) {
+    let client = Client::new(config);
+    let versions = list_versions(&client, bucket, "").await;
+    let uploads = list_incomplete_uploads(config, bucket).await;
+    let markers = versions
+        .iter()
+        .filter(|version| version.is_delete_marker)
+        .count();
+    let bytes: u64 = versions.iter().map(|version| version.size).sum();
+    println!(
+        "The bucket '{}' holds {} object version(s) ({}), {} delete marker(s) and {} incomplete upload(s)\n",
+        bucket.red().bold(),
+        (versions.len() - markers).to_string().red().bold(),
+        format_size(bytes).red().bold(),
+        markers.to_string().red().bold(),
+        uploads.len().to_string().red().bold()
+    );
+    let confirm = assume_yes || {
+        let typed = Text::new("Type the bucket name to permanently delete all of it\n")
+            .with_help_message("Deleted versions can't be recovered")
+            .with_formatter(&|str| format!(".....{str}.....\n"))
+            .prompt()
+            .unwrap();
+        typed.trim() == bucket
+    };
+    if !confirm {
+        println!(
+            "{}\n",
+            "The name didn't match; nothing was deleted".yellow().bold()
+        );
+        return;
+    }
+
+    let deleted = delete_versions(&client, bucket, &versions).await;
+    println!(
+        "{} of {} version(s) and delete marker(s) deleted\n",
+        deleted.to_string().green().bold(),
+        versions.len()
+    );
+    abort_uploads(config, bucket, &uploads).await;
+    if deleted < versions.len() {
+        println!(
+            "{}\n",
+            "Some versions couldn't be deleted, so the bucket is kept"
+                .red()
+                .bold()
+        );
+        return;
+    }
+    match client.delete_bucket().bucket(bucket).send().await {
+        Ok(_) => println!("The bucket '{}' is deleted\n", bucket.green().bold()),
+        Err(error) => println!(
+            "{}: {}\n",
+            "Error while deleting the bucket".red().bold(),
+            error.into_service_error()
+        ),
+    }
+}
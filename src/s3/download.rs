@@ -0,0 +1,337 @@
+use aws_config::SdkConfig;
+use aws_sdk_s3::types::{ChecksumMode, ServerSideEncryption};
+use aws_sdk_s3::Client;
+use colored::Colorize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use super::checksum::{first_part_size, matches_checksum, matches_etag, Checksum};
+use super::encryption::Encryption;
+use super::transfer_bar;
+
+/// A byte range in the forms accepted by 'parse_range': '0-1023', '1024-' or '-500' (the last 500 bytes)
+#[derive(Clone, Copy)]
+pub enum ByteRange {
+    Span(u64, u64),
+    From(u64),
+    Last(u64),
+}
+
+impl ByteRange {
+    fn header(&self) -> String {
+        match self {
+            ByteRange::Span(start, end) => format!("bytes={start}-{end}"),
+            ByteRange::From(start) => format!("bytes={start}-"),
+            ByteRange::Last(length) => format!("bytes=-{length}"),
+        }
+    }
+}
+
+pub fn parse_range(input: &str) -> Result<ByteRange, String> {
+    let invalid = || format!("'{input}' should look like '0-1023', '1024-' or '-500'");
+    let (start, end) = input.trim().split_once('-').ok_or_else(invalid)?;
+    let number = |part: &str| part.trim().parse::<u64>().map_err(|_| invalid());
+    match (start.trim().is_empty(), end.trim().is_empty()) {
+        (false, false) => {
+            let (start, end) = (number(start)?, number(end)?);
+            match start <= end {
+                true => Ok(ByteRange::Span(start, end)),
+                false => Err(format!("The range '{input}' ends before it starts")),
+            }
+        }
+        (false, true) => Ok(ByteRange::From(number(start)?)),
+        (true, false) => Ok(ByteRange::Last(number(end)?)),
+        (true, true) => Err(invalid()),
+    }
+}
+
+pub struct DownloadOptions {
+    /// Download only part of the object; ranged downloads are neither resumed nor verified
+    pub range: Option<ByteRange>,
+    /// Compare the finished file with the object's SHA-256 or CRC32C checksum, or its ETag
+    /// when it was uploaded without one
+    pub verify: bool,
+    /// Download this version instead of the current one
+    pub version_id: Option<String>,
+    /// The SSE-C key the object was uploaded with; other encryptions need nothing here
+    pub encryption: Encryption,
+}
+
+/// Where the object ends up: a directory gets the last segment of the key as file name
+pub fn destination_path(destination: &Path, key: &str) -> PathBuf {
+    match destination.is_dir() || destination.as_os_str().is_empty() {
+        true => destination.join(key.rsplit('/').next().unwrap_or(key)),
+        false => destination.to_path_buf(),
+    }
+}
+
+fn sidecar(path: &Path, extension: &str) -> PathBuf {
+    path.with_file_name(format!(
+        "{}.{extension}",
+        path.file_name().unwrap().to_string_lossy()
+    ))
+}
+
+/// Streams an object to disk. Whole-object downloads are written to '<file>.part' and continue
+/// from where they stopped when run again, as long as the object's ETag hasn't changed.
+/// Returns whether the download completed (and verified, when asked)
+pub async fn download_object(
+    config: &SdkConfig,
+    bucket: &str,
+    key: &str,
+    destination: &Path,
+    options: &DownloadOptions,
+) -> bool {
+    let client = Client::new(config);
+    let path = destination_path(destination, key);
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent).expect("Error while creating the destination directory\n");
+    }
+    let head = client
+        .head_object()
+        .bucket(bucket)
+        .key(key)
+        .set_version_id(options.version_id.clone())
+        .set_sse_customer_algorithm(options.encryption.customer_algorithm())
+        .set_sse_customer_key(options.encryption.customer_key())
+        .set_sse_customer_key_md5(options.encryption.customer_key_md5())
+        .checksum_mode(ChecksumMode::Enabled)
+        .send()
+        .await
+        .expect("Error while getting the object's metadata; an SSE-C object needs the key it was uploaded with\n");
+    let checksum = Checksum::from_headers(head.checksum_sha256(), head.checksum_crc32_c());
+    let object_size = head.content_length().max(0) as u64;
+    let e_tag = head.e_tag().unwrap_or_default().to_string();
+    // The ETag of SSE-KMS and SSE-C objects isn't the MD5 of their content
+    let comparable_e_tag = head.sse_customer_algorithm().is_none()
+        && !matches!(
+            head.server_side_encryption(),
+            Some(ServerSideEncryption::AwsKms) | Some(ServerSideEncryption::AwsKmsDsse)
+        );
+
+    if let Some(range) = options.range {
+        return download_range(&client, bucket, key, &path, range, options).await;
+    }
+
+    let partial = sidecar(&path, "part");
+    let partial_tag = sidecar(&path, "part.etag");
+    let resume_from = match fs::read_to_string(&partial_tag) {
+        Ok(saved_tag) if saved_tag == e_tag => fs::metadata(&partial)
+            .map(|metadata| metadata.len())
+            .unwrap_or_default()
+            .min(object_size),
+        _ => 0,
+    };
+    if resume_from > 0 {
+        println!(
+            "Resuming the download of '{}' from {:.2} MB\n",
+            key.green().bold(),
+            resume_from as f64 / (1024.0 * 1024.0)
+        );
+    }
+    fs::write(&partial_tag, &e_tag).expect("Error while saving the download state\n");
+
+    if resume_from < object_size || object_size == 0 {
+        let mut request = client
+            .get_object()
+            .bucket(bucket)
+            .key(key)
+            .set_version_id(options.version_id.clone())
+            .set_sse_customer_algorithm(options.encryption.customer_algorithm())
+            .set_sse_customer_key(options.encryption.customer_key())
+            .set_sse_customer_key_md5(options.encryption.customer_key_md5())
+            .if_match(&e_tag);
+        if resume_from > 0 {
+            request = request.range(ByteRange::From(resume_from).header());
+        }
+        let output = request.send().await.expect(
+            "Error while downloading the object; it may have changed since the download started\n",
+        );
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(resume_from > 0)
+            .truncate(resume_from == 0)
+            .open(&partial)
+            .await
+            .expect("Error while creating the destination file\n");
+        let bar = transfer_bar(object_size, "Downloading");
+        bar.set_position(resume_from);
+        bar.reset_eta();
+        let mut body = output.body.into_async_read();
+        let mut buffer = vec![0; 256 * 1024];
+        loop {
+            let read = match body.read(&mut buffer).await {
+                Ok(read) => read,
+                Err(error) => {
+                    bar.abandon();
+                    println!(
+                        "{}: {error}\nRun the same download again to continue from {}\n",
+                        "The download was interrupted".red().bold(),
+                        bar.position()
+                    );
+                    return false;
+                }
+            };
+            if read == 0 {
+                break;
+            }
+            file.write_all(&buffer[..read])
+                .await
+                .expect("Error while writing the destination file\n");
+            bar.inc(read as u64);
+        }
+        file.flush()
+            .await
+            .expect("Error while writing the destination file\n");
+        bar.finish();
+    }
+
+    let written = fs::metadata(&partial)
+        .map(|metadata| metadata.len())
+        .unwrap_or_default();
+    if written != object_size {
+        println!(
+            "{}\n",
+            format!(
+                "Expected {object_size} bytes but {written} were written; run the download again"
+            )
+            .red()
+            .bold()
+        );
+        return false;
+    }
+    if let (true, Some(checksum)) = (options.verify, &checksum) {
+        // Checksums of multipart uploads combine the parts, so the part size is needed
+        let part_size = match checksum.parts() {
+            Some(_) => {
+                first_part_size(
+                    &client,
+                    bucket,
+                    key,
+                    options.version_id.clone(),
+                    &options.encryption,
+                )
+                .await
+            }
+            None => None,
+        };
+        let name = checksum.algorithm.name();
+        match matches_checksum(&partial, checksum, part_size) {
+            Some(true) => println!(
+                "{}",
+                format!("The file matches the object's {name} checksum")
+                    .green()
+                    .bold()
+            ),
+            Some(false) => {
+                let _ = fs::remove_file(&partial);
+                let _ = fs::remove_file(&partial_tag);
+                println!(
+                    "{}\n",
+                    format!("The downloaded file doesn't match the object's {name} checksum and was removed")
+                        .red()
+                        .bold()
+                );
+                return false;
+            }
+            None => println!(
+                "{}",
+                format!("The {name} checksum of the parts can't be reproduced locally, so only the size was checked")
+                    .yellow()
+                    .bold()
+            ),
+        }
+    } else if options.verify {
+        let matches = match comparable_e_tag {
+            true => matches_etag(&partial, &e_tag),
+            false => None,
+        };
+        match matches {
+            Some(true) => println!("{}", "The file matches the object's ETag".green().bold()),
+            Some(false) => {
+                let _ = fs::remove_file(&partial);
+                let _ = fs::remove_file(&partial_tag);
+                println!(
+                    "{}\n",
+                    "The downloaded file doesn't match the object's ETag and was removed"
+                        .red()
+                        .bold()
+                );
+                return false;
+            }
+            None => println!(
+                "{}",
+                "The ETag can't be reproduced locally (SSE-KMS, SSE-C or an unusual part size), so only the size was checked"
+                    .yellow()
+                    .bold()
+            ),
+        }
+    }
+    fs::rename(&partial, &path).expect("Error while moving the download into place\n");
+    let _ = fs::remove_file(&partial_tag);
+    println!(
+        "'{}' is saved to {}\n",
+        key.green().bold(),
+        path.display().to_string().green().bold()
+    );
+    true
+}
+
+async fn download_range(
+    client: &Client,
+    bucket: &str,
+    key: &str,
+    path: &Path,
+    range: ByteRange,
+    options: &DownloadOptions,
+) -> bool {
+    let output = client
+        .get_object()
+        .bucket(bucket)
+        .key(key)
+        .set_version_id(options.version_id.clone())
+        .set_sse_customer_algorithm(options.encryption.customer_algorithm())
+        .set_sse_customer_key(options.encryption.customer_key())
+        .set_sse_customer_key_md5(options.encryption.customer_key_md5())
+        .range(range.header())
+        .send()
+        .await
+        .expect("Error while downloading the range; check that it starts inside the object\n");
+    let length = output.content_length().max(0) as u64;
+    let content_range = output.content_range().unwrap_or_default().to_string();
+    let mut file = tokio::fs::File::create(path)
+        .await
+        .expect("Error while creating the destination file\n");
+    let bar = transfer_bar(length, "Downloading");
+    let mut body = output.body.into_async_read();
+    let mut buffer = vec![0; 256 * 1024];
+    loop {
+        let read = body
+            .read(&mut buffer)
+            .await
+            .expect("The download was interrupted\n");
+        if read == 0 {
+            break;
+        }
+        file.write_all(&buffer[..read])
+            .await
+            .expect("Error while writing the destination file\n");
+        bar.inc(read as u64);
+    }
+    file.flush()
+        .await
+        .expect("Error while writing the destination file\n");
+    bar.finish();
+    println!(
+        "The range {} of '{}' is saved to {}\n",
+        content_range.green().bold(),
+        key.green().bold(),
+        path.display().to_string().green().bold()
+    );
+    true
+}
@@ -0,0 +1,473 @@
+use aws_config::SdkConfig;
+use aws_sdk_s3::error::ProvideErrorMetadata;
+use aws_sdk_s3::types::{
+    ServerSideEncryption, ServerSideEncryptionByDefault, ServerSideEncryptionConfiguration,
+    ServerSideEncryptionRule,
+};
+use aws_sdk_s3::Client;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use colored::Colorize;
+use inquire::{Confirm, Select, Text};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env::var;
+use std::fs;
+use std::path::Path;
+use tokio::task::JoinSet;
+
+/// How an object is encrypted at rest
+#[derive(Clone, Default)]
+pub enum Encryption {
+    /// Whatever the bucket's default encryption applies
+    #[default]
+    BucketDefault,
+    /// SSE-S3, keys managed by S3
+    S3,
+    /// SSE-KMS, with the account's aws/s3 key when no key id is given
+    Kms { key_id: Option<String> },
+    /// SSE-C, a 256-bit key kept by the user that S3 never stores. The same key is needed
+    /// to download the object again
+    Customer { key: Vec<u8> },
+}
+
+/// Reads an SSE-C key file, holding either the 32 raw bytes of the key or their base64 encoding
+pub fn read_customer_key(path: &Path) -> Result<Vec<u8>, String> {
+    let content = fs::read(path).map_err(|error| format!("{}: {error}", path.display()))?;
+    let key = match content.len() {
+        32 => content,
+        _ => BASE64
+            .decode(String::from_utf8_lossy(&content).trim())
+            .map_err(|_| {
+                format!(
+                    "{} should hold 32 bytes or their base64 encoding",
+                    path.display()
+                )
+            })?,
+    };
+    match key.len() {
+        32 => Ok(key),
+        length => Err(format!(
+            "The key in {} is {length} bytes long; SSE-C keys are 32 bytes",
+            path.display()
+        )),
+    }
+}
+
+impl Encryption {
+    /// Builds the encryption from the '--sse' values: 'AES256' or 's3', 'aws:kms' or 'kms'
+    /// (optionally with a key id), or 'C'/'customer' with a key file
+    pub fn from_args(
+        sse: Option<&str>,
+        kms_key_id: Option<String>,
+        customer_key_file: Option<&str>,
+    ) -> Result<Self, String> {
+        if let Some(file) = customer_key_file {
+            return Ok(Encryption::Customer {
+                key: read_customer_key(Path::new(file))?,
+            });
+        }
+        match sse.map(str::to_lowercase).as_deref() {
+            None if kms_key_id.is_some() => Ok(Encryption::Kms { key_id: kms_key_id }),
+            None => Ok(Encryption::BucketDefault),
+            Some("aes256") | Some("s3") | Some("sse-s3") => Ok(Encryption::S3),
+            Some("aws:kms") | Some("kms") | Some("sse-kms") => {
+                Ok(Encryption::Kms { key_id: kms_key_id })
+            }
+            Some("c") | Some("customer") | Some("sse-c") => {
+                Err("SSE-C needs --sse-c-key-file with the key".into())
+            }
+            Some(other) => Err(format!(
+                "Unknown encryption '{other}'; use AES256, aws:kms or an --sse-c-key-file"
+            )),
+        }
+    }
+
+    /// The encryption set by S3_SSE ('AES256' or 'aws:kms'), S3_SSE_KMS_KEY_ID and
+    /// S3_SSE_C_KEY_FILE in the .env file
+    pub fn from_env() -> Self {
+        dotenv::dotenv().ok();
+        Self::from_args(
+            var("S3_SSE").ok().as_deref(),
+            var("S3_SSE_KMS_KEY_ID").ok(),
+            var("S3_SSE_C_KEY_FILE").ok().as_deref(),
+        )
+        .unwrap_or_else(|message| panic!("{message}\n"))
+    }
+
+    pub fn server_side_encryption(&self) -> Option<ServerSideEncryption> {
+        match self {
+            Encryption::S3 => Some(ServerSideEncryption::Aes256),
+            Encryption::Kms { .. } => Some(ServerSideEncryption::AwsKms),
+            _ => None,
+        }
+    }
+
+    pub fn kms_key_id(&self) -> Option<String> {
+        match self {
+            Encryption::Kms { key_id } => key_id.clone(),
+            _ => None,
+        }
+    }
+
+    /// 'AES256' for SSE-C, the only algorithm S3 accepts for customer keys
+    pub fn customer_algorithm(&self) -> Option<String> {
+        match self {
+            Encryption::Customer { .. } => Some("AES256".into()),
+            _ => None,
+        }
+    }
+
+    pub fn customer_key(&self) -> Option<String> {
+        match self {
+            Encryption::Customer { key } => Some(BASE64.encode(key)),
+            _ => None,
+        }
+    }
+
+    pub fn customer_key_md5(&self) -> Option<String> {
+        match self {
+            Encryption::Customer { key } => Some(BASE64.encode(md5::compute(key).0)),
+            _ => None,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Encryption::BucketDefault => "the bucket default".into(),
+            Encryption::S3 => "SSE-S3".into(),
+            Encryption::Kms { key_id: None } => "SSE-KMS (aws/s3 key)".into(),
+            Encryption::Kms {
+                key_id: Some(key_id),
+            } => format!("SSE-KMS ({key_id})"),
+            Encryption::Customer { .. } => "SSE-C".into(),
+        }
+    }
+}
+
+/// The encryption an upload was started with, as kept in its state file. An SSE-C key is
+/// never written to disk, only its MD5 so the key given on resume can be checked
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
+pub enum SavedEncryption {
+    #[default]
+    BucketDefault,
+    S3,
+    Kms {
+        key_id: Option<String>,
+    },
+    Customer {
+        key_md5: String,
+    },
+}
+
+impl From<&Encryption> for SavedEncryption {
+    fn from(encryption: &Encryption) -> Self {
+        match encryption {
+            Encryption::BucketDefault => SavedEncryption::BucketDefault,
+            Encryption::S3 => SavedEncryption::S3,
+            Encryption::Kms { key_id } => SavedEncryption::Kms {
+                key_id: key_id.clone(),
+            },
+            Encryption::Customer { .. } => SavedEncryption::Customer {
+                key_md5: encryption.customer_key_md5().unwrap_or_default(),
+            },
+        }
+    }
+}
+
+impl SavedEncryption {
+    /// The encryption to resume the upload with. An SSE-C key comes from 'given' when it's the
+    /// one the upload was started with, and is asked for otherwise
+    pub fn restore(&self, given: &Encryption) -> Encryption {
+        match self {
+            SavedEncryption::BucketDefault => Encryption::BucketDefault,
+            SavedEncryption::S3 => Encryption::S3,
+            SavedEncryption::Kms { key_id } => Encryption::Kms {
+                key_id: key_id.clone(),
+            },
+            SavedEncryption::Customer { key_md5 } => {
+                if given.customer_key_md5().as_ref() == Some(key_md5) {
+                    return given.clone();
+                }
+                loop {
+                    let path = Text::new(
+                        "The upload was started with SSE-C; enter the path of the same key file\n",
+                    )
+                    .with_formatter(&|str| format!(".....{str}.....\n"))
+                    .prompt()
+                    .unwrap();
+                    match read_customer_key(Path::new(path.trim())) {
+                        Ok(key) => {
+                            let encryption = Encryption::Customer { key };
+                            if encryption.customer_key_md5().as_ref() == Some(key_md5) {
+                                break encryption;
+                            }
+                            println!(
+                                "{}\n",
+                                "This isn't the key the upload was started with"
+                                    .red()
+                                    .bold()
+                            );
+                        }
+                        Err(message) => println!("{}\n", message.red().bold()),
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Asks how objects should be encrypted, starting from the .env settings
+pub fn prompt_encryption() -> Encryption {
+    let default = Encryption::from_env();
+    let choice = Select::new(
+        &format!(
+            "Select the encryption (the .env default is {})\n",
+            default.describe()
+        ),
+        vec![
+            "Keep the default",
+            "SSE-S3",
+            "SSE-KMS",
+            "SSE-C with a local key file",
+        ],
+    )
+    .prompt()
+    .unwrap();
+    match choice {
+        "SSE-S3" => Encryption::S3,
+        "SSE-KMS" => {
+            let key_id =
+                Text::new("Enter the KMS key id or ARN, or leave it empty for the aws/s3 key\n")
+                    .with_formatter(&|str| format!(".....{str}.....\n"))
+                    .prompt()
+                    .unwrap();
+            Encryption::Kms {
+                key_id: Some(key_id.trim().to_string()).filter(|key_id| !key_id.is_empty()),
+            }
+        }
+        "SSE-C with a local key file" => {
+            loop {
+                let path = Text::new("Enter the path of the key file\n")
+                .with_placeholder("32 raw bytes or their base64 encoding, e.g. from 'openssl rand 32 > sse.key'")
+                .with_formatter(&|str| format!(".....{str}.....\n"))
+                .prompt()
+                .unwrap();
+                match read_customer_key(Path::new(path.trim())) {
+                    Ok(key) => break Encryption::Customer { key },
+                    Err(message) => println!("{}\n", message.red().bold()),
+                }
+            }
+        }
+        _ => default,
+    }
+}
+
+/// Asks for the SSE-C key file of an object being downloaded, falling back to the .env settings
+pub fn prompt_download_key() -> Encryption {
+    loop {
+        let path = Text::new("Enter the SSE-C key file if the object was uploaded with one, or press Enter to skip\n")
+            .with_formatter(&|str| format!(".....{str}.....\n"))
+            .prompt_skippable()
+            .unwrap()
+            .unwrap_or_default();
+        if path.trim().is_empty() {
+            break Encryption::from_env();
+        }
+        match read_customer_key(Path::new(path.trim())) {
+            Ok(key) => break Encryption::Customer { key },
+            Err(message) => println!("{}\n", message.red().bold()),
+        }
+    }
+}
+
+/// The default encryption of a bucket as 'SSE-S3', 'SSE-KMS (key)' or 'none'
+pub async fn get_bucket_encryption(config: &SdkConfig, bucket: &str) -> String {
+    let client = Client::new(config);
+    let output = match client.get_bucket_encryption().bucket(bucket).send().await {
+        Ok(output) => output,
+        Err(error) if error.code() == Some("ServerSideEncryptionConfigurationNotFoundError") => {
+            return "none".into()
+        }
+        Err(error) => panic!(
+            "Error while getting the default encryption: {}\n",
+            error.into_service_error()
+        ),
+    };
+    let rule = output
+        .server_side_encryption_configuration()
+        .and_then(|configuration| configuration.rules())
+        .and_then(|rules| rules.first());
+    let Some(rule) = rule else {
+        return "none".into();
+    };
+    let default = rule.apply_server_side_encryption_by_default();
+    let algorithm = default
+        .and_then(|default| default.sse_algorithm())
+        .map(|algorithm| algorithm.as_str().to_string())
+        .unwrap_or_default();
+    let description = describe_algorithm(
+        &algorithm,
+        default.and_then(|default| default.kms_master_key_id()),
+    );
+    match rule.bucket_key_enabled() {
+        true => format!("{description} with an S3 Bucket Key"),
+        false => description,
+    }
+}
+
+fn describe_algorithm(algorithm: &str, kms_key_id: Option<&str>) -> String {
+    match (algorithm, kms_key_id) {
+        ("AES256", _) => "SSE-S3".into(),
+        ("aws:kms", Some(key_id)) => format!("SSE-KMS ({key_id})"),
+        ("aws:kms", None) => "SSE-KMS (aws/s3 key)".into(),
+        ("aws:kms:dsse", _) => "DSSE-KMS".into(),
+        ("", _) => "none".into(),
+        (other, _) => other.into(),
+    }
+}
+
+/// The configuration that makes 'algorithm' the default for new objects, with the KMS key of
+/// 'encryption' when it has one
+pub fn default_encryption(
+    algorithm: ServerSideEncryption,
+    encryption: &Encryption,
+    bucket_key: bool,
+) -> ServerSideEncryptionConfiguration {
+    let rule = ServerSideEncryptionRule::builder()
+        .apply_server_side_encryption_by_default(
+            ServerSideEncryptionByDefault::builder()
+                .sse_algorithm(algorithm)
+                .set_kms_master_key_id(encryption.kms_key_id())
+                .build(),
+        )
+        .bucket_key_enabled(bucket_key)
+        .build();
+    ServerSideEncryptionConfiguration::builder()
+        .rules(rule)
+        .build()
+}
+
+/// Sets the encryption S3 applies to new objects that don't ask for one. SSE-C can't be a
+/// bucket default, since S3 never stores the key
+pub async fn set_bucket_encryption(
+    config: &SdkConfig,
+    bucket: &str,
+    encryption: &Encryption,
+    bucket_key: bool,
+) {
+    let Some(algorithm) = encryption.server_side_encryption() else {
+        println!(
+            "{}\n",
+            "Only SSE-S3 and SSE-KMS can be a bucket default"
+                .red()
+                .bold()
+        );
+        return;
+    };
+    let client = Client::new(config);
+    client
+        .put_bucket_encryption()
+        .bucket(bucket)
+        .server_side_encryption_configuration(default_encryption(algorithm, encryption, bucket_key))
+        .send()
+        .await
+        .expect("Error while setting the default encryption\n");
+    println!(
+        "New objects in '{}' are now encrypted with {}\n",
+        bucket.green().bold(),
+        encryption.describe().green().bold()
+    );
+}
+
+/// The encryption of each object, read with a HEAD request per key since listings don't
+/// include it. SSE-C objects refuse the request without their key, which identifies them
+pub async fn object_encryptions(
+    config: &SdkConfig,
+    bucket: &str,
+    keys: Vec<String>,
+) -> HashMap<String, String> {
+    let client = Client::new(config);
+    let mut encryptions = HashMap::new();
+    let mut pending = keys.into_iter();
+    let mut tasks = JoinSet::new();
+    loop {
+        while tasks.len() < 16 {
+            let Some(key) = pending.next() else {
+                break;
+            };
+            let request = client.head_object().bucket(bucket).key(&key);
+            tasks.spawn(async move {
+                let description = match request.send().await {
+                    Ok(head) => match head.sse_customer_algorithm() {
+                        Some(_) => "SSE-C".to_string(),
+                        None => describe_algorithm(
+                            head.server_side_encryption()
+                                .map(|algorithm| algorithm.as_str())
+                                .unwrap_or_default(),
+                            head.ssekms_key_id(),
+                        ),
+                    },
+                    Err(error)
+                        if error
+                            .raw_response()
+                            .is_some_and(|raw| raw.http().status() == 400) =>
+                    {
+                        "SSE-C".to_string()
+                    }
+                    Err(_) => "unknown".to_string(),
+                };
+                (key, description)
+            });
+        }
+        let Some(result) = tasks.join_next().await else {
+            break encryptions;
+        };
+        if let Ok((key, description)) = result {
+            encryptions.insert(key, description);
+        }
+    }
+}
+
+/// Asks for a bucket default encryption and, for SSE-KMS, whether to use a Bucket Key.
+/// With 'keep', that choice is offered too and returns None
+pub fn prompt_bucket_encryption(keep: Option<&str>) -> Option<(Encryption, bool)> {
+    let mut choices = keep.into_iter().collect::<Vec<_>>();
+    choices.extend(["SSE-S3", "SSE-KMS"]);
+    let choice = Select::new("Select the new default encryption\n", choices)
+        .prompt()
+        .unwrap();
+    let encryption = match choice {
+        "SSE-S3" => Encryption::S3,
+        "SSE-KMS" => {
+            let key_id =
+                Text::new("Enter the KMS key id or ARN, or leave it empty for the aws/s3 key\n")
+                    .with_formatter(&|str| format!(".....{str}.....\n"))
+                    .prompt()
+                    .unwrap();
+            Encryption::Kms {
+                key_id: Some(key_id.trim().to_string()).filter(|key_id| !key_id.is_empty()),
+            }
+        }
+        _ => return None,
+    };
+    let bucket_key = matches!(encryption, Encryption::Kms { .. })
+        && Confirm::new("Use an S3 Bucket Key to reduce the KMS requests and their cost?\n")
+            .with_default(true)
+            .prompt()
+            .unwrap();
+    Some((encryption, bucket_key))
+}
+
+/// Shows the default encryption of a bucket and offers to change it
+pub async fn manage_bucket_encryption(config: &SdkConfig, bucket: &str) {
+    println!(
+        "The default encryption of '{}' is {}\n",
+        bucket.green().bold(),
+        get_bucket_encryption(config, bucket).await.green().bold()
+    );
+    let Some((encryption, bucket_key)) = prompt_bucket_encryption(Some("Keep it")) else {
+        return;
+    };
+    set_bucket_encryption(config, bucket, &encryption, bucket_key).await;
+}
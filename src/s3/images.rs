@@ -0,0 +1,413 @@
+use aws_config::SdkConfig;
+use colored::Colorize;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, ImageOutputFormat, Rgba, RgbaImage};
+use imageproc::drawing::{draw_text_mut, text_size};
+use inquire::{MultiSelect, Select, Text};
+use rusttype::{Font, Scale};
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use super::multipart::{upload_file, UploadSettings};
+
+/// The widths offered for the variants of an image
+pub const PRESET_WIDTHS: [u32; 6] = [320, 640, 800, 1280, 1920, 2560];
+const DEFAULT_QUALITY: u8 = 82;
+const FONT: &[u8] = include_bytes!("../../assets/font.ttf");
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum VariantFormat {
+    Png,
+    Jpeg,
+    WebP,
+}
+
+impl VariantFormat {
+    pub fn parse(input: &str) -> Result<Self, String> {
+        match input.trim().to_lowercase().as_str() {
+            "png" => Ok(VariantFormat::Png),
+            "jpeg" | "jpg" => Ok(VariantFormat::Jpeg),
+            "webp" => Ok(VariantFormat::WebP),
+            other => Err(format!(
+                "Unknown image format '{other}'; use png, jpeg or webp"
+            )),
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            VariantFormat::Png => "png",
+            VariantFormat::Jpeg => "jpg",
+            VariantFormat::WebP => "webp",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            VariantFormat::Png => "image/png",
+            VariantFormat::Jpeg => "image/jpeg",
+            VariantFormat::WebP => "image/webp",
+        }
+    }
+}
+
+/// A width in pixels such as '800' or '800w'
+pub fn parse_width(input: &str) -> Result<u32, String> {
+    match input.trim().trim_end_matches('w').parse::<u32>() {
+        Ok(width) if (16..=10_000).contains(&width) => Ok(width),
+        _ => Err(format!(
+            "'{input}' should be a width between 16 and 10000 pixels"
+        )),
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum WatermarkPosition {
+    BottomRight,
+    BottomLeft,
+    TopRight,
+    TopLeft,
+    Center,
+}
+
+impl WatermarkPosition {
+    pub const ALL: [&'static str; 5] = [
+        "bottom-right",
+        "bottom-left",
+        "top-right",
+        "top-left",
+        "center",
+    ];
+
+    pub fn parse(input: &str) -> Result<Self, String> {
+        match input.trim().to_lowercase().as_str() {
+            "bottom-right" => Ok(WatermarkPosition::BottomRight),
+            "bottom-left" => Ok(WatermarkPosition::BottomLeft),
+            "top-right" => Ok(WatermarkPosition::TopRight),
+            "top-left" => Ok(WatermarkPosition::TopLeft),
+            "center" => Ok(WatermarkPosition::Center),
+            other => Err(format!(
+                "Unknown position '{other}'; use {}",
+                Self::ALL.join(", ")
+            )),
+        }
+    }
+}
+
+pub struct Watermark {
+    pub text: String,
+    pub position: WatermarkPosition,
+    /// From 0.0 (invisible) to 1.0 (opaque)
+    pub opacity: f32,
+}
+
+/// What to make of one source image
+pub struct ImagePipeline {
+    /// Widths wider than the source are skipped, since upscaling only adds bytes
+    pub widths: Vec<u32>,
+    pub formats: Vec<VariantFormat>,
+    /// JPEG and WebP quality from 1 to 100
+    pub quality: u8,
+    pub watermark: Option<Watermark>,
+}
+
+/// One encoded variant, named 'name@800w.webp'
+pub struct ImageVariant {
+    pub file_name: String,
+    pub width: u32,
+    pub height: u32,
+    pub format: VariantFormat,
+    pub bytes: Vec<u8>,
+}
+
+/// The font shipped in 'assets', used for watermarks and contact sheets
+pub fn bundled_font() -> Font<'static> {
+    Font::try_from_bytes(FONT).expect("The bundled font in assets/font.ttf is invalid\n")
+}
+
+/// Loads an image with its EXIF orientation applied. The EXIF data itself is dropped,
+/// since none of the encoders write it back; returns how many EXIF fields were found
+pub fn load_oriented(path: &Path) -> (DynamicImage, usize) {
+    let bytes = fs::read(path).expect("Error while reading the image\n");
+    decode_oriented(&bytes)
+        .unwrap_or_else(|error| panic!("'{}' isn't a supported image: {error}\n", path.display()))
+}
+
+/// Decodes image bytes the way 'load_oriented' does
+pub fn decode_oriented(bytes: &[u8]) -> Result<(DynamicImage, usize), String> {
+    let image = image::load_from_memory(bytes).map_err(|error| error.to_string())?;
+    let exif = exif::Reader::new()
+        .read_from_container(&mut Cursor::new(bytes))
+        .ok();
+    let orientation = exif
+        .as_ref()
+        .and_then(|exif| exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY))
+        .and_then(|field| field.value.get_uint(0))
+        .unwrap_or(1);
+    let fields = exif.map_or(0, |exif| exif.fields().count());
+    let image = match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image,
+    };
+    Ok((image, fields))
+}
+
+/// Draws the text over the image. The text is drawn with a dark shadow on a transparent layer
+/// first, so the opacity applies to the text as a whole
+pub fn draw_watermark(image: &mut RgbaImage, watermark: &Watermark, font: &Font) {
+    let (width, height) = image.dimensions();
+    let scale = Scale::uniform((width as f32 / 24.0).max(12.0));
+    let (text_width, text_height) = text_size(scale, font, &watermark.text);
+    let margin = (text_height / 2).max(4);
+    let shadow = (text_height / 16).max(1);
+    let (x, y) = match watermark.position {
+        WatermarkPosition::BottomRight => (
+            width as i32 - text_width - margin,
+            height as i32 - text_height - margin,
+        ),
+        WatermarkPosition::BottomLeft => (margin, height as i32 - text_height - margin),
+        WatermarkPosition::TopRight => (width as i32 - text_width - margin, margin),
+        WatermarkPosition::TopLeft => (margin, margin),
+        WatermarkPosition::Center => (
+            (width as i32 - text_width) / 2,
+            (height as i32 - text_height) / 2,
+        ),
+    };
+    let mut layer = RgbaImage::new(width, height);
+    draw_text_mut(
+        &mut layer,
+        Rgba([0, 0, 0, 255]),
+        x + shadow,
+        y + shadow,
+        scale,
+        font,
+        &watermark.text,
+    );
+    draw_text_mut(
+        &mut layer,
+        Rgba([255, 255, 255, 255]),
+        x,
+        y,
+        scale,
+        font,
+        &watermark.text,
+    );
+    // The layer starts transparent black, so its colors are already multiplied by the coverage
+    let opacity = watermark.opacity.clamp(0.0, 1.0);
+    for (pixel, overlay) in image.pixels_mut().zip(layer.pixels()) {
+        let alpha = overlay[3] as f32 / 255.0 * opacity;
+        for channel in 0..3 {
+            pixel[channel] = (pixel[channel] as f32 * (1.0 - alpha)
+                + overlay[channel] as f32 * opacity)
+                .round()
+                .min(255.0) as u8;
+        }
+    }
+}
+
+/// Encodes an image; JPEG has no alpha channel, so transparent parts turn black
+pub fn encode(image: &DynamicImage, format: VariantFormat, quality: u8) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    match format {
+        VariantFormat::Png => image
+            .write_to(&mut Cursor::new(&mut bytes), ImageOutputFormat::Png)
+            .expect("Error while encoding the PNG\n"),
+        VariantFormat::Jpeg => JpegEncoder::new_with_quality(&mut bytes, quality)
+            .encode_image(&image.to_rgb8())
+            .expect("Error while encoding the JPEG\n"),
+        VariantFormat::WebP => {
+            let rgba = DynamicImage::ImageRgba8(image.to_rgba8());
+            let encoded = webp::Encoder::from_image(&rgba)
+                .expect("Error while encoding the WebP\n")
+                .encode(quality as f32);
+            bytes.extend_from_slice(&encoded);
+        }
+    }
+    bytes
+}
+
+/// Makes every width and format of the image, named after 'name'
+pub fn process_image(
+    image: &DynamicImage,
+    name: &str,
+    pipeline: &ImagePipeline,
+) -> Vec<ImageVariant> {
+    let (source_width, source_height) = image.dimensions();
+    let mut widths = pipeline
+        .widths
+        .iter()
+        .copied()
+        .filter(|width| *width <= source_width)
+        .collect::<Vec<_>>();
+    if widths.len() < pipeline.widths.len() {
+        println!(
+            "{}",
+            format!("Widths over the source's {source_width}px are skipped").yellow()
+        );
+    }
+    if widths.is_empty() {
+        widths.push(source_width);
+    }
+    widths.sort_unstable();
+    widths.dedup();
+    let font = bundled_font();
+    let mut variants = Vec::new();
+    for width in widths {
+        let height =
+            ((source_height as f64 * width as f64 / source_width as f64).round() as u32).max(1);
+        let mut resized = match width == source_width {
+            true => image.to_rgba8(),
+            false => image
+                .resize_exact(width, height, FilterType::Lanczos3)
+                .to_rgba8(),
+        };
+        if let Some(watermark) = &pipeline.watermark {
+            draw_watermark(&mut resized, watermark, &font);
+        }
+        let resized = DynamicImage::ImageRgba8(resized);
+        for format in &pipeline.formats {
+            variants.push(ImageVariant {
+                file_name: format!("{name}@{width}w.{}", format.extension()),
+                width,
+                height,
+                format: *format,
+                bytes: encode(&resized, *format, pipeline.quality),
+            });
+        }
+    }
+    variants
+}
+
+/// Runs the pipeline on one file, saves the variants to 'output_dir' and uploads them under
+/// 'prefix' unless no bucket is given
+pub async fn upload_image_variants(
+    config: &SdkConfig,
+    file: &Path,
+    destination: Option<(&str, &str)>,
+    name: &str,
+    pipeline: &ImagePipeline,
+    output_dir: &Path,
+    settings: &UploadSettings,
+) -> bool {
+    let (image, exif_fields) = load_oriented(file);
+    let (source_width, source_height) = image.dimensions();
+    println!(
+        "'{}' is {source_width}x{source_height}{}\n",
+        file.display().to_string().green().bold(),
+        match exif_fields {
+            0 => String::new(),
+            fields => format!("; its {fields} EXIF fields are left out of the variants"),
+        }
+    );
+    let variants = process_image(&image, name, pipeline);
+    fs::create_dir_all(output_dir).expect("Error while creating the output directory\n");
+    let mut paths = Vec::new();
+    for variant in &variants {
+        let path = output_dir.join(&variant.file_name);
+        fs::write(&path, &variant.bytes).expect("Error while saving the variant\n");
+        println!(
+            "  {:<28} {:>5}x{:<5} {:>8.1} KB",
+            variant.file_name,
+            variant.width,
+            variant.height,
+            variant.bytes.len() as f64 / 1024.0
+        );
+        paths.push((path, variant));
+    }
+    println!();
+    let Some((bucket, prefix)) = destination else {
+        println!(
+            "The variants are saved in {}\n",
+            output_dir.display().to_string().green().bold()
+        );
+        return true;
+    };
+    let mut completed = true;
+    for (path, variant) in paths {
+        let mut metadata = settings.metadata.clone();
+        metadata.content_type = Some(variant.format.content_type().to_string());
+        let settings = settings.clone().with_metadata(metadata);
+        let key = format!("{prefix}{}", variant.file_name);
+        completed &= upload_file(config, bucket, &path, &key, &settings).await;
+    }
+    completed
+}
+
+/// A folder under the system temp directory for variants that are only uploaded
+pub fn scratch_dir(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("cli-images-{}-{name}", std::process::id()))
+}
+
+/// Asks for the widths, formats, quality and watermark of the variants
+pub fn prompt_pipeline() -> ImagePipeline {
+    let width_labels = PRESET_WIDTHS
+        .iter()
+        .map(|width| format!("{width}w"))
+        .collect::<Vec<_>>();
+    let widths = MultiSelect::new("Select the widths to make\n", width_labels)
+        .with_default(&[1, 2, 3])
+        .with_help_message("Space selects, Enter confirms; widths over the source's are skipped")
+        .raw_prompt()
+        .unwrap()
+        .into_iter()
+        .map(|option| PRESET_WIDTHS[option.index])
+        .collect();
+    let formats = MultiSelect::new("Select the formats\n", vec!["webp", "jpeg", "png"])
+        .with_default(&[0, 1])
+        .prompt()
+        .unwrap()
+        .into_iter()
+        .map(|format| VariantFormat::parse(format).unwrap())
+        .collect::<Vec<_>>();
+    let quality = Text::new("Enter the JPEG and WebP quality from 1 to 100\n")
+        .with_default(&DEFAULT_QUALITY.to_string())
+        .with_formatter(&|str| format!(".....{str}.....\n"))
+        .with_validator(|input: &str| match input.trim().parse::<u8>() {
+            Ok(1..=100) => Ok(inquire::validator::Validation::Valid),
+            _ => Ok(inquire::validator::Validation::Invalid(
+                "Enter a whole number from 1 to 100".into(),
+            )),
+        })
+        .prompt()
+        .unwrap()
+        .trim()
+        .parse()
+        .unwrap();
+    let text = Text::new("Enter a watermark text, or press Enter for none\n")
+        .with_placeholder("e.g. © Example Photography")
+        .with_formatter(&|str| format!(".....{str}.....\n"))
+        .prompt_skippable()
+        .unwrap()
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty());
+    let watermark = text.map(|text| {
+        let position = Select::new(
+            "Select where the watermark goes\n",
+            WatermarkPosition::ALL.to_vec(),
+        )
+        .prompt()
+        .unwrap();
+        Watermark {
+            text,
+            position: WatermarkPosition::parse(position).unwrap(),
+            opacity: 0.6,
+        }
+    });
+    ImagePipeline {
+        widths,
+        formats: match formats.is_empty() {
+            true => vec![VariantFormat::WebP],
+            false => formats,
+        },
+        quality,
+        watermark,
+    }
+}
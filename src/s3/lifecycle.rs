@@ -0,0 +1,774 @@
+use aws_config::SdkConfig;
+use aws_sdk_s3::error::ProvideErrorMetadata;
+use aws_sdk_s3::types::{
+    AbortIncompleteMultipartUpload, BucketLifecycleConfiguration, ExpirationStatus,
+    LifecycleExpiration, LifecycleRule, LifecycleRuleFilter, NoncurrentVersionExpiration,
+    Transition, TransitionStorageClass,
+};
+use aws_sdk_s3::Client;
+use chrono::Utc;
+use colored::Colorize;
+use inquire::{Confirm, MultiSelect, Select, Text};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use super::format_size;
+use super::objects::list_objects;
+
+/// The storage classes a rule can move objects to, with the minimum age S3 accepts for each
+const TRANSITION_CLASSES: [(&str, i32); 6] = [
+    ("STANDARD_IA", 30),
+    ("ONEZONE_IA", 30),
+    ("INTELLIGENT_TIERING", 0),
+    ("GLACIER_IR", 0),
+    ("GLACIER", 0),
+    ("DEEP_ARCHIVE", 0),
+];
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct TransitionSpec {
+    pub days: i32,
+    pub storage_class: String,
+}
+
+/// A lifecycle rule as written in a rules file, e.g.
+///
+/// ```yaml
+/// rules:
+///   - id: archive-logs
+///     prefix: logs/
+///     transitions:
+///       - { days: 30, storage_class: STANDARD_IA }
+///       - { days: 90, storage_class: GLACIER }
+///     expiration_days: 365
+///     abort_incomplete_multipart_days: 7
+/// ```
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct RuleSpec {
+    pub id: String,
+    #[serde(default)]
+    pub prefix: String,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transitions: Vec<TransitionSpec>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expiration_days: Option<i32>,
+    /// Removes older versions this many days after they stop being current
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub noncurrent_expiration_days: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub abort_incomplete_multipart_days: Option<i32>,
+    /// The rule as the bucket returned it, which is saved back unchanged so the parts the
+    /// fields above can't hold aren't lost. None for new or edited rules
+    #[serde(skip)]
+    original: Option<LifecycleRule>,
+    /// What the original rule uses beyond the fields above, e.g. "a tag filter"
+    #[serde(skip)]
+    pub unsupported: Vec<&'static str>,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+#[derive(Serialize, Deserialize)]
+struct RulesFile {
+    rules: Vec<RuleSpec>,
+}
+
+// The filters and actions of a rule that RuleSpec has no field for
+fn unsupported_parts(rule: &LifecycleRule) -> Vec<&'static str> {
+    let mut parts = Vec::new();
+    match rule.filter() {
+        Some(LifecycleRuleFilter::Tag(_)) => parts.push("a tag filter"),
+        Some(LifecycleRuleFilter::ObjectSizeGreaterThan(_))
+        | Some(LifecycleRuleFilter::ObjectSizeLessThan(_)) => parts.push("an object size filter"),
+        Some(LifecycleRuleFilter::And(and)) => {
+            if !and.tags().unwrap_or_default().is_empty() {
+                parts.push("a tag filter");
+            }
+            if and.object_size_greater_than() > 0 || and.object_size_less_than() > 0 {
+                parts.push("an object size filter");
+            }
+        }
+        _ => {}
+    }
+    if let Some(expiration) = rule.expiration() {
+        if expiration.date().is_some() {
+            parts.push("an expiration date");
+        }
+        if expiration.expired_object_delete_marker() {
+            parts.push("the removal of expired delete markers");
+        }
+    }
+    let transitions = rule.transitions().unwrap_or_default();
+    if transitions
+        .iter()
+        .any(|transition| transition.date().is_some())
+    {
+        parts.push("a transition date");
+    }
+    if !rule
+        .noncurrent_version_transitions()
+        .unwrap_or_default()
+        .is_empty()
+    {
+        parts.push("transitions of old versions");
+    }
+    if rule
+        .noncurrent_version_expiration()
+        .is_some_and(|expiration| expiration.newer_noncurrent_versions() > 0)
+    {
+        parts.push("a number of old versions to keep");
+    }
+    parts
+}
+
+impl RuleSpec {
+    /// Rules with parts the prompts can't show are only kept or deleted, since saving an
+    /// edited copy would silently drop those parts
+    pub fn check_editable(&self) -> Result<(), String> {
+        match self.unsupported.is_empty() {
+            true => Ok(()),
+            false => Err(format!(
+                "The rule '{}' uses {}, which can't be edited here without losing it",
+                self.id,
+                self.unsupported.join(", ")
+            )),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let id = &self.id;
+        if id.trim().is_empty() || id.len() > 255 {
+            return Err("A rule needs an id of 1 to 255 characters".into());
+        }
+        if self.transitions.is_empty()
+            && self.expiration_days.is_none()
+            && self.noncurrent_expiration_days.is_none()
+            && self.abort_incomplete_multipart_days.is_none()
+        {
+            return Err(format!(
+                "The rule '{id}' has no transition, expiration or abort action"
+            ));
+        }
+        let days = [
+            self.expiration_days,
+            self.noncurrent_expiration_days,
+            self.abort_incomplete_multipart_days,
+        ];
+        if days.iter().flatten().any(|days| *days < 1) {
+            return Err(format!("The days in the rule '{id}' should be at least 1"));
+        }
+        let mut previous = None;
+        for transition in &self.transitions {
+            let class = transition.storage_class.as_str();
+            let Some((_, minimum)) = TRANSITION_CLASSES.iter().find(|(name, _)| *name == class)
+            else {
+                return Err(format!(
+                    "'{class}' in the rule '{id}' isn't a transition storage class; use one of {}",
+                    TRANSITION_CLASSES.map(|(name, _)| name).join(", ")
+                ));
+            };
+            if transition.days < *minimum {
+                return Err(format!(
+                    "Objects can only move to {class} after at least {minimum} days (rule '{id}')"
+                ));
+            }
+            if previous.is_some_and(|previous| transition.days <= previous) {
+                return Err(format!(
+                    "The transitions in the rule '{id}' should be ordered by increasing days"
+                ));
+            }
+            previous = Some(transition.days);
+        }
+        if let (Some(expiration), Some(last)) = (self.expiration_days, previous) {
+            if expiration <= last {
+                return Err(format!(
+                    "The rule '{id}' expires objects before their last transition"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn to_rule(&self) -> LifecycleRule {
+        if let Some(rule) = &self.original {
+            return rule.clone();
+        }
+        let status = match self.enabled {
+            true => ExpirationStatus::Enabled,
+            false => ExpirationStatus::Disabled,
+        };
+        let transitions = self
+            .transitions
+            .iter()
+            .map(|transition| {
+                Transition::builder()
+                    .days(transition.days)
+                    .storage_class(TransitionStorageClass::from(
+                        transition.storage_class.as_str(),
+                    ))
+                    .build()
+            })
+            .collect::<Vec<_>>();
+        LifecycleRule::builder()
+            .id(&self.id)
+            .status(status)
+            .filter(LifecycleRuleFilter::Prefix(self.prefix.clone()))
+            .set_transitions((!transitions.is_empty()).then_some(transitions))
+            .set_expiration(
+                self.expiration_days
+                    .map(|days| LifecycleExpiration::builder().days(days).build()),
+            )
+            .set_noncurrent_version_expiration(self.noncurrent_expiration_days.map(|days| {
+                NoncurrentVersionExpiration::builder()
+                    .noncurrent_days(days)
+                    .build()
+            }))
+            .set_abort_incomplete_multipart_upload(self.abort_incomplete_multipart_days.map(
+                |days| {
+                    AbortIncompleteMultipartUpload::builder()
+                        .days_after_initiation(days)
+                        .build()
+                },
+            ))
+            .build()
+    }
+
+    fn from_rule(rule: &LifecycleRule) -> Self {
+        // Days are plain integers in the SDK, with 0 meaning the action isn't set
+        let days = |days: i32| (days > 0).then_some(days);
+        let prefix = match rule.filter() {
+            Some(LifecycleRuleFilter::Prefix(prefix)) => prefix.clone(),
+            Some(LifecycleRuleFilter::And(and)) => and.prefix().unwrap_or_default().to_string(),
+            // Rules created before filters existed keep their prefix in the deprecated field
+            #[allow(deprecated)]
+            _ => rule.prefix().unwrap_or_default().to_string(),
+        };
+        Self {
+            id: rule.id().unwrap_or_default().to_string(),
+            prefix,
+            enabled: rule.status() == Some(&ExpirationStatus::Enabled),
+            transitions: rule
+                .transitions()
+                .unwrap_or_default()
+                .iter()
+                .map(|transition| TransitionSpec {
+                    days: transition.days(),
+                    storage_class: transition
+                        .storage_class()
+                        .map(|class| class.as_str().to_string())
+                        .unwrap_or_default(),
+                })
+                .collect(),
+            expiration_days: rule
+                .expiration()
+                .and_then(|expiration| days(expiration.days())),
+            noncurrent_expiration_days: rule
+                .noncurrent_version_expiration()
+                .and_then(|expiration| days(expiration.noncurrent_days())),
+            abort_incomplete_multipart_days: rule
+                .abort_incomplete_multipart_upload()
+                .and_then(|abort| days(abort.days_after_initiation())),
+            original: Some(rule.clone()),
+            unsupported: unsupported_parts(rule),
+        }
+    }
+}
+
+/// Reads rules from a .json file, or from YAML otherwise. Both a bare list of rules and a
+/// 'rules:' key holding the list are accepted
+pub fn read_rules_file(path: &Path) -> Result<Vec<RuleSpec>, String> {
+    let content =
+        fs::read_to_string(path).map_err(|error| format!("{}: {error}", path.display()))?;
+    let is_json = path
+        .extension()
+        .is_some_and(|extension| extension == "json");
+    let rules = match is_json {
+        true => serde_json::from_str::<RulesFile>(&content)
+            .map(|file| file.rules)
+            .or_else(|_| serde_json::from_str::<Vec<RuleSpec>>(&content))
+            .map_err(|error| format!("{}: {error}", path.display()))?,
+        false => serde_yaml::from_str::<RulesFile>(&content)
+            .map(|file| file.rules)
+            .or_else(|_| serde_yaml::from_str::<Vec<RuleSpec>>(&content))
+            .map_err(|error| format!("{}: {error}", path.display()))?,
+    };
+    for rule in &rules {
+        rule.validate()?;
+    }
+    Ok(rules)
+}
+
+/// Writes the rules in the same format 'read_rules_file' reads, so they can be edited and applied again
+pub fn write_rules_file(path: &Path, rules: &[RuleSpec]) {
+    for rule in rules.iter().filter(|rule| !rule.unsupported.is_empty()) {
+        println!(
+            "{} the file leaves out {} of the rule '{}'",
+            "Warning:".yellow().bold(),
+            rule.unsupported.join(", "),
+            rule.id
+        );
+    }
+    let file = RulesFile {
+        rules: rules.to_vec(),
+    };
+    let content = match path
+        .extension()
+        .is_some_and(|extension| extension == "json")
+    {
+        true => serde_json::to_string_pretty(&file).unwrap(),
+        false => serde_yaml::to_string(&file).unwrap(),
+    };
+    fs::write(path, content).expect("Error while writing the rules file\n");
+    println!(
+        "The rules are written to {}\n",
+        path.display().to_string().green().bold()
+    );
+}
+
+/// The rules of a bucket; a bucket without a lifecycle configuration has none
+pub async fn get_rules(config: &SdkConfig, bucket: &str) -> Vec<RuleSpec> {
+    let client = Client::new(config);
+    match client
+        .get_bucket_lifecycle_configuration()
+        .bucket(bucket)
+        .send()
+        .await
+    {
+        Ok(output) => output
+            .rules()
+            .unwrap_or_default()
+            .iter()
+            .map(RuleSpec::from_rule)
+            .collect(),
+        Err(error) if error.code() == Some("NoSuchLifecycleConfiguration") => Vec::new(),
+        Err(error) => panic!(
+            "Error while getting the lifecycle rules: {}\n",
+            error.into_service_error()
+        ),
+    }
+}
+
+/// Replaces every rule of the bucket; an empty list removes the lifecycle configuration
+pub async fn put_rules(config: &SdkConfig, bucket: &str, rules: &[RuleSpec]) {
+    let client = Client::new(config);
+    if rules.is_empty() {
+        client
+            .delete_bucket_lifecycle()
+            .bucket(bucket)
+            .send()
+            .await
+            .expect("Error while removing the lifecycle rules\n");
+        println!(
+            "The lifecycle rules of '{}' are removed\n",
+            bucket.green().bold()
+        );
+        return;
+    }
+    let configuration = BucketLifecycleConfiguration::builder()
+        .set_rules(Some(rules.iter().map(RuleSpec::to_rule).collect()))
+        .build();
+    client
+        .put_bucket_lifecycle_configuration()
+        .bucket(bucket)
+        .lifecycle_configuration(configuration)
+        .send()
+        .await
+        .expect("Error while saving the lifecycle rules\n");
+    println!(
+        "{} lifecycle rule(s) saved on '{}'\n",
+        rules.len().to_string().green().bold(),
+        bucket.green().bold()
+    );
+}
+
+/// Adds the rules to the bucket's, replacing the ones with the same id
+pub fn merge_rules(existing: Vec<RuleSpec>, added: Vec<RuleSpec>) -> Vec<RuleSpec> {
+    let mut rules = existing
+        .into_iter()
+        .filter(|rule| !added.iter().any(|new| new.id == rule.id))
+        .collect::<Vec<_>>();
+    rules.extend(added);
+    rules
+}
+
+fn describe_rule(rule: &RuleSpec) -> String {
+    let mut actions = rule
+        .transitions
+        .iter()
+        .map(|transition| {
+            format!(
+                "to {} after {} days",
+                transition.storage_class, transition.days
+            )
+        })
+        .collect::<Vec<_>>();
+    if let Some(days) = rule.expiration_days {
+        actions.push(format!("expire after {days} days"));
+    }
+    if let Some(days) = rule.noncurrent_expiration_days {
+        actions.push(format!("delete old versions after {days} days"));
+    }
+    if let Some(days) = rule.abort_incomplete_multipart_days {
+        actions.push(format!("abort incomplete uploads after {days} days"));
+    }
+    if !rule.unsupported.is_empty() {
+        actions.push(format!("also {}", rule.unsupported.join(", ")));
+    }
+    actions.join(", ")
+}
+
+pub fn print_rules(bucket: &str, rules: &[RuleSpec]) {
+    if rules.is_empty() {
+        println!(
+            "{}\n",
+            format!("'{bucket}' has no lifecycle rules").yellow().bold()
+        );
+        return;
+    }
+    for rule in rules {
+        let prefix = match rule.prefix.is_empty() {
+            true => "the whole bucket".to_string(),
+            false => rule.prefix.clone(),
+        };
+        let status = match rule.enabled {
+            true => "enabled".green(),
+            false => "disabled".yellow(),
+        };
+        println!(
+            "{} ({status}) on {}\n    {}\n",
+            rule.id.green().bold(),
+            prefix.bold(),
+            describe_rule(rule)
+        );
+    }
+}
+
+/// Counts the current objects each rule covers and how many are already old enough for its
+/// transitions and expiration, so nothing unexpected happens once the rules are applied
+pub async fn preview_rules(config: &SdkConfig, bucket: &str, rules: &[RuleSpec]) {
+    let client = Client::new(config);
+    let now = Utc::now().timestamp();
+    for rule in rules {
+        let objects = list_objects(&client, bucket, &rule.prefix).await;
+        let bytes: u64 = objects.iter().map(|object| object.size).sum();
+        let older_than = |days: i32| {
+            let cutoff = now - days as i64 * 24 * 60 * 60;
+            let matching = objects
+                .iter()
+                .filter(|object| object.last_modified < cutoff)
+                .collect::<Vec<_>>();
+            let bytes: u64 = matching.iter().map(|object| object.size).sum();
+            format!("{} object(s), {}", matching.len(), format_size(bytes))
+        };
+        println!(
+            "{} covers s3://{bucket}/{}: {} object(s), {}",
+            rule.id.green().bold(),
+            rule.prefix,
+            objects.len().to_string().bold(),
+            format_size(bytes).bold()
+        );
+        for transition in &rule.transitions {
+            println!(
+                "    would move to {} right away: {}",
+                transition.storage_class,
+                older_than(transition.days).yellow()
+            );
+        }
+        if let Some(days) = rule.expiration_days {
+            println!("    would expire right away: {}", older_than(days).red());
+        }
+        println!();
+    }
+}
+
+/// Shows the rules the bucket will have and what they affect, then saves them after a
+/// confirmation that 'assume_yes' skips
+pub async fn review_and_put_rules(
+    config: &SdkConfig,
+    bucket: &str,
+    rules: &[RuleSpec],
+    assume_yes: bool,
+) {
+    println!("{}\n", "The bucket will have these rules:".bold());
+    print_rules(bucket, rules);
+    preview_rules(config, bucket, rules).await;
+    let confirm = assume_yes
+        || Confirm::new("Save these lifecycle rules?\n")
+            .with_default(false)
+            .prompt()
+            .unwrap();
+    match confirm {
+        true => put_rules(config, bucket, rules).await,
+        false => println!(
+            "{}\n",
+            "The lifecycle rules weren't changed".yellow().bold()
+        ),
+    }
+}
+
+fn prompt_days(message: &str, current: Option<i32>) -> Option<i32> {
+    let current = current.map(|days| days.to_string()).unwrap_or_default();
+    let input = Text::new(message)
+        .with_initial_value(&current)
+        .with_placeholder("Leave empty to skip")
+        .with_validator(|input: &str| match input.trim() {
+            "" => Ok(inquire::validator::Validation::Valid),
+            days => match days.parse::<i32>() {
+                Ok(days) if days > 0 => Ok(inquire::validator::Validation::Valid),
+                _ => Ok(inquire::validator::Validation::Invalid(
+                    "Enter a number of days greater than 0".into(),
+                )),
+            },
+        })
+        .with_formatter(&|str| format!(".....{str}.....\n"))
+        .prompt()
+        .unwrap();
+    input.trim().parse().ok()
+}
+
+/// Builds a rule from prompts, starting from 'current' when a rule is edited.
+/// Returns None when the rule isn't valid and the user stops editing it
+pub fn prompt_rule(current: Option<&RuleSpec>) -> Option<RuleSpec> {
+    loop {
+        let id = Text::new("Enter the id of the rule\n")
+            .with_initial_value(current.map_or("", |rule| rule.id.as_str()))
+            .with_formatter(&|str| format!(".....{str}.....\n"))
+            .prompt()
+            .unwrap();
+        let prefix = Text::new(
+            "Enter the prefix the rule applies to, or leave it empty for the whole bucket\n",
+        )
+        .with_initial_value(current.map_or("", |rule| rule.prefix.as_str()))
+        .with_placeholder("logs/")
+        .with_formatter(&|str| format!(".....{str}.....\n"))
+        .prompt()
+        .unwrap();
+        let transition_days = |class: &str| {
+            current.and_then(|rule| {
+                rule.transitions
+                    .iter()
+                    .find(|transition| transition.storage_class == class)
+                    .map(|transition| transition.days)
+            })
+        };
+        let mut transitions = Vec::new();
+        if let Some(days) = prompt_days(
+            "Move objects to STANDARD_IA after how many days? (at least 30)\n",
+            transition_days("STANDARD_IA"),
+        ) {
+            transitions.push(TransitionSpec {
+                days,
+                storage_class: "STANDARD_IA".into(),
+            });
+        }
+        if let Some(days) = prompt_days(
+            "Move objects to GLACIER after how many days?\n",
+            transition_days("GLACIER"),
+        ) {
+            transitions.push(TransitionSpec {
+                days,
+                storage_class: "GLACIER".into(),
+            });
+        }
+        // Other classes only come from rule files, and are kept as they were when editing
+        transitions.extend(current.into_iter().flat_map(|rule| {
+            rule.transitions
+                .iter()
+                .filter(|transition| {
+                    !["STANDARD_IA", "GLACIER"].contains(&transition.storage_class.as_str())
+                })
+                .cloned()
+        }));
+        transitions.sort_by_key(|transition| transition.days);
+        let rule = RuleSpec {
+            id: id.trim().to_string(),
+            prefix: prefix.trim().to_string(),
+            enabled: current.map_or(true, |rule| rule.enabled),
+            transitions,
+            expiration_days: prompt_days(
+                "Delete objects after how many days?\n",
+                current.and_then(|rule| rule.expiration_days),
+            ),
+            noncurrent_expiration_days: prompt_days(
+                "Delete old versions how many days after they are replaced?\n",
+                current.and_then(|rule| rule.noncurrent_expiration_days),
+            ),
+            abort_incomplete_multipart_days: prompt_days(
+                "Abort incomplete multipart uploads after how many days?\n",
+                current.map_or(Some(7), |rule| rule.abort_incomplete_multipart_days),
+            ),
+            original: None,
+            unsupported: Vec::new(),
+        };
+        match rule.validate() {
+            Ok(()) => break Some(rule),
+            Err(message) => {
+                println!("{}\n", message.red().bold());
+                let retry = Confirm::new("Edit the rule again?\n")
+                    .with_default(true)
+                    .prompt()
+                    .unwrap();
+                if !retry {
+                    break None;
+                }
+            }
+        }
+    }
+}
+
+/// The interactive menu for the lifecycle rules of a bucket
+pub async fn manage_rules(config: &SdkConfig, bucket: &str) {
+    loop {
+        let rules = get_rules(config, bucket).await;
+        print_rules(bucket, &rules);
+        let mut choices = vec!["Add a rule", "Apply rules from a YAML or JSON file"];
+        if !rules.is_empty() {
+            choices.extend([
+                "Edit a rule",
+                "Delete rules",
+                "Preview the affected objects",
+            ]);
+        }
+        choices.push("Done");
+        let choice = Select::new("What do you want to do with the rules?\n", choices)
+            .prompt()
+            .unwrap();
+        let ids = rules.iter().map(|rule| rule.id.clone()).collect::<Vec<_>>();
+        let updated = match choice {
+            "Add a rule" => match prompt_rule(None) {
+                Some(rule) => merge_rules(rules, vec![rule]),
+                None => continue,
+            },
+            "Apply rules from a YAML or JSON file" => {
+                let path = Text::new("Enter the path of the rules file\n")
+                    .with_placeholder("lifecycle.yaml")
+                    .with_formatter(&|str| format!(".....{str}.....\n"))
+                    .prompt()
+                    .unwrap();
+                match read_rules_file(Path::new(path.trim())) {
+                    Ok(rules) => rules,
+                    Err(message) => {
+                        println!("{}\n", message.red().bold());
+                        continue;
+                    }
+                }
+            }
+            "Edit a rule" => {
+                let id = Select::new("Select the rule to edit\n", ids)
+                    .prompt()
+                    .unwrap();
+                let current = rules.iter().find(|rule| rule.id == id).unwrap();
+                if let Err(message) = current.check_editable() {
+                    println!("{}\n", message.red().bold());
+                    continue;
+                }
+                let Some(edited) = prompt_rule(Some(current)) else {
+                    continue;
+                };
+                rules
+                    .iter()
+                    .map(|rule| match rule.id == id {
+                        true => edited.clone(),
+                        false => rule.clone(),
+                    })
+                    .collect()
+            }
+            "Delete rules" => {
+                let removed = MultiSelect::new("Select the rules to delete\n", ids)
+                    .prompt()
+                    .unwrap();
+                rules
+                    .into_iter()
+                    .filter(|rule| !removed.contains(&rule.id))
+                    .collect()
+            }
+            "Preview the affected objects" => {
+                preview_rules(config, bucket, &rules).await;
+                continue;
+            }
+            _ => return,
+        };
+        review_and_put_rules(config, bucket, &updated, false).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(yaml: &str) -> RuleSpec {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    fn error(yaml: &str) -> String {
+        rule(yaml).validate().unwrap_err()
+    }
+
+    #[test]
+    fn valid_rules() {
+        let archive = rule(
+            "id: archive-logs
+prefix: logs/
+transitions:
+  - { days: 30, storage_class: STANDARD_IA }
+  - { days: 90, storage_class: GLACIER }
+expiration_days: 365",
+        );
+        assert!(archive.enabled);
+        assert_eq!(archive.validate(), Ok(()));
+        assert_eq!(
+            rule("{ id: uploads, abort_incomplete_multipart_days: 7 }").validate(),
+            Ok(())
+        );
+        assert_eq!(
+            rule("{ id: tiering, transitions: [{ days: 0, storage_class: INTELLIGENT_TIERING }] }")
+                .validate(),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn id_and_action_are_required() {
+        assert!(error("{ id: ' ', expiration_days: 1 }").contains("needs an id"));
+        let long = format!("{{ id: {}, expiration_days: 1 }}", "a".repeat(256));
+        assert!(error(&long).contains("needs an id"));
+        assert!(error("{ id: empty, prefix: logs/ }").contains("has no transition"));
+    }
+
+    #[test]
+    fn days_are_at_least_one() {
+        for yaml in [
+            "{ id: r, expiration_days: 0 }",
+            "{ id: r, noncurrent_expiration_days: -1 }",
+            "{ id: r, abort_incomplete_multipart_days: 0 }",
+        ] {
+            assert!(error(yaml).contains("at least 1"), "{yaml}");
+        }
+    }
+
+    #[test]
+    fn invalid_transitions() {
+        assert!(
+            error("{ id: r, transitions: [{ days: 30, storage_class: STANDARD }] }")
+                .contains("isn't a transition storage class")
+        );
+        assert!(
+            error("{ id: r, transitions: [{ days: 10, storage_class: STANDARD_IA }] }")
+                .contains("at least 30 days")
+        );
+        let unordered = "id: r
+transitions:
+  - { days: 90, storage_class: GLACIER }
+  - { days: 60, storage_class: DEEP_ARCHIVE }";
+        assert!(error(unordered).contains("increasing days"));
+        let early_expiration = "id: r
+transitions: [{ days: 90, storage_class: GLACIER }]
+expiration_days: 90";
+        assert!(error(early_expiration).contains("before their last transition"));
+    }
+}
@@ -0,0 +1,368 @@
+use aws_config::SdkConfig;
+use aws_sdk_s3::Client;
+use chrono::{DateTime, NaiveDate, Utc};
+use colored::Colorize;
+use inquire::Select;
+use std::collections::HashMap;
+
+use super::encryption::object_encryptions;
+use super::format_size;
+use super::objects::{list_page, ListingPage, RemoteObject};
+
+/// Conditions on size, modification date and storage class, written as space separated terms
+/// such as 'size>10MB size<1GB modified>2024-01-31 modified<30d class=GLACIER'
+#[derive(Default)]
+pub struct ObjectFilter {
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    pub modified_after: Option<i64>,
+    pub modified_before: Option<i64>,
+    pub storage_class: Option<String>,
+}
+
+/// Parses '1536', '10KB', '1.5MB' or '2G' into bytes
+pub fn parse_size(input: &str) -> Result<u64, String> {
+    let input = input.trim().to_uppercase();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("'{input}' isn't a size such as 500, 10KB or 1.5GB"))?;
+    let multiplier: u64 = match unit.trim_end_matches('B').trim_end_matches('I') {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        "T" => 1 << 40,
+        _ => return Err(format!("Unknown size unit in '{input}'")),
+    };
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// Parses a date like '2024-01-31' or a number of days ago like '30d' into seconds since the epoch
+pub fn parse_date(input: &str) -> Result<i64, String> {
+    let input = input.trim();
+    if let Some(days) = input.strip_suffix('d') {
+        let days: i64 = days
+            .parse()
+            .map_err(|_| format!("'{input}' should be a number of days such as 30d"))?;
+        return Ok(Utc::now().timestamp() - days * 24 * 60 * 60);
+    }
+    NaiveDate::parse_from_str(input, "%Y-%m-%d")
+        .map(|date| date.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp())
+        .map_err(|_| {
+            format!("'{input}' should be a date such as 2024-01-31 or a number of days such as 30d")
+        })
+}
+
+impl ObjectFilter {
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let mut filter = Self::default();
+        for term in input.split_whitespace() {
+            let position = term.find(['<', '>', '=']).ok_or(format!(
+                "'{term}' should look like size>10MB, modified<30d or class=GLACIER"
+            ))?;
+            let (field, operator, value) = (
+                &term[..position],
+                &term[position..=position],
+                &term[position + 1..],
+            );
+            match (field.to_lowercase().as_str(), operator) {
+                ("size", ">") => filter.min_size = Some(parse_size(value)?),
+                ("size", "<") => filter.max_size = Some(parse_size(value)?),
+                ("modified", ">") => filter.modified_after = Some(parse_date(value)?),
+                ("modified", "<") => filter.modified_before = Some(parse_date(value)?),
+                ("class", "=") => filter.storage_class = Some(value.to_uppercase()),
+                _ => {
+                    return Err(format!(
+                        "Unknown filter '{term}'; use size>, size<, modified>, modified< or class="
+                    ))
+                }
+            }
+        }
+        Ok(filter)
+    }
+
+    pub fn matches(&self, object: &RemoteObject) -> bool {
+        self.min_size.map_or(true, |min| object.size > min)
+            && self.max_size.map_or(true, |max| object.size < max)
+            && self
+                .modified_after
+                .map_or(true, |after| object.last_modified > after)
+            && self
+                .modified_before
+                .map_or(true, |before| object.last_modified < before)
+            && self
+                .storage_class
+                .as_ref()
+                .map_or(true, |class| object.storage_class == *class)
+    }
+}
+
+pub fn format_date(secs: i64) -> String {
+    DateTime::<Utc>::from_timestamp(secs, 0)
+        .map(|date| date.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_default()
+}
+
+/// Running totals over the pages that have been printed
+#[derive(Default)]
+pub struct ListingSummary {
+    pub objects: u64,
+    pub bytes: u64,
+}
+
+impl ListingSummary {
+    pub fn print(&self) {
+        println!(
+            "Total: {} object(s), {}\n",
+            self.objects.to_string().green().bold(),
+            format_size(self.bytes).green().bold()
+        );
+    }
+}
+
+/// Prints the folders and the objects passing the filter, with an encryption column when
+/// 'encryptions' holds the encryption of each key
+pub fn print_page(
+    page: &ListingPage,
+    filter: &ObjectFilter,
+    encryptions: Option<&HashMap<String, String>>,
+    summary: &mut ListingSummary,
+) {
+    let encryption_column = |key: Option<&str>| match (encryptions, key) {
+        (Some(encryptions), Some(key)) => format!(
+            "{:<24}  ",
+            encryptions.get(key).map_or("unknown", String::as_str)
+        ),
+        (Some(_), None) => format!("{:<24}  ", ""),
+        (None, _) => String::new(),
+    };
+    for folder in &page.folders {
+        println!(
+            "{:>10}  {:<19}  {:<19}  {:<34}  {}{}",
+            "",
+            "",
+            "FOLDER",
+            "",
+            encryption_column(None),
+            folder.blue().bold()
+        );
+    }
+    for object in page.objects.iter().filter(|object| filter.matches(object)) {
+        println!(
+            "{:>10}  {:<19}  {:<19}  {:<34}  {}{}",
+            format_size(object.size),
+            format_date(object.last_modified),
+            object.storage_class,
+            object.e_tag,
+            encryption_column(Some(&object.key)),
+            object.key.green().bold()
+        );
+        summary.objects += 1;
+        summary.bytes += object.size;
+    }
+}
+
+fn print_header(encryption: bool) {
+    let encryption = match encryption {
+        true => format!("{:<24}  ", "ENCRYPTION"),
+        false => String::new(),
+    };
+    println!(
+        "{}",
+        format!(
+            "{:>10}  {:<19}  {:<19}  {:<34}  {encryption}{}",
+            "SIZE", "LAST MODIFIED", "STORAGE CLASS", "ETAG", "KEY"
+        )
+        .bold()
+    );
+}
+
+pub struct ListingOptions {
+    /// List every key under the prefix instead of grouping them into folders
+    pub recursive: bool,
+    pub filter: ObjectFilter,
+    pub page_size: i32,
+    pub page_token: Option<String>,
+    /// Follow the continuation tokens instead of stopping after one page
+    pub all: bool,
+    /// Show how each object is encrypted, which costs one HEAD request per object
+    pub encryption: bool,
+}
+
+/// Prints a listing page by page. Without 'all', only one page is printed together with
+/// the token that continues it
+pub async fn print_listing(
+    config: &SdkConfig,
+    bucket: &str,
+    prefix: &str,
+    options: ListingOptions,
+) {
+    let client = Client::new(config);
+    let delimiter = match options.recursive {
+        true => None,
+        false => Some("/"),
+    };
+    let mut token = options.page_token;
+    let mut summary = ListingSummary::default();
+    print_header(options.encryption);
+    loop {
+        let page = list_page(&client, bucket, prefix, delimiter, options.page_size, token).await;
+        let encryptions = match options.encryption {
+            true => {
+                let keys = page
+                    .objects
+                    .iter()
+                    .filter(|object| options.filter.matches(object))
+                    .map(|object| object.key.clone())
+                    .collect();
+                Some(object_encryptions(config, bucket, keys).await)
+            }
+            false => None,
+        };
+        print_page(&page, &options.filter, encryptions.as_ref(), &mut summary);
+        token = page.next_token;
+        if token.is_none() || !options.all {
+            break;
+        }
+    }
+    println!();
+    summary.print();
+    if let Some(token) = token {
+        println!(
+            "More objects follow; continue with --page-token {} or list everything with --all\n",
+            token.yellow()
+        );
+    }
+}
+
+/// Browses a bucket one folder and one page at a time
+pub async fn browse(
+    config: &SdkConfig,
+    bucket: &str,
+    prefix: &str,
+    filter: &ObjectFilter,
+    page_size: i32,
+) {
+    let client = Client::new(config);
+    let mut prefix = prefix.to_string();
+    let mut token = None;
+    let mut summary = ListingSummary::default();
+    loop {
+        println!("\n{}\n", format!("s3://{bucket}/{prefix}").bold());
+        print_header(false);
+        let page = list_page(&client, bucket, &prefix, Some("/"), page_size, token.take()).await;
+        print_page(&page, filter, None, &mut summary);
+        println!();
+        summary.print();
+
+        let mut choices = Vec::new();
+        if page.next_token.is_some() {
+            choices.push("Next page");
+        }
+        if !page.folders.is_empty() {
+            choices.push("Open a folder");
+        }
+        if !prefix.is_empty() {
+            choices.push("Up one level");
+        }
+        choices.push("Done");
+        let choice = Select::new("What next?\n", choices).prompt().unwrap();
+        match choice {
+            "Next page" => token = page.next_token,
+            "Open a folder" => {
+                prefix = Select::new("Select the folder to open\n", page.folders)
+                    .prompt()
+                    .unwrap();
+                summary = ListingSummary::default();
+            }
+            "Up one level" => {
+                let trimmed = prefix.trim_end_matches('/');
+                prefix = match trimmed.rfind('/') {
+                    Some(position) => trimmed[..=position].to_string(),
+                    None => String::new(),
+                };
+                summary = ListingSummary::default();
+            }
+            _ => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(size: u64, last_modified: i64, storage_class: &str) -> RemoteObject {
+        RemoteObject {
+            key: "logs/app.log".into(),
+            size,
+            last_modified,
+            e_tag: String::new(),
+            storage_class: storage_class.into(),
+        }
+    }
+
+    #[test]
+    fn sizes_with_units() {
+        assert_eq!(parse_size("1536"), Ok(1536));
+        assert_eq!(parse_size("10KB"), Ok(10 * 1024));
+        assert_eq!(parse_size("1.5mb"), Ok(1536 * 1024));
+        assert_eq!(parse_size(" 2G "), Ok(2 << 30));
+        assert_eq!(parse_size("1TiB"), Ok(1 << 40));
+    }
+
+    #[test]
+    fn invalid_sizes() {
+        for input in ["", "MB", "10XB", "1.2.3KB", "-5"] {
+            assert!(parse_size(input).is_err(), "{input}");
+        }
+    }
+
+    #[test]
+    fn filter_terms() {
+        let filter =
+            ObjectFilter::parse("size>10MB SIZE<1GB modified>2024-01-31 class=glacier").unwrap();
+        assert_eq!(filter.min_size, Some(10 << 20));
+        assert_eq!(filter.max_size, Some(1 << 30));
+        assert_eq!(filter.modified_after, Some(1_706_659_200));
+        assert_eq!(filter.modified_before, None);
+        assert_eq!(filter.storage_class.as_deref(), Some("GLACIER"));
+
+        let recent = ObjectFilter::parse("modified<30d").unwrap();
+        let days_ago = (Utc::now().timestamp() - recent.modified_before.unwrap()) / 86_400;
+        assert_eq!(days_ago, 30);
+    }
+
+    #[test]
+    fn invalid_filter_terms() {
+        for input in [
+            "size",
+            "size=10MB",
+            "owner=me",
+            "class>GLACIER",
+            "size>ten",
+            "modified>31-01-2024",
+        ] {
+            assert!(ObjectFilter::parse(input).is_err(), "{input}");
+        }
+    }
+
+    #[test]
+    fn empty_filter_matches_everything() {
+        let filter = ObjectFilter::parse("  ").unwrap();
+        assert!(filter.matches(&object(0, 0, "STANDARD")));
+    }
+
+    #[test]
+    fn filter_matches() {
+        let filter = ObjectFilter::parse("size>1KB size<1MB class=STANDARD").unwrap();
+        assert!(filter.matches(&object(4096, 0, "STANDARD")));
+        assert!(!filter.matches(&object(1024, 0, "STANDARD")));
+        assert!(!filter.matches(&object(1 << 20, 0, "STANDARD")));
+        assert!(!filter.matches(&object(4096, 0, "GLACIER")));
+    }
+}
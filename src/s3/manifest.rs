@@ -0,0 +1,255 @@
+use aws_config::SdkConfig;
+use aws_sdk_s3::error::ProvideErrorMetadata;
+use aws_sdk_s3::types::ChecksumMode;
+use aws_sdk_s3::Client;
+use chrono::Utc;
+use colored::Colorize;
+use indicatif::ProgressBar;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use tokio::task::JoinSet;
+
+use super::checksum::Checksum;
+use super::objects::list_objects;
+use super::regional_client;
+
+/// What an object looked like when the manifest was written
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ManifestEntry {
+    pub key: String,
+    pub size: u64,
+    /// None for objects uploaded without a checksum, which are compared by ETag instead
+    #[serde(default)]
+    pub checksum: Option<Checksum>,
+    #[serde(default)]
+    pub e_tag: String,
+}
+
+/// The keys, sizes and checksums of a set of objects, e.g. the result of a sync, so the
+/// bucket can be checked against it later
+#[derive(Serialize, Deserialize)]
+pub struct Manifest {
+    pub bucket: String,
+    pub created: String,
+    pub entries: Vec<ManifestEntry>,
+}
+
+enum Difference {
+    Missing,
+    Size(u64),
+    Checksum(&'static str),
+    ETag,
+}
+
+async fn head_entry(
+    client: Client,
+    bucket: String,
+    key: String,
+) -> Result<Option<ManifestEntry>, String> {
+    let result = client
+        .head_object()
+        .bucket(bucket)
+        .key(&key)
+        .checksum_mode(ChecksumMode::Enabled)
+        .send()
+        .await;
+    match result {
+        Ok(head) => Ok(Some(ManifestEntry {
+            checksum: Checksum::from_headers(head.checksum_sha256(), head.checksum_crc32_c()),
+            size: head.content_length().max(0) as u64,
+            e_tag: head.e_tag().unwrap_or_default().to_string(),
+            key,
+        })),
+        Err(error) if matches!(error.code(), Some("NotFound") | Some("NoSuchKey")) => Ok(None),
+        Err(error) => Err(format!("{key}: {}", error.into_service_error())),
+    }
+}
+
+// Heads the keys 'jobs' at a time; an entry is None when its object doesn't exist
+async fn head_entries(
+    client: &Client,
+    bucket: &str,
+    keys: Vec<String>,
+    jobs: usize,
+) -> Vec<(String, Option<ManifestEntry>)> {
+    let bar = ProgressBar::new(keys.len() as u64);
+    let mut pending = keys.into_iter();
+    let mut tasks = JoinSet::new();
+    let mut entries = Vec::new();
+    loop {
+        while tasks.len() < jobs.max(1) {
+            let Some(key) = pending.next() else {
+                break;
+            };
+            let (client, bucket) = (client.clone(), bucket.to_string());
+            tasks.spawn(async move { (key.clone(), head_entry(client, bucket, key).await) });
+        }
+        let Some(result) = tasks.join_next().await else {
+            break;
+        };
+        let (key, result) = result.expect("The head task panicked\n");
+        bar.inc(1);
+        match result {
+            Ok(entry) => entries.push((key, entry)),
+            Err(error) => bar.println(format!(
+                "{}: {error}",
+                "Error while reading the object".red().bold()
+            )),
+        }
+    }
+    bar.finish_and_clear();
+    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+    entries
+}
+
+/// Records the size, checksum and ETag S3 reports for each key. Keys that don't exist are left out
+pub async fn build_manifest(
+    config: &SdkConfig,
+    bucket: &str,
+    keys: Vec<String>,
+    jobs: usize,
+) -> Manifest {
+    let client = regional_client(config, bucket).await;
+    let entries = head_entries(&client, bucket, keys, jobs)
+        .await
+        .into_iter()
+        .filter_map(|(_, entry)| entry)
+        .collect::<Vec<_>>();
+    let without_checksum = entries
+        .iter()
+        .filter(|entry| entry.checksum.is_none())
+        .count();
+    if without_checksum > 0 {
+        println!(
+            "{} {without_checksum} object(s) have no checksum, so only their size and ETag are recorded",
+            "Warning:".yellow().bold()
+        );
+    }
+    Manifest {
+        bucket: bucket.into(),
+        created: Utc::now().to_rfc3339(),
+        entries,
+    }
+}
+
+/// A manifest of every object under the prefix
+pub async fn prefix_manifest(
+    config: &SdkConfig,
+    bucket: &str,
+    prefix: &str,
+    jobs: usize,
+) -> Manifest {
+    let client = regional_client(config, bucket).await;
+    let keys = list_objects(&client, bucket, prefix)
+        .await
+        .into_iter()
+        .filter(|object| !object.key.ends_with('/'))
+        .map(|object| object.key)
+        .collect();
+    build_manifest(config, bucket, keys, jobs).await
+}
+
+pub fn write_manifest(path: &Path, manifest: &Manifest) -> Result<(), String> {
+    fs::write(path, serde_json::to_string_pretty(manifest).unwrap())
+        .map_err(|error| format!("{}: {error}", path.display()))?;
+    println!(
+        "The manifest of {} object(s) is written to {}\n",
+        manifest.entries.len().to_string().green().bold(),
+        path.display().to_string().green().bold()
+    );
+    Ok(())
+}
+
+pub fn read_manifest(path: &Path) -> Result<Manifest, String> {
+    let content =
+        fs::read_to_string(path).map_err(|error| format!("{}: {error}", path.display()))?;
+    serde_json::from_str(&content).map_err(|error| format!("{}: {error}", path.display()))
+}
+
+// Objects with a checksum in the manifest must still have it; the rest fall back to the ETag
+fn compare(expected: &ManifestEntry, actual: Option<&ManifestEntry>) -> Option<Difference> {
+    let Some(actual) = actual else {
+        return Some(Difference::Missing);
+    };
+    if actual.size != expected.size {
+        return Some(Difference::Size(actual.size));
+    }
+    match &expected.checksum {
+        Some(checksum) if actual.checksum.as_ref() != Some(checksum) => {
+            Some(Difference::Checksum(checksum.algorithm.name()))
+        }
+        Some(_) => None,
+        None if actual.e_tag != expected.e_tag => Some(Difference::ETag),
+        None => None,
+    }
+}
+
+/// Checks every object of the manifest against the bucket: it must still exist with the same
+/// size and checksum. Returns whether all of them match
+pub async fn verify_manifest(config: &SdkConfig, manifest: &Manifest, jobs: usize) -> bool {
+    let bucket = &manifest.bucket;
+    let client = regional_client(config, bucket).await;
+    let keys = manifest
+        .entries
+        .iter()
+        .map(|entry| entry.key.clone())
+        .collect();
+    let actual = head_entries(&client, bucket, keys, jobs)
+        .await
+        .into_iter()
+        .collect::<BTreeMap<_, _>>();
+
+    let (mut matching, mut unreadable) = (0, 0);
+    for expected in &manifest.entries {
+        let Some(entry) = actual.get(&expected.key) else {
+            unreadable += 1;
+            continue;
+        };
+        let problem = match compare(expected, entry.as_ref()) {
+            None => {
+                matching += 1;
+                continue;
+            }
+            Some(Difference::Missing) => "is missing".to_string(),
+            Some(Difference::Size(size)) => {
+                format!("is {size} bytes instead of {}", expected.size)
+            }
+            Some(Difference::Checksum(name)) => format!("no longer matches its {name} checksum"),
+            Some(Difference::ETag) => format!("has changed from the ETag {}", expected.e_tag),
+        };
+        println!(
+            "{} {}",
+            format!("s3://{bucket}/{}", expected.key).red().bold(),
+            problem
+        );
+    }
+    let total = manifest.entries.len();
+    if unreadable > 0 {
+        println!(
+            "{}",
+            format!("{unreadable} object(s) couldn't be read, see the errors above")
+                .yellow()
+                .bold()
+        );
+    }
+    match matching == total {
+        true => println!(
+            "{}\n",
+            format!(
+                "All {total} object(s) match the manifest from {}",
+                manifest.created
+            )
+            .green()
+            .bold()
+        ),
+        false => println!(
+            "{}\n",
+            format!("{matching} of {total} object(s) match the manifest")
+                .red()
+                .bold()
+        ),
+    }
+    matching == total
+}
@@ -0,0 +1,608 @@
+use aws_config::SdkConfig;
+use aws_sdk_s3::types::{MetadataDirective, ServerSideEncryption, Tag, Tagging};
+use aws_sdk_s3::Client;
+use colored::Colorize;
+use inquire::{MultiSelect, Select, Text};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use super::encryption::Encryption;
+use super::listing::format_date;
+use super::{copy_source, format_size};
+
+/// S3 allows at most 10 tags per object
+pub const MAX_TAGS: usize = 10;
+// User metadata is sent as 'x-amz-meta-*' headers, which share a 2KB limit
+const MAX_METADATA_BYTES: usize = 2 * 1024;
+const FALLBACK_CONTENT_TYPE: &str = "application/octet-stream";
+
+/// The MIME type from the file extension, or from the first bytes of the content when the
+/// extension is missing or unknown (e.g. a PNG saved without '.png')
+pub fn detect_content_type(path: &Path) -> String {
+    if let Some(content_type) = mime_guess::from_path(path).first_raw() {
+        return content_type.to_string();
+    }
+    let mut head = Vec::with_capacity(8192);
+    if let Ok(file) = File::open(path) {
+        let _ = file.take(8192).read_to_end(&mut head);
+    }
+    infer::get(&head)
+        .map(|kind| kind.mime_type().to_string())
+        .unwrap_or(FALLBACK_CONTENT_TYPE.into())
+}
+
+/// Parses 'key=value' for user metadata. S3 stores the key in lowercase and sends both as
+/// HTTP headers, so they have to be plain ASCII
+pub fn parse_metadata_pair(input: &str) -> Result<(String, String), String> {
+    let (key, value) = input.split_once('=').ok_or(format!(
+        "The metadata '{input}' should be in the form key=value"
+    ))?;
+    let (key, value) = (key.trim().to_lowercase(), value.trim().to_string());
+    if key.is_empty()
+        || !key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!(
+            "The metadata key '{key}' can only contain letters, numbers, '-' and '_'"
+        ));
+    }
+    match value.chars().all(|c| c.is_ascii() && !c.is_ascii_control()) {
+        true => Ok((key, value)),
+        false => Err(format!(
+            "The metadata value of '{key}' can only contain printable ASCII characters"
+        )),
+    }
+}
+
+/// Parses 'key=value' for an object tag, within the limits S3 puts on tags
+pub fn parse_tag_pair(input: &str) -> Result<(String, String), String> {
+    let (key, value) = input
+        .split_once('=')
+        .ok_or(format!("The tag '{input}' should be in the form key=value"))?;
+    let (key, value) = (key.trim(), value.trim());
+    let allowed = |part: &str| {
+        part.chars()
+            .all(|c| c.is_alphanumeric() || " +-=._:/@".contains(c))
+    };
+    match key {
+        "" => Err("A tag key can't be empty".into()),
+        key if key.chars().count() > 128 => {
+            Err(format!("The tag key '{key}' is longer than 128 characters"))
+        }
+        key if key.starts_with("aws:") => Err(format!(
+            "The tag key '{key}' uses the reserved 'aws:' prefix"
+        )),
+        _ if value.chars().count() > 256 => Err(format!(
+            "The value of the tag '{key}' is longer than 256 characters"
+        )),
+        _ if !allowed(key) || !allowed(value) => Err(format!(
+            "The tag '{input}' can only contain letters, numbers, spaces and + - = . _ : / @"
+        )),
+        _ => Ok((key.to_string(), value.to_string())),
+    }
+}
+
+/// Parses a comma separated list such as 'team=web, env=prod' with one of the parsers above
+pub fn parse_pairs(
+    input: &str,
+    parse: fn(&str) -> Result<(String, String), String>,
+) -> Result<Vec<(String, String)>, String> {
+    input
+        .split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(parse)
+        .collect()
+}
+
+fn check_limits(
+    metadata: &BTreeMap<String, String>,
+    tags: &[(String, String)],
+) -> Result<(), String> {
+    let metadata_bytes: usize = metadata
+        .iter()
+        .map(|(key, value)| "x-amz-meta-".len() + key.len() + value.len())
+        .sum();
+    if metadata_bytes > MAX_METADATA_BYTES {
+        return Err(format!(
+            "The metadata takes {metadata_bytes} bytes; S3 allows 2KB"
+        ));
+    }
+    match tags.len() > MAX_TAGS {
+        true => Err(format!(
+            "{} tags were given; S3 allows {MAX_TAGS}",
+            tags.len()
+        )),
+        false => Ok(()),
+    }
+}
+
+// Tags are sent as a URL query string in the 'x-amz-tagging' header
+fn encode_query(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'.' | b'-' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+/// The headers an upload sends besides the content: its type, caching, user metadata and tags
+#[derive(Clone, Default)]
+pub struct ObjectMetadata {
+    /// Replaces the detected type
+    pub content_type: Option<String>,
+    pub cache_control: Option<String>,
+    pub metadata: BTreeMap<String, String>,
+    pub tags: Vec<(String, String)>,
+}
+
+impl ObjectMetadata {
+    pub fn new(
+        content_type: Option<String>,
+        cache_control: Option<String>,
+        metadata: Vec<(String, String)>,
+        tags: Vec<(String, String)>,
+    ) -> Result<Self, String> {
+        let metadata = metadata.into_iter().collect();
+        check_limits(&metadata, &tags)?;
+        Ok(Self {
+            content_type,
+            cache_control,
+            metadata,
+            tags,
+        })
+    }
+
+    pub fn content_type_for(&self, file: &Path) -> String {
+        self.content_type
+            .clone()
+            .unwrap_or_else(|| detect_content_type(file))
+    }
+
+    pub fn user_metadata(&self) -> Option<HashMap<String, String>> {
+        match self.metadata.is_empty() {
+            true => None,
+            false => Some(self.metadata.clone().into_iter().collect()),
+        }
+    }
+
+    pub fn tagging(&self) -> Option<String> {
+        match self.tags.is_empty() {
+            true => None,
+            false => Some(
+                self.tags
+                    .iter()
+                    .map(|(key, value)| format!("{}={}", encode_query(key), encode_query(value)))
+                    .collect::<Vec<_>>()
+                    .join("&"),
+            ),
+        }
+    }
+}
+
+/// Asks for the headers of an upload, offering the detected type as the default
+pub fn prompt_object_metadata(file: &Path) -> ObjectMetadata {
+    let detected = detect_content_type(file);
+    let content_type = Text::new("Enter the Content-Type of the object\n")
+        .with_default(&detected)
+        .with_help_message(
+            "Detected from the extension and the content; browsers use it to render the object",
+        )
+        .with_formatter(&|str| format!(".....{str}.....\n"))
+        .prompt()
+        .unwrap();
+    let cache_control =
+        Text::new("Enter the Cache-Control header, or press Enter to leave it out\n")
+            .with_placeholder("e.g. max-age=86400 or no-cache")
+            .with_formatter(&|str| format!(".....{str}.....\n"))
+            .prompt_skippable()
+            .unwrap()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty());
+    let pairs = |message: &str, parse: fn(&str) -> Result<(String, String), String>| {
+        let input = Text::new(message)
+            .with_placeholder("e.g. team=web, env=prod")
+            .with_formatter(&|str| format!(".....{str}.....\n"))
+            .with_validator(move |input: &str| match parse_pairs(input, parse) {
+                Ok(_) => Ok(inquire::validator::Validation::Valid),
+                Err(message) => Ok(inquire::validator::Validation::Invalid(message.into())),
+            })
+            .prompt_skippable()
+            .unwrap()
+            .unwrap_or_default();
+        parse_pairs(&input, parse).unwrap()
+    };
+    loop {
+        let metadata = pairs(
+            "Enter user metadata as key=value pairs, or press Enter for none\n",
+            parse_metadata_pair,
+        );
+        let tags = pairs(
+            "Enter tags as key=value pairs, or press Enter for none\n",
+            parse_tag_pair,
+        );
+        let content_type = Some(content_type.trim().to_string())
+            .filter(|content_type| !content_type.is_empty() && *content_type != detected);
+        match ObjectMetadata::new(content_type, cache_control.clone(), metadata, tags) {
+            Ok(metadata) => break metadata,
+            Err(message) => println!("{}\n", message.red().bold()),
+        }
+    }
+}
+
+/// What HEAD returns for an object, together with its tags
+pub struct ObjectHead {
+    pub size: u64,
+    pub last_modified: i64,
+    pub e_tag: String,
+    pub content_type: Option<String>,
+    pub cache_control: Option<String>,
+    pub content_disposition: Option<String>,
+    pub content_encoding: Option<String>,
+    pub content_language: Option<String>,
+    pub storage_class: String,
+    pub server_side_encryption: Option<ServerSideEncryption>,
+    pub kms_key_id: Option<String>,
+    /// 'AES256' for SSE-C objects
+    pub sse_customer_algorithm: Option<String>,
+    pub version_id: Option<String>,
+    pub metadata: BTreeMap<String, String>,
+    pub tags: Vec<(String, String)>,
+}
+
+/// The headers and tags of an object; an SSE-C object can only be read with the key in 'encryption'
+pub async fn head_object(
+    config: &SdkConfig,
+    bucket: &str,
+    key: &str,
+    encryption: &Encryption,
+) -> Result<ObjectHead, String> {
+    let client = Client::new(config);
+    let head = client
+        .head_object()
+        .bucket(bucket)
+        .key(key)
+        .set_sse_customer_algorithm(encryption.customer_algorithm())
+        .set_sse_customer_key(encryption.customer_key())
+        .set_sse_customer_key_md5(encryption.customer_key_md5())
+        .send()
+        .await
+        .map_err(|error| {
+            let hint = match encryption.customer_key() {
+                Some(_) => "",
+                None => " (an SSE-C object needs the key it was uploaded with)",
+            };
+            format!("{}{hint}", error.into_service_error())
+        })?;
+    let tagging = client
+        .get_object_tagging()
+        .bucket(bucket)
+        .key(key)
+        .send()
+        .await
+        .map_err(|error| error.into_service_error().to_string())?;
+    Ok(ObjectHead {
+        size: head.content_length().max(0) as u64,
+        last_modified: head.last_modified().map_or(0, |date| date.secs()),
+        e_tag: head
+            .e_tag()
+            .unwrap_or_default()
+            .trim_matches('"')
+            .to_string(),
+        content_type: head.content_type().map(str::to_string),
+        cache_control: head.cache_control().map(str::to_string),
+        content_disposition: head.content_disposition().map(str::to_string),
+        content_encoding: head.content_encoding().map(str::to_string),
+        content_language: head.content_language().map(str::to_string),
+        storage_class: head
+            .storage_class()
+            .map_or("STANDARD", |class| class.as_str())
+            .to_string(),
+        server_side_encryption: head.server_side_encryption().cloned(),
+        kms_key_id: head.ssekms_key_id().map(str::to_string),
+        sse_customer_algorithm: head.sse_customer_algorithm().map(str::to_string),
+        version_id: head.version_id().map(str::to_string),
+        metadata: head
+            .metadata()
+            .map(|metadata| metadata.clone().into_iter().collect())
+            .unwrap_or_default(),
+        tags: tagging
+            .tag_set()
+            .unwrap_or_default()
+            .iter()
+            .map(|tag| {
+                (
+                    tag.key().unwrap_or_default().to_string(),
+                    tag.value().unwrap_or_default().to_string(),
+                )
+            })
+            .collect(),
+    })
+}
+
+pub fn print_head(bucket: &str, key: &str, head: &ObjectHead) {
+    println!("{}", format!("s3://{bucket}/{key}").green().bold());
+    let optional = |value: &Option<String>| value.clone().unwrap_or("-".into());
+    let rows = [
+        (
+            "Size",
+            format!("{} ({} bytes)", format_size(head.size), head.size),
+        ),
+        ("Last modified", format_date(head.last_modified)),
+        ("ETag", head.e_tag.clone()),
+        ("Content-Type", optional(&head.content_type)),
+        ("Cache-Control", optional(&head.cache_control)),
+        ("Content-Disposition", optional(&head.content_disposition)),
+        ("Content-Encoding", optional(&head.content_encoding)),
+        ("Content-Language", optional(&head.content_language)),
+        ("Storage class", head.storage_class.clone()),
+        (
+            "Encryption",
+            match (&head.server_side_encryption, &head.kms_key_id) {
+                _ if head.sse_customer_algorithm.is_some() => "SSE-C".into(),
+                (Some(algorithm), Some(key_id)) => format!("{} ({key_id})", algorithm.as_str()),
+                (Some(algorithm), None) => algorithm.as_str().to_string(),
+                (None, _) => "-".into(),
+            },
+        ),
+        ("Version", optional(&head.version_id)),
+    ];
+    for (name, value) in rows {
+        println!("  {:<20} {value}", format!("{name}:").bold());
+    }
+    println!("  {}", "Metadata:".bold());
+    if head.metadata.is_empty() {
+        println!("    -");
+    }
+    for (key, value) in &head.metadata {
+        println!("    {} = {value}", key.yellow());
+    }
+    println!("  {}", "Tags:".bold());
+    if head.tags.is_empty() {
+        println!("    -");
+    }
+    for (key, value) in &head.tags {
+        println!("    {} = {value}", key.yellow());
+    }
+    println!();
+}
+
+/// Changes to apply to an existing object; the fields left empty keep their value
+#[derive(Default)]
+pub struct MetadataChanges {
+    pub content_type: Option<String>,
+    pub cache_control: Option<String>,
+    pub set_metadata: Vec<(String, String)>,
+    pub remove_metadata: Vec<String>,
+    pub set_tags: Vec<(String, String)>,
+    pub remove_tags: Vec<String>,
+}
+
+impl MetadataChanges {
+    fn changes_headers(&self) -> bool {
+        self.content_type.is_some()
+            || self.cache_control.is_some()
+            || !self.set_metadata.is_empty()
+            || !self.remove_metadata.is_empty()
+    }
+
+    fn changes_tags(&self) -> bool {
+        !self.set_tags.is_empty() || !self.remove_tags.is_empty()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.changes_headers() && !self.changes_tags()
+    }
+}
+
+/// Applies the changes in place. Headers and metadata can only change by copying the object
+/// onto itself, which keeps its storage class, encryption and tags; tags change on their own.
+/// An empty Cache-Control removes the header. SSE-C objects need their key in 'encryption'
+pub async fn update_object(
+    config: &SdkConfig,
+    bucket: &str,
+    key: &str,
+    changes: &MetadataChanges,
+    encryption: &Encryption,
+) -> bool {
+    let client = Client::new(config);
+    let head = match head_object(config, bucket, key, encryption).await {
+        Ok(head) => head,
+        Err(error) => {
+            println!(
+                "{}: {error}\n",
+                "Error while reading the object".red().bold()
+            );
+            return false;
+        }
+    };
+
+    let mut metadata = head.metadata.clone();
+    for (name, value) in &changes.set_metadata {
+        metadata.insert(name.clone(), value.clone());
+    }
+    for name in &changes.remove_metadata {
+        metadata.remove(&name.to_lowercase());
+    }
+    let mut tags = head.tags.clone();
+    for (name, value) in &changes.set_tags {
+        match tags.iter_mut().find(|(existing, _)| existing == name) {
+            Some(tag) => tag.1 = value.clone(),
+            None => tags.push((name.clone(), value.clone())),
+        }
+    }
+    tags.retain(|(name, _)| !changes.remove_tags.contains(name));
+    if let Err(message) = check_limits(&metadata, &tags) {
+        println!("{}\n", message.red().bold());
+        return false;
+    }
+
+    if changes.changes_headers() {
+        let cache_control = changes
+            .cache_control
+            .clone()
+            .or(head.cache_control.clone())
+            .filter(|value| !value.is_empty());
+        let result = client
+            .copy_object()
+            .bucket(bucket)
+            .key(key)
+            .copy_source(copy_source(bucket, key, None))
+            .metadata_directive(MetadataDirective::Replace)
+            .set_content_type(changes.content_type.clone().or(head.content_type.clone()))
+            .set_cache_control(cache_control)
+            .set_content_disposition(head.content_disposition.clone())
+            .set_content_encoding(head.content_encoding.clone())
+            .set_content_language(head.content_language.clone())
+            .set_metadata(Some(metadata.into_iter().collect()))
+            .storage_class(head.storage_class.as_str().into())
+            .set_server_side_encryption(head.server_side_encryption.clone())
+            .set_ssekms_key_id(head.kms_key_id.clone())
+            .set_copy_source_sse_customer_algorithm(encryption.customer_algorithm())
+            .set_copy_source_sse_customer_key(encryption.customer_key())
+            .set_copy_source_sse_customer_key_md5(encryption.customer_key_md5())
+            .set_sse_customer_algorithm(encryption.customer_algorithm())
+            .set_sse_customer_key(encryption.customer_key())
+            .set_sse_customer_key_md5(encryption.customer_key_md5())
+            .send()
+            .await;
+        if let Err(error) = result {
+            println!(
+                "{}: {}\n",
+                "Error while updating the metadata; objects over 5GB can't be copied in one request"
+                    .red()
+                    .bold(),
+                error.into_service_error()
+            );
+            return false;
+        }
+    }
+    if changes.changes_tags() {
+        let tag_set = tags
+            .into_iter()
+            .map(|(key, value)| Tag::builder().key(key).value(value).build())
+            .collect();
+        let result = client
+            .put_object_tagging()
+            .bucket(bucket)
+            .key(key)
+            .tagging(Tagging::builder().set_tag_set(Some(tag_set)).build())
+            .send()
+            .await;
+        if let Err(error) = result {
+            println!(
+                "{}: {}\n",
+                "Error while updating the tags".red().bold(),
+                error.into_service_error()
+            );
+            return false;
+        }
+    }
+    println!("'{}' is updated\n", key.green().bold());
+    true
+}
+
+/// Shows the metadata and tags of an object and edits them until the user is done
+pub async fn manage_metadata(config: &SdkConfig, bucket: &str, key: &str, encryption: &Encryption) {
+    loop {
+        let head = match head_object(config, bucket, key, encryption).await {
+            Ok(head) => head,
+            Err(error) => {
+                println!(
+                    "{}: {error}\n",
+                    "Error while reading the object".red().bold()
+                );
+                return;
+            }
+        };
+        print_head(bucket, key, &head);
+        let action = Select::new(
+            "What do you want to change?\n",
+            vec![
+                "Content-Type",
+                "Cache-Control",
+                "Add or change metadata",
+                "Remove metadata",
+                "Add or change tags",
+                "Remove tags",
+                "Done",
+            ],
+        )
+        .prompt()
+        .unwrap();
+        let mut changes = MetadataChanges::default();
+        let prompt_value = |message: &str, current: &Option<String>| {
+            Text::new(message)
+                .with_initial_value(current.as_deref().unwrap_or_default())
+                .with_formatter(&|str| format!(".....{str}.....\n"))
+                .prompt()
+                .unwrap()
+                .trim()
+                .to_string()
+        };
+        let prompt_pairs = |message: &str, parse: fn(&str) -> Result<(String, String), String>| {
+            let input = Text::new(message)
+                .with_placeholder("e.g. team=web, env=prod")
+                .with_formatter(&|str| format!(".....{str}.....\n"))
+                .with_validator(move |input: &str| match parse_pairs(input, parse) {
+                    Ok(_) => Ok(inquire::validator::Validation::Valid),
+                    Err(message) => Ok(inquire::validator::Validation::Invalid(message.into())),
+                })
+                .prompt()
+                .unwrap();
+            parse_pairs(&input, parse).unwrap()
+        };
+        let prompt_removals = |message: &str, current: Vec<String>| match current.is_empty() {
+            true => Vec::new(),
+            false => MultiSelect::new(message, current)
+                .with_help_message("Space selects, Enter confirms")
+                .prompt()
+                .unwrap(),
+        };
+        match action {
+            "Content-Type" => {
+                let value = prompt_value("Enter the Content-Type\n", &head.content_type);
+                changes.content_type = Some(value).filter(|value| !value.is_empty());
+            }
+            "Cache-Control" => {
+                changes.cache_control = Some(prompt_value(
+                    "Enter the Cache-Control header, or clear it to remove the header\n",
+                    &head.cache_control,
+                ));
+            }
+            "Add or change metadata" => {
+                changes.set_metadata = prompt_pairs(
+                    "Enter the metadata as key=value pairs\n",
+                    parse_metadata_pair,
+                )
+            }
+            "Remove metadata" => {
+                changes.remove_metadata = prompt_removals(
+                    "Select the metadata to remove\n",
+                    head.metadata.keys().cloned().collect(),
+                )
+            }
+            "Add or change tags" => {
+                changes.set_tags =
+                    prompt_pairs("Enter the tags as key=value pairs\n", parse_tag_pair)
+            }
+            "Remove tags" => {
+                changes.remove_tags = prompt_removals(
+                    "Select the tags to remove\n",
+                    head.tags.iter().map(|(key, _)| key.clone()).collect(),
+                )
+            }
+            _ => return,
+        }
+        if !changes.is_empty() {
+            update_object(config, bucket, key, &changes, encryption).await;
+        }
+    }
+}
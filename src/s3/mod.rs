@@ -0,0 +1,124 @@
+pub mod checksum;
+pub mod copy;
+pub mod cors;
+pub mod creation;
+pub mod deletion;
+pub mod download;
+pub mod encryption;
+pub mod images;
+pub mod lifecycle;
+pub mod listing;
+pub mod manifest;
+pub mod metadata;
+pub mod multipart;
+pub mod objects;
+pub mod pickers;
+pub mod policy;
+pub mod presign;
+pub mod sync;
+pub mod thumbnails;
+pub mod usage;
+pub mod versioning;
+pub mod website;
+
+use aws_config::SdkConfig;
+use aws_sdk_s3::config::{Builder, Region};
+use aws_sdk_s3::Client;
+use indicatif::{ProgressBar, ProgressStyle};
+
+/// Splits 's3://bucket/some/key' into the bucket and the key, which is empty for 's3://bucket'
+pub fn parse_s3_uri(uri: &str) -> Option<(String, String)> {
+    let path = uri.strip_prefix("s3://")?;
+    let (bucket, key) = path.split_once('/').unwrap_or((path, ""));
+    match bucket.is_empty() {
+        true => None,
+        false => Some((bucket.to_string(), key.to_string())),
+    }
+}
+
+/// The 'bucket/key' value CopyObject and UploadPartCopy expect, with the key URL-encoded
+pub fn copy_source(bucket: &str, key: &str, version_id: Option<&str>) -> String {
+    let key: String = key
+        .bytes()
+        .map(|byte| match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'.' | b'-' | b'_' | b'~' | b'/' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect();
+    match version_id {
+        Some(version_id) => format!("{bucket}/{key}?versionId={version_id}"),
+        None => format!("{bucket}/{key}"),
+    }
+}
+
+/// The region the bucket lives in, which decides its website endpoint and where copies into it are sent
+pub async fn bucket_region(config: &SdkConfig, bucket: &str) -> String {
+    let client = Client::new(config);
+    let output = client
+        .get_bucket_location()
+        .bucket(bucket)
+        .send()
+        .await
+        .expect("Error while getting the bucket region\n");
+    // Buckets in us-east-1 have no location constraint, and the oldest ones in Ireland say 'EU'
+    match output
+        .location_constraint()
+        .map(|location| location.as_str())
+    {
+        None | Some("") => "us-east-1".into(),
+        Some("EU") => "eu-west-1".into(),
+        Some(region) => region.into(),
+    }
+}
+
+/// A client for the bucket's own region. S3 answers requests sent to another region with a
+/// redirect, which the SDK doesn't follow
+pub async fn regional_client(config: &SdkConfig, bucket: &str) -> Client {
+    client_for_region(config, &bucket_region(config, bucket).await)
+}
+
+/// A client with the credentials of 'config' that sends its requests to 'region'
+pub fn client_for_region(config: &SdkConfig, region: &str) -> Client {
+    if config
+        .region()
+        .is_some_and(|current| current.as_ref() == region)
+    {
+        return Client::new(config);
+    }
+    Client::from_conf(
+        Builder::from(config)
+            .region(Region::new(region.to_string()))
+            .build(),
+    )
+}
+
+/// A progress bar showing transferred bytes, throughput and the remaining time
+pub fn transfer_bar(total_bytes: u64, message: &str) -> ProgressBar {
+    let bar = ProgressBar::new(total_bytes);
+    bar.set_style(
+        ProgressStyle::with_template(
+            "{msg} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({binary_bytes_per_sec}, {eta})",
+        )
+        .unwrap()
+        .progress_chars("=> "),
+    );
+    bar.set_message(message.to_string());
+    bar
+}
+
+/// Formats a byte count with binary units, e.g. 1536 as '1.5 KB'
+pub fn format_size(bytes: u64) -> String {
+    let units = ["B", "KB", "MB", "GB", "TB", "PB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{bytes} B"),
+        _ => format!("{size:.1} {}", units[unit]),
+    }
+}
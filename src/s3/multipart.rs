@@ -0,0 +1,820 @@
+use aws_config::SdkConfig;
+use aws_sdk_s3::error::ProvideErrorMetadata;
+use aws_sdk_s3::primitives::{ByteStream, DateTimeFormat};
+use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart};
+use aws_sdk_s3::Client;
+use aws_smithy_http::body::{self, SdkBody};
+use bytes::Bytes;
+use colored::Colorize;
+use http::HeaderMap;
+use http_body::{Body, SizeHint};
+use indicatif::ProgressBar;
+use inquire::Text;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env::var;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Instant, UNIX_EPOCH};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, ReadBuf};
+use tokio::task::JoinSet;
+
+use super::checksum::{file_checksum, Checksum, ChecksumKind};
+use super::encryption::{prompt_encryption, Encryption, SavedEncryption};
+use super::metadata::ObjectMetadata;
+use super::transfer_bar;
+
+// S3 rejects parts smaller than 5MB (except the last one) and uploads with more than 10,000 parts
+const MIN_PART_SIZE: u64 = 5 * 1024 * 1024;
+const MAX_PARTS: u64 = 10_000;
+const PART_ATTEMPTS: usize = 3;
+
+/// Part size and the number of parts uploaded at the same time, read from
+/// 'S3_PART_SIZE_MB' (default 8) and 'S3_CONCURRENCY' (default 4), and the encryption
+/// from 'S3_SSE', 'S3_SSE_KMS_KEY_ID' and 'S3_SSE_C_KEY_FILE'. The Content-Type is detected
+/// per file unless the metadata sets one. The checksum from 'S3_CHECKSUM' is computed locally
+/// and sent with the file, so S3 rejects an upload that arrives different
+#[derive(Clone)]
+pub struct UploadSettings {
+    pub part_size: u64,
+    pub concurrency: usize,
+    pub encryption: Encryption,
+    pub metadata: ObjectMetadata,
+    pub checksum: Option<ChecksumKind>,
+}
+
+impl UploadSettings {
+    pub fn from_env() -> Self {
+        dotenv::dotenv().ok();
+        let part_size_mb = var("S3_PART_SIZE_MB")
+            .ok()
+            .and_then(|size| size.parse().ok())
+            .unwrap_or(8);
+        let concurrency = var("S3_CONCURRENCY")
+            .ok()
+            .and_then(|concurrency| concurrency.parse().ok())
+            .unwrap_or(4);
+        Self {
+            encryption: Encryption::from_env(),
+            checksum: ChecksumKind::from_env(),
+            ..Self::new(part_size_mb, concurrency)
+        }
+    }
+
+    pub fn new(part_size_mb: u64, concurrency: usize) -> Self {
+        Self {
+            part_size: (part_size_mb * 1024 * 1024).max(MIN_PART_SIZE),
+            concurrency: concurrency.max(1),
+            encryption: Encryption::BucketDefault,
+            metadata: ObjectMetadata::default(),
+            checksum: None,
+        }
+    }
+
+    pub fn with_overrides(self, part_size_mb: Option<u64>, concurrency: Option<usize>) -> Self {
+        Self {
+            encryption: self.encryption.clone(),
+            metadata: self.metadata.clone(),
+            checksum: self.checksum,
+            ..Self::new(
+                part_size_mb.unwrap_or(self.part_size / (1024 * 1024)),
+                concurrency.unwrap_or(self.concurrency),
+            )
+        }
+    }
+
+    pub fn with_encryption(self, encryption: Encryption) -> Self {
+        Self { encryption, ..self }
+    }
+
+    pub fn with_metadata(self, metadata: ObjectMetadata) -> Self {
+        Self { metadata, ..self }
+    }
+
+    pub fn with_checksum(self, checksum: Option<ChecksumKind>) -> Self {
+        Self { checksum, ..self }
+    }
+
+    // Large files get bigger parts so they stay within the 10,000 part limit
+    fn part_size_for(&self, file_size: u64) -> u64 {
+        self.part_size.max(file_size.div_ceil(MAX_PARTS))
+    }
+}
+
+/// Asks for the part size, concurrency and encryption, showing the values from the .env file as defaults
+pub fn prompt_upload_settings() -> UploadSettings {
+    let defaults = UploadSettings::from_env();
+    let placeholder = format!(
+        "Default part size: {} MB, concurrency: {}\n",
+        defaults.part_size / (1024 * 1024),
+        defaults.concurrency
+    );
+    let input = Text::new("Enter the part size in MB and the number of parallel parts, e.g. '16 8', or press Enter to use the defaults\n")
+        .with_placeholder(&placeholder)
+        .with_formatter(&|str| format!(".....{str}.....\n"))
+        .with_help_message("Files larger than one part are uploaded in parts and can be resumed if interrupted")
+        .with_validator(|input: &str| {
+            let valid = input
+                .split_whitespace()
+                .all(|number| number.parse::<u64>().is_ok());
+            match valid {
+                true => Ok(inquire::validator::Validation::Valid),
+                false => Ok(inquire::validator::Validation::Invalid(
+                    "Enter up to two whole numbers separated by a space".into(),
+                )),
+            }
+        })
+        .prompt_skippable()
+        .unwrap()
+        .unwrap_or_default();
+    let mut numbers = input
+        .split_whitespace()
+        .map(|number| number.parse().unwrap());
+    let part_size_mb = numbers.next();
+    let concurrency = numbers.next().map(|concurrency| concurrency as usize);
+    defaults
+        .with_overrides(part_size_mb, concurrency)
+        .with_encryption(prompt_encryption())
+}
+
+/// What is needed to resume an interrupted multipart upload, kept in 'S3_STATE_DIR' (default '.s3-uploads')
+#[derive(Serialize, Deserialize)]
+pub struct UploadState {
+    pub bucket: String,
+    pub key: String,
+    pub file: PathBuf,
+    pub file_size: u64,
+    pub modified: u64,
+    pub part_size: u64,
+    pub upload_id: String,
+    pub parts: BTreeMap<i32, String>,
+    /// The algorithm the upload was started with; every part must send a checksum of it
+    #[serde(default)]
+    pub checksum: Option<ChecksumKind>,
+    #[serde(default)]
+    pub part_checksums: BTreeMap<i32, String>,
+    /// How the upload is encrypted; resuming an SSE-C upload needs the same key again
+    #[serde(default)]
+    pub encryption: SavedEncryption,
+}
+
+fn state_dir() -> PathBuf {
+    dotenv::dotenv().ok();
+    var("S3_STATE_DIR").unwrap_or(".s3-uploads".into()).into()
+}
+
+fn state_path(bucket: &str, key: &str) -> PathBuf {
+    let encoded: String = format!("{bucket}/{key}")
+        .bytes()
+        .map(|byte| match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'.' | b'-' | b'_' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect();
+    state_dir().join(format!("{encoded}.json"))
+}
+
+fn modified_secs(path: &Path) -> u64 {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .map(|modified| modified.duration_since(UNIX_EPOCH).unwrap().as_secs())
+        .unwrap_or_default()
+}
+
+impl UploadState {
+    fn load(bucket: &str, key: &str) -> Option<Self> {
+        let content = fs::read_to_string(state_path(bucket, key)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    fn save(&self) {
+        fs::create_dir_all(state_dir()).expect("Error while creating the upload state directory\n");
+        fs::write(
+            state_path(&self.bucket, &self.key),
+            serde_json::to_string_pretty(self).unwrap(),
+        )
+        .expect("Error while saving the upload state\n");
+    }
+
+    fn remove(bucket: &str, key: &str) {
+        let _ = fs::remove_file(state_path(bucket, key));
+    }
+
+    // A state only applies while the local file is unchanged
+    fn matches(&self, file: &Path, file_size: u64) -> bool {
+        self.file == file && self.file_size == file_size && self.modified == modified_secs(file)
+    }
+
+    /// The settings to resume this upload with: 'settings' with the encryption the upload
+    /// was started with, asking for the SSE-C key when 'settings' doesn't have it
+    pub fn resume_settings(&self, settings: UploadSettings) -> UploadSettings {
+        let encryption = self.encryption.restore(&settings.encryption);
+        settings.with_encryption(encryption)
+    }
+}
+
+/// The interrupted uploads that have a local state file and can be resumed
+pub fn saved_uploads() -> Vec<UploadState> {
+    fs::read_dir(state_dir())
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+                .filter_map(|content| serde_json::from_str(&content).ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Uploads a file, in parts when it is larger than the part size. An upload that was interrupted
+/// resumes from its state file, skipping the parts S3 already has. Returns whether the upload completed
+pub async fn upload_file(
+    config: &SdkConfig,
+    bucket: &str,
+    file: &Path,
+    key: &str,
+    settings: &UploadSettings,
+) -> bool {
+    let file_size = fs::metadata(file)
+        .expect("Error while reading the metadata of the file you specified\n")
+        .len();
+    let client = Client::new(config);
+    let content_type = settings.metadata.content_type_for(file);
+    let start = Instant::now();
+    let completed = match file_size <= settings.part_size_for(file_size) {
+        true => {
+            upload_single(
+                &client,
+                bucket,
+                file,
+                key,
+                file_size,
+                &content_type,
+                settings,
+            )
+            .await
+        }
+        false => {
+            upload_parts(
+                &client,
+                bucket,
+                file,
+                key,
+                file_size,
+                &content_type,
+                settings,
+            )
+            .await
+        }
+    };
+    if completed {
+        let elapsed = start.elapsed().as_secs_f64().max(0.001);
+        println!(
+            "'{}' is uploaded to '{}' as {} in {:.1} seconds ({:.2} MB/s)\n",
+            file.display().to_string().green().bold(),
+            format!("s3://{bucket}/{key}").green().bold(),
+            content_type.yellow(),
+            elapsed,
+            file_size as f64 / (1024.0 * 1024.0) / elapsed
+        );
+    }
+    completed
+}
+
+// The body of a single request upload, read from the file like ByteStream::from_path does
+// but advancing the bar as the SDK sends it
+struct ProgressBody {
+    file: tokio::fs::File,
+    bar: ProgressBar,
+    buffer: Vec<u8>,
+    remaining: u64,
+}
+
+impl Body for ProgressBody {
+    type Data = Bytes;
+    type Error = body::Error;
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let this = &mut *self;
+        if this.remaining == 0 {
+            return Poll::Ready(None);
+        }
+        let mut buffer = ReadBuf::new(&mut this.buffer);
+        match Pin::new(&mut this.file).poll_read(cx, &mut buffer) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(Err(error)) => Poll::Ready(Some(Err(error.into()))),
+            Poll::Ready(Ok(())) if buffer.filled().is_empty() => Poll::Ready(None),
+            Poll::Ready(Ok(())) => {
+                let read = buffer.filled();
+                this.remaining = this.remaining.saturating_sub(read.len() as u64);
+                this.bar.inc(read.len() as u64);
+                Poll::Ready(Some(Ok(Bytes::copy_from_slice(read))))
+            }
+        }
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        Poll::Ready(Ok(None))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.remaining == 0
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        SizeHint::with_exact(self.remaining)
+    }
+}
+
+// A retried request reads the file again, so the bar starts over with it
+fn progress_stream(file: &Path, file_size: u64, bar: &ProgressBar) -> ByteStream {
+    let (file, bar) = (file.to_path_buf(), bar.clone());
+    ByteStream::new(SdkBody::retryable(move || {
+        bar.set_position(0);
+        let body = ProgressBody {
+            file: tokio::fs::File::from_std(
+                fs::File::open(&file).expect("Error while reading the file you specified\n"),
+            ),
+            bar: bar.clone(),
+            buffer: vec![0; 64 * 1024],
+            remaining: file_size,
+        };
+        SdkBody::from_dyn(body.boxed())
+    }))
+}
+
+async fn upload_single(
+    client: &Client,
+    bucket: &str,
+    file: &Path,
+    key: &str,
+    file_size: u64,
+    content_type: &str,
+    settings: &UploadSettings,
+) -> bool {
+    let (encryption, metadata) = (&settings.encryption, &settings.metadata);
+    let checksum = settings.checksum.map(|algorithm| {
+        file_checksum(file, algorithm).expect("Error while reading the file you specified\n")
+    });
+    let bar = transfer_bar(file_size, "Uploading");
+    let body = progress_stream(file, file_size, &bar);
+    let result = client
+        .put_object()
+        .bucket(bucket)
+        .key(key)
+        .body(body)
+        .content_length(file_size as i64)
+        .content_type(content_type)
+        .set_cache_control(metadata.cache_control.clone())
+        .set_metadata(metadata.user_metadata())
+        .set_tagging(metadata.tagging())
+        .set_server_side_encryption(encryption.server_side_encryption())
+        .set_ssekms_key_id(encryption.kms_key_id())
+        .set_sse_customer_algorithm(encryption.customer_algorithm())
+        .set_sse_customer_key(encryption.customer_key())
+        .set_sse_customer_key_md5(encryption.customer_key_md5())
+        .set_checksum_sha256(checksum.as_ref().and_then(Checksum::sha256))
+        .set_checksum_crc32_c(checksum.as_ref().and_then(Checksum::crc32c))
+        .send()
+        .await;
+    match result {
+        Ok(_) => {
+            bar.finish();
+            true
+        }
+        Err(error) => {
+            bar.abandon();
+            println!(
+                "{}: {error}\n",
+                "Error while uploading the file".red().bold()
+            );
+            false
+        }
+    }
+}
+
+async fn upload_parts(
+    client: &Client,
+    bucket: &str,
+    file: &Path,
+    key: &str,
+    file_size: u64,
+    content_type: &str,
+    settings: &UploadSettings,
+) -> bool {
+    let mut state = match UploadState::load(bucket, key) {
+        Some(state)
+            if state.matches(file, file_size)
+                && state.encryption == SavedEncryption::from(&settings.encryption) =>
+        {
+            match remote_parts(client, &state).await {
+                Some((parts, part_checksums)) => {
+                    println!(
+                        "Resuming the upload of '{}' with {} of {} parts already uploaded\n",
+                        key.green().bold(),
+                        parts.len().to_string().green().bold(),
+                        file_size.div_ceil(state.part_size)
+                    );
+                    UploadState {
+                        parts,
+                        part_checksums,
+                        ..state
+                    }
+                }
+                None => {
+                    start_upload(client, bucket, file, key, file_size, content_type, settings).await
+                }
+            }
+        }
+        Some(stale) => {
+            let reason = match stale.matches(file, file_size) {
+                true => "The upload was started with a different encryption, so it starts over",
+                false => "The file changed since the interrupted upload, so it starts over",
+            };
+            println!("{}\n", reason.yellow().bold());
+            abort_upload(client, &stale.bucket, &stale.key, &stale.upload_id).await;
+            start_upload(client, bucket, file, key, file_size, content_type, settings).await
+        }
+        None => start_upload(client, bucket, file, key, file_size, content_type, settings).await,
+    };
+    state.save();
+
+    let part_count = (file_size.div_ceil(state.part_size)) as i32;
+    let mut pending = (1..=part_count)
+        .filter(|part_number| !state.parts.contains_key(part_number))
+        .collect::<Vec<_>>()
+        .into_iter();
+    let uploaded_bytes = state
+        .parts
+        .keys()
+        .map(|part_number| part_length(*part_number, state.part_size, file_size))
+        .sum();
+    let bar = transfer_bar(file_size, "Uploading");
+    bar.set_position(uploaded_bytes);
+    bar.reset_eta();
+
+    let job = PartJob {
+        client: client.clone(),
+        bucket: state.bucket.clone(),
+        key: state.key.clone(),
+        upload_id: state.upload_id.clone(),
+        file: file.to_path_buf(),
+        part_size: state.part_size,
+        file_size,
+        encryption: settings.encryption.clone(),
+        checksum: state.checksum,
+    };
+    let mut tasks = JoinSet::new();
+    let mut failed = false;
+    loop {
+        while !failed && tasks.len() < settings.concurrency {
+            let Some(part_number) = pending.next() else {
+                break;
+            };
+            tasks.spawn(upload_part(job.clone(), part_number));
+        }
+        let Some(result) = tasks.join_next().await else {
+            break;
+        };
+        match result.expect("The part upload task panicked\n") {
+            Ok((part_number, e_tag, checksum)) => {
+                bar.inc(part_length(part_number, state.part_size, file_size));
+                state.parts.insert(part_number, e_tag);
+                if let Some(checksum) = checksum {
+                    state.part_checksums.insert(part_number, checksum.value);
+                }
+                // Saving after every part means an interruption only loses the parts in flight
+                state.save();
+            }
+            Err(error) => {
+                failed = true;
+                bar.println(format!(
+                    "{}: {error}",
+                    "Error while uploading a part".red().bold()
+                ));
+            }
+        }
+    }
+    if failed {
+        bar.abandon();
+        println!(
+            "{}\n",
+            "The upload is incomplete; run the same upload again or choose 'Resume Interrupted Uploads' to continue"
+                .yellow()
+                .bold()
+        );
+        return false;
+    }
+    bar.finish();
+
+    let parts = state
+        .parts
+        .iter()
+        .map(|(part_number, e_tag)| {
+            let checksum = state
+                .checksum
+                .zip(state.part_checksums.get(part_number))
+                .map(|(algorithm, value)| Checksum {
+                    algorithm,
+                    value: value.clone(),
+                });
+            CompletedPart::builder()
+                .part_number(*part_number)
+                .e_tag(e_tag)
+                .set_checksum_sha256(checksum.as_ref().and_then(Checksum::sha256))
+                .set_checksum_crc32_c(checksum.as_ref().and_then(Checksum::crc32c))
+                .build()
+        })
+        .collect();
+    let encryption = &settings.encryption;
+    let result = client
+        .complete_multipart_upload()
+        .bucket(bucket)
+        .key(key)
+        .upload_id(&state.upload_id)
+        .set_sse_customer_algorithm(encryption.customer_algorithm())
+        .set_sse_customer_key(encryption.customer_key())
+        .set_sse_customer_key_md5(encryption.customer_key_md5())
+        .multipart_upload(
+            CompletedMultipartUpload::builder()
+                .set_parts(Some(parts))
+                .build(),
+        )
+        .send()
+        .await;
+    if let Err(error) = result {
+        // The parts stay on S3 and in the state file, so the upload can still be resumed or aborted
+        println!(
+            "{}: {}\n",
+            "Error while completing the multipart upload".red().bold(),
+            error.into_service_error()
+        );
+        println!(
+            "{}\n",
+            "Run the same upload again or choose 'Resume Interrupted Uploads' to retry, or abort it to stop paying for its parts"
+                .yellow()
+                .bold()
+        );
+        return false;
+    }
+    UploadState::remove(bucket, key);
+    true
+}
+
+async fn start_upload(
+    client: &Client,
+    bucket: &str,
+    file: &Path,
+    key: &str,
+    file_size: u64,
+    content_type: &str,
+    settings: &UploadSettings,
+) -> UploadState {
+    let (encryption, metadata) = (&settings.encryption, &settings.metadata);
+    let output = client
+        .create_multipart_upload()
+        .bucket(bucket)
+        .key(key)
+        .content_type(content_type)
+        .set_cache_control(metadata.cache_control.clone())
+        .set_metadata(metadata.user_metadata())
+        .set_tagging(metadata.tagging())
+        .set_server_side_encryption(encryption.server_side_encryption())
+        .set_ssekms_key_id(encryption.kms_key_id())
+        .set_sse_customer_algorithm(encryption.customer_algorithm())
+        .set_sse_customer_key(encryption.customer_key())
+        .set_sse_customer_key_md5(encryption.customer_key_md5())
+        .set_checksum_algorithm(settings.checksum.map(|algorithm| algorithm.algorithm()))
+        .send()
+        .await
+        .expect("Error while starting the multipart upload\n");
+    UploadState {
+        bucket: bucket.into(),
+        key: key.into(),
+        file: file.to_path_buf(),
+        file_size,
+        modified: modified_secs(file),
+        part_size: settings.part_size_for(file_size),
+        upload_id: output.upload_id().unwrap_or_default().into(),
+        parts: BTreeMap::new(),
+        checksum: settings.checksum,
+        part_checksums: BTreeMap::new(),
+        encryption: SavedEncryption::from(encryption),
+    }
+}
+
+// The ETags and checksums of the parts S3 already has for an upload, or None when the upload
+// no longer exists
+async fn remote_parts(
+    client: &Client,
+    state: &UploadState,
+) -> Option<(BTreeMap<i32, String>, BTreeMap<i32, String>)> {
+    let mut parts = BTreeMap::new();
+    let mut checksums = BTreeMap::new();
+    let mut marker = None;
+    loop {
+        let output = client
+            .list_parts()
+            .bucket(&state.bucket)
+            .key(&state.key)
+            .upload_id(&state.upload_id)
+            .set_part_number_marker(marker)
+            .send()
+            .await
+            .ok()?;
+        for part in output.parts().unwrap_or_default() {
+            if let Some(e_tag) = part.e_tag() {
+                parts.insert(part.part_number(), e_tag.to_string());
+            }
+            if let Some(checksum) =
+                Checksum::from_headers(part.checksum_sha256(), part.checksum_crc32_c())
+            {
+                checksums.insert(part.part_number(), checksum.value);
+            }
+        }
+        marker = output
+            .next_part_number_marker()
+            .map(|marker| marker.to_string());
+        if !output.is_truncated() {
+            break Some((parts, checksums));
+        }
+    }
+}
+
+fn part_length(part_number: i32, part_size: u64, file_size: u64) -> u64 {
+    let offset = (part_number as u64 - 1) * part_size;
+    part_size.min(file_size - offset)
+}
+
+// Everything a spawned task needs to upload one part of the file
+#[derive(Clone)]
+struct PartJob {
+    client: Client,
+    bucket: String,
+    key: String,
+    upload_id: String,
+    file: PathBuf,
+    part_size: u64,
+    file_size: u64,
+    encryption: Encryption,
+    checksum: Option<ChecksumKind>,
+}
+
+async fn upload_part(
+    job: PartJob,
+    part_number: i32,
+) -> Result<(i32, String, Option<Checksum>), String> {
+    let length = part_length(part_number, job.part_size, job.file_size);
+    let mut reader = tokio::fs::File::open(&job.file)
+        .await
+        .map_err(|error| error.to_string())?;
+    reader
+        .seek(std::io::SeekFrom::Start(
+            (part_number as u64 - 1) * job.part_size,
+        ))
+        .await
+        .map_err(|error| error.to_string())?;
+    let mut buffer = vec![0; length as usize];
+    reader
+        .read_exact(&mut buffer)
+        .await
+        .map_err(|error| error.to_string())?;
+    let checksum = job
+        .checksum
+        .map(|algorithm| Checksum::of(algorithm, &buffer));
+    let mut last_error = String::new();
+    for _ in 0..PART_ATTEMPTS {
+        let result = job
+            .client
+            .upload_part()
+            .bucket(&job.bucket)
+            .key(&job.key)
+            .upload_id(&job.upload_id)
+            .part_number(part_number)
+            .content_length(length as i64)
+            .body(ByteStream::from(buffer.clone()))
+            .set_sse_customer_algorithm(job.encryption.customer_algorithm())
+            .set_sse_customer_key(job.encryption.customer_key())
+            .set_sse_customer_key_md5(job.encryption.customer_key_md5())
+            .set_checksum_sha256(checksum.as_ref().and_then(Checksum::sha256))
+            .set_checksum_crc32_c(checksum.as_ref().and_then(Checksum::crc32c))
+            .send()
+            .await;
+        match result {
+            Ok(output) => {
+                return Ok((
+                    part_number,
+                    output.e_tag().unwrap_or_default().into(),
+                    checksum,
+                ))
+            }
+            Err(error) => last_error = format!("part {part_number}: {error}"),
+        }
+    }
+    Err(last_error)
+}
+
+/// A multipart upload that was started but never completed or aborted
+pub struct IncompleteUpload {
+    pub key: String,
+    pub upload_id: String,
+    pub initiated: String,
+}
+
+pub async fn list_incomplete_uploads(config: &SdkConfig, bucket: &str) -> Vec<IncompleteUpload> {
+    let client = Client::new(config);
+    let mut uploads = Vec::new();
+    let (mut key_marker, mut upload_id_marker) = (None, None);
+    loop {
+        let output = client
+            .list_multipart_uploads()
+            .bucket(bucket)
+            .set_key_marker(key_marker)
+            .set_upload_id_marker(upload_id_marker)
+            .send()
+            .await
+            .expect("Error while listing the multipart uploads\n");
+        for upload in output.uploads().unwrap_or_default() {
+            uploads.push(IncompleteUpload {
+                key: upload.key().unwrap_or_default().into(),
+                upload_id: upload.upload_id().unwrap_or_default().into(),
+                initiated: upload
+                    .initiated()
+                    .and_then(|initiated| initiated.fmt(DateTimeFormat::DateTime).ok())
+                    .unwrap_or_default(),
+            });
+        }
+        key_marker = output.next_key_marker().map(|marker| marker.to_string());
+        upload_id_marker = output
+            .next_upload_id_marker()
+            .map(|marker| marker.to_string());
+        if !output.is_truncated() {
+            break uploads;
+        }
+    }
+}
+
+pub fn print_incomplete_uploads(bucket: &str, uploads: &[IncompleteUpload]) {
+    if uploads.is_empty() {
+        println!(
+            "{}\n",
+            format!("There are no incomplete multipart uploads in '{bucket}'")
+                .green()
+                .bold()
+        );
+    }
+    for upload in uploads {
+        println!(
+            "Key: {}\nUpload Id: {}\nInitiated: {}\n",
+            upload.key.green().bold(),
+            upload.upload_id.yellow(),
+            upload.initiated
+        );
+    }
+}
+
+/// Aborts an upload so S3 stops charging for its parts, and forgets its local state. An upload
+/// S3 no longer knows, because it expired or was already aborted, counts as aborted
+pub async fn abort_upload(client: &Client, bucket: &str, key: &str, upload_id: &str) {
+    let result = client
+        .abort_multipart_upload()
+        .bucket(bucket)
+        .key(key)
+        .upload_id(upload_id)
+        .send()
+        .await;
+    match result {
+        Ok(_) => println!(
+            "The multipart upload of '{}' has been aborted\n",
+            key.green().bold()
+        ),
+        Err(error) if error.code() == Some("NoSuchUpload") => println!(
+            "The multipart upload of '{}' no longer exists on S3\n",
+            key.green().bold()
+        ),
+        Err(error) => panic!(
+            "Error while aborting the multipart upload: {}\n",
+            error.into_service_error()
+        ),
+    }
+    if UploadState::load(bucket, key).is_some_and(|state| state.upload_id == upload_id) {
+        UploadState::remove(bucket, key);
+    }
+}
+
+pub async fn abort_uploads(config: &SdkConfig, bucket: &str, uploads: &[IncompleteUpload]) {
+    let client = Client::new(config);
+    for upload in uploads {
+        abort_upload(&client, bucket, &upload.key, &upload.upload_id).await;
+    }
+}
@@ -0,0 +1,231 @@
+use aws_sdk_s3::primitives::DateTime;
+use aws_sdk_s3::types::{Delete, Object, ObjectIdentifier};
+use aws_sdk_s3::Client;
+use colored::Colorize;
+
+/// An object as returned by a listing
+#[derive(Clone)]
+pub struct RemoteObject {
+    pub key: String,
+    pub size: u64,
+    /// Seconds since the Unix epoch
+    pub last_modified: i64,
+    /// The ETag without the surrounding quotes
+    pub e_tag: String,
+    pub storage_class: String,
+}
+
+impl RemoteObject {
+    /// Objects uploaded in one request have the MD5 of their content as ETag; multipart,
+    /// SSE-KMS and SSE-C ETags are something else. Only the multipart ones contain a '-', so
+    /// the encryption has to be checked with a HEAD before this is taken as the MD5
+    pub fn md5(&self) -> Option<&str> {
+        match self.e_tag.len() == 32 && !self.e_tag.contains('-') {
+            true => Some(&self.e_tag),
+            false => None,
+        }
+    }
+}
+
+impl From<&Object> for RemoteObject {
+    fn from(object: &Object) -> Self {
+        Self {
+            key: object.key().unwrap_or_default().into(),
+            size: object.size().max(0) as u64,
+            last_modified: object
+                .last_modified()
+                .map(|modified| modified.secs())
+                .unwrap_or_default(),
+            e_tag: object.e_tag().unwrap_or_default().trim_matches('"').into(),
+            storage_class: object
+                .storage_class()
+                .map(|class| class.as_str().to_string())
+                .unwrap_or("STANDARD".into()),
+        }
+    }
+}
+
+/// One page of a listing. With a delimiter, keys below the next '/' are grouped into folders
+pub struct ListingPage {
+    pub folders: Vec<String>,
+    pub objects: Vec<RemoteObject>,
+    pub next_token: Option<String>,
+}
+
+pub async fn list_page(
+    client: &Client,
+    bucket: &str,
+    prefix: &str,
+    delimiter: Option<&str>,
+    page_size: i32,
+    token: Option<String>,
+) -> ListingPage {
+    let output = client
+        .list_objects_v2()
+        .bucket(bucket)
+        .prefix(prefix)
+        .set_delimiter(delimiter.map(|delimiter| delimiter.to_string()))
+        .max_keys(page_size)
+        .set_continuation_token(token)
+        .send()
+        .await
+        .expect("Error while listing the objects of the bucket\n");
+    ListingPage {
+        folders: output
+            .common_prefixes()
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|folder| folder.prefix())
+            .map(|folder| folder.to_string())
+            .collect(),
+        objects: output
+            .contents()
+            .unwrap_or_default()
+            .iter()
+            .map(RemoteObject::from)
+            .collect(),
+        next_token: output
+            .next_continuation_token()
+            .map(|token| token.to_string()),
+    }
+}
+
+/// Every object under the prefix, following the continuation tokens
+pub async fn list_objects(client: &Client, bucket: &str, prefix: &str) -> Vec<RemoteObject> {
+    let mut objects = Vec::new();
+    let mut token = None;
+    loop {
+        let page = list_page(client, bucket, prefix, None, 1000, token).await;
+        objects.extend(page.objects);
+        token = page.next_token;
+        if token.is_none() {
+            break objects;
+        }
+    }
+}
+
+/// One version of an object, or a delete marker, as returned by ListObjectVersions
+#[derive(Clone)]
+pub struct VersionEntry {
+    pub key: String,
+    pub version_id: String,
+    pub size: u64,
+    pub last_modified: i64,
+    pub is_latest: bool,
+    pub is_delete_marker: bool,
+}
+
+/// Every version and delete marker under the prefix, following the markers
+pub async fn list_versions(client: &Client, bucket: &str, prefix: &str) -> Vec<VersionEntry> {
+    let mut entries = Vec::new();
+    let (mut key_marker, mut version_id_marker) = (None, None);
+    loop {
+        let output = client
+            .list_object_versions()
+            .bucket(bucket)
+            .prefix(prefix)
+            .set_key_marker(key_marker)
+            .set_version_id_marker(version_id_marker)
+            .send()
+            .await
+            .expect("Error while listing the object versions\n");
+        let secs = |modified: Option<&DateTime>| modified.map(DateTime::secs).unwrap_or_default();
+        entries.extend(
+            output
+                .versions()
+                .unwrap_or_default()
+                .iter()
+                .map(|version| VersionEntry {
+                    key: version.key().unwrap_or_default().into(),
+                    version_id: version.version_id().unwrap_or("null").into(),
+                    size: version.size().max(0) as u64,
+                    last_modified: secs(version.last_modified()),
+                    is_latest: version.is_latest(),
+                    is_delete_marker: false,
+                }),
+        );
+        entries.extend(
+            output
+                .delete_markers()
+                .unwrap_or_default()
+                .iter()
+                .map(|marker| VersionEntry {
+                    key: marker.key().unwrap_or_default().into(),
+                    version_id: marker.version_id().unwrap_or("null").into(),
+                    size: 0,
+                    last_modified: secs(marker.last_modified()),
+                    is_latest: marker.is_latest(),
+                    is_delete_marker: true,
+                }),
+        );
+        if !output.is_truncated() {
+            break;
+        }
+        key_marker = output.next_key_marker().map(str::to_string);
+        version_id_marker = output.next_version_id_marker().map(str::to_string);
+    }
+    // Versions and delete markers come in separate lists; newest first within each key
+    entries.sort_by(|a, b| {
+        a.key
+            .cmp(&b.key)
+            .then(b.last_modified.cmp(&a.last_modified))
+    });
+    entries
+}
+
+/// Deletes the keys in batches of 1000, the most a DeleteObjects request accepts.
+/// Returns how many were deleted; the ones that failed are printed
+pub async fn delete_keys(client: &Client, bucket: &str, keys: &[String]) -> usize {
+    let identifiers = keys
+        .iter()
+        .map(|key| ObjectIdentifier::builder().key(key).build())
+        .collect::<Vec<_>>();
+    delete_identifiers(client, bucket, identifiers).await
+}
+
+/// Permanently deletes the given versions and delete markers, in batches like 'delete_keys'
+pub async fn delete_versions(client: &Client, bucket: &str, versions: &[VersionEntry]) -> usize {
+    let identifiers = versions
+        .iter()
+        .map(|version| {
+            ObjectIdentifier::builder()
+                .key(&version.key)
+                .version_id(&version.version_id)
+                .build()
+        })
+        .collect::<Vec<_>>();
+    delete_identifiers(client, bucket, identifiers).await
+}
+
+async fn delete_identifiers(
+    client: &Client,
+    bucket: &str,
+    identifiers: Vec<ObjectIdentifier>,
+) -> usize {
+    let mut deleted = 0;
+    for batch in identifiers.chunks(1000) {
+        let output = client
+            .delete_objects()
+            .bucket(bucket)
+            .delete(
+                Delete::builder()
+                    .set_objects(Some(batch.to_vec()))
+                    .quiet(true)
+                    .build(),
+            )
+            .send()
+            .await
+            .expect("Error while deleting the objects\n");
+        let errors = output.errors().unwrap_or_default();
+        for error in errors {
+            println!(
+                "{} {}: {}",
+                "Error while deleting".red().bold(),
+                error.key().unwrap_or_default().red().bold(),
+                error.message().unwrap_or_default()
+            );
+        }
+        deleted += batch.len() - errors.len();
+    }
+    deleted
+}
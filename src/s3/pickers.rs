@@ -0,0 +1,187 @@
+use aws_config::SdkConfig;
+use aws_sdk_s3::Client;
+use colored::Colorize;
+use inquire::Select;
+use std::fmt;
+
+use super::format_size;
+use super::objects::{list_page, RemoteObject};
+
+const PAGE_SIZE: i32 = 200;
+
+/// Keeps the options whose text contains the typed characters in order, ignoring case,
+/// so 'rptpdf' finds 'reports/2024/report.pdf'
+pub fn fuzzy_filter<T>(input: &str, _: &T, value: &str, _: usize) -> bool {
+    let mut value = value.chars().flat_map(char::to_lowercase);
+    input
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .all(|wanted| value.any(|c| c == wanted))
+}
+
+/// The names of every bucket in the account
+pub async fn bucket_names(config: &SdkConfig) -> Vec<String> {
+    let client = Client::new(config);
+    let output = client
+        .list_buckets()
+        .send()
+        .await
+        .expect("Error while listing the buckets\n");
+    output
+        .buckets()
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|bucket| bucket.name().map(str::to_string))
+        .collect()
+}
+
+/// Lets the user choose one of the account's buckets. Returns None when there are no buckets
+/// or the prompt is skipped with Esc
+pub async fn pick_bucket(config: &SdkConfig, message: &str) -> Option<String> {
+    let buckets = bucket_names(config).await;
+    if buckets.is_empty() {
+        println!(
+            "{}\n",
+            "There are no buckets in this account".yellow().bold()
+        );
+        return None;
+    }
+    Select::new(message, buckets)
+        .with_filter(&fuzzy_filter)
+        .with_page_size(15)
+        .with_help_message("Type to search, Esc to cancel")
+        .prompt_skippable()
+        .unwrap()
+}
+
+enum Entry {
+    UseFolder(String),
+    Up,
+    Folder { prefix: String, name: String },
+    Object { object: RemoteObject, name: String },
+    More,
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Entry::UseFolder(prefix) if prefix.is_empty() => write!(f, "[ use the top level ]"),
+            Entry::UseFolder(prefix) => write!(f, "[ use {prefix} ]"),
+            Entry::Up => write!(f, "../"),
+            Entry::Folder { name, .. } => write!(f, "{name}"),
+            Entry::Object { object, name } => write!(f, "{name}  ({})", format_size(object.size)),
+            Entry::More => write!(f, "[ load more ]"),
+        }
+    }
+}
+
+/// What the browser returns: objects for download, delete, ACL and presign, folders for put
+#[derive(Clone, Copy, PartialEq)]
+enum Target {
+    Object,
+    Folder,
+}
+
+/// Browses the bucket folder by folder until an object is chosen. Returns None on Esc
+pub async fn pick_key(config: &SdkConfig, bucket: &str, message: &str) -> Option<String> {
+    browse_for(config, bucket, message, Target::Object).await
+}
+
+/// Browses the bucket until a folder is chosen with '[ use ... ]', e.g. where to put a file.
+/// The returned prefix is empty for the top level and ends with '/' otherwise
+pub async fn pick_folder(config: &SdkConfig, bucket: &str, message: &str) -> Option<String> {
+    browse_for(config, bucket, message, Target::Folder).await
+}
+
+/// A bucket followed by a key, the pair most operations need
+pub async fn pick_object(config: &SdkConfig, message: &str) -> Option<(String, String)> {
+    let bucket = pick_bucket(config, "Select the bucket\n").await?;
+    let key = pick_key(config, &bucket, message).await?;
+    Some((bucket, key))
+}
+
+async fn browse_for(
+    config: &SdkConfig,
+    bucket: &str,
+    message: &str,
+    target: Target,
+) -> Option<String> {
+    let client = Client::new(config);
+    let mut prefix = String::new();
+    // Folders and objects of the current prefix, with more pages appended on '[ load more ]'
+    let mut folders: Vec<String> = Vec::new();
+    let mut objects: Vec<RemoteObject> = Vec::new();
+    let mut token = None;
+    let mut loaded = false;
+    loop {
+        if !loaded {
+            let page =
+                list_page(&client, bucket, &prefix, Some("/"), PAGE_SIZE, token.take()).await;
+            folders.extend(page.folders);
+            if target == Target::Object {
+                // Skipping the placeholder object some consoles create for a folder
+                objects.extend(
+                    page.objects
+                        .into_iter()
+                        .filter(|object| object.key != prefix),
+                );
+            }
+            token = page.next_token;
+            loaded = true;
+        }
+        if folders.is_empty() && objects.is_empty() && target == Target::Object && prefix.is_empty()
+        {
+            println!(
+                "{}\n",
+                format!("The bucket '{bucket}' is empty").yellow().bold()
+            );
+            return None;
+        }
+
+        let relative = |path: &str| path[prefix.len()..].to_string();
+        let mut choices = Vec::new();
+        if target == Target::Folder {
+            choices.push(Entry::UseFolder(prefix.clone()));
+        }
+        if !prefix.is_empty() {
+            choices.push(Entry::Up);
+        }
+        choices.extend(folders.iter().map(|folder| Entry::Folder {
+            prefix: folder.clone(),
+            name: relative(folder),
+        }));
+        choices.extend(objects.iter().map(|object| Entry::Object {
+            name: relative(&object.key),
+            object: object.clone(),
+        }));
+        if token.is_some() {
+            choices.push(Entry::More);
+        }
+
+        let location = format!("s3://{bucket}/{prefix}");
+        let choice = Select::new(message, choices)
+            .with_filter(&fuzzy_filter)
+            .with_page_size(15)
+            .with_help_message(&format!("{location}   Type to search, Esc to cancel"))
+            .prompt_skippable()
+            .unwrap()?;
+        match choice {
+            Entry::UseFolder(prefix) => return Some(prefix),
+            Entry::Object { object, .. } => return Some(object.key),
+            Entry::More => loaded = false,
+            Entry::Folder { prefix: folder, .. } => {
+                prefix = folder;
+                (folders, objects, token, loaded) = (Vec::new(), Vec::new(), None, false);
+            }
+            Entry::Up => {
+                let trimmed = prefix.trim_end_matches('/');
+                prefix = match trimmed.rfind('/') {
+                    Some(position) => trimmed[..=position].to_string(),
+                    None => String::new(),
+                };
+                (folders, objects, token, loaded) = (Vec::new(), Vec::new(), None, false);
+            }
+        }
+    }
+}
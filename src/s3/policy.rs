@@ -0,0 +1,833 @@
+use aws_config::SdkConfig;
+use aws_sdk_s3::error::ProvideErrorMetadata;
+use aws_sdk_s3::types::PublicAccessBlockConfiguration;
+use aws_sdk_s3::Client;
+use colored::Colorize;
+use inquire::{Confirm, MultiSelect, Select, Text};
+use serde_json::{json, Value};
+use similar::{ChangeTag, TextDiff};
+use std::env::var;
+use std::fs;
+use std::process::Command;
+
+pub const POLICY_VERSION: &str = "2012-10-17";
+
+/// The current policy of the bucket, pretty-printed, or None when it has none
+pub async fn get_policy(config: &SdkConfig, bucket: &str) -> Option<String> {
+    let client = Client::new(config);
+    match client.get_bucket_policy().bucket(bucket).send().await {
+        Ok(output) => output.policy().map(pretty_policy),
+        Err(error) if error.code() == Some("NoSuchBucketPolicy") => None,
+        Err(error) => panic!(
+            "Error while getting the bucket policy: {}\n",
+            error.into_service_error()
+        ),
+    }
+}
+
+/// A policy without statements, as a starting point for writing one
+pub fn empty_policy() -> String {
+    serde_json::to_string_pretty(&json!({ "Version": POLICY_VERSION, "Statement": [] })).unwrap()
+}
+
+fn pretty_policy(policy: &str) -> String {
+    serde_json::from_str::<Value>(policy)
+        .map(|value| serde_json::to_string_pretty(&value).unwrap())
+        .unwrap_or(policy.to_string())
+}
+
+fn as_list(value: &Value) -> Vec<&Value> {
+    match value {
+        Value::Array(values) => values.iter().collect(),
+        value => vec![value],
+    }
+}
+
+/// Checks the JSON syntax and the parts of the policy grammar that S3 rejects most often.
+/// Returns warnings for policies that are valid but probably not intended, like public access
+pub fn validate_policy(policy: &str, bucket: &str) -> Result<Vec<String>, String> {
+    let value: Value = serde_json::from_str(policy).map_err(|error| {
+        format!(
+            "Invalid JSON at line {}, column {}: {error}",
+            error.line(),
+            error.column()
+        )
+    })?;
+    let document = value
+        .as_object()
+        .ok_or("The policy should be a JSON object")?;
+    let mut warnings = Vec::new();
+    match document.get("Version").and_then(Value::as_str) {
+        Some(POLICY_VERSION) => {}
+        Some(version) => {
+            return Err(format!(
+                "The Version '{version}' isn't supported; use \"{POLICY_VERSION}\""
+            ))
+        }
+        None => warnings.push(format!(
+            "Without \"Version\": \"{POLICY_VERSION}\", policy variables aren't available"
+        )),
+    }
+    let statements = as_list(
+        document
+            .get("Statement")
+            .ok_or("The policy has no Statement")?,
+    );
+    if statements.is_empty() {
+        return Err("The Statement list is empty".into());
+    }
+    let bucket_arn = format!("arn:aws:s3:::{bucket}");
+    let mut sids = Vec::new();
+    for (index, statement) in statements.iter().enumerate() {
+        let statement = statement
+            .as_object()
+            .ok_or(format!("Statement {} should be a JSON object", index + 1))?;
+        let name = match statement.get("Sid") {
+            Some(Value::String(sid)) => {
+                if !sid.chars().all(|c| c.is_ascii_alphanumeric()) {
+                    return Err(format!(
+                        "The Sid '{sid}' may only contain letters and digits"
+                    ));
+                }
+                if sids.contains(sid) {
+                    return Err(format!("The Sid '{sid}' is used more than once"));
+                }
+                sids.push(sid.clone());
+                format!("Statement '{sid}'")
+            }
+            Some(_) => {
+                return Err(format!(
+                    "The Sid of statement {} should be a string",
+                    index + 1
+                ))
+            }
+            None => format!("Statement {}", index + 1),
+        };
+        match statement.get("Effect").and_then(Value::as_str) {
+            Some("Allow") | Some("Deny") => {}
+            _ => return Err(format!("{name} needs \"Effect\": \"Allow\" or \"Deny\"")),
+        }
+        let exactly_one = |first: &str, second: &str| match (
+            statement.contains_key(first),
+            statement.contains_key(second),
+        ) {
+            (true, false) | (false, true) => Ok(()),
+            _ => Err(format!("{name} needs exactly one of {first} and {second}")),
+        };
+        exactly_one("Action", "NotAction")?;
+        exactly_one("Resource", "NotResource")?;
+        exactly_one("Principal", "NotPrincipal")?;
+
+        let actions = statement
+            .get("Action")
+            .or(statement.get("NotAction"))
+            .map(as_list)
+            .unwrap_or_default();
+        for action in actions {
+            match action.as_str() {
+                Some(action) if action == "*" || action.to_lowercase().starts_with("s3:") => {}
+                _ => {
+                    return Err(format!(
+                        "{name} has the action {action}, which isn't an S3 action"
+                    ))
+                }
+            }
+        }
+        let resources = statement
+            .get("Resource")
+            .or(statement.get("NotResource"))
+            .map(as_list)
+            .unwrap_or_default();
+        for resource in resources {
+            let Some(resource) = resource.as_str() else {
+                return Err(format!("{name} has a resource that isn't a string"));
+            };
+            let in_bucket =
+                resource == bucket_arn || resource.starts_with(&format!("{bucket_arn}/"));
+            if resource != "*" && !in_bucket {
+                return Err(format!(
+                    "{name} names the resource '{resource}', which isn't in {bucket_arn}"
+                ));
+            }
+        }
+        let public = match statement.get("Principal") {
+            Some(Value::String(principal)) => principal == "*",
+            Some(Value::Object(principal)) => principal
+                .get("AWS")
+                .is_some_and(|aws| as_list(aws).iter().any(|aws| aws.as_str() == Some("*"))),
+            _ => false,
+        };
+        let allows = statement.get("Effect").and_then(Value::as_str) == Some("Allow");
+        if public && allows && !statement.contains_key("Condition") {
+            warnings.push(format!("{name} allows anyone on the internet"));
+        }
+    }
+    Ok(warnings)
+}
+
+/// Common statements that are added to the bucket's policy
+pub enum PolicyTemplate {
+    /// Anyone can read the objects under the prefix, e.g. for a public website or assets
+    PublicRead { prefix: String },
+    /// Only the given CloudFront distribution can read, through Origin Access Control
+    CloudFrontOac { distribution_arn: String },
+    /// Another AWS account can list the bucket and read the objects under the prefix
+    CrossAccountRead { account_id: String, prefix: String },
+}
+
+impl PolicyTemplate {
+    pub fn statement(&self, bucket: &str) -> Value {
+        let bucket_arn = format!("arn:aws:s3:::{bucket}");
+        match self {
+            PolicyTemplate::PublicRead { prefix } => json!({
+                "Sid": public_read_sid(prefix),
+                "Effect": "Allow",
+                "Principal": "*",
+                "Action": "s3:GetObject",
+                "Resource": format!("{bucket_arn}/{prefix}*"),
+            }),
+            PolicyTemplate::CloudFrontOac { distribution_arn } => json!({
+                "Sid": "AllowCloudFrontServicePrincipal",
+                "Effect": "Allow",
+                "Principal": { "Service": "cloudfront.amazonaws.com" },
+                "Action": "s3:GetObject",
+                "Resource": format!("{bucket_arn}/*"),
+                "Condition": { "StringEquals": { "AWS:SourceArn": distribution_arn } },
+            }),
+            PolicyTemplate::CrossAccountRead { account_id, prefix } => json!({
+                "Sid": format!("CrossAccountRead{account_id}"),
+                "Effect": "Allow",
+                "Principal": { "AWS": format!("arn:aws:iam::{account_id}:root") },
+                "Action": ["s3:GetObject", "s3:ListBucket"],
+                "Resource": [bucket_arn.clone(), format!("{bucket_arn}/{prefix}*")],
+            }),
+        }
+    }
+
+    /// Whether the statement only works with the public policy blocks turned off
+    pub fn is_public(&self) -> bool {
+        matches!(self, PolicyTemplate::PublicRead { .. })
+    }
+}
+
+/// Each prefix gets its own Sid, e.g. 'PublicReadAssetsImg' for 'assets/img/', so public reads
+/// of different prefixes don't replace each other. The whole bucket keeps 'PublicReadGetObject'
+fn public_read_sid(prefix: &str) -> String {
+    let name = prefix
+        .split(|char: char| !char.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| part[..1].to_uppercase() + &part[1..])
+        .collect::<String>();
+    if name.is_empty() {
+        "PublicReadGetObject".into()
+    } else {
+        format!("PublicRead{name}")
+    }
+}
+
+/// Whether the policy has a statement with the same Sid as 'statement'
+pub fn has_statement(policy: &str, statement: &Value) -> bool {
+    let Some(sid) = statement.get("Sid").and_then(Value::as_str) else {
+        return false;
+    };
+    serde_json::from_str::<Value>(policy)
+        .ok()
+        .and_then(|document| document.get("Statement").cloned())
+        .is_some_and(|statements| {
+            as_list(&statements)
+                .iter()
+                .any(|existing| existing.get("Sid").and_then(Value::as_str) == Some(sid))
+        })
+}
+
+/// Removes the statement with the given Sid from the bucket policy, deleting the policy when
+/// nothing else is left in it. Returns false when the bucket has no such statement
+pub async fn remove_statement(
+    config: &SdkConfig,
+    bucket: &str,
+    sid: &str,
+    assume_yes: bool,
+) -> bool {
+    let Some(current) = get_policy(config, bucket).await else {
+        return false;
+    };
+    let Ok(mut document) = serde_json::from_str::<Value>(&current) else {
+        return false;
+    };
+    let statements = document
+        .get("Statement")
+        .map(|statements| as_list(statements).into_iter().cloned().collect::<Vec<_>>())
+        .unwrap_or_default();
+    let remaining = statements
+        .iter()
+        .filter(|statement| statement.get("Sid").and_then(Value::as_str) != Some(sid))
+        .cloned()
+        .collect::<Vec<_>>();
+    if remaining.len() == statements.len() {
+        return false;
+    }
+    if remaining.is_empty() {
+        delete_policy(config, bucket, assume_yes).await;
+        return true;
+    }
+    document["Statement"] = Value::Array(remaining);
+    put_policy(
+        config,
+        bucket,
+        &serde_json::to_string_pretty(&document).unwrap(),
+        assume_yes,
+    )
+    .await
+}
+
+/// Adds the statement to the policy, replacing a statement with the same Sid
+pub fn add_statement(policy: Option<&str>, statement: Value) -> String {
+    let mut document = policy
+        .and_then(|policy| serde_json::from_str::<Value>(policy).ok())
+        .unwrap_or(json!({ "Version": POLICY_VERSION, "Statement": [] }));
+    let mut statements = document
+        .get("Statement")
+        .map(|statements| as_list(statements).into_iter().cloned().collect::<Vec<_>>())
+        .unwrap_or_default();
+    statements.retain(|existing| existing.get("Sid") != statement.get("Sid"));
+    statements.push(statement);
+    document["Statement"] = Value::Array(statements);
+    serde_json::to_string_pretty(&document).unwrap()
+}
+
+/// Adds the template's statement to the bucket policy. Public statements first need the
+/// public policy switches of Block Public Access turned off, which is offered when they are on
+pub async fn apply_template(
+    config: &SdkConfig,
+    bucket: &str,
+    template: &PolicyTemplate,
+    assume_yes: bool,
+) -> bool {
+    if template.is_public() {
+        let block = get_public_access_block(config, bucket).await;
+        if block.block_public_policy || block.restrict_public_buckets {
+            println!(
+                "{}\n",
+                "Block Public Access refuses public policies on this bucket"
+                    .yellow()
+                    .bold()
+            );
+            let unblocked = PublicAccessBlock {
+                block_public_policy: false,
+                restrict_public_buckets: false,
+                ..block
+            };
+            if !put_public_access_block(config, bucket, unblocked, assume_yes).await {
+                println!(
+                    "{}\n",
+                    "The policy isn't changed, since S3 would reject it while public policies are blocked"
+                        .yellow()
+                        .bold()
+                );
+                return false;
+            }
+        }
+    }
+    let current = get_policy(config, bucket).await;
+    let policy = add_statement(current.as_deref(), template.statement(bucket));
+    put_policy(config, bucket, &policy, assume_yes).await
+}
+
+fn print_diff(old: &str, new: &str) {
+    let diff = TextDiff::from_lines(old, new);
+    for hunk in diff.unified_diff().context_radius(3).iter_hunks() {
+        println!("{}", hunk.header().to_string().cyan());
+        for change in hunk.iter_changes() {
+            let line = change.value().trim_end_matches('\n');
+            match change.tag() {
+                ChangeTag::Delete => println!("{}", format!("-{line}").red()),
+                ChangeTag::Insert => println!("{}", format!("+{line}").green()),
+                ChangeTag::Equal => println!(" {line}"),
+            }
+        }
+    }
+    println!();
+}
+
+/// Validates the policy, shows how it differs from the current one and saves it after a
+/// confirmation that 'assume_yes' skips. Returns whether the policy was saved
+pub async fn put_policy(config: &SdkConfig, bucket: &str, policy: &str, assume_yes: bool) -> bool {
+    let warnings = match validate_policy(policy, bucket) {
+        Ok(warnings) => warnings,
+        Err(message) => {
+            println!("{}\n", message.red().bold());
+            return false;
+        }
+    };
+    let current = get_policy(config, bucket).await.unwrap_or_default();
+    let policy = pretty_policy(policy);
+    if current == policy {
+        println!("{}\n", "The policy has no changes".green().bold());
+        return false;
+    }
+    println!(
+        "{}",
+        format!("--- {bucket} (bucket policy)").yellow().bold()
+    );
+    print_diff(&current, &policy);
+    for warning in &warnings {
+        println!("{} {warning}", "Warning:".yellow().bold());
+    }
+    let confirm = assume_yes
+        || Confirm::new("Apply this bucket policy?\n")
+            .with_default(false)
+            .prompt()
+            .unwrap();
+    if !confirm {
+        println!("{}\n", "The policy wasn't changed".yellow().bold());
+        return false;
+    }
+    let client = Client::new(config);
+    match client
+        .put_bucket_policy()
+        .bucket(bucket)
+        .policy(&policy)
+        .send()
+        .await
+    {
+        Ok(_) => {
+            println!("The policy of '{}' is updated\n", bucket.green().bold());
+            true
+        }
+        Err(error) => {
+            let error = error.into_service_error();
+            println!("{}: {error}", "Error while saving the policy".red().bold());
+            if error.code() == Some("AccessDenied") {
+                println!("Public policies are refused while Block Public Access blocks them; check 'public-access show'");
+            }
+            println!();
+            false
+        }
+    }
+}
+
+pub async fn delete_policy(config: &SdkConfig, bucket: &str, assume_yes: bool) {
+    let Some(current) = get_policy(config, bucket).await else {
+        println!(
+            "{}\n",
+            format!("'{bucket}' has no bucket policy").yellow().bold()
+        );
+        return;
+    };
+    print_diff(&current, "");
+    let confirm = assume_yes
+        || Confirm::new("Delete this bucket policy?\n")
+            .with_default(false)
+            .prompt()
+            .unwrap();
+    if confirm {
+        let client = Client::new(config);
+        client
+            .delete_bucket_policy()
+            .bucket(bucket)
+            .send()
+            .await
+            .expect("Error while deleting the bucket policy\n");
+        println!("The policy of '{}' is deleted\n", bucket.green().bold());
+    }
+}
+
+/// Opens the policy in $VISUAL or $EDITOR until it validates, or the user gives up.
+/// Returns the edited policy
+pub fn edit_policy(bucket: &str, policy: &str) -> Option<String> {
+    let editor = var("VISUAL")
+        .or(var("EDITOR"))
+        .unwrap_or(match cfg!(windows) {
+            true => "notepad".into(),
+            false => "vi".into(),
+        });
+    let path = std::env::temp_dir().join(format!("{bucket}-policy.json"));
+    fs::write(&path, policy).expect("Error while writing the policy to a temporary file\n");
+    loop {
+        let status = Command::new(&editor)
+            .arg(&path)
+            .status()
+            .unwrap_or_else(|error| {
+                panic!("Error while starting the editor '{editor}': {error}\n")
+            });
+        if !status.success() {
+            println!("{}\n", "The editor exited with an error".red().bold());
+            return None;
+        }
+        let edited = fs::read_to_string(&path).expect("Error while reading the edited policy\n");
+        match validate_policy(&edited, bucket) {
+            Ok(_) => {
+                let _ = fs::remove_file(&path);
+                return Some(edited);
+            }
+            Err(message) => {
+                println!("{}\n", message.red().bold());
+                let again = Confirm::new("Edit the policy again?\n")
+                    .with_default(true)
+                    .prompt()
+                    .unwrap();
+                if !again {
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+/// The four Block Public Access switches, all off for a bucket that has none configured
+#[derive(Clone, Copy, PartialEq)]
+pub struct PublicAccessBlock {
+    pub block_public_acls: bool,
+    pub ignore_public_acls: bool,
+    pub block_public_policy: bool,
+    pub restrict_public_buckets: bool,
+}
+
+impl PublicAccessBlock {
+    pub fn all(blocked: bool) -> Self {
+        Self {
+            block_public_acls: blocked,
+            ignore_public_acls: blocked,
+            block_public_policy: blocked,
+            restrict_public_buckets: blocked,
+        }
+    }
+
+    fn settings(&self) -> [(&'static str, bool); 4] {
+        [
+            ("BlockPublicAcls", self.block_public_acls),
+            ("IgnorePublicAcls", self.ignore_public_acls),
+            ("BlockPublicPolicy", self.block_public_policy),
+            ("RestrictPublicBuckets", self.restrict_public_buckets),
+        ]
+    }
+
+    pub fn print(&self, bucket: &str) {
+        println!("Block Public Access of '{}':", bucket.green().bold());
+        for (name, enabled) in self.settings() {
+            let value = match enabled {
+                true => "on".green().bold(),
+                false => "off".yellow().bold(),
+            };
+            println!("    {name:<22} {value}");
+        }
+        println!();
+    }
+}
+
+pub async fn get_public_access_block(config: &SdkConfig, bucket: &str) -> PublicAccessBlock {
+    let client = Client::new(config);
+    match client.get_public_access_block().bucket(bucket).send().await {
+        Ok(output) => output
+            .public_access_block_configuration()
+            .map(|block| PublicAccessBlock {
+                block_public_acls: block.block_public_acls(),
+                ignore_public_acls: block.ignore_public_acls(),
+                block_public_policy: block.block_public_policy(),
+                restrict_public_buckets: block.restrict_public_buckets(),
+            })
+            .unwrap_or(PublicAccessBlock::all(false)),
+        Err(error) if error.code() == Some("NoSuchPublicAccessBlockConfiguration") => {
+            PublicAccessBlock::all(false)
+        }
+        Err(error) => panic!(
+            "Error while getting the Block Public Access settings: {}\n",
+            error.into_service_error()
+        ),
+    }
+}
+
+/// Shows which switches change and saves them after a confirmation that 'assume_yes' skips.
+/// Returns whether the bucket ends up with these settings
+pub async fn put_public_access_block(
+    config: &SdkConfig,
+    bucket: &str,
+    block: PublicAccessBlock,
+    assume_yes: bool,
+) -> bool {
+    let current = get_public_access_block(config, bucket).await;
+    if current == block {
+        println!("{}\n", "The settings have no changes".green().bold());
+        return true;
+    }
+    for ((name, old), (_, new)) in current.settings().into_iter().zip(block.settings()) {
+        if old != new {
+            let describe = |enabled: bool| match enabled {
+                true => "on",
+                false => "off",
+            };
+            println!(
+                "    {name:<22} {} -> {}",
+                describe(old).red(),
+                describe(new).green()
+            );
+        }
+    }
+    println!();
+    let confirm = assume_yes
+        || Confirm::new("Apply these Block Public Access settings?\n")
+            .with_default(false)
+            .prompt()
+            .unwrap();
+    if !confirm {
+        return false;
+    }
+    let client = Client::new(config);
+    client
+        .put_public_access_block()
+        .bucket(bucket)
+        .public_access_block_configuration(
+            PublicAccessBlockConfiguration::builder()
+                .block_public_acls(block.block_public_acls)
+                .ignore_public_acls(block.ignore_public_acls)
+                .block_public_policy(block.block_public_policy)
+                .restrict_public_buckets(block.restrict_public_buckets)
+                .build(),
+        )
+        .send()
+        .await
+        .expect("Error while saving the Block Public Access settings\n");
+    println!(
+        "The Block Public Access settings of '{}' are updated\n",
+        bucket.green().bold()
+    );
+    true
+}
+
+fn prompt_template() -> Option<PolicyTemplate> {
+    let kind = Select::new(
+        "Select the template\n",
+        vec![
+            "Public read of a prefix",
+            "CloudFront Origin Access Control",
+            "Cross-account read",
+        ],
+    )
+    .prompt_skippable()
+    .unwrap()?;
+    let prompt = |message: &str, placeholder: &str| {
+        Text::new(message)
+            .with_placeholder(placeholder)
+            .with_formatter(&|str| format!(".....{str}.....\n"))
+            .prompt()
+            .unwrap()
+            .trim()
+            .to_string()
+    };
+    let template = match kind {
+        "Public read of a prefix" => PolicyTemplate::PublicRead {
+            prefix: prompt(
+                "Enter the prefix to make public, or leave it empty for the whole bucket\n",
+                "assets/",
+            ),
+        },
+        "CloudFront Origin Access Control" => PolicyTemplate::CloudFrontOac {
+            distribution_arn: prompt(
+                "Enter the ARN of the CloudFront distribution\n",
+                "arn:aws:cloudfront::111122223333:distribution/EDFDVBD6EXAMPLE",
+            ),
+        },
+        _ => PolicyTemplate::CrossAccountRead {
+            account_id: prompt(
+                "Enter the 12 digit id of the other account\n",
+                "111122223333",
+            ),
+            prefix: prompt(
+                "Enter the prefix it may read, or leave it empty for the whole bucket\n",
+                "shared/",
+            ),
+        },
+    };
+    Some(template)
+}
+
+/// The interactive menu for the policy and Block Public Access settings of a bucket
+pub async fn manage_policy(config: &SdkConfig, bucket: &str) {
+    loop {
+        let choice = Select::new(
+            "What do you want to do with the bucket policy?\n",
+            vec![
+                "Show the policy",
+                "Edit the policy",
+                "Add a statement from a template",
+                "Delete the policy",
+                "Change Block Public Access",
+                "Done",
+            ],
+        )
+        .prompt()
+        .unwrap();
+        match choice {
+            "Show the policy" => match get_policy(config, bucket).await {
+                Some(policy) => println!("{policy}\n"),
+                None => println!(
+                    "{}\n",
+                    format!("'{bucket}' has no bucket policy").yellow().bold()
+                ),
+            },
+            "Edit the policy" => {
+                let current = get_policy(config, bucket)
+                    .await
+                    .unwrap_or_else(empty_policy);
+                if let Some(edited) = edit_policy(bucket, &current) {
+                    put_policy(config, bucket, &edited, false).await;
+                }
+            }
+            "Add a statement from a template" => {
+                if let Some(template) = prompt_template() {
+                    apply_template(config, bucket, &template, false).await;
+                }
+            }
+            "Delete the policy" => delete_policy(config, bucket, false).await,
+            "Change Block Public Access" => {
+                let current = get_public_access_block(config, bucket).await;
+                current.print(bucket);
+                let names = current.settings().map(|(name, _)| name).to_vec();
+                let defaults = current
+                    .settings()
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, (_, enabled))| *enabled)
+                    .map(|(index, _)| index)
+                    .collect::<Vec<_>>();
+                let Some(chosen) = MultiSelect::new("Select the settings to turn on\n", names)
+                    .with_default(&defaults)
+                    .with_help_message("Space toggles, Enter confirms, Esc cancels")
+                    .prompt_skippable()
+                    .unwrap()
+                else {
+                    continue;
+                };
+                let block = PublicAccessBlock {
+                    block_public_acls: chosen.contains(&"BlockPublicAcls"),
+                    ignore_public_acls: chosen.contains(&"IgnorePublicAcls"),
+                    block_public_policy: chosen.contains(&"BlockPublicPolicy"),
+                    restrict_public_buckets: chosen.contains(&"RestrictPublicBuckets"),
+                };
+                put_public_access_block(config, bucket, block, false).await;
+            }
+            _ => return,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(statements: Value) -> String {
+        json!({ "Version": POLICY_VERSION, "Statement": statements }).to_string()
+    }
+
+    #[test]
+    fn valid_policy_without_warnings() {
+        let policy = policy(json!([{
+            "Sid": "ReadAssets",
+            "Effect": "Allow",
+            "Principal": { "AWS": "arn:aws:iam::123456789012:root" },
+            "Action": ["s3:GetObject", "s3:ListBucket"],
+            "Resource": ["arn:aws:s3:::site", "arn:aws:s3:::site/assets/*"],
+        }]));
+        assert_eq!(validate_policy(&policy, "site"), Ok(vec![]));
+    }
+
+    #[test]
+    fn duplicate_sid() {
+        let statement = json!({
+            "Sid": "Read",
+            "Effect": "Allow",
+            "Principal": "*",
+            "Action": "s3:GetObject",
+            "Resource": "arn:aws:s3:::site/*",
+        });
+        let error = validate_policy(&policy(json!([statement, statement])), "site").unwrap_err();
+        assert!(error.contains("'Read' is used more than once"), "{error}");
+    }
+
+    #[test]
+    fn non_s3_action() {
+        let policy = policy(json!([{
+            "Effect": "Allow",
+            "Principal": "*",
+            "Action": "ec2:StartInstances",
+            "Resource": "arn:aws:s3:::site/*",
+        }]));
+        let error = validate_policy(&policy, "site").unwrap_err();
+        assert!(error.contains("isn't an S3 action"), "{error}");
+    }
+
+    #[test]
+    fn resource_outside_the_bucket() {
+        for resource in ["arn:aws:s3:::other/*", "arn:aws:s3:::site-logs/*"] {
+            let policy = policy(json!([{
+                "Effect": "Allow",
+                "Principal": "*",
+                "Action": "s3:GetObject",
+                "Resource": resource,
+            }]));
+            let error = validate_policy(&policy, "site").unwrap_err();
+            assert!(error.contains("isn't in arn:aws:s3:::site"), "{error}");
+        }
+    }
+
+    #[test]
+    fn public_principal_warns() {
+        let public = policy(json!([PolicyTemplate::PublicRead {
+            prefix: String::new()
+        }
+        .statement("site")]));
+        let warnings = validate_policy(&public, "site").unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("allows anyone"), "{}", warnings[0]);
+
+        let aws_star = policy(json!([{
+            "Effect": "Allow",
+            "Principal": { "AWS": ["*"] },
+            "Action": "s3:GetObject",
+            "Resource": "arn:aws:s3:::site/*",
+        }]));
+        assert_eq!(validate_policy(&aws_star, "site").unwrap().len(), 1);
+
+        let conditional = policy(
+            PolicyTemplate::CloudFrontOac {
+                distribution_arn: "arn:aws:cloudfront::123456789012:distribution/E1".into(),
+            }
+            .statement("site"),
+        );
+        assert_eq!(validate_policy(&conditional, "site"), Ok(vec![]));
+    }
+
+    #[test]
+    fn public_read_sids_per_prefix() {
+        let sid = |prefix: &str| {
+            PolicyTemplate::PublicRead {
+                prefix: prefix.into(),
+            }
+            .statement("site")["Sid"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(sid(""), "PublicReadGetObject");
+        assert_eq!(sid("assets/"), "PublicReadAssets");
+        assert_eq!(sid("assets/img-2x/"), "PublicReadAssetsImg2x");
+
+        let both = add_statement(
+            Some(&add_statement(
+                None,
+                PolicyTemplate::PublicRead {
+                    prefix: "assets/".into(),
+                }
+                .statement("site"),
+            )),
+            PolicyTemplate::PublicRead {
+                prefix: "docs/".into(),
+            }
+            .statement("site"),
+        );
+        let statements = serde_json::from_str::<Value>(&both).unwrap()["Statement"].clone();
+        assert_eq!(as_list(&statements).len(), 2);
+        assert!(has_statement(&both, &json!({ "Sid": "PublicReadDocs" })));
+        assert!(!has_statement(&both, &json!({ "Sid": "PublicRead" })));
+    }
+}
@@ -0,0 +1,489 @@
+use aws_config::SdkConfig;
+use aws_credential_types::provider::ProvideCredentials;
+use aws_sdk_s3::presigning::PresigningConfig;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::{Duration as ChronoDuration, Utc};
+use colored::Colorize;
+use hmac::{Hmac, Mac};
+use image::Luma;
+use inquire::{MultiSelect, Text};
+use qrcode::render::unicode::Dense1x2;
+use qrcode::QrCode;
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::fs;
+use std::time::Duration;
+
+use super::listing::parse_size;
+use super::{bucket_region, regional_client};
+
+/// The longest a SigV4 signature stays valid, and so the longest expiry of any presigned request
+pub const MAX_EXPIRY: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Parses an expiry such as '15m', '2d', '1h30m' or '3600s' ('w' for weeks works too).
+/// A bare number is read as hours, the unit the menu always used
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let input = input.trim().to_lowercase();
+    if input.is_empty() {
+        return Err("The expiry can't be empty".into());
+    }
+    let seconds = match input.parse::<u64>() {
+        Ok(hours) => hours.saturating_mul(60 * 60),
+        Err(_) => {
+            let mut seconds = 0;
+            let mut number = String::new();
+            for c in input.chars() {
+                match c {
+                    '0'..='9' => number.push(c),
+                    's' | 'm' | 'h' | 'd' | 'w' if !number.is_empty() => {
+                        let unit = match c {
+                            's' => 1,
+                            'm' => 60,
+                            'h' => 60 * 60,
+                            'd' => 24 * 60 * 60,
+                            _ => 7 * 24 * 60 * 60,
+                        };
+                        let value = number.parse::<u64>().unwrap_or(u64::MAX);
+                        seconds = value.saturating_mul(unit).saturating_add(seconds);
+                        number.clear();
+                    }
+                    _ => {
+                        return Err(format!(
+                            "'{input}' should look like '15m', '12h', '2d' or '1h30m'"
+                        ))
+                    }
+                }
+            }
+            if !number.is_empty() {
+                return Err(format!("'{input}' ends without a unit (s, m, h, d or w)"));
+            }
+            seconds
+        }
+    };
+    let duration = Duration::from_secs(seconds);
+    match duration {
+        duration if duration.is_zero() => Err("The expiry should be at least one second".into()),
+        duration if duration > MAX_EXPIRY => Err(format!(
+            "'{input}' is longer than the 7 days a presigned request can stay valid"
+        )),
+        duration => Ok(duration),
+    }
+}
+
+/// How a duration is shown back, e.g. '2d 3h' or '15m'
+pub fn format_duration(duration: Duration) -> String {
+    let mut seconds = duration.as_secs();
+    let mut parts = Vec::new();
+    for (unit, size) in [("d", 86_400), ("h", 3_600), ("m", 60), ("s", 1)] {
+        if seconds >= size {
+            parts.push(format!("{}{unit}", seconds / size));
+            seconds %= size;
+        }
+    }
+    parts.join(" ")
+}
+
+/// A URL anyone can download the object with until it expires. Like the other presigned
+/// requests it's signed for the bucket's own region, since S3 rejects other regions
+pub async fn presign_get(
+    config: &SdkConfig,
+    bucket: &str,
+    key: &str,
+    expires_in: Duration,
+) -> String {
+    let client = regional_client(config, bucket).await;
+    let request = client
+        .get_object()
+        .bucket(bucket)
+        .key(key)
+        .presigned(PresigningConfig::expires_in(expires_in).expect("Invalid expiry\n"))
+        .await
+        .expect("Error while presigning the download URL\n");
+    request.uri().to_string()
+}
+
+/// A URL that uploads one object with an HTTP PUT, e.g. `curl -T file URL`. When a content
+/// type is signed into the URL, the upload has to send the same 'Content-Type' header
+pub async fn presign_put(
+    config: &SdkConfig,
+    bucket: &str,
+    key: &str,
+    expires_in: Duration,
+    content_type: Option<&str>,
+) -> String {
+    let client = regional_client(config, bucket).await;
+    let request = client
+        .put_object()
+        .bucket(bucket)
+        .key(key)
+        .set_content_type(content_type.map(str::to_string))
+        .presigned(PresigningConfig::expires_in(expires_in).expect("Invalid expiry\n"))
+        .await
+        .expect("Error while presigning the upload URL\n");
+    request.uri().to_string()
+}
+
+/// What a presigned POST form accepts. A content type ending with '*' only has to start with
+/// the part before it, e.g. 'image/*'; the client then has to fill in the file's actual type
+#[derive(Default)]
+pub struct PostConditions {
+    pub content_type: Option<String>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+}
+
+/// The URL a browser form posts to, with the fields that have to come before the file
+pub struct PresignedPost {
+    pub url: String,
+    pub fields: Vec<(String, String)>,
+    /// Fields whose value is only the start of what the client has to send, like the
+    /// Content-Type of a wildcard type, which S3 rejects when it's left as 'image/'
+    pub client_fields: Vec<String>,
+}
+
+impl PresignedPost {
+    pub fn to_json(&self) -> String {
+        let fields: serde_json::Map<String, Value> = self
+            .fields
+            .iter()
+            .map(|(name, value)| (name.clone(), Value::String(value.clone())))
+            .collect();
+        serde_json::to_string_pretty(&json!({ "url": self.url, "fields": fields })).unwrap()
+    }
+
+    /// A minimal page with the upload form, handy for testing the conditions in a browser
+    pub fn to_html(&self) -> String {
+        let escape = |value: &str| {
+            value
+                .replace('&', "&amp;")
+                .replace('"', "&quot;")
+                .replace('<', "&lt;")
+        };
+        let inputs: String = self
+            .fields
+            .iter()
+            .map(|(name, value)| match self.client_fields.contains(name) {
+                true => format!(
+                    "  <label>{} <input type=\"text\" name=\"{}\" value=\"{}\" required></label>\n",
+                    escape(name),
+                    escape(name),
+                    escape(value)
+                ),
+                false => format!(
+                    "  <input type=\"hidden\" name=\"{}\" value=\"{}\">\n",
+                    escape(name),
+                    escape(value)
+                ),
+            })
+            .collect();
+        format!(
+            "<!DOCTYPE html>\n<html>\n<body>\n<form action=\"{}\" method=\"post\" enctype=\"multipart/form-data\">\n{inputs}  <input type=\"file\" name=\"file\">\n  <input type=\"submit\" value=\"Upload\">\n</form>\n</body>\n</html>\n",
+            escape(&self.url)
+        )
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Signs a POST policy for browser uploads. The SDK only presigns single requests, so the
+/// policy is signed here with SigV4 the way S3 documents it. A key ending with '/' lets the
+/// browser pick the file name under that prefix
+pub async fn presign_post(
+    config: &SdkConfig,
+    bucket: &str,
+    key: &str,
+    expires_in: Duration,
+    conditions: &PostConditions,
+) -> PresignedPost {
+    let credentials = config
+        .credentials_provider()
+        .expect("No credentials are configured\n")
+        .provide_credentials()
+        .await
+        .expect("Error while loading the credentials\n");
+    let region = bucket_region(config, bucket).await;
+    let now = Utc::now();
+    let date = now.format("%Y%m%d").to_string();
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let expiration = (now + ChronoDuration::from_std(expires_in).unwrap())
+        .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+        .to_string();
+    let credential = format!(
+        "{}/{date}/{region}/s3/aws4_request",
+        credentials.access_key_id()
+    );
+
+    let mut fields = Vec::new();
+    let mut client_fields = Vec::new();
+    let mut policy_conditions = vec![json!({ "bucket": bucket })];
+    match key.ends_with('/') || key.is_empty() {
+        true => {
+            policy_conditions.push(json!(["starts-with", "$key", key]));
+            fields.push(("key".to_string(), format!("{key}${{filename}}")));
+        }
+        false => {
+            policy_conditions.push(json!({ "key": key }));
+            fields.push(("key".to_string(), key.to_string()));
+        }
+    }
+    if let Some(content_type) = conditions.content_type.as_deref() {
+        match content_type.strip_suffix('*') {
+            Some(prefix) => {
+                policy_conditions.push(json!(["starts-with", "$Content-Type", prefix]));
+                fields.push(("Content-Type".to_string(), prefix.to_string()));
+                client_fields.push("Content-Type".to_string());
+            }
+            None => {
+                policy_conditions.push(json!({ "Content-Type": content_type }));
+                fields.push(("Content-Type".to_string(), content_type.to_string()));
+            }
+        }
+    }
+    if conditions.min_size.is_some() || conditions.max_size.is_some() {
+        policy_conditions.push(json!([
+            "content-length-range",
+            conditions.min_size.unwrap_or(0),
+            conditions.max_size.unwrap_or(5 * 1024 * 1024 * 1024)
+        ]));
+    }
+    let mut signed_fields = vec![
+        ("x-amz-algorithm", "AWS4-HMAC-SHA256".to_string()),
+        ("x-amz-credential", credential),
+        ("x-amz-date", amz_date),
+    ];
+    if let Some(token) = credentials.session_token() {
+        signed_fields.push(("x-amz-security-token", token.to_string()));
+    }
+    for (name, value) in &signed_fields {
+        let mut condition = serde_json::Map::new();
+        condition.insert(name.to_string(), Value::String(value.clone()));
+        policy_conditions.push(Value::Object(condition));
+        fields.push((name.to_string(), value.clone()));
+    }
+
+    let policy = json!({ "expiration": expiration, "conditions": policy_conditions });
+    let policy = BASE64.encode(policy.to_string());
+    let date_key = hmac_sha256(
+        format!("AWS4{}", credentials.secret_access_key()).as_bytes(),
+        date.as_bytes(),
+    );
+    let region_key = hmac_sha256(&date_key, region.as_bytes());
+    let service_key = hmac_sha256(&region_key, b"s3");
+    let signing_key = hmac_sha256(&service_key, b"aws4_request");
+    let signature: String = hmac_sha256(&signing_key, policy.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    fields.push(("policy".to_string(), policy));
+    fields.push(("x-amz-signature".to_string(), signature));
+    PresignedPost {
+        url: format!("https://{bucket}.s3.{region}.amazonaws.com/"),
+        fields,
+        client_fields,
+    }
+}
+
+/// Prints a QR code of the text in the terminal, or saves it as a PNG when a path is given
+pub fn write_qr_code(text: &str, png: Option<&str>) {
+    let code = QrCode::new(text.as_bytes())
+        .expect("The text is too long to fit in a QR code; use a file instead\n");
+    match png {
+        Some(path) => {
+            code.render::<Luma<u8>>()
+                .min_dimensions(400, 400)
+                .build()
+                .save(path)
+                .expect("Error while saving the QR code\n");
+            println!("The QR code is saved to {}\n", path.green().bold());
+        }
+        None => {
+            let rendered = code
+                .render::<Dense1x2>()
+                .dark_color(Dense1x2::Light)
+                .light_color(Dense1x2::Dark)
+                .quiet_zone(true)
+                .build();
+            println!("{rendered}\n");
+        }
+    }
+}
+
+/// Writes the URL, or the POST form as JSON ('.html' files get an upload page instead)
+pub fn write_output(path: &str, content: &str) {
+    fs::write(path, content).expect("Error while writing the output file\n");
+    println!("Written to {}\n", path.green().bold());
+}
+
+/// Prints a presigned URL with its expiry and hands it to the chosen outputs
+pub fn deliver_url(url: &str, expires_in: Duration, output: Option<&str>, qr: Option<&str>) {
+    println!(
+        "The URL is valid for {}:\n{}\n",
+        format_duration(expires_in).green().bold(),
+        url.yellow()
+    );
+    if let Some(path) = output {
+        write_output(path, &format!("{url}\n"));
+    }
+    if let Some(qr) = qr {
+        write_qr_code(url, Some(qr).filter(|qr| *qr != "-"));
+    }
+}
+
+/// Prints the POST form and writes it to the output file as JSON or HTML
+pub fn deliver_post(post: &PresignedPost, expires_in: Duration, output: Option<&str>) {
+    println!(
+        "POST the file as the last field of a multipart form to {} within {}, with these fields:\n",
+        post.url.yellow(),
+        format_duration(expires_in).green().bold()
+    );
+    for (name, value) in &post.fields {
+        match post.client_fields.contains(name) {
+            true => println!(
+                "  {}: {value}{}",
+                name.green().bold(),
+                " (complete it with the file's type)".yellow()
+            ),
+            false => println!("  {}: {value}", name.green().bold()),
+        }
+    }
+    println!();
+    if let Some(path) = output {
+        match path.ends_with(".html") || path.ends_with(".htm") {
+            true => write_output(path, &post.to_html()),
+            false => write_output(path, &post.to_json()),
+        }
+    }
+}
+
+/// Asks how long a presigned request stays valid
+pub fn prompt_expiry() -> Duration {
+    let input = Text::new("Enter how long the URL stays valid\n")
+        .with_default("1h")
+        .with_help_message("e.g. 15m, 12h, 2d or 1h30m; at most 7d")
+        .with_formatter(&|str| format!(".....{str}.....\n"))
+        .with_validator(|input: &str| match parse_duration(input) {
+            Ok(_) => Ok(inquire::validator::Validation::Valid),
+            Err(message) => Ok(inquire::validator::Validation::Invalid(message.into())),
+        })
+        .prompt()
+        .unwrap();
+    parse_duration(&input).unwrap()
+}
+
+/// Asks where a presigned URL should go besides the terminal: a file, a QR code, or both.
+/// Returns the file and the QR target ('-' for the terminal, otherwise a PNG path)
+pub fn prompt_url_output() -> (Option<String>, Option<String>) {
+    let choices = MultiSelect::new(
+        "Where else should the URL go? Select none to only print it\n",
+        vec![
+            "Write it to a file",
+            "Show a QR code",
+            "Save a QR code as PNG",
+        ],
+    )
+    .with_help_message("Space selects, Enter confirms")
+    .prompt()
+    .unwrap();
+    let ask_path = |message: &str| {
+        Text::new(message)
+            .with_formatter(&|str| format!(".....{str}.....\n"))
+            .prompt()
+            .unwrap()
+            .trim()
+            .to_string()
+    };
+    let output = choices
+        .contains(&"Write it to a file")
+        .then(|| ask_path("Enter the path of the file\n"));
+    let qr = match (
+        choices.contains(&"Save a QR code as PNG"),
+        choices.contains(&"Show a QR code"),
+    ) {
+        (true, _) => Some(ask_path("Enter the path of the PNG file\n")),
+        (false, true) => Some("-".to_string()),
+        (false, false) => None,
+    };
+    (output, qr)
+}
+
+/// Asks for the conditions of a POST form; empty answers leave them out
+pub fn prompt_post_conditions() -> PostConditions {
+    let content_type = Text::new("Enter the accepted Content-Type, or press Enter to accept any\n")
+        .with_placeholder("e.g. image/png, or image/* for every image type")
+        .with_formatter(&|str| format!(".....{str}.....\n"))
+        .prompt_skippable()
+        .unwrap()
+        .map(|content_type| content_type.trim().to_string())
+        .filter(|content_type| !content_type.is_empty());
+    let max_size = Text::new("Enter the largest accepted file, or press Enter for no limit\n")
+        .with_placeholder("e.g. 10MB")
+        .with_formatter(&|str| format!(".....{str}.....\n"))
+        .with_validator(|input: &str| match input.trim().is_empty() {
+            true => Ok(inquire::validator::Validation::Valid),
+            false => match parse_size(input) {
+                Ok(_) => Ok(inquire::validator::Validation::Valid),
+                Err(message) => Ok(inquire::validator::Validation::Invalid(message.into())),
+            },
+        })
+        .prompt_skippable()
+        .unwrap()
+        .filter(|size| !size.trim().is_empty())
+        .map(|size| parse_size(&size).unwrap());
+    PostConditions {
+        content_type,
+        min_size: None,
+        max_size,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_with_units() {
+        assert_eq!(parse_duration("15m"), Ok(Duration::from_secs(15 * 60)));
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(90 * 60)));
+        assert_eq!(parse_duration(" 2D "), Ok(Duration::from_secs(2 * 86_400)));
+        assert_eq!(parse_duration("1w"), Ok(MAX_EXPIRY));
+    }
+
+    #[test]
+    fn bare_numbers_are_hours() {
+        assert_eq!(parse_duration("3"), Ok(Duration::from_secs(3 * 3_600)));
+    }
+
+    #[test]
+    fn invalid_durations() {
+        for input in [
+            "",
+            "0",
+            "0s",
+            "169",
+            "8d",
+            "h",
+            "15x",
+            "1h30",
+            "99999999999999999999w",
+        ] {
+            assert!(
+                parse_duration(input).is_err(),
+                "'{input}' should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn durations_are_shown_back() {
+        assert_eq!(
+            format_duration(Duration::from_secs(2 * 86_400 + 3 * 3_600)),
+            "2d 3h"
+        );
+        assert_eq!(format_duration(Duration::from_secs(15 * 60)), "15m");
+    }
+}
@@ -0,0 +1,594 @@
+use aws_config::SdkConfig;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{ChecksumMode, ServerSideEncryption};
+use aws_sdk_s3::Client;
+use colored::Colorize;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+use tokio::task::JoinSet;
+
+use super::checksum::{file_checksum, file_md5, first_part_size, matches_checksum, Checksum};
+use super::manifest::{build_manifest, write_manifest};
+use super::multipart::{upload_file, UploadSettings};
+use super::objects::{delete_keys, list_objects, RemoteObject};
+use super::transfer_bar;
+
+pub const DEFAULT_JOBS: usize = 8;
+
+/// Which side is the source; the other side is made to match it
+#[derive(Clone, Copy)]
+pub enum SyncDirection {
+    Upload,
+    Download,
+}
+
+pub struct SyncOptions {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    /// Remove files from the destination that don't exist in the source
+    pub delete: bool,
+    pub dry_run: bool,
+    pub jobs: usize,
+    /// Afterwards, write the size and checksum of every synced object to this file
+    pub manifest: Option<PathBuf>,
+}
+
+struct PathFilter {
+    include: GlobSet,
+    exclude: GlobSet,
+    has_include: bool,
+}
+
+impl PathFilter {
+    fn new(options: &SyncOptions) -> Self {
+        let build = |patterns: &[String]| {
+            let mut builder = GlobSetBuilder::new();
+            for pattern in patterns {
+                builder.add(
+                    Glob::new(pattern)
+                        .unwrap_or_else(|error| panic!("Invalid glob '{pattern}': {error}\n")),
+                );
+            }
+            builder.build().unwrap()
+        };
+        Self {
+            include: build(&options.include),
+            exclude: build(&options.exclude),
+            has_include: !options.include.is_empty(),
+        }
+    }
+
+    // Patterns are matched against the path relative to the synced folder or prefix, e.g. 'img/*.png'
+    fn allows(&self, relative: &str) -> bool {
+        (!self.has_include || self.include.is_match(relative)) && !self.exclude.is_match(relative)
+    }
+}
+
+struct LocalFile {
+    path: PathBuf,
+    size: u64,
+    modified: i64,
+}
+
+// Every file under the root. A folder or file that can't be read is an error rather than
+// missing, since a missing file would be deleted from the bucket
+fn local_files(root: &Path) -> Result<BTreeMap<String, LocalFile>, Vec<String>> {
+    let mut files = BTreeMap::new();
+    let mut errors = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(error) => {
+                errors.push(format!("{}: {error}", dir.display()));
+                continue;
+            }
+        };
+        for entry in entries {
+            let path = match entry {
+                Ok(entry) => entry.path(),
+                Err(error) => {
+                    errors.push(format!("{}: {error}", dir.display()));
+                    continue;
+                }
+            };
+            let metadata = match fs::metadata(&path) {
+                Ok(metadata) => metadata,
+                Err(error) => {
+                    errors.push(format!("{}: {error}", path.display()));
+                    continue;
+                }
+            };
+            if metadata.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            let relative = path
+                .strip_prefix(root)
+                .unwrap()
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            let modified = metadata
+                .modified()
+                .map(|modified| modified.duration_since(UNIX_EPOCH).unwrap().as_secs() as i64)
+                .unwrap_or_default();
+            files.insert(
+                relative,
+                LocalFile {
+                    path,
+                    size: metadata.len(),
+                    modified,
+                },
+            );
+        }
+    }
+    match errors.is_empty() {
+        true => Ok(files),
+        false => Err(errors),
+    }
+}
+
+// The keys whose ETag looks like an MD5 but isn't one, because the object is encrypted with
+// SSE-KMS or SSE-C, which only a HEAD tells. Objects that can't be read are included too
+async fn opaque_e_tags(
+    client: &Client,
+    bucket: &str,
+    keys: Vec<String>,
+    jobs: usize,
+) -> HashSet<String> {
+    let mut pending = keys.into_iter();
+    let mut tasks = JoinSet::new();
+    let mut opaque = HashSet::new();
+    loop {
+        while tasks.len() < jobs.max(1) {
+            let Some(key) = pending.next() else {
+                break;
+            };
+            let request = client.head_object().bucket(bucket).key(&key);
+            tasks.spawn(async move { (key, request.send().await) });
+        }
+        let Some(result) = tasks.join_next().await else {
+            break;
+        };
+        let (key, result) = result.expect("The head task panicked\n");
+        let comparable = result.is_ok_and(|head| {
+            head.sse_customer_algorithm().is_none()
+                && !matches!(
+                    head.server_side_encryption(),
+                    Some(ServerSideEncryption::AwsKms) | Some(ServerSideEncryption::AwsKmsDsse)
+                )
+        });
+        if !comparable {
+            opaque.insert(key);
+        }
+    }
+    opaque
+}
+
+// Sizes are compared first, then the MD5 when the ETag is one, and otherwise the modification times
+fn differs(
+    local: &LocalFile,
+    remote: &RemoteObject,
+    direction: &SyncDirection,
+    opaque: &HashSet<String>,
+) -> bool {
+    if local.size != remote.size {
+        return true;
+    }
+    if let Some(remote_md5) = remote.md5().filter(|_| !opaque.contains(&remote.key)) {
+        return file_md5(&local.path).as_deref() != Some(remote_md5);
+    }
+    match direction {
+        SyncDirection::Upload => local.modified > remote.last_modified,
+        SyncDirection::Download => remote.last_modified > local.modified,
+    }
+}
+
+enum Action {
+    Upload {
+        path: PathBuf,
+        key: String,
+        size: u64,
+    },
+    Download {
+        key: String,
+        path: PathBuf,
+        size: u64,
+        modified: i64,
+    },
+}
+
+impl Action {
+    fn size(&self) -> u64 {
+        match self {
+            Action::Upload { size, .. } | Action::Download { size, .. } => *size,
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Action::Upload { path, key, .. } => format!("upload {} -> {key}", path.display()),
+            Action::Download { key, path, .. } => format!("download {key} -> {}", path.display()),
+        }
+    }
+}
+
+// Keys such as '../x', '/etc/x' or 'a//b' would be written outside the local folder, or
+// collide with another key once joined to it, so only plain relative paths are synced
+fn is_safe_relative(relative: &str) -> bool {
+    !relative.is_empty()
+        && relative
+            .split('/')
+            .all(|part| !part.is_empty() && part != "." && part != "..")
+        && Path::new(relative)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+}
+
+/// Makes the destination match the source: new and changed files are transferred in parallel
+/// and, with 'delete', files missing from the source are removed from the destination.
+/// Nothing is listed or changed when the local folder can't be read completely. Uploads use the
+/// encryption, metadata and checksum of the .env upload settings, and downloads send its
+/// SSE-C key. Returns whether the sync completed
+pub async fn sync(
+    config: &SdkConfig,
+    direction: SyncDirection,
+    local_root: &Path,
+    bucket: &str,
+    prefix: &str,
+    options: &SyncOptions,
+) -> bool {
+    // A download may create the folder, but an upload from a missing one would look empty
+    let readable = match direction {
+        SyncDirection::Upload => local_root.is_dir(),
+        SyncDirection::Download => local_root.is_dir() || !local_root.exists(),
+    };
+    if !readable {
+        println!(
+            "{}\n",
+            format!("'{}' isn't a folder that can be read", local_root.display())
+                .red()
+                .bold()
+        );
+        return false;
+    }
+    let local_files = match local_root.exists() {
+        true => local_files(local_root),
+        false => Ok(BTreeMap::new()),
+    };
+    let local_files = match local_files {
+        Ok(files) => files,
+        Err(errors) => {
+            for error in &errors {
+                println!("{} {error}", "Error while reading".red().bold());
+            }
+            println!(
+                "{}\n",
+                "Nothing was synced, since unreadable files would count as deleted"
+                    .red()
+                    .bold()
+            );
+            return false;
+        }
+    };
+
+    let client = Client::new(config);
+    let prefix = match prefix.is_empty() || prefix.ends_with('/') {
+        true => prefix.to_string(),
+        false => format!("{prefix}/"),
+    };
+    let filter = PathFilter::new(options);
+    let local = local_files
+        .into_iter()
+        .filter(|(relative, _)| filter.allows(relative))
+        .collect::<BTreeMap<_, _>>();
+    let remote = list_objects(&client, bucket, &prefix)
+        .await
+        .into_iter()
+        .filter(|object| !object.key.ends_with('/'))
+        .map(|object| (object.key[prefix.len()..].to_string(), object))
+        .filter(|(relative, object)| {
+            let safe = is_safe_relative(relative);
+            if !safe {
+                println!(
+                    "{} s3://{bucket}/{} doesn't map to a file inside the local folder",
+                    "Skipped:".yellow().bold(),
+                    object.key
+                );
+            }
+            safe
+        })
+        .filter(|(relative, _)| filter.allows(relative))
+        .collect::<BTreeMap<_, _>>();
+
+    // Only objects of the same size as their file are compared by ETag, so only those are checked
+    let same_size = remote
+        .iter()
+        .filter(|(relative, object)| {
+            object.md5().is_some()
+                && local
+                    .get(*relative)
+                    .is_some_and(|file| file.size == object.size)
+        })
+        .map(|(_, object)| object.key.clone())
+        .collect();
+    let opaque = opaque_e_tags(&client, bucket, same_size, options.jobs).await;
+
+    let mut actions = Vec::new();
+    let mut deletions = Vec::new();
+    match direction {
+        SyncDirection::Upload => {
+            for (relative, file) in &local {
+                let changed = remote
+                    .get(relative)
+                    .map_or(true, |object| differs(file, object, &direction, &opaque));
+                if changed {
+                    actions.push(Action::Upload {
+                        path: file.path.clone(),
+                        key: format!("{prefix}{relative}"),
+                        size: file.size,
+                    });
+                }
+            }
+            deletions.extend(
+                remote
+                    .keys()
+                    .filter(|relative| !local.contains_key(*relative))
+                    .map(|relative| format!("{prefix}{relative}")),
+            );
+        }
+        SyncDirection::Download => {
+            for (relative, object) in &remote {
+                let changed = local
+                    .get(relative)
+                    .map_or(true, |file| differs(file, object, &direction, &opaque));
+                if changed {
+                    actions.push(Action::Download {
+                        key: object.key.clone(),
+                        path: local_root.join(relative),
+                        size: object.size,
+                        modified: object.last_modified,
+                    });
+                }
+            }
+            deletions.extend(
+                local
+                    .iter()
+                    .filter(|(relative, _)| !remote.contains_key(*relative))
+                    .map(|(_, file)| file.path.display().to_string()),
+            );
+        }
+    }
+    if !options.delete {
+        deletions.clear();
+    }
+
+    if options.dry_run {
+        for action in &actions {
+            println!("{} {}", "(dry run)".yellow().bold(), action.describe());
+        }
+        for deletion in &deletions {
+            println!("{} delete {deletion}", "(dry run)".yellow().bold());
+        }
+    }
+    let total_bytes: u64 = actions.iter().map(Action::size).sum();
+    println!(
+        "{} file(s) to transfer ({:.2} MB), {} to delete, {} unchanged\n",
+        actions.len().to_string().green().bold(),
+        total_bytes as f64 / (1024.0 * 1024.0),
+        deletions.len().to_string().green().bold(),
+        match direction {
+            SyncDirection::Upload => local.len(),
+            SyncDirection::Download => remote.len(),
+        } - actions.len()
+    );
+    if options.dry_run {
+        return true;
+    }
+    let synced_keys = match direction {
+        SyncDirection::Upload => local
+            .keys()
+            .map(|relative| format!("{prefix}{relative}"))
+            .collect::<Vec<_>>(),
+        SyncDirection::Download => remote.values().map(|object| object.key.clone()).collect(),
+    };
+
+    // Files bigger than one part go through the resumable multipart upload after the rest
+    let settings = UploadSettings::from_env();
+    let (large, parallel): (Vec<_>, Vec<_>) = actions.into_iter().partition(
+        |action| matches!(action, Action::Upload { size, .. } if *size > settings.part_size),
+    );
+    let mut completed = parallel.is_empty()
+        || transfer_parallel(&client, bucket, parallel, options.jobs, &settings).await;
+    for action in large {
+        if let Action::Upload { path, key, .. } = action {
+            completed &= upload_file(config, bucket, &path, &key, &settings).await;
+        }
+    }
+
+    if !deletions.is_empty() {
+        let deleted = match direction {
+            SyncDirection::Upload => delete_keys(&client, bucket, &deletions).await,
+            SyncDirection::Download => deletions
+                .iter()
+                .filter(|path| fs::remove_file(path).is_ok())
+                .count(),
+        };
+        println!("{} file(s) deleted\n", deleted.to_string().green().bold());
+    }
+
+    if let Some(path) = &options.manifest {
+        if !completed {
+            println!(
+                "{}\n",
+                "The manifest isn't written because some transfers failed"
+                    .yellow()
+                    .bold()
+            );
+            return false;
+        }
+        let manifest = build_manifest(config, bucket, synced_keys, options.jobs).await;
+        if let Err(message) = write_manifest(path, &manifest) {
+            println!("{}\n", message.red().bold());
+        }
+    }
+    completed
+}
+
+// Returns whether every transfer succeeded
+async fn transfer_parallel(
+    client: &Client,
+    bucket: &str,
+    actions: Vec<Action>,
+    jobs: usize,
+    settings: &UploadSettings,
+) -> bool {
+    let bar = transfer_bar(actions.iter().map(Action::size).sum(), "Syncing");
+    let mut pending = actions.into_iter();
+    let mut tasks = JoinSet::new();
+    let mut failures = 0;
+    loop {
+        while tasks.len() < jobs.max(1) {
+            let Some(action) = pending.next() else {
+                break;
+            };
+            tasks.spawn(transfer(
+                client.clone(),
+                bucket.to_string(),
+                action,
+                settings.clone(),
+            ));
+        }
+        let Some(result) = tasks.join_next().await else {
+            break;
+        };
+        match result.expect("The transfer task panicked\n") {
+            Ok(size) => bar.inc(size),
+            Err(error) => {
+                failures += 1;
+                bar.println(format!(
+                    "{}: {error}",
+                    "Error while transferring".red().bold()
+                ));
+            }
+        }
+    }
+    bar.finish();
+    match failures {
+        0 => println!("{}\n", "The transfers completed".green().bold()),
+        _ => println!(
+            "{}\n",
+            format!("{failures} transfer(s) failed; run the sync again to retry them")
+                .red()
+                .bold()
+        ),
+    }
+    failures == 0
+}
+
+async fn transfer(
+    client: Client,
+    bucket: String,
+    action: Action,
+    settings: UploadSettings,
+) -> Result<u64, String> {
+    let size = action.size();
+    let description = action.describe();
+    let with_context = |error: String| format!("{description}: {error}");
+    let (encryption, metadata) = (&settings.encryption, &settings.metadata);
+    match action {
+        Action::Upload { path, key, .. } => {
+            let checksum = match settings.checksum {
+                Some(algorithm) => Some(
+                    file_checksum(&path, algorithm)
+                        .ok_or_else(|| with_context("the file can't be read".into()))?,
+                ),
+                None => None,
+            };
+            let body = ByteStream::from_path(&path)
+                .await
+                .map_err(|error| with_context(error.to_string()))?;
+            client
+                .put_object()
+                .bucket(bucket)
+                .key(key)
+                .content_type(metadata.content_type_for(&path))
+                .body(body)
+                .set_cache_control(metadata.cache_control.clone())
+                .set_metadata(metadata.user_metadata())
+                .set_tagging(metadata.tagging())
+                .set_server_side_encryption(encryption.server_side_encryption())
+                .set_ssekms_key_id(encryption.kms_key_id())
+                .set_sse_customer_algorithm(encryption.customer_algorithm())
+                .set_sse_customer_key(encryption.customer_key())
+                .set_sse_customer_key_md5(encryption.customer_key_md5())
+                .set_checksum_sha256(checksum.as_ref().and_then(Checksum::sha256))
+                .set_checksum_crc32_c(checksum.as_ref().and_then(Checksum::crc32c))
+                .send()
+                .await
+                .map_err(|error| with_context(error.to_string()))?;
+        }
+        Action::Download {
+            key,
+            path,
+            modified,
+            ..
+        } => {
+            let output = client
+                .get_object()
+                .bucket(&bucket)
+                .key(&key)
+                .checksum_mode(ChecksumMode::Enabled)
+                .set_sse_customer_algorithm(encryption.customer_algorithm())
+                .set_sse_customer_key(encryption.customer_key())
+                .set_sse_customer_key_md5(encryption.customer_key_md5())
+                .send()
+                .await
+                .map_err(|error| with_context(error.to_string()))?;
+            let checksum =
+                Checksum::from_headers(output.checksum_sha256(), output.checksum_crc32_c());
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).map_err(|error| with_context(error.to_string()))?;
+            }
+            // Written next to the destination first, so an interrupted download never leaves a partial file
+            let partial = path.with_file_name(format!(
+                "{}.part",
+                path.file_name().unwrap().to_string_lossy()
+            ));
+            let mut file = tokio::fs::File::create(&partial)
+                .await
+                .map_err(|error| with_context(error.to_string()))?;
+            tokio::io::copy(&mut output.body.into_async_read(), &mut file)
+                .await
+                .map_err(|error| with_context(error.to_string()))?;
+            drop(file);
+            if let Some(checksum) = checksum {
+                let part_size = match checksum.parts() {
+                    Some(_) => first_part_size(&client, &bucket, &key, None, encryption).await,
+                    None => None,
+                };
+                if matches_checksum(&partial, &checksum, part_size) == Some(false) {
+                    let _ = fs::remove_file(&partial);
+                    return Err(with_context(format!(
+                        "the file doesn't match the object's {} checksum",
+                        checksum.algorithm.name()
+                    )));
+                }
+            }
+            fs::rename(&partial, &path).map_err(|error| with_context(error.to_string()))?;
+            // Matching the remote modification time keeps the next sync from downloading it again
+            let _ = fs::File::options()
+                .write(true)
+                .open(&path)
+                .and_then(|file| {
+                    file.set_modified(UNIX_EPOCH + Duration::from_secs(modified as u64))
+                });
+        }
+    }
+    Ok(size)
+}
@@ -0,0 +1,357 @@
+use aws_config::SdkConfig;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::Client;
+use colored::Colorize;
+use image::{imageops, DynamicImage, GenericImageView, Rgba, RgbaImage};
+use imageproc::drawing::{draw_text_mut, text_size};
+use indicatif::{ProgressBar, ProgressStyle};
+use rusttype::{Font, Scale};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use tokio::task::JoinSet;
+
+use super::images::{bundled_font, decode_oriented, encode, VariantFormat};
+use super::multipart::UploadSettings;
+use super::objects::list_objects;
+use super::transfer_bar;
+
+const IMAGE_EXTENSIONS: [&str; 9] = [
+    "jpg", "jpeg", "png", "webp", "gif", "bmp", "tif", "tiff", "ico",
+];
+const PARALLEL_REQUESTS: usize = 8;
+const PADDING: u32 = 8;
+
+pub struct ThumbnailOptions {
+    /// The longest side of a thumbnail in pixels
+    pub size: u32,
+    pub format: VariantFormat,
+    pub quality: u8,
+    /// Thumbnails per row on the contact sheet; no sheet is made when zero
+    pub columns: u32,
+    /// Thumbnails per sheet before another sheet is started
+    pub per_sheet: usize,
+}
+
+impl Default for ThumbnailOptions {
+    fn default() -> Self {
+        Self {
+            size: 256,
+            format: VariantFormat::Jpeg,
+            quality: 80,
+            columns: 6,
+            per_sheet: 120,
+        }
+    }
+}
+
+fn is_image(key: &str) -> bool {
+    key.rsplit_once('.')
+        .is_some_and(|(_, extension)| IMAGE_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+}
+
+fn count_bar(total: u64, message: &str) -> ProgressBar {
+    let bar = ProgressBar::new(total);
+    bar.set_style(
+        ProgressStyle::with_template("{msg} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len}")
+            .unwrap()
+            .progress_chars("=> "),
+    );
+    bar.set_message(message.to_string());
+    bar
+}
+
+/// Shortens a label from the start until it fits, since the end of a file name says the most
+fn fit_label(label: &str, width: i32, scale: Scale, font: &Font) -> String {
+    if text_size(scale, font, label).0 <= width {
+        return label.to_string();
+    }
+    let chars = label.chars().collect::<Vec<_>>();
+    (1..chars.len())
+        .map(|start| format!("...{}", chars[start..].iter().collect::<String>()))
+        .find(|shortened| text_size(scale, font, shortened).0 <= width)
+        .unwrap_or_default()
+}
+
+/// Lays the thumbnails out in a grid on a white sheet, each with its name underneath
+pub fn contact_sheet(
+    thumbnails: &[(String, DynamicImage)],
+    size: u32,
+    columns: u32,
+    font: &Font,
+) -> RgbaImage {
+    let scale = Scale::uniform((size as f32 / 16.0).max(11.0));
+    let label_height = text_size(scale, font, "Ag").1.max(1) as u32 + PADDING;
+    let (cell_width, cell_height) = (size + 2 * PADDING, size + 2 * PADDING + label_height);
+    let columns = columns.min(thumbnails.len() as u32).max(1);
+    let rows = (thumbnails.len() as u32).div_ceil(columns);
+    let mut sheet = RgbaImage::from_pixel(
+        columns * cell_width,
+        rows * cell_height,
+        Rgba([255, 255, 255, 255]),
+    );
+    for (index, (label, thumbnail)) in thumbnails.iter().enumerate() {
+        let (column, row) = (index as u32 % columns, index as u32 / columns);
+        let (left, top) = (column * cell_width, row * cell_height);
+        let (width, height) = thumbnail.dimensions();
+        // Thumbnails keep their aspect ratio, so they are centred in the square they fit in
+        imageops::overlay(
+            &mut sheet,
+            &thumbnail.to_rgba8(),
+            (left + PADDING + (size - width) / 2) as i64,
+            (top + PADDING + (size - height) / 2) as i64,
+        );
+        let label = fit_label(label, size as i32, scale, font);
+        let label_width = text_size(scale, font, &label).0;
+        draw_text_mut(
+            &mut sheet,
+            Rgba([40, 40, 40, 255]),
+            (left + PADDING) as i32 + (size as i32 - label_width) / 2,
+            (top + 2 * PADDING + size) as i32,
+            scale,
+            font,
+            &label,
+        );
+    }
+    sheet
+}
+
+// The name of an image's thumbnail relative to the destination, e.g. 'cats/tom.jpg' for 'cats/tom.png'
+fn thumbnail_name(relative: &str, format: VariantFormat) -> String {
+    let stem = relative.rsplit_once('.').map_or(relative, |(stem, _)| stem);
+    format!("{stem}.{}", format.extension())
+}
+
+fn sheet_name(index: usize, count: usize) -> String {
+    match count {
+        1 => "contact-sheet.png".to_string(),
+        _ => format!("contact-sheet-{}.png", index + 1),
+    }
+}
+
+/// Downloads every image under 'prefix', uploads a thumbnail of each under 'destination_prefix'
+/// with the same relative name, and adds contact sheets named 'contact-sheet.png' (or
+/// 'contact-sheet-2.png' and so on). When the destination is inside the source, the images
+/// already there are left out. Nothing is made when a thumbnail or sheet would replace one
+/// of the source objects
+pub async fn make_thumbnails(
+    config: &SdkConfig,
+    bucket: &str,
+    prefix: &str,
+    destination: (&str, &str),
+    options: &ThumbnailOptions,
+    output_dir: Option<&Path>,
+    settings: &UploadSettings,
+) -> bool {
+    let client = Client::new(config);
+    let (destination_bucket, destination_prefix) = destination;
+    let objects = list_objects(&client, bucket, prefix).await;
+    let same_bucket = destination_bucket == bucket;
+    // Only a destination below the source holds earlier thumbnails among the listed images
+    let inside_source = same_bucket
+        && destination_prefix.len() > prefix.len()
+        && destination_prefix.starts_with(prefix);
+    let sources = objects
+        .into_iter()
+        .filter(|object| !inside_source || !object.key.starts_with(destination_prefix))
+        .collect::<Vec<_>>();
+    let source_keys = match same_bucket {
+        true => sources
+            .iter()
+            .map(|object| object.key.clone())
+            .collect::<HashSet<_>>(),
+        false => HashSet::new(),
+    };
+    let images = sources
+        .into_iter()
+        .filter(|object| is_image(&object.key))
+        .collect::<Vec<_>>();
+    if images.is_empty() {
+        println!(
+            "{}\n",
+            format!("No images were found under s3://{bucket}/{prefix}")
+                .yellow()
+                .bold()
+        );
+        return false;
+    }
+    let sheet_count = match options.columns {
+        0 => 0,
+        _ => images.len().div_ceil(options.per_sheet.max(1)),
+    };
+    let planned = images
+        .iter()
+        .map(|object| {
+            let relative = object.key.strip_prefix(prefix).unwrap_or(&object.key);
+            thumbnail_name(relative, options.format)
+        })
+        .chain((0..sheet_count).map(|index| sheet_name(index, sheet_count)))
+        .map(|name| format!("{destination_prefix}{name}"))
+        .filter(|key| source_keys.contains(key))
+        .collect::<Vec<_>>();
+    if !planned.is_empty() {
+        println!(
+            "{}",
+            format!(
+                "{} thumbnail(s) or sheet(s) would overwrite source objects, so nothing is made; choose a destination outside s3://{bucket}/{prefix}",
+                planned.len()
+            )
+            .red()
+            .bold()
+        );
+        for key in planned.iter().take(5) {
+            println!("    s3://{destination_bucket}/{key}");
+        }
+        println!();
+        return false;
+    }
+    println!(
+        "Making thumbnails of {} image(s) under s3://{bucket}/{prefix}\n",
+        images.len().to_string().green().bold()
+    );
+
+    let bar = transfer_bar(images.iter().map(|object| object.size).sum(), "Downloading");
+    let mut pending = images.into_iter();
+    let mut tasks = JoinSet::new();
+    let mut thumbnails = Vec::new();
+    let mut failures = Vec::new();
+    loop {
+        while tasks.len() < PARALLEL_REQUESTS {
+            let Some(object) = pending.next() else {
+                break;
+            };
+            let request = client.get_object().bucket(bucket).key(&object.key);
+            let size = options.size;
+            tasks.spawn(async move {
+                let result = async {
+                    let output = request.send().await.map_err(|error| error.to_string())?;
+                    let bytes = output
+                        .body
+                        .collect()
+                        .await
+                        .map_err(|error| error.to_string())?
+                        .into_bytes();
+                    let (image, _) = decode_oriented(&bytes)?;
+                    Ok::<_, String>(image.thumbnail(size, size))
+                }
+                .await;
+                (object, result)
+            });
+        }
+        let Some(joined) = tasks.join_next().await else {
+            break;
+        };
+        let (object, result) = joined.expect("The thumbnail task panicked\n");
+        bar.inc(object.size);
+        match result {
+            Ok(thumbnail) => thumbnails.push((object.key, thumbnail)),
+            Err(error) => failures.push((object.key, error)),
+        }
+    }
+    bar.finish();
+    for (key, error) in &failures {
+        println!("{} {key}: {error}", "Skipped".yellow().bold());
+    }
+    thumbnails.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let mut uploads = Vec::new();
+    for (key, thumbnail) in &thumbnails {
+        let relative = key.strip_prefix(prefix).unwrap_or(key);
+        uploads.push((
+            thumbnail_name(relative, options.format),
+            options.format.content_type(),
+            encode(thumbnail, options.format, options.quality),
+        ));
+    }
+    if options.columns > 0 {
+        let font = bundled_font();
+        let labelled = thumbnails
+            .into_iter()
+            .map(|(key, thumbnail)| {
+                let relative = key.strip_prefix(prefix).unwrap_or(&key).to_string();
+                (relative, thumbnail)
+            })
+            .collect::<Vec<_>>();
+        let sheets = labelled
+            .chunks(options.per_sheet.max(1))
+            .collect::<Vec<_>>();
+        for (index, chunk) in sheets.iter().enumerate() {
+            let sheet = contact_sheet(chunk, options.size, options.columns, &font);
+            uploads.push((
+                sheet_name(index, sheets.len()),
+                "image/png",
+                encode(&DynamicImage::ImageRgba8(sheet), VariantFormat::Png, 100),
+            ));
+        }
+    }
+
+    if let Some(output_dir) = output_dir {
+        for (name, _, bytes) in &uploads {
+            let path = output_dir.join(name);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).expect("Error while creating the output directory\n");
+            }
+            fs::write(&path, bytes).expect("Error while saving a thumbnail\n");
+        }
+        println!(
+            "The thumbnails and sheets are saved in {}",
+            output_dir.display().to_string().green().bold()
+        );
+    }
+
+    let bar = count_bar(uploads.len() as u64, "Uploading");
+    let encryption = &settings.encryption;
+    let mut pending = uploads.into_iter();
+    let mut tasks = JoinSet::new();
+    let mut failed = 0;
+    loop {
+        while tasks.len() < PARALLEL_REQUESTS {
+            let Some((name, content_type, bytes)) = pending.next() else {
+                break;
+            };
+            let key = format!("{destination_prefix}{name}");
+            let request = client
+                .put_object()
+                .bucket(destination_bucket)
+                .key(&key)
+                .content_type(content_type)
+                .set_cache_control(settings.metadata.cache_control.clone())
+                .body(ByteStream::from(bytes))
+                .set_server_side_encryption(encryption.server_side_encryption())
+                .set_ssekms_key_id(encryption.kms_key_id())
+                .set_sse_customer_algorithm(encryption.customer_algorithm())
+                .set_sse_customer_key(encryption.customer_key())
+                .set_sse_customer_key_md5(encryption.customer_key_md5());
+            tasks.spawn(async move { (key, request.send().await) });
+        }
+        let Some(joined) = tasks.join_next().await else {
+            break;
+        };
+        let (key, result) = joined.expect("The upload task panicked\n");
+        bar.inc(1);
+        if let Err(error) = result {
+            failed += 1;
+            bar.println(format!(
+                "{} {key}: {}",
+                "Error while uploading".red().bold(),
+                error.into_service_error()
+            ));
+        }
+    }
+    bar.finish();
+    println!(
+        "\n{} thumbnail(s) and sheet(s) uploaded to {}{}\n",
+        (bar.length().unwrap_or_default() - failed)
+            .to_string()
+            .green()
+            .bold(),
+        format!("s3://{destination_bucket}/{destination_prefix}")
+            .green()
+            .bold(),
+        match failures.len() {
+            0 => String::new(),
+            skipped => format!("; {skipped} image(s) couldn't be read"),
+        }
+    );
+    failed == 0 && failures.is_empty()
+}
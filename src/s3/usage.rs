@@ -0,0 +1,388 @@
+use aws_config::SdkConfig;
+use colored::Colorize;
+use indicatif::ProgressBar;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet};
+use std::env::var;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use super::objects::list_page;
+use super::{format_size, regional_client};
+
+const KB: u64 = 1024;
+const GB: f64 = 1024.0 * 1024.0 * 1024.0;
+/// Monthly storage prices per GB in us-east-1, in USD; a prices file replaces any of them
+const DEFAULT_PRICES: [(&str, f64); 8] = [
+    ("STANDARD", 0.023),
+    ("INTELLIGENT_TIERING", 0.023),
+    ("STANDARD_IA", 0.0125),
+    ("ONEZONE_IA", 0.01),
+    ("GLACIER_IR", 0.004),
+    ("GLACIER", 0.0036),
+    ("DEEP_ARCHIVE", 0.00099),
+    ("REDUCED_REDUNDANCY", 0.024),
+];
+const TOP_PREFIXES: usize = 15;
+
+/// What a GB of each storage class costs per month, read from a YAML or JSON file, e.g.
+///
+/// ```yaml
+/// currency: EUR
+/// prices:
+///   STANDARD: 0.0245
+///   GLACIER: 0.0045
+/// ```
+///
+/// Classes the file leaves out keep the us-east-1 USD prices
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PriceTable {
+    #[serde(default = "default_currency")]
+    pub currency: String,
+    #[serde(default)]
+    pub prices: BTreeMap<String, f64>,
+}
+
+fn default_currency() -> String {
+    "USD".into()
+}
+
+impl Default for PriceTable {
+    fn default() -> Self {
+        Self {
+            currency: default_currency(),
+            prices: DEFAULT_PRICES
+                .iter()
+                .map(|(class, price)| (class.to_string(), *price))
+                .collect(),
+        }
+    }
+}
+
+impl PriceTable {
+    /// The prices from 'path', or from 'S3_PRICES_FILE' in the .env file, over the defaults
+    pub fn load(path: Option<&Path>) -> Result<Self, String> {
+        dotenv::dotenv().ok();
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match var("S3_PRICES_FILE") {
+                Ok(path) => path.into(),
+                Err(_) => return Ok(Self::default()),
+            },
+        };
+        let content =
+            fs::read_to_string(&path).map_err(|error| format!("{}: {error}", path.display()))?;
+        let table = match path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            true => serde_json::from_str::<PriceTable>(&content)
+                .map_err(|error| format!("{}: {error}", path.display()))?,
+            false => serde_yaml::from_str::<PriceTable>(&content)
+                .map_err(|error| format!("{}: {error}", path.display()))?,
+        };
+        if let Some((class, _)) = table.prices.iter().find(|(_, price)| **price < 0.0) {
+            return Err(format!("The price of {class} can't be negative"));
+        }
+        let mut prices = Self::default().prices;
+        prices.extend(table.prices);
+        Ok(Self {
+            currency: table.currency,
+            prices,
+        })
+    }
+
+    /// None when the table has no price for the class
+    pub fn monthly_cost(
+        &self,
+        storage_class: &str,
+        billable_bytes: u64,
+        objects: u64,
+    ) -> Option<f64> {
+        let price = self.prices.get(storage_class)?;
+        let mut cost = billable_bytes as f64 / GB * price;
+        // Archived objects also keep 8KB of metadata each in STANDARD
+        if ["GLACIER", "DEEP_ARCHIVE"].contains(&storage_class) {
+            let standard = self.prices.get("STANDARD").copied().unwrap_or_default();
+            cost += (objects * 8 * KB) as f64 / GB * standard;
+        }
+        Some(cost)
+    }
+}
+
+// The size S3 bills for: the infrequent access classes have a 128KB minimum, and archived
+// objects carry 32KB of index at the archive price
+fn billable_bytes(storage_class: &str, size: u64) -> u64 {
+    match storage_class {
+        "STANDARD_IA" | "ONEZONE_IA" | "GLACIER_IR" => size.max(128 * KB),
+        "GLACIER" | "DEEP_ARCHIVE" => size + 32 * KB,
+        _ => size,
+    }
+}
+
+/// The objects of one storage class under one top-level prefix of a bucket
+#[derive(Serialize, Clone)]
+pub struct UsageRow {
+    pub bucket: String,
+    /// The first folder below the walked prefix, or the walked prefix itself for objects
+    /// directly in it
+    pub prefix: String,
+    pub storage_class: String,
+    pub objects: u64,
+    pub bytes: u64,
+    pub billable_bytes: u64,
+    /// Empty when the price table has no price for the storage class
+    pub monthly_cost: Option<f64>,
+}
+
+#[derive(Serialize, Default, Clone)]
+pub struct UsageTotals {
+    pub objects: u64,
+    pub bytes: u64,
+    pub monthly_cost: f64,
+}
+
+impl UsageTotals {
+    fn add(&mut self, row: &UsageRow) {
+        self.objects += row.objects;
+        self.bytes += row.bytes;
+        self.monthly_cost += row.monthly_cost.unwrap_or_default();
+    }
+}
+
+pub struct UsageReport {
+    pub currency: String,
+    pub rows: Vec<UsageRow>,
+}
+
+impl UsageReport {
+    fn grouped<'a>(
+        rows: impl Iterator<Item = &'a UsageRow>,
+        key: impl Fn(&UsageRow) -> String,
+    ) -> BTreeMap<String, UsageTotals> {
+        let mut groups = BTreeMap::<String, UsageTotals>::new();
+        for row in rows {
+            groups.entry(key(row)).or_default().add(row);
+        }
+        groups
+    }
+
+    pub fn total(&self) -> UsageTotals {
+        let mut total = UsageTotals::default();
+        for row in &self.rows {
+            total.add(row);
+        }
+        total
+    }
+
+    pub fn by_storage_class(&self) -> BTreeMap<String, UsageTotals> {
+        Self::grouped(self.rows.iter(), |row| row.storage_class.clone())
+    }
+
+    pub fn by_prefix(&self) -> BTreeMap<String, UsageTotals> {
+        Self::grouped(self.rows.iter(), |row| {
+            format!("s3://{}/{}", row.bucket, row.prefix)
+        })
+    }
+
+    /// The storage classes that were found but have no price
+    pub fn unpriced_classes(&self) -> BTreeSet<String> {
+        self.rows
+            .iter()
+            .filter(|row| row.monthly_cost.is_none())
+            .map(|row| row.storage_class.clone())
+            .collect()
+    }
+}
+
+/// Walks every object under each (bucket, prefix) and totals the objects and bytes by
+/// top-level prefix and storage class, with the monthly cost from the price table.
+/// Only current versions are counted
+pub async fn usage_report(
+    config: &SdkConfig,
+    locations: &[(String, String)],
+    prices: &PriceTable,
+) -> UsageReport {
+    let mut rows = Vec::new();
+    let spinner = ProgressBar::new_spinner();
+    spinner.enable_steady_tick(Duration::from_millis(120));
+    for (bucket, prefix) in locations {
+        let client = regional_client(config, bucket).await;
+        // (top-level prefix, storage class) -> (objects, bytes, billable bytes)
+        let mut totals = BTreeMap::<(String, String), (u64, u64, u64)>::new();
+        let mut count = 0;
+        let mut token = None;
+        loop {
+            let page = list_page(&client, bucket, prefix, None, 1000, token).await;
+            for object in page.objects {
+                let relative = &object.key[prefix.len()..];
+                let top = match relative.split_once('/') {
+                    Some((folder, _)) => format!("{prefix}{folder}/"),
+                    None => prefix.clone(),
+                };
+                let entry = totals
+                    .entry((top, object.storage_class.clone()))
+                    .or_default();
+                entry.0 += 1;
+                entry.1 += object.size;
+                entry.2 += billable_bytes(&object.storage_class, object.size);
+                count += 1;
+            }
+            spinner.set_message(format!("Walking s3://{bucket}/{prefix}: {count} object(s)"));
+            token = page.next_token;
+            if token.is_none() {
+                break;
+            }
+        }
+        rows.extend(totals.into_iter().map(
+            |((top, storage_class), (objects, bytes, billable))| UsageRow {
+                bucket: bucket.clone(),
+                prefix: top,
+                monthly_cost: prices.monthly_cost(&storage_class, billable, objects),
+                storage_class,
+                objects,
+                bytes,
+                billable_bytes: billable,
+            },
+        ));
+    }
+    spinner.finish_and_clear();
+    UsageReport {
+        currency: prices.currency.clone(),
+        rows,
+    }
+}
+
+fn print_totals(name: &str, totals: &UsageTotals, currency: &str) {
+    println!(
+        "    {:<40} {:>12} {:>12} {:>14}",
+        name,
+        totals.objects,
+        format_size(totals.bytes),
+        format!("{:.2} {currency}", totals.monthly_cost)
+    );
+}
+
+/// Prints each bucket by storage class and by its largest top-level prefixes, then the
+/// totals of all of them
+pub fn print_usage(report: &UsageReport) {
+    let currency = &report.currency;
+    let buckets = report
+        .rows
+        .iter()
+        .map(|row| row.bucket.as_str())
+        .collect::<BTreeSet<_>>();
+    if buckets.is_empty() {
+        println!("{}\n", "No objects were found".yellow().bold());
+        return;
+    }
+    let header = || {
+        println!(
+            "    {:<40} {:>12} {:>12} {:>14}",
+            "", "Objects", "Size", "Per month"
+        )
+    };
+    for bucket in &buckets {
+        let rows = report.rows.iter().filter(|row| row.bucket == *bucket);
+        let classes = UsageReport::grouped(rows.clone(), |row| row.storage_class.clone());
+        let mut prefixes = UsageReport::grouped(rows, |row| match row.prefix.as_str() {
+            "" => "(top level)".into(),
+            prefix => prefix.into(),
+        })
+        .into_iter()
+        .collect::<Vec<_>>();
+        prefixes.sort_by(|(_, a), (_, b)| b.bytes.cmp(&a.bytes));
+        let total = classes
+            .values()
+            .fold(UsageTotals::default(), |mut total, class| {
+                total.objects += class.objects;
+                total.bytes += class.bytes;
+                total.monthly_cost += class.monthly_cost;
+                total
+            });
+        println!(
+            "{}: {} object(s), {}, about {}",
+            bucket.green().bold(),
+            total.objects.to_string().bold(),
+            format_size(total.bytes).bold(),
+            format!("{:.2} {currency} a month", total.monthly_cost).bold()
+        );
+        println!("  {}", "By storage class".bold());
+        header();
+        for (class, totals) in &classes {
+            print_totals(class, totals, currency);
+        }
+        println!("  {}", "By top-level prefix".bold());
+        header();
+        for (prefix, totals) in prefixes.iter().take(TOP_PREFIXES) {
+            print_totals(prefix, totals, currency);
+        }
+        if prefixes.len() > TOP_PREFIXES {
+            println!(
+                "    ...and {} more; export the report to see them all",
+                prefixes.len() - TOP_PREFIXES
+            );
+        }
+        println!();
+    }
+    if buckets.len() > 1 {
+        println!("{}", "All buckets by storage class".green().bold());
+        header();
+        for (class, totals) in &report.by_storage_class() {
+            print_totals(class, totals, currency);
+        }
+        print_totals("Total", &report.total(), currency);
+        println!();
+    }
+    let unpriced = report.unpriced_classes();
+    if !unpriced.is_empty() {
+        println!(
+            "{} no price for {}, so they aren't in the cost",
+            "Warning:".yellow().bold(),
+            unpriced.into_iter().collect::<Vec<_>>().join(", ")
+        );
+    }
+    println!(
+        "{}\n",
+        "Estimates cover storage only, not requests, transfer or Intelligent-Tiering's cheaper tiers"
+            .dimmed()
+    );
+}
+
+/// Writes every row as CSV when the file ends with .csv, or the rows with the totals by
+/// storage class and prefix as JSON otherwise
+pub fn write_report(path: &Path, report: &UsageReport) -> Result<(), String> {
+    let with_path = |error: String| format!("{}: {error}", path.display());
+    match path.extension().is_some_and(|extension| extension == "csv") {
+        true => {
+            let mut writer =
+                csv::Writer::from_path(path).map_err(|error| with_path(error.to_string()))?;
+            for row in &report.rows {
+                writer
+                    .serialize(row)
+                    .map_err(|error| with_path(error.to_string()))?;
+            }
+            writer
+                .flush()
+                .map_err(|error| with_path(error.to_string()))?;
+        }
+        false => {
+            let document = json!({
+                "currency": report.currency,
+                "total": report.total(),
+                "by_storage_class": report.by_storage_class(),
+                "by_prefix": report.by_prefix(),
+                "rows": report.rows,
+            });
+            fs::write(path, serde_json::to_string_pretty(&document).unwrap())
+                .map_err(|error| with_path(error.to_string()))?;
+        }
+    }
+    println!(
+        "The report is written to {}\n",
+        path.display().to_string().green().bold()
+    );
+    Ok(())
+}
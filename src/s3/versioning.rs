@@ -0,0 +1,239 @@
+use aws_config::SdkConfig;
+use aws_sdk_s3::types::{BucketVersioningStatus, VersioningConfiguration};
+use aws_sdk_s3::Client;
+use colored::Colorize;
+use inquire::{Confirm, MultiSelect, Select, Text};
+use std::path::Path;
+
+use super::download::{download_object, DownloadOptions};
+use super::encryption::Encryption;
+use super::listing::format_date;
+use super::objects::{delete_versions, list_versions, VersionEntry};
+use super::{copy_source, format_size};
+
+/// 'Enabled', 'Suspended', or 'Disabled' for a bucket that never had versioning turned on
+pub async fn versioning_status(config: &SdkConfig, bucket: &str) -> String {
+    let client = Client::new(config);
+    let output = client
+        .get_bucket_versioning()
+        .bucket(bucket)
+        .send()
+        .await
+        .expect("Error while getting the versioning status of the bucket\n");
+    output
+        .status()
+        .map(|status| status.as_str().to_string())
+        .unwrap_or("Disabled".into())
+}
+
+/// Enables versioning, or suspends it: existing versions are kept but new writes replace the
+/// current object instead of adding versions
+pub async fn set_versioning(config: &SdkConfig, bucket: &str, enabled: bool) {
+    let client = Client::new(config);
+    let status = match enabled {
+        true => BucketVersioningStatus::Enabled,
+        false => BucketVersioningStatus::Suspended,
+    };
+    client
+        .put_bucket_versioning()
+        .bucket(bucket)
+        .versioning_configuration(VersioningConfiguration::builder().status(status).build())
+        .send()
+        .await
+        .expect("Error while changing the versioning of the bucket\n");
+    println!(
+        "Versioning of '{}' is now {}\n",
+        bucket.green().bold(),
+        match enabled {
+            true => "enabled".green().bold(),
+            false => "suspended".yellow().bold(),
+        }
+    );
+}
+
+/// The versions and delete markers of one key, newest first. A key that is empty or ends
+/// with '/' is treated as a prefix
+pub async fn key_versions(config: &SdkConfig, bucket: &str, key: &str) -> Vec<VersionEntry> {
+    let client = Client::new(config);
+    let as_prefix = key.is_empty() || key.ends_with('/');
+    list_versions(&client, bucket, key)
+        .await
+        .into_iter()
+        .filter(|version| as_prefix || version.key == key)
+        .collect()
+}
+
+/// How a version is shown in tables and pickers
+pub fn describe_version(version: &VersionEntry) -> String {
+    let kind = match version.is_delete_marker {
+        true => "delete marker".to_string(),
+        false => format_size(version.size),
+    };
+    let latest = match version.is_latest {
+        true => "  (latest)",
+        false => "",
+    };
+    format!(
+        "{}  {}  {kind}{latest}",
+        version.version_id,
+        format_date(version.last_modified)
+    )
+}
+
+pub fn print_versions(bucket: &str, versions: &[VersionEntry]) {
+    if versions.is_empty() {
+        println!(
+            "{}\n",
+            format!("No versions were found in '{bucket}'")
+                .yellow()
+                .bold()
+        );
+        return;
+    }
+    let mut current_key = None;
+    for version in versions {
+        if current_key != Some(&version.key) {
+            println!("\n{}", version.key.green().bold());
+            current_key = Some(&version.key);
+        }
+        let line = describe_version(version);
+        match version.is_delete_marker {
+            true => println!("    {}", line.red()),
+            false => println!("    {line}"),
+        }
+    }
+    println!();
+}
+
+/// Makes an older version the current one by copying it over the key. The copy becomes a new
+/// version, so nothing is lost, and a delete marker on top of the key is covered the same way
+pub async fn restore_version(config: &SdkConfig, bucket: &str, key: &str, version_id: &str) {
+    let client = Client::new(config);
+    let output = client
+        .copy_object()
+        .bucket(bucket)
+        .key(key)
+        .copy_source(copy_source(bucket, key, Some(version_id)))
+        .send()
+        .await
+        .expect("Error while restoring the version; delete markers can't be restored and versions over 5GB need a multipart copy\n");
+    println!(
+        "The version {} of '{}' is now the current one (new version {})\n",
+        version_id.yellow(),
+        key.green().bold(),
+        output.version_id().unwrap_or("null").green()
+    );
+}
+
+/// Permanently deletes the given versions or delete markers of the key. Deleting the delete
+/// marker on top of a key brings the previous version back
+pub async fn delete_key_versions(
+    config: &SdkConfig,
+    bucket: &str,
+    versions: &[VersionEntry],
+) -> usize {
+    let client = Client::new(config);
+    let deleted = delete_versions(&client, bucket, versions).await;
+    println!(
+        "{} of {} version(s) permanently deleted\n",
+        deleted.to_string().green().bold(),
+        versions.len()
+    );
+    deleted
+}
+
+fn version_label(version: &VersionEntry) -> String {
+    format!("{}  {}", version.key, describe_version(version))
+}
+
+/// The interactive menu for the versions of a key: download, restore or permanently delete them
+pub async fn manage_versions(config: &SdkConfig, bucket: &str, key: &str) {
+    loop {
+        let versions = key_versions(config, bucket, key).await;
+        print_versions(bucket, &versions);
+        if versions.is_empty() {
+            return;
+        }
+        let labels = versions.iter().map(version_label).collect::<Vec<_>>();
+        let action = Select::new(
+            "What do you want to do with these versions?\n",
+            vec![
+                "Download a version",
+                "Restore a version",
+                "Permanently delete versions",
+                "Done",
+            ],
+        )
+        .prompt()
+        .unwrap();
+        match action {
+            "Download a version" | "Restore a version" => {
+                let candidates = versions
+                    .iter()
+                    .zip(&labels)
+                    .filter(|(version, _)| !version.is_delete_marker)
+                    .collect::<Vec<_>>();
+                if candidates.is_empty() {
+                    println!("{}\n", "Only delete markers are left".yellow().bold());
+                    continue;
+                }
+                let choice = Select::new(
+                    "Select the version\n",
+                    candidates.iter().map(|(_, label)| *label).collect(),
+                )
+                .raw_prompt()
+                .unwrap();
+                let version = candidates[choice.index].0;
+                match action {
+                    "Download a version" => {
+                        let destination = Text::new("Enter the destination file or directory, or press Enter for the current directory\n")
+                            .with_formatter(&|str| format!(".....{str}.....\n"))
+                            .prompt_skippable()
+                            .unwrap()
+                            .unwrap_or_default();
+                        let options = DownloadOptions {
+                            range: None,
+                            verify: true,
+                            version_id: Some(version.version_id.clone()),
+                            encryption: Encryption::from_env(),
+                        };
+                        download_object(
+                            config,
+                            bucket,
+                            &version.key,
+                            Path::new(destination.trim()),
+                            &options,
+                        )
+                        .await;
+                    }
+                    _ => restore_version(config, bucket, &version.key, &version.version_id).await,
+                }
+            }
+            "Permanently delete versions" => {
+                let chosen = MultiSelect::new(
+                    "Select the versions and delete markers to delete for good\n",
+                    labels.clone(),
+                )
+                .with_help_message("Space selects, Enter confirms")
+                .raw_prompt()
+                .unwrap();
+                let chosen = chosen
+                    .into_iter()
+                    .map(|option| versions[option.index].clone())
+                    .collect::<Vec<_>>();
+                let confirm = !chosen.is_empty()
+                    && Confirm::new(&format!(
+                        "Permanently delete {} version(s)? This can't be undone\n",
+                        chosen.len()
+                    ))
+                    .with_default(false)
+                    .prompt()
+                    .unwrap();
+                if confirm {
+                    delete_key_versions(config, bucket, &chosen).await;
+                }
+            }
+            _ => return,
+        }
+    }
+}
@@ -0,0 +1,323 @@
+use aws_config::SdkConfig;
+use aws_sdk_s3::error::ProvideErrorMetadata;
+use aws_sdk_s3::types::{ErrorDocument, IndexDocument, WebsiteConfiguration};
+use aws_sdk_s3::Client;
+use colored::Colorize;
+use inquire::{Confirm, Select, Text};
+use std::path::Path;
+
+use super::bucket_region;
+use super::policy::{apply_template, get_policy, has_statement, remove_statement, PolicyTemplate};
+use super::sync::{sync, SyncDirection, SyncOptions, DEFAULT_JOBS};
+
+pub const DEFAULT_INDEX: &str = "index.html";
+pub const DEFAULT_ERROR: &str = "error.html";
+
+// The oldest regions use 's3-website-region', the rest 's3-website.region'
+const DASH_REGIONS: [&str; 9] = [
+    "us-east-1",
+    "us-west-1",
+    "us-west-2",
+    "ap-southeast-1",
+    "ap-southeast-2",
+    "ap-northeast-1",
+    "eu-west-1",
+    "sa-east-1",
+    "us-gov-west-1",
+];
+
+pub struct WebsiteSettings {
+    /// Served for requests to the root and to every folder, e.g. 'index.html'
+    pub index: String,
+    /// Served with the error status, e.g. for a missing page; None shows the S3 error page
+    pub error: Option<String>,
+}
+
+pub fn website_endpoint(bucket: &str, region: &str) -> String {
+    let separator = match DASH_REGIONS.contains(&region) {
+        true => '-',
+        false => '.',
+    };
+    format!("http://{bucket}.s3-website{separator}{region}.amazonaws.com")
+}
+
+/// The index and error documents of the bucket, or None when website hosting is off
+pub async fn get_website(config: &SdkConfig, bucket: &str) -> Option<WebsiteSettings> {
+    let client = Client::new(config);
+    match client.get_bucket_website().bucket(bucket).send().await {
+        Ok(output) => Some(WebsiteSettings {
+            index: output
+                .index_document()
+                .and_then(|document| document.suffix())
+                .unwrap_or_default()
+                .to_string(),
+            error: output
+                .error_document()
+                .and_then(|document| document.key())
+                .map(str::to_string),
+        }),
+        Err(error) if error.code() == Some("NoSuchWebsiteConfiguration") => None,
+        Err(error) => panic!(
+            "Error while getting the website configuration: {}\n",
+            error.into_service_error()
+        ),
+    }
+}
+
+pub async fn print_website(config: &SdkConfig, bucket: &str) {
+    let Some(settings) = get_website(config, bucket).await else {
+        println!(
+            "{}\n",
+            format!("Website hosting is off for '{bucket}'")
+                .yellow()
+                .bold()
+        );
+        return;
+    };
+    let region = bucket_region(config, bucket).await;
+    println!("Index document: {}", settings.index.green().bold());
+    println!(
+        "Error document: {}",
+        settings.error.as_deref().unwrap_or("none").green().bold()
+    );
+    println!(
+        "Endpoint:       {}\n",
+        website_endpoint(bucket, &region).green().bold()
+    );
+}
+
+/// Turns on website hosting with the given documents, replacing any earlier configuration
+pub async fn enable_website(config: &SdkConfig, bucket: &str, settings: &WebsiteSettings) {
+    // S3 refuses suffixes with a slash, since it is appended to every folder request
+    if settings.index.is_empty() || settings.index.contains('/') {
+        panic!(
+            "The index document should be a file name such as index.html, not '{}'\n",
+            settings.index
+        );
+    }
+    let client = Client::new(config);
+    let configuration = WebsiteConfiguration::builder()
+        .index_document(IndexDocument::builder().suffix(&settings.index).build())
+        .set_error_document(
+            settings
+                .error
+                .as_ref()
+                .map(|key| ErrorDocument::builder().key(key).build()),
+        )
+        .build();
+    client
+        .put_bucket_website()
+        .bucket(bucket)
+        .website_configuration(configuration)
+        .send()
+        .await
+        .expect("Error while turning on website hosting\n");
+    println!("Website hosting is on for '{}'\n", bucket.green().bold());
+}
+
+/// Turns on hosting, uploads 'build_dir' to the root of the bucket with content types from
+/// the file names, adds a public-read statement to the policy and prints the endpoint.
+/// With 'delete', objects that are no longer in the build are removed
+pub async fn publish_website(
+    config: &SdkConfig,
+    bucket: &str,
+    build_dir: &Path,
+    settings: &WebsiteSettings,
+    delete: bool,
+    assume_yes: bool,
+) -> bool {
+    if !build_dir.is_dir() {
+        println!(
+            "{}\n",
+            format!("'{}' isn't a folder", build_dir.display())
+                .red()
+                .bold()
+        );
+        return false;
+    }
+    for document in [Some(&settings.index), settings.error.as_ref()]
+        .into_iter()
+        .flatten()
+    {
+        if !build_dir.join(document).is_file() {
+            println!(
+                "{} '{document}' isn't in {}",
+                "Warning:".yellow().bold(),
+                build_dir.display()
+            );
+        }
+    }
+
+    enable_website(config, bucket, settings).await;
+    let options = SyncOptions {
+        include: Vec::new(),
+        exclude: Vec::new(),
+        delete,
+        dry_run: false,
+        jobs: DEFAULT_JOBS,
+        manifest: None,
+    };
+    if !sync(
+        config,
+        SyncDirection::Upload,
+        build_dir,
+        bucket,
+        "",
+        &options,
+    )
+    .await
+    {
+        println!(
+            "{}\n",
+            "The site wasn't fully uploaded, so it isn't made public"
+                .red()
+                .bold()
+        );
+        return false;
+    }
+    let template = PolicyTemplate::PublicRead {
+        prefix: String::new(),
+    };
+    if !apply_template(config, bucket, &template, assume_yes).await {
+        // An unchanged policy means the statement is already there
+        let statement = template.statement(bucket);
+        let current = get_policy(config, bucket).await;
+        let present = current.is_some_and(|policy| has_statement(&policy, &statement));
+        if !present {
+            println!(
+                "{}\n",
+                "The site is uploaded but not public, so the endpoint answers 403 Forbidden"
+                    .yellow()
+                    .bold()
+            );
+            return false;
+        }
+    }
+    let region = bucket_region(config, bucket).await;
+    println!(
+        "The site is published at {}\n",
+        website_endpoint(bucket, &region).green().bold()
+    );
+    true
+}
+
+/// Turns off website hosting and, unless 'keep_policy', removes the public-read statement
+/// 'publish' added. The objects stay in the bucket
+pub async fn disable_website(
+    config: &SdkConfig,
+    bucket: &str,
+    keep_policy: bool,
+    assume_yes: bool,
+) {
+    if get_website(config, bucket).await.is_none() {
+        println!(
+            "{}\n",
+            format!("Website hosting is already off for '{bucket}'")
+                .yellow()
+                .bold()
+        );
+    } else {
+        let confirm = assume_yes
+            || Confirm::new(&format!("Turn off website hosting for '{bucket}'?\n"))
+                .with_default(false)
+                .prompt()
+                .unwrap();
+        if !confirm {
+            return;
+        }
+        let client = Client::new(config);
+        client
+            .delete_bucket_website()
+            .bucket(bucket)
+            .send()
+            .await
+            .expect("Error while turning off website hosting\n");
+        println!("Website hosting is off for '{}'\n", bucket.green().bold());
+    }
+    if keep_policy {
+        return;
+    }
+    let statement = PolicyTemplate::PublicRead {
+        prefix: String::new(),
+    }
+    .statement(bucket);
+    let sid = statement["Sid"].as_str().unwrap_or_default();
+    if !remove_statement(config, bucket, sid, assume_yes).await {
+        println!(
+            "{}\n",
+            "The bucket policy has no public-read statement to remove".yellow()
+        );
+    }
+}
+
+fn prompt_settings() -> WebsiteSettings {
+    let index = Text::new("Enter the index document\n")
+        .with_default(DEFAULT_INDEX)
+        .with_help_message("Served for the root and for every folder")
+        .with_formatter(&|str| format!(".....{str}.....\n"))
+        .prompt()
+        .unwrap();
+    let error = Text::new("Enter the error document, or leave it empty for none\n")
+        .with_default(DEFAULT_ERROR)
+        .with_help_message("Single page apps often use index.html here")
+        .with_formatter(&|str| format!(".....{str}.....\n"))
+        .prompt()
+        .unwrap();
+    WebsiteSettings {
+        index: index.trim().to_string(),
+        error: Some(error.trim().to_string()).filter(|error| !error.is_empty()),
+    }
+}
+
+/// The interactive menu for static website hosting of a bucket
+pub async fn manage_website(config: &SdkConfig, bucket: &str) {
+    loop {
+        let choice = Select::new(
+            "What do you want to do with website hosting?\n",
+            vec![
+                "Show the website settings",
+                "Publish a local build folder",
+                "Disable the website",
+                "Done",
+            ],
+        )
+        .prompt()
+        .unwrap();
+        match choice {
+            "Show the website settings" => print_website(config, bucket).await,
+            "Publish a local build folder" => {
+                let build_dir = Text::new("Enter the path of the build folder\n")
+                    .with_placeholder("dist")
+                    .with_formatter(&|str| format!(".....{str}.....\n"))
+                    .prompt()
+                    .unwrap();
+                let settings = prompt_settings();
+                let delete = Confirm::new(
+                    "Delete objects from the bucket that aren't in the build folder?\n",
+                )
+                .with_default(false)
+                .prompt()
+                .unwrap();
+                publish_website(
+                    config,
+                    bucket,
+                    Path::new(build_dir.trim()),
+                    &settings,
+                    delete,
+                    false,
+                )
+                .await;
+            }
+            "Disable the website" => {
+                let keep_policy = !Confirm::new(
+                    "Also remove the public-read statement from the bucket policy?\n",
+                )
+                .with_default(true)
+                .prompt()
+                .unwrap();
+                disable_website(config, bucket, keep_policy, false).await;
+            }
+            _ => break,
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use aws_config::SdkConfig;
+use aws_sdk_sesv2::types::{
+    CloudWatchDestination, CloudWatchDimensionConfiguration, DimensionValueSource,
+    EventDestinationDefinition, EventType, KinesisFirehoseDestination, SnsDestination,
+};
+use aws_sdk_sesv2::Client;
+use colored::Colorize;
+use inquire::Select;
+
+pub const EVENT_TYPES: [&str; 10] = [
+    "SEND",
+    "REJECT",
+    "BOUNCE",
+    "COMPLAINT",
+    "DELIVERY",
+    "OPEN",
+    "CLICK",
+    "RENDERING_FAILURE",
+    "DELIVERY_DELAY",
+    "SUBSCRIPTION",
+];
+
+/// Where the sending events of a configuration set are published
+pub enum EventDestinationKind {
+    Sns {
+        topic_arn: String,
+    },
+    CloudWatch {
+        dimension_name: String,
+        default_value: String,
+    },
+    Firehose {
+        delivery_stream_arn: String,
+        iam_role_arn: String,
+    },
+}
+
+pub async fn create_configuration_set(config: &SdkConfig, name: &str) {
+    Client::new(config)
+        .create_configuration_set()
+        .configuration_set_name(name)
+        .send()
+        .await
+        .map(|_| {
+            println!(
+                "The configuration set '{}' has been created\n",
+                name.green().bold()
+            )
+        })
+        .expect("Error while creating the configuration set\n");
+}
+
+pub async fn list_configuration_sets(config: &SdkConfig) -> Vec<String> {
+    let client = Client::new(config);
+    let mut names = Vec::new();
+    let mut next_token = None;
+    loop {
+        let output = client
+            .list_configuration_sets()
+            .set_next_token(next_token)
+            .send()
+            .await
+            .expect("Error while listing the configuration sets\n");
+        names.extend(output.configuration_sets().unwrap_or_default().to_vec());
+        next_token = output.next_token().map(|token| token.to_string());
+        if next_token.is_none() {
+            break names;
+        }
+    }
+}
+
+/// Asks for one of the account's configuration sets; None when there are none or the user cancels
+pub async fn pick_configuration_set(config: &SdkConfig, message: &str) -> Option<String> {
+    let names = list_configuration_sets(config).await;
+    if names.is_empty() {
+        println!(
+            "{}\n",
+            "There are no configuration sets in this account"
+                .yellow()
+                .bold()
+        );
+        return None;
+    }
+    Select::new(message, names).prompt_skippable().unwrap()
+}
+
+pub async fn delete_configuration_set(config: &SdkConfig, name: &str) {
+    Client::new(config)
+        .delete_configuration_set()
+        .configuration_set_name(name)
+        .send()
+        .await
+        .map(|_| {
+            println!(
+                "The configuration set '{}' has been deleted\n",
+                name.green().bold()
+            )
+        })
+        .expect("Error while deleting the configuration set\n");
+}
+
+/// Event types are accepted in any case, e.g. 'bounce,complaint'
+pub fn parse_event_types(input: &str) -> Result<Vec<EventType>, String> {
+    input
+        .split(',')
+        .map(|event| event.trim().to_uppercase())
+        .filter(|event| !event.is_empty())
+        .map(|event| match EVENT_TYPES.contains(&event.as_str()) {
+            true => Ok(EventType::from(event.as_str())),
+            false => Err(format!(
+                "'{event}' isn't an event type, the possible values are: {}",
+                EVENT_TYPES.join(", ")
+            )),
+        })
+        .collect()
+}
+
+pub async fn add_event_destination(
+    config: &SdkConfig,
+    configuration_set: &str,
+    destination_name: &str,
+    event_types: Vec<EventType>,
+    kind: EventDestinationKind,
+) {
+    let definition = EventDestinationDefinition::builder()
+        .enabled(true)
+        .set_matching_event_types(Some(event_types));
+    let definition = match kind {
+        EventDestinationKind::Sns { topic_arn } => {
+            definition.sns_destination(SnsDestination::builder().topic_arn(topic_arn).build())
+        }
+        EventDestinationKind::CloudWatch {
+            dimension_name,
+            default_value,
+        } => definition.cloud_watch_destination(
+            CloudWatchDestination::builder()
+                .dimension_configurations(
+                    CloudWatchDimensionConfiguration::builder()
+                        .dimension_name(dimension_name)
+                        .dimension_value_source(DimensionValueSource::MessageTag)
+                        .default_dimension_value(default_value)
+                        .build(),
+                )
+                .build(),
+        ),
+        EventDestinationKind::Firehose {
+            delivery_stream_arn,
+            iam_role_arn,
+        } => definition.kinesis_firehose_destination(
+            KinesisFirehoseDestination::builder()
+                .delivery_stream_arn(delivery_stream_arn)
+                .iam_role_arn(iam_role_arn)
+                .build(),
+        ),
+    };
+    Client::new(config)
+        .create_configuration_set_event_destination()
+        .configuration_set_name(configuration_set)
+        .event_destination_name(destination_name)
+        .event_destination(definition.build())
+        .send()
+        .await
+        .map(|_| {
+            println!(
+                "The event destination '{}' has been added to '{}'\n",
+                destination_name.green().bold(),
+                configuration_set.green().bold()
+            )
+        })
+        .expect("Error while adding the event destination\n");
+}
+
+pub async fn print_event_destinations(config: &SdkConfig, configuration_set: &str) {
+    let output = Client::new(config)
+        .get_configuration_set_event_destinations()
+        .configuration_set_name(configuration_set)
+        .send()
+        .await
+        .expect("Error while getting the event destinations\n");
+    let destinations = output.event_destinations().unwrap_or_default();
+    if destinations.is_empty() {
+        println!(
+            "{}\n",
+            "No event destinations are attached to this configuration set"
+                .yellow()
+                .bold()
+        );
+    }
+    for destination in destinations {
+        let events = destination
+            .matching_event_types()
+            .unwrap_or_default()
+            .iter()
+            .map(|event| event.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let target = if let Some(sns) = destination.sns_destination() {
+            format!("SNS topic {}", sns.topic_arn().unwrap_or_default())
+        } else if let Some(firehose) = destination.kinesis_firehose_destination() {
+            format!(
+                "Firehose stream {}",
+                firehose.delivery_stream_arn().unwrap_or_default()
+            )
+        } else if destination.cloud_watch_destination().is_some() {
+            "CloudWatch metrics".to_string()
+        } else {
+            "Other destination".to_string()
+        };
+        println!(
+            "Name: {}\nEnabled: {}\nEvents: {}\nDestination: {}\n",
+            destination.name().unwrap_or_default().green().bold(),
+            destination.enabled(),
+            events.green().bold(),
+            target.green().bold()
+        );
+    }
+}
+
+pub async fn delete_event_destination(
+    config: &SdkConfig,
+    configuration_set: &str,
+    destination_name: &str,
+) {
+    Client::new(config)
+        .delete_configuration_set_event_destination()
+        .configuration_set_name(configuration_set)
+        .event_destination_name(destination_name)
+        .send()
+        .await
+        .map(|_| {
+            println!(
+                "The event destination '{}' has been deleted\n",
+                destination_name.green().bold()
+            )
+        })
+        .expect("Error while deleting the event destination\n");
+}
@@ -0,0 +1,370 @@
+use aws_config::SdkConfig;
+use colored::Colorize;
+use hickory_resolver::proto::rr::{RData, RecordType};
+use hickory_resolver::TokioAsyncResolver;
+
+/// The records SES expects to find for a domain identity.
+pub struct ExpectedRecords {
+    pub domain: String,
+    pub dkim_tokens: Vec<String>,
+    pub mail_from_domain: Option<String>,
+    pub region: String,
+}
+
+impl ExpectedRecords {
+    /// Reads the DKIM tokens and the custom MAIL FROM domain of the identity from SES
+    pub async fn from_ses(config: &SdkConfig, domain: &str) -> Self {
+        let client = aws_sdk_sesv2::Client::new(config);
+        let identity = client
+            .get_email_identity()
+            .email_identity(domain)
+            .send()
+            .await
+            .expect("Error while getting the domain identity; make sure it was created through the 'Create Email Identity' option\n");
+        let dkim_tokens = identity
+            .dkim_attributes()
+            .and_then(|dkim| dkim.tokens())
+            .map(|tokens| tokens.to_vec())
+            .unwrap_or_default();
+        let mail_from_domain = identity
+            .mail_from_attributes()
+            .and_then(|mail_from| mail_from.mail_from_domain())
+            .map(normalize_name);
+        Self {
+            domain: normalize_name(domain),
+            dkim_tokens,
+            mail_from_domain,
+            region: region_of(config),
+        }
+    }
+
+    /// Builds the expectations from tokens supplied by the user, so the check can run without SES
+    pub fn from_tokens(
+        region: &str,
+        domain: &str,
+        dkim_tokens: Vec<String>,
+        mail_from_domain: Option<&str>,
+    ) -> Self {
+        Self {
+            domain: normalize_name(domain),
+            dkim_tokens,
+            mail_from_domain: mail_from_domain.map(normalize_name),
+            region: region.into(),
+        }
+    }
+}
+
+fn region_of(config: &SdkConfig) -> String {
+    config
+        .region()
+        .map(|region| region.to_string())
+        .unwrap_or("us-east-1".into())
+}
+
+/// Where the published records are read from
+pub enum RecordSource {
+    System(TokioAsyncResolver),
+    Zone(ZoneFile),
+}
+
+impl RecordSource {
+    pub fn system() -> Self {
+        let resolver = TokioAsyncResolver::tokio_from_system_conf()
+            .expect("Error while reading the system resolver configuration\n");
+        RecordSource::System(resolver)
+    }
+
+    pub fn zone_file(path: &str) -> Self {
+        let data = std::fs::read_to_string(path)
+            .expect("Error opening the zone file path you specified\n");
+        RecordSource::Zone(ZoneFile::parse(&data))
+    }
+
+    async fn lookup(&self, name: &str, record_type: RecordType) -> Vec<String> {
+        match self {
+            RecordSource::Zone(zone) => zone.lookup(name, record_type),
+            RecordSource::System(resolver) => {
+                let Ok(lookup) = resolver.lookup(format!("{name}."), record_type).await else {
+                    return Vec::new();
+                };
+                lookup
+                    .record_iter()
+                    .filter(|record| record.record_type() == record_type)
+                    .filter_map(|record| match record.data()? {
+                        RData::CNAME(cname) => Some(normalize_name(&cname.0.to_string())),
+                        RData::MX(mx) => Some(normalize_name(&mx.exchange().to_string())),
+                        RData::TXT(txt) => Some(
+                            txt.txt_data()
+                                .iter()
+                                .map(|part| String::from_utf8_lossy(part).into_owned())
+                                .collect::<String>(),
+                        ),
+                        _ => None,
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// A minimal reader for BIND-style zone files, only covering what is needed for the SES records
+pub struct ZoneFile {
+    records: Vec<(String, RecordType, String)>,
+}
+
+impl ZoneFile {
+    pub fn parse(data: &str) -> Self {
+        let mut records = Vec::new();
+        let mut origin = String::new();
+        let mut last_name = String::new();
+        let mut pending = String::new();
+
+        for raw_line in data.lines() {
+            let line = strip_comment(raw_line);
+            // Records spanning several lines are wrapped in parentheses
+            pending.push_str(&line);
+            pending.push(' ');
+            if pending.matches('(').count() > pending.matches(')').count() {
+                continue;
+            }
+            let entry = std::mem::take(&mut pending).replace(['(', ')'], " ");
+            let starts_with_blank = entry.starts_with(char::is_whitespace);
+            let tokens = tokenize(&entry);
+            let Some(first) = tokens.first() else {
+                continue;
+            };
+            match first.as_str() {
+                "$ORIGIN" => {
+                    origin = tokens.get(1).map(|o| normalize_name(o)).unwrap_or_default();
+                    continue;
+                }
+                "$TTL" | "$INCLUDE" => continue,
+                _ => {}
+            }
+
+            let mut rest = tokens.iter();
+            let name = match starts_with_blank {
+                true => last_name.clone(),
+                false => absolute_name(rest.next().unwrap(), &origin),
+            };
+            last_name = name.clone();
+
+            // The TTL and the class are both optional and may come in any order
+            let mut record_type = None;
+            for token in rest.by_ref() {
+                let upper = token.to_uppercase();
+                if token.chars().all(|c| c.is_ascii_digit())
+                    || ["IN", "CH", "HS"].contains(&upper.as_str())
+                {
+                    continue;
+                }
+                record_type = upper.parse::<RecordType>().ok();
+                break;
+            }
+            let rdata: Vec<&String> = rest.collect();
+            let value = match record_type {
+                Some(RecordType::TXT) => rdata.iter().map(|part| part.as_str()).collect::<String>(),
+                Some(RecordType::CNAME) | Some(RecordType::MX) => match rdata.last() {
+                    Some(target) => absolute_name(target, &origin),
+                    None => continue,
+                },
+                _ => continue,
+            };
+            records.push((name, record_type.unwrap(), value));
+        }
+        Self { records }
+    }
+
+    fn lookup(&self, name: &str, record_type: RecordType) -> Vec<String> {
+        let name = normalize_name(name);
+        self.records
+            .iter()
+            .filter(|(record_name, rtype, _)| *record_name == name && *rtype == record_type)
+            .map(|(_, _, value)| value.clone())
+            .collect()
+    }
+}
+
+fn strip_comment(line: &str) -> String {
+    let mut in_quotes = false;
+    let mut stripped = String::new();
+    for c in line.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ';' if !in_quotes => break,
+            _ => {}
+        }
+        stripped.push(c);
+    }
+    stripped
+}
+
+// Splits on whitespace while keeping quoted TXT strings together, without their quotation marks
+fn tokenize(entry: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    for c in entry.chars() {
+        match c {
+            '"' => {
+                if in_quotes {
+                    tokens.push(std::mem::take(&mut current));
+                }
+                in_quotes = !in_quotes;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+fn absolute_name(name: &str, origin: &str) -> String {
+    match name {
+        "@" => origin.to_string(),
+        name if name.ends_with('.') || origin.is_empty() => normalize_name(name),
+        name => normalize_name(&format!("{name}.{origin}")),
+    }
+}
+
+fn normalize_name(name: &str) -> String {
+    name.trim().trim_end_matches('.').to_lowercase()
+}
+
+#[derive(PartialEq)]
+pub enum CheckStatus {
+    Ok,
+    Missing,
+    Mismatch,
+}
+
+pub struct CheckResult {
+    pub record: String,
+    pub expected: String,
+    pub found: Vec<String>,
+    pub status: CheckStatus,
+}
+
+/// Compares the published records with what SES expects for the identity
+pub async fn check_domain(expected: &ExpectedRecords, source: &RecordSource) -> Vec<CheckResult> {
+    let mut results = Vec::new();
+
+    for token in &expected.dkim_tokens {
+        let record = format!("{token}._domainkey.{}", expected.domain);
+        let target = format!("{token}.dkim.amazonses.com");
+        let found = source.lookup(&record, RecordType::CNAME).await;
+        let status = match (found.is_empty(), found.contains(&target)) {
+            (true, _) => CheckStatus::Missing,
+            (false, true) => CheckStatus::Ok,
+            (false, false) => CheckStatus::Mismatch,
+        };
+        results.push(CheckResult {
+            record: format!("CNAME {record}"),
+            expected: target,
+            found,
+            status,
+        });
+    }
+
+    // SES only needs SPF on the custom MAIL FROM domain, but an SPF record on the domain itself
+    // should still allow SES when the domain is used as the 'From' address
+    let spf_domain = expected
+        .mail_from_domain
+        .clone()
+        .unwrap_or(expected.domain.clone());
+    let found: Vec<String> = source
+        .lookup(&spf_domain, RecordType::TXT)
+        .await
+        .into_iter()
+        .filter(|txt| txt.to_lowercase().starts_with("v=spf1"))
+        .collect();
+    let status = match found.len() {
+        0 => CheckStatus::Missing,
+        1 if found[0].to_lowercase().contains("include:amazonses.com") => CheckStatus::Ok,
+        // More than one SPF record makes every SPF check fail with a permerror
+        _ => CheckStatus::Mismatch,
+    };
+    results.push(CheckResult {
+        record: format!("TXT {spf_domain} (SPF)"),
+        expected: "a single 'v=spf1' record containing 'include:amazonses.com'".into(),
+        found,
+        status,
+    });
+
+    if let Some(mail_from_domain) = &expected.mail_from_domain {
+        let target = format!("feedback-smtp.{}.amazonses.com", expected.region);
+        let found = source.lookup(mail_from_domain, RecordType::MX).await;
+        let status = match (found.is_empty(), found.contains(&target)) {
+            (true, _) => CheckStatus::Missing,
+            (false, true) => CheckStatus::Ok,
+            (false, false) => CheckStatus::Mismatch,
+        };
+        results.push(CheckResult {
+            record: format!("MX {mail_from_domain} (MAIL FROM)"),
+            expected: target,
+            found,
+            status,
+        });
+    }
+
+    let dmarc_record = format!("_dmarc.{}", expected.domain);
+    let found: Vec<String> = source
+        .lookup(&dmarc_record, RecordType::TXT)
+        .await
+        .into_iter()
+        .filter(|txt| txt.to_lowercase().starts_with("v=dmarc1"))
+        .collect();
+    let status = match found.len() {
+        0 => CheckStatus::Missing,
+        1 if found[0].to_lowercase().replace(' ', "").contains(";p=") => CheckStatus::Ok,
+        _ => CheckStatus::Mismatch,
+    };
+    results.push(CheckResult {
+        record: format!("TXT {dmarc_record} (DMARC)"),
+        expected: "a single 'v=DMARC1' record with a 'p=' policy tag".into(),
+        found,
+        status,
+    });
+
+    results
+}
+
+pub fn print_report(results: &[CheckResult]) {
+    for result in results {
+        let status = match result.status {
+            CheckStatus::Ok => "OK".green().bold(),
+            CheckStatus::Missing => "MISSING".red().bold(),
+            CheckStatus::Mismatch => "MISMATCH".red().bold(),
+        };
+        println!("[{status}] {}", result.record.yellow().bold());
+        println!("    Expected: {}", result.expected);
+        match result.found.is_empty() {
+            false => result
+                .found
+                .iter()
+                .for_each(|found| println!("    Found:    {found}")),
+            true => println!("    Found:    nothing"),
+        }
+        println!();
+    }
+    let problems = results
+        .iter()
+        .filter(|result| result.status != CheckStatus::Ok)
+        .count();
+    match problems {
+        0 => println!("{}\n", "All records match what SES expects".green().bold()),
+        count => println!(
+            "{}\n",
+            format!("{count} record(s) need attention before SES can verify the domain")
+                .red()
+                .bold()
+        ),
+    }
+}
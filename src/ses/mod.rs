@@ -0,0 +1,6 @@
+pub mod configuration_sets;
+pub mod dns_check;
+pub mod sending;
+pub mod template_lint;
+pub mod template_repo;
+pub mod template_variants;
@@ -0,0 +1,316 @@
+use aws_config::SdkConfig;
+use aws_sdk_sesv2::types::{
+    Body, BulkEmailContent, BulkEmailEntry, BulkEmailStatus, Content, Destination, EmailContent,
+    Message, MessageTag, ReplacementEmailContent, ReplacementTemplate, Template,
+};
+use aws_sdk_sesv2::Client;
+use colored::Colorize;
+use inquire::Text;
+use std::env::var;
+
+use super::template_variants::group_entries_by_variant;
+
+// SES accepts at most 50 destinations in a single SendBulkEmail request
+const BULK_BATCH_SIZE: usize = 50;
+
+/// The configuration set and message tags attached to every email that is sent
+#[derive(Clone, Default)]
+pub struct SendOptions {
+    pub configuration_set: Option<String>,
+    pub tags: Vec<(String, String)>,
+}
+
+impl SendOptions {
+    /// Reads 'CONFIGURATION_SET' and 'MESSAGE_TAGS' (e.g. campaign=launch,team=growth) from the environment
+    pub fn from_env() -> Self {
+        dotenv::dotenv().ok();
+        let configuration_set = var("CONFIGURATION_SET")
+            .ok()
+            .filter(|name| !name.is_empty());
+        let tags = var("MESSAGE_TAGS")
+            .map(|tags| parse_tags(&tags).expect("Invalid 'MESSAGE_TAGS' value in the .env file\n"))
+            .unwrap_or_default();
+        Self {
+            configuration_set,
+            tags,
+        }
+    }
+
+    /// Values from flags or prompts replace the configuration set, while tags are merged by name
+    pub fn with_overrides(
+        mut self,
+        configuration_set: Option<String>,
+        tags: Vec<(String, String)>,
+    ) -> Self {
+        if configuration_set.is_some() {
+            self.configuration_set = configuration_set;
+        }
+        for (name, value) in tags {
+            self.tags.retain(|(existing, _)| *existing != name);
+            self.tags.push((name, value));
+        }
+        self
+    }
+
+    pub fn configuration_set_name(&self) -> Option<String> {
+        self.configuration_set.clone()
+    }
+
+    pub fn email_tags(&self) -> Option<Vec<MessageTag>> {
+        match self.tags.is_empty() {
+            true => None,
+            false => Some(
+                self.tags
+                    .iter()
+                    .map(|(name, value)| MessageTag::builder().name(name).value(value).build())
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Parses tags in the form 'key=value,key2=value2'
+pub fn parse_tags(input: &str) -> Result<Vec<(String, String)>, String> {
+    input
+        .split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(parse_tag)
+        .collect()
+}
+
+pub fn parse_tag(pair: &str) -> Result<(String, String), String> {
+    let (name, value) = pair
+        .split_once('=')
+        .ok_or(format!("The tag '{pair}' should be in the form key=value"))?;
+    let (name, value) = (name.trim(), value.trim());
+    // SES only allows ASCII letters, numbers, underscores and dashes in tag names and values
+    let is_valid = |part: &str| {
+        !part.is_empty()
+            && part.len() <= 256
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    };
+    match (is_valid(name), is_valid(value)) {
+        (true, true) => Ok((name.into(), value.into())),
+        _ => Err(format!(
+            "The tag '{pair}' may only contain letters, numbers, '_' and '-', up to 256 characters"
+        )),
+    }
+}
+
+/// Asks for the configuration set and tags, showing the values from the .env file as defaults
+pub fn prompt_send_options() -> SendOptions {
+    let defaults = SendOptions::from_env();
+    let default_configuration_set = format!(
+        "Default configuration set: {}\n",
+        defaults.configuration_set.clone().unwrap_or("none".into())
+    );
+    let default_tags = format!(
+        "Default message tags: {}\n",
+        defaults
+            .tags
+            .iter()
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect::<Vec<_>>()
+            .join(",")
+    );
+    let configuration_set = Text::new(
+        "Enter the configuration set name, or press Enter to use the default\n",
+    )
+    .with_placeholder(&default_configuration_set)
+    .with_formatter(&|str| format!(".....{str}.....\n"))
+    .with_help_message(
+        "Configuration sets route sending events to the event destinations attached to them",
+    )
+    .prompt_skippable()
+    .unwrap()
+    .unwrap_or_default();
+    let tags = Text::new("Enter message tags as key=value pairs separated by commas, or press Enter to use the defaults\n")
+        .with_placeholder(&default_tags)
+        .with_formatter(&|str| format!(".....{str}.....\n"))
+        .with_validator(|input: &str| match parse_tags(input) {
+            Ok(_) => Ok(inquire::validator::Validation::Valid),
+            Err(message) => Ok(inquire::validator::Validation::Invalid(message.into())),
+        })
+        .prompt_skippable()
+        .unwrap()
+        .unwrap_or_default();
+    let configuration_set = match configuration_set.is_empty() {
+        false => Some(configuration_set),
+        true => None,
+    };
+    defaults.with_overrides(configuration_set, parse_tags(&tags).unwrap())
+}
+
+/// Returns the addresses of a contact list that haven't unsubscribed from all topics
+pub async fn list_contact_emails(client: &Client, list_name: &str) -> Vec<String> {
+    let mut emails = Vec::new();
+    let mut next_token = None;
+    loop {
+        let output = client
+            .list_contacts()
+            .contact_list_name(list_name)
+            .set_next_token(next_token)
+            .send()
+            .await
+            .expect("Error while listing the contacts of the list\n");
+        output
+            .contacts()
+            .unwrap_or_default()
+            .iter()
+            .filter(|contact| !contact.unsubscribe_all())
+            .filter_map(|contact| contact.email_address())
+            .for_each(|email| emails.push(email.to_string()));
+        next_token = output.next_token().map(|token| token.to_string());
+        if next_token.is_none() {
+            break emails;
+        }
+    }
+}
+
+/// Sends the same simple email to every contact of the list, one request per recipient
+pub async fn send_bulk_simple(
+    config: &SdkConfig,
+    list_name: &str,
+    from_address: &str,
+    subject: &str,
+    body: &str,
+    options: &SendOptions,
+) {
+    let client = Client::new(config);
+    let emails = list_contact_emails(&client, list_name).await;
+    let content = EmailContent::builder()
+        .simple(
+            Message::builder()
+                .subject(Content::builder().data(subject).charset("UTF-8").build())
+                .body(
+                    Body::builder()
+                        .html(Content::builder().data(body).charset("UTF-8").build())
+                        .build(),
+                )
+                .build(),
+        )
+        .build();
+    for email in emails {
+        let result = client
+            .send_email()
+            .from_email_address(from_address)
+            .destination(Destination::builder().to_addresses(&email).build())
+            .content(content.clone())
+            .set_configuration_set_name(options.configuration_set_name())
+            .set_email_tags(options.email_tags())
+            .send()
+            .await;
+        match result {
+            Ok(_) => println!("The simple email is sent to: {}", email.green().bold()),
+            Err(error) => println!(
+                "{} {}: {error}",
+                "Error while sending to".red().bold(),
+                email.red().bold()
+            ),
+        }
+    }
+    println!();
+}
+
+/// Sends a templated email to every contact of the list, using each contact's attributes as its template data.
+/// When the template is a family such as 'welcome', each contact gets the variant for its locale
+pub async fn send_bulk_templated(
+    config: &SdkConfig,
+    list_name: &str,
+    from_address: &str,
+    template_name: &str,
+    options: &SendOptions,
+) {
+    let client = Client::new(config);
+    let emails = list_contact_emails(&client, list_name).await;
+    let mut entries = Vec::new();
+    for email in emails {
+        let template_data = contact_attributes(&client, list_name, &email).await;
+        entries.push((email, template_data));
+    }
+    // A template family name sends each contact the variant matching its locale attribute
+    for (variant, entries) in group_entries_by_variant(&client, template_name, entries).await {
+        send_templated_entries(&client, from_address, &variant, entries, options).await;
+    }
+}
+
+pub async fn contact_attributes(client: &Client, list_name: &str, email: &str) -> String {
+    client
+        .get_contact()
+        .contact_list_name(list_name)
+        .email_address(email)
+        .send()
+        .await
+        .expect("Error while getting the contact\n")
+        .attributes_data()
+        .filter(|data| !data.is_empty())
+        .unwrap_or("{}")
+        .to_string()
+}
+
+/// Sends one template to several recipients, each with its own template data, in batches of 50
+pub async fn send_templated_entries(
+    client: &Client,
+    from_address: &str,
+    template_name: &str,
+    entries: Vec<(String, String)>,
+    options: &SendOptions,
+) {
+    for batch in entries.chunks(BULK_BATCH_SIZE) {
+        let bulk_entries = batch
+            .iter()
+            .map(|(email, template_data)| {
+                BulkEmailEntry::builder()
+                    .destination(Destination::builder().to_addresses(email).build())
+                    .replacement_email_content(
+                        ReplacementEmailContent::builder()
+                            .replacement_template(
+                                ReplacementTemplate::builder()
+                                    .replacement_template_data(template_data)
+                                    .build(),
+                            )
+                            .build(),
+                    )
+                    .build()
+            })
+            .collect();
+        let output = client
+            .send_bulk_email()
+            .from_email_address(from_address)
+            .default_content(
+                BulkEmailContent::builder()
+                    .template(
+                        Template::builder()
+                            .template_name(template_name)
+                            .template_data("{}")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .set_bulk_email_entries(Some(bulk_entries))
+            .set_configuration_set_name(options.configuration_set_name())
+            .set_default_email_tags(options.email_tags())
+            .send()
+            .await
+            .expect("Error while sending the bulk templated emails\n");
+        let results = output.bulk_email_entry_results().unwrap_or_default();
+        for ((email, _), result) in batch.iter().zip(results) {
+            match result.status() {
+                Some(BulkEmailStatus::Success) => {
+                    println!("The template email is send to: {}", email.green().bold())
+                }
+                status => println!(
+                    "{} {}: {:?} {}",
+                    "Error while sending to".red().bold(),
+                    email.red().bold(),
+                    status,
+                    result.error().unwrap_or_default()
+                ),
+            }
+        }
+    }
+    println!();
+}
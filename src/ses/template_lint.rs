@@ -0,0 +1,580 @@
+use colored::Colorize;
+use inquire::Confirm;
+use regex::Regex;
+use std::path::Path;
+
+// Gmail clips messages whose HTML is larger than about 102KB
+const CLIPPING_LIMIT: usize = 102 * 1024;
+// Most email layouts are 600px wide, so wider images get scaled down or overflow
+const MAX_IMAGE_WIDTH: u32 = 600;
+const MAX_IMAGE_BYTES: u64 = 1024 * 1024;
+const VOID_ELEMENTS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+#[derive(PartialEq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+pub struct LintIssue {
+    pub severity: Severity,
+    pub line: usize,
+    pub message: String,
+}
+
+fn line_of(html: &str, offset: usize) -> usize {
+    html[..offset].matches('\n').count() + 1
+}
+
+fn issue(severity: Severity, html: &str, offset: usize, message: String) -> LintIssue {
+    LintIssue {
+        severity,
+        line: line_of(html, offset),
+        message,
+    }
+}
+
+/// Checks the template for problems that commonly break rendering in mail clients.
+/// Image sizes are only fetched for remote images when 'check_remote_images' is set
+pub async fn lint_template(
+    html: &str,
+    text: Option<&str>,
+    base_dir: Option<&Path>,
+    check_remote_images: bool,
+) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+    issues.extend(check_handlebars(html));
+    issues.extend(check_html_structure(html));
+    issues.extend(check_css(html));
+    issues.extend(check_images(html, base_dir, check_remote_images).await);
+    if text.map(|text| text.trim().is_empty()).unwrap_or(true) {
+        issues.push(LintIssue {
+            severity: Severity::Warning,
+            line: 0,
+            message: "There is no text part; recipients whose clients don't render HTML will see an empty email".into(),
+        });
+    }
+    if html.len() > CLIPPING_LIMIT {
+        issues.push(LintIssue {
+            severity: Severity::Warning,
+            line: 0,
+            message: format!(
+                "The HTML is {}KB; Gmail clips messages larger than 102KB",
+                html.len() / 1024
+            ),
+        });
+    }
+    issues.sort_by_key(|issue| issue.line);
+    issues
+}
+
+fn check_handlebars(html: &str) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+    let mut open_blocks: Vec<(String, usize)> = Vec::new();
+    let mut position = 0;
+    while let Some(start) = html[position..].find("{{").map(|index| index + position) {
+        let Some(end) = html[start..].find("}}").map(|index| index + start) else {
+            issues.push(issue(
+                Severity::Error,
+                html,
+                start,
+                "'{{' is never closed with '}}'".into(),
+            ));
+            break;
+        };
+        let expression = html[start + 2..end]
+            .trim_matches(|c: char| c == '{' || c == '~')
+            .trim();
+        position = end + 2;
+        if expression.starts_with('!') {
+            continue;
+        }
+        if let Some(block) = expression.strip_prefix('#') {
+            let name = block.split_whitespace().next().unwrap_or_default();
+            open_blocks.push((name.to_string(), start));
+        } else if let Some(block) = expression.strip_prefix('/') {
+            let name = block.trim();
+            match open_blocks.pop() {
+                Some((open, _)) if open == name => {}
+                Some((open, offset)) => {
+                    issues.push(issue(
+                        Severity::Error,
+                        html,
+                        start,
+                        format!(
+                            "'{{{{/{name}}}}}' closes '{{{{#{open}}}}}' opened on line {}",
+                            line_of(html, offset)
+                        ),
+                    ));
+                }
+                None => issues.push(issue(
+                    Severity::Error,
+                    html,
+                    start,
+                    format!("'{{{{/{name}}}}}' has no matching '{{{{#{name}}}}}'"),
+                )),
+            }
+        }
+    }
+    for (name, offset) in open_blocks {
+        issues.push(issue(
+            Severity::Error,
+            html,
+            offset,
+            format!("'{{{{#{name}}}}}' is never closed with '{{{{/{name}}}}}'"),
+        ));
+    }
+    issues
+}
+
+// Replaces every character but newlines with as many spaces as it has bytes, so the byte
+// offsets and line numbers of the text around it stay the same
+fn blank_out(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\n' => "\n".to_string(),
+            c => " ".repeat(c.len_utf8()),
+        })
+        .collect()
+}
+
+fn check_html_structure(html: &str) -> Vec<LintIssue> {
+    let comments = Regex::new(r"(?s)<!--.*?-->").unwrap();
+    // Blank out comments and raw text elements so their contents aren't parsed as tags
+    let html_without_comments = comments
+        .replace_all(html, |captures: &regex::Captures| blank_out(&captures[0]))
+        .into_owned();
+    let raw_text = Regex::new(r"(?is)(<(style|script)[^>]*>)(.*?)(</(style|script)>)").unwrap();
+    let cleaned = raw_text
+        .replace_all(&html_without_comments, |captures: &regex::Captures| {
+            format!(
+                "{}{}{}",
+                &captures[1],
+                blank_out(&captures[3]),
+                &captures[4]
+            )
+        })
+        .into_owned();
+
+    let tags = Regex::new(r"<(/?)([a-zA-Z][a-zA-Z0-9-]*)([^>]*?)(/?)>").unwrap();
+    let mut issues = Vec::new();
+    let mut stack: Vec<(String, usize)> = Vec::new();
+    for captures in tags.captures_iter(&cleaned) {
+        let offset = captures.get(0).unwrap().start();
+        let name = captures[2].to_lowercase();
+        let closing = !captures[1].is_empty();
+        let self_closing = !captures[4].is_empty();
+        if VOID_ELEMENTS.contains(&name.as_str()) || self_closing {
+            continue;
+        }
+        match closing {
+            false => stack.push((name, offset)),
+            true => match stack.iter().rposition(|(open, _)| *open == name) {
+                Some(index) => {
+                    for (unclosed, open_offset) in stack.drain(index..).skip(1) {
+                        issues.push(issue(
+                            Severity::Error,
+                            html,
+                            open_offset,
+                            format!("<{unclosed}> is not closed before </{name}>"),
+                        ));
+                    }
+                }
+                None => issues.push(issue(
+                    Severity::Error,
+                    html,
+                    offset,
+                    format!("</{name}> has no matching opening tag"),
+                )),
+            },
+        }
+    }
+    for (unclosed, offset) in stack {
+        issues.push(issue(
+            Severity::Error,
+            html,
+            offset,
+            format!("<{unclosed}> is never closed"),
+        ));
+    }
+    issues
+}
+
+fn check_css(html: &str) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+    let links = Regex::new(r#"(?is)<link\b[^>]*rel\s*=\s*["']?stylesheet[^>]*>"#).unwrap();
+    for found in links.find_iter(html) {
+        issues.push(issue(
+            Severity::Error,
+            html,
+            found.start(),
+            "External stylesheets are not loaded by most mail clients".into(),
+        ));
+    }
+    let imports = Regex::new(r"(?i)@import\b").unwrap();
+    for found in imports.find_iter(html) {
+        issues.push(issue(
+            Severity::Error,
+            html,
+            found.start(),
+            "'@import' of external CSS is not supported by most mail clients".into(),
+        ));
+    }
+    let styles = Regex::new(r"(?i)<style\b").unwrap();
+    for found in styles.find_iter(html) {
+        issues.push(issue(
+            Severity::Warning,
+            html,
+            found.start(),
+            "Many mail clients strip <style> blocks; inline the CSS before uploading".into(),
+        ));
+    }
+    issues
+}
+
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let pattern = format!(r#"(?i)\s{name}\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s>]+))"#);
+    let captures = Regex::new(&pattern).unwrap().captures(tag)?;
+    captures
+        .get(1)
+        .or(captures.get(2))
+        .or(captures.get(3))
+        .map(|value| value.as_str().to_string())
+}
+
+async fn check_images(
+    html: &str,
+    base_dir: Option<&Path>,
+    check_remote_images: bool,
+) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+    let images = Regex::new(r"(?is)<img\b[^>]*>").unwrap();
+    for found in images.find_iter(html) {
+        let tag = found.as_str();
+        let offset = found.start();
+        if attribute(tag, "alt").is_none() {
+            issues.push(issue(
+                Severity::Warning,
+                html,
+                offset,
+                "<img> has no alt text, which is shown when images are blocked".into(),
+            ));
+        }
+        if let Some(width) = attribute(tag, "width")
+            .and_then(|width| width.trim_end_matches("px").parse::<u32>().ok())
+        {
+            if width > MAX_IMAGE_WIDTH {
+                issues.push(issue(
+                    Severity::Warning,
+                    html,
+                    offset,
+                    format!("<img> is {width}px wide, more than the usual {MAX_IMAGE_WIDTH}px email width"),
+                ));
+            }
+        }
+        let Some(src) = attribute(tag, "src") else {
+            issues.push(issue(
+                Severity::Error,
+                html,
+                offset,
+                "<img> has no src".into(),
+            ));
+            continue;
+        };
+        let size = if src.starts_with("http://") || src.starts_with("https://") {
+            match check_remote_images {
+                true => remote_size(&src).await,
+                false => None,
+            }
+        } else if src.starts_with("data:") {
+            // Base64 takes four characters for every three bytes
+            Some(src.len() as u64 * 3 / 4)
+        } else if src.contains("{{") || src.starts_with("cid:") {
+            None
+        } else {
+            issues.push(issue(
+                Severity::Error,
+                html,
+                offset,
+                format!(
+                    "The image '{src}' is a local path; recipients can only load absolute URLs"
+                ),
+            ));
+            let path = base_dir
+                .map(|dir| dir.join(&src))
+                .unwrap_or(src.clone().into());
+            std::fs::metadata(path).ok().map(|metadata| metadata.len())
+        };
+        if let Some(size) = size.filter(|size| *size > MAX_IMAGE_BYTES) {
+            issues.push(issue(
+                Severity::Warning,
+                html,
+                offset,
+                format!(
+                    "The image '{src}' is {}KB; large images load slowly on mobile connections",
+                    size / 1024
+                ),
+            ));
+        }
+    }
+    issues
+}
+
+async fn remote_size(url: &str) -> Option<u64> {
+    reqwest::Client::new()
+        .head(url)
+        .send()
+        .await
+        .ok()?
+        .content_length()
+}
+
+/// Prints the issues and returns whether the template is free of errors
+pub fn print_issues(name: &str, issues: &[LintIssue]) -> bool {
+    for issue in issues {
+        let severity = match issue.severity {
+            Severity::Error => "error".red().bold(),
+            Severity::Warning => "warning".yellow().bold(),
+        };
+        match issue.line {
+            0 => println!("{severity}: {name}: {}", issue.message),
+            line => println!("{severity}: {name}:{line}: {}", issue.message),
+        }
+    }
+    let errors = issues
+        .iter()
+        .filter(|issue| issue.severity == Severity::Error)
+        .count();
+    let warnings = issues.len() - errors;
+    match issues.is_empty() {
+        true => println!("{}\n", format!("'{name}' has no issues").green().bold()),
+        false => println!("\n{errors} error(s) and {warnings} warning(s) in '{name}'\n"),
+    }
+    errors == 0
+}
+
+/// Lints the template before it is uploaded and offers to inline its CSS.
+/// Returns the HTML to upload, or None when the upload is abandoned because of errors
+pub async fn review_before_upload(name: &str, html: &str, text: Option<&str>) -> Option<String> {
+    let issues = lint_template(html, text, None, false).await;
+    if !print_issues(name, &issues) {
+        let proceed = Confirm::new("The template has errors, upload it anyway?\n")
+            .with_default(false)
+            .prompt()
+            .unwrap();
+        if !proceed {
+            return None;
+        }
+    }
+    let has_style_block = Regex::new(r"(?i)<style\b").unwrap().is_match(html);
+    let inline = has_style_block
+        && Confirm::new("Inline the CSS of the <style> blocks into style attributes?\n")
+            .with_default(true)
+            .with_help_message("Rules such as ':hover' and '@media' stay in a <style> block")
+            .prompt()
+            .unwrap();
+    match inline {
+        true => Some(inline_css(html)),
+        false => Some(html.to_string()),
+    }
+}
+
+struct CssRule {
+    selector: String,
+    declarations: Vec<(String, String)>,
+    specificity: (usize, usize, usize),
+    order: usize,
+}
+
+// Only simple selectors such as 'p', '.title', '#header' or 'a.cta-button' can be inlined.
+// Pseudo-classes, combinators and at-rules stay in the <style> block
+fn is_inlinable(selector: &str) -> bool {
+    let simple =
+        Regex::new(r"^(\*|[a-zA-Z][a-zA-Z0-9-]*)?([.#][a-zA-Z_-][a-zA-Z0-9_-]*)*$").unwrap();
+    !selector.is_empty() && simple.is_match(selector)
+}
+
+fn specificity(selector: &str) -> (usize, usize, usize) {
+    let ids = selector.matches('#').count();
+    let classes = selector.matches('.').count();
+    let tag = match selector.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        true => 1,
+        false => 0,
+    };
+    (ids, classes, tag)
+}
+
+fn selector_matches(selector: &str, tag: &str, id: Option<&str>, classes: &[&str]) -> bool {
+    let parts = Regex::new(r"[.#]?[^.#]+").unwrap();
+    let matches = parts.find_iter(selector).all(|part| {
+        let part = part.as_str();
+        if let Some(class) = part.strip_prefix('.') {
+            classes.contains(&class)
+        } else if let Some(expected_id) = part.strip_prefix('#') {
+            id == Some(expected_id)
+        } else {
+            part == "*" || part.eq_ignore_ascii_case(tag)
+        }
+    });
+    matches
+}
+
+fn parse_declarations(block: &str) -> Vec<(String, String)> {
+    block
+        .split(';')
+        .filter_map(|declaration| declaration.split_once(':'))
+        .map(|(property, value)| (property.trim().to_lowercase(), value.trim().to_string()))
+        .filter(|(property, value)| !property.is_empty() && !value.is_empty())
+        .collect()
+}
+
+// Splits the stylesheet into inlinable rules and the CSS that has to stay in a <style> block
+fn parse_css(css: &str, rules: &mut Vec<CssRule>, retained: &mut String) {
+    let comments = Regex::new(r"(?s)/\*.*?\*/").unwrap();
+    let css = comments.replace_all(css, "");
+    let mut rest = css.trim();
+    while let Some(open) = rest.find('{') {
+        let prelude = rest[..open].trim();
+        // Find the matching closing brace, at-rules like @media contain nested blocks
+        let mut depth = 0;
+        let mut close = None;
+        for (index, c) in rest[open..].char_indices() {
+            match c {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        close = Some(open + index);
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        let Some(close) = close else {
+            retained.push_str(rest);
+            break;
+        };
+        let body = &rest[open + 1..close];
+        if prelude.starts_with('@') {
+            retained.push_str(&format!("{prelude} {{{body}}}\n"));
+        } else {
+            let declarations = parse_declarations(body);
+            let mut kept = Vec::new();
+            for selector in prelude.split(',').map(str::trim) {
+                match is_inlinable(selector) {
+                    true => rules.push(CssRule {
+                        selector: selector.into(),
+                        declarations: declarations.clone(),
+                        specificity: specificity(selector),
+                        order: rules.len(),
+                    }),
+                    false => kept.push(selector),
+                }
+            }
+            if !kept.is_empty() {
+                retained.push_str(&format!("{} {{{body}}}\n", kept.join(", ")));
+            }
+        }
+        rest = rest[close + 1..].trim_start();
+    }
+}
+
+/// Moves the rules of the <style> blocks into style attributes. Existing inline styles win over the
+/// inlined ones, and rules that can't be inlined (e.g. ':hover' or '@media') are kept in a single <style> block
+pub fn inline_css(html: &str) -> String {
+    let style_blocks = Regex::new(r"(?is)<style[^>]*>(.*?)</style>").unwrap();
+    let mut rules = Vec::new();
+    let mut retained = String::new();
+    for captures in style_blocks.captures_iter(html) {
+        parse_css(&captures[1], &mut rules, &mut retained);
+    }
+    if rules.is_empty() {
+        return html.to_string();
+    }
+    rules.sort_by_key(|rule| (rule.specificity, rule.order));
+
+    let mut first_block = true;
+    let html = style_blocks.replace_all(html, |_: &regex::Captures| {
+        match std::mem::replace(&mut first_block, false) && !retained.trim().is_empty() {
+            true => format!("<style>\n{retained}</style>"),
+            false => String::new(),
+        }
+    });
+
+    let skipped = [
+        "html", "head", "meta", "title", "style", "link", "script", "base",
+    ];
+    let tags = Regex::new(r"<([a-zA-Z][a-zA-Z0-9-]*)(\s[^<>]*?)?(\s*/?)>").unwrap();
+    let style_attribute = Regex::new(r#"(?i)\sstyle\s*=\s*(?:"[^"]*"|'[^']*')"#).unwrap();
+    tags.replace_all(&html, |captures: &regex::Captures| {
+        let tag = &captures[1];
+        let attributes = captures.get(2).map(|m| m.as_str()).unwrap_or_default();
+        if skipped.contains(&tag.to_lowercase().as_str()) {
+            return captures[0].to_string();
+        }
+        let id = attribute(attributes, "id");
+        let class = attribute(attributes, "class").unwrap_or_default();
+        let classes: Vec<&str> = class.split_whitespace().collect();
+        let mut declarations: Vec<(String, String)> = Vec::new();
+        let mut apply = |new: &[(String, String)]| {
+            for (property, value) in new {
+                declarations.retain(|(existing, _)| existing != property);
+                declarations.push((property.clone(), value.clone()));
+            }
+        };
+        rules
+            .iter()
+            .filter(|rule| selector_matches(&rule.selector, tag, id.as_deref(), &classes))
+            .for_each(|rule| apply(&rule.declarations));
+        if let Some(inline) = attribute(attributes, "style") {
+            apply(&parse_declarations(&inline));
+        }
+        if declarations.is_empty() {
+            return captures[0].to_string();
+        }
+        let style = declarations
+            .iter()
+            .map(|(property, value)| format!("{property}: {value}"))
+            .collect::<Vec<_>>()
+            .join("; ")
+            .replace('"', "'");
+        let attributes = style_attribute.replace_all(attributes, "");
+        format!("<{tag}{attributes} style=\"{style};\"{}>", &captures[3])
+    })
+    .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blank_out_keeps_byte_offsets() {
+        let text = "café\n<b>ü</b>";
+        let blanked = blank_out(text);
+        assert_eq!(blanked.len(), text.len());
+        assert_eq!(blanked.find('\n'), text.find('\n'));
+    }
+
+    #[test]
+    fn non_ascii_comments_keep_line_numbers() {
+        let html = "<!-- café -->\n<p>ok</p>\n<div>\n<!-- naïve <span> -->\n";
+        let issues = check_html_structure(html);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].line, 3);
+        assert_eq!(issues[0].message, "<div> is never closed");
+    }
+
+    #[test]
+    fn non_ascii_styles_are_not_parsed_as_tags() {
+        let html = "<style>/* «<p>» */</style>\n<table></tr></table>";
+        let issues = check_html_structure(html);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].line, 2);
+        assert_eq!(issues[0].message, "</tr> has no matching opening tag");
+    }
+}
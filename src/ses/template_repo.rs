@@ -0,0 +1,314 @@
+use aws_apis::SesOps;
+use colored::Colorize;
+use inquire::Confirm;
+use similar::{ChangeTag, TextDiff};
+use std::env::var;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::template_lint::inline_css;
+
+// Each template lives in its own folder, using the same file names as the bundled assets
+const SUBJECT_FILE: &str = "subject.html";
+const HTML_FILE: &str = "template.html";
+const TEXT_FILE: &str = "text_body.txt";
+const BACKUP_DIR: &str = ".backups";
+
+/// The subject, HTML body and optional text body of an email template
+#[derive(PartialEq)]
+pub struct TemplateFiles {
+    pub subject: String,
+    pub html: String,
+    pub text: Option<String>,
+}
+
+impl TemplateFiles {
+    pub fn new(subject: &str, html: &str, text: Option<&str>) -> Self {
+        Self {
+            subject: subject.into(),
+            html: html.into(),
+            // SES returns an empty text part for templates created without one
+            text: text.filter(|text| !text.is_empty()).map(|text| text.into()),
+        }
+    }
+
+    pub fn read(dir: &Path) -> Option<Self> {
+        let subject = fs::read_to_string(dir.join(SUBJECT_FILE)).ok()?;
+        let html = fs::read_to_string(dir.join(HTML_FILE)).ok()?;
+        let text = fs::read_to_string(dir.join(TEXT_FILE)).ok();
+        Some(Self::new(&subject, &html, text.as_deref()))
+    }
+
+    pub fn write(&self, dir: &Path) {
+        fs::create_dir_all(dir).expect("Error while creating the template directory\n");
+        fs::write(dir.join(SUBJECT_FILE), &self.subject)
+            .expect("Error while writing the subject file\n");
+        fs::write(dir.join(HTML_FILE), &self.html).expect("Error while writing the HTML file\n");
+        match &self.text {
+            Some(text) => {
+                fs::write(dir.join(TEXT_FILE), text).expect("Error while writing the text file\n")
+            }
+            None => {
+                let _ = fs::remove_file(dir.join(TEXT_FILE));
+            }
+        }
+    }
+
+    pub async fn fetch(ses_ops: &SesOps, name: &str) -> Self {
+        let (subject, html, text) = ses_ops
+            .get_template_subject_html_and_text(name, false)
+            .await;
+        Self::new(&subject, &html, Some(&text))
+    }
+}
+
+/// The local template repository, './templates' unless 'TEMPLATE_DIR' is set
+pub fn template_dir() -> PathBuf {
+    dotenv::dotenv().ok();
+    var("TEMPLATE_DIR").unwrap_or("templates".into()).into()
+}
+
+pub fn local_template_names(root: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(root)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().join(HTML_FILE).exists())
+                .filter_map(|entry| entry.file_name().into_string().ok())
+                .filter(|name| name != BACKUP_DIR)
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+/// Prints a line diff of each part that changed and returns whether anything changed
+pub fn print_diff(name: &str, old: &TemplateFiles, new: &TemplateFiles) -> bool {
+    let empty = String::new();
+    let parts = [
+        ("Subject", &old.subject, &new.subject),
+        ("HTML body", &old.html, &new.html),
+        (
+            "Text body",
+            old.text.as_ref().unwrap_or(&empty),
+            new.text.as_ref().unwrap_or(&empty),
+        ),
+    ];
+    let mut changed = false;
+    for (part, old, new) in parts {
+        if old == new {
+            continue;
+        }
+        changed = true;
+        println!("{}", format!("--- {name} ({part})").yellow().bold());
+        let diff = TextDiff::from_lines(old.as_str(), new.as_str());
+        for hunk in diff.unified_diff().context_radius(3).iter_hunks() {
+            println!("{}", hunk.header().to_string().cyan());
+            for change in hunk.iter_changes() {
+                let line = change.value().trim_end_matches('\n');
+                match change.tag() {
+                    ChangeTag::Delete => println!("{}", format!("-{line}").red()),
+                    ChangeTag::Insert => println!("{}", format!("+{line}").green()),
+                    ChangeTag::Equal => println!(" {line}"),
+                }
+            }
+        }
+        println!();
+    }
+    if !changed {
+        println!("{}\n", format!("'{name}' has no changes").green().bold());
+    }
+    changed
+}
+
+/// Keeps a copy of the remote version under '.backups/<name>/<timestamp>' and returns the timestamp
+pub fn backup_template(root: &Path, name: &str, files: &TemplateFiles) -> String {
+    let timestamp = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    files.write(&root.join(BACKUP_DIR).join(name).join(&timestamp));
+    println!(
+        "The previous version of '{}' is saved as backup '{}'\n",
+        name.green().bold(),
+        timestamp.green().bold()
+    );
+    timestamp
+}
+
+/// Backup timestamps of a template, oldest first
+pub fn backups(root: &Path, name: &str) -> Vec<String> {
+    let mut timestamps: Vec<String> = fs::read_dir(root.join(BACKUP_DIR).join(name))
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| entry.file_name().into_string().ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    timestamps.sort();
+    timestamps
+}
+
+/// Names of the templates that have at least one backup, including templates since deleted from SES
+pub fn backed_up_template_names(root: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(root.join(BACKUP_DIR))
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| entry.file_name().into_string().ok())
+                .filter(|name| !backups(root, name).is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+/// Writes the remote templates into the local repository, all of them when no names are given.
+/// Local copies that differ are only overwritten after showing the diff
+pub async fn pull(ses_ops: &SesOps, root: &Path, names: Vec<String>, assume_yes: bool) {
+    let names = match names.is_empty() {
+        false => names,
+        true => ses_ops.list_email_templates().await,
+    };
+    for name in names {
+        let remote = TemplateFiles::fetch(ses_ops, &name).await;
+        let dir = root.join(&name);
+        if let Some(local) = TemplateFiles::read(&dir) {
+            if local == remote {
+                println!("'{}' is already up to date\n", name.green().bold());
+                continue;
+            }
+            print_diff(&name, &local, &remote);
+            let confirm = assume_yes
+                || Confirm::new(&format!(
+                    "Overwrite the local copy of '{name}' with the remote version?\n"
+                ))
+                .with_default(false)
+                .prompt()
+                .unwrap();
+            if !confirm {
+                continue;
+            }
+        }
+        remote.write(&dir);
+        println!(
+            "The template '{}' is written to {}\n",
+            name.green().bold(),
+            dir.display().to_string().green().bold()
+        );
+    }
+}
+
+/// Creates or updates the remote templates from the local repository, all of them when no names are given.
+/// Every update shows a diff first and keeps a backup of the version it replaces.
+/// With 'inline' set, the CSS of the <style> blocks is moved into style attributes before uploading
+pub async fn push(
+    ses_ops: &SesOps,
+    root: &Path,
+    names: Vec<String>,
+    assume_yes: bool,
+    inline: bool,
+) {
+    let names = match names.is_empty() {
+        false => names,
+        true => local_template_names(root),
+    };
+    let remote_names = ses_ops.list_email_templates().await;
+    for name in names {
+        let Some(local) = TemplateFiles::read(&root.join(&name)) else {
+            println!(
+                "{}\n",
+                format!("No '{HTML_FILE}' and '{SUBJECT_FILE}' found for '{name}'")
+                    .red()
+                    .bold()
+            );
+            continue;
+        };
+        let local = match inline {
+            true => TemplateFiles {
+                html: inline_css(&local.html),
+                ..local
+            },
+            false => local,
+        };
+        match remote_names.contains(&name) {
+            false => {
+                ses_ops
+                    .create_email_template(&name, &local.subject, &local.html, local.text.clone())
+                    .await;
+            }
+            true => {
+                let remote = TemplateFiles::fetch(ses_ops, &name).await;
+                update_with_backup(ses_ops, root, &name, &remote, &local, assume_yes).await;
+            }
+        }
+    }
+}
+
+/// Replaces a remote template after showing the diff and saving the remote version as a backup
+pub async fn update_with_backup(
+    ses_ops: &SesOps,
+    root: &Path,
+    name: &str,
+    remote: &TemplateFiles,
+    local: &TemplateFiles,
+    assume_yes: bool,
+) {
+    if !print_diff(name, remote, local) {
+        return;
+    }
+    let confirm = assume_yes
+        || Confirm::new(&format!(
+            "Update the remote template '{name}' with these changes?\n"
+        ))
+        .with_default(false)
+        .prompt()
+        .unwrap();
+    if confirm {
+        backup_template(root, name, remote);
+        ses_ops
+            .update_template(name, &local.subject, &local.html, local.text.clone())
+            .await;
+    }
+}
+
+/// Restores a backup, the most recent one when no timestamp is given.
+/// A template that was deleted from SES is created again from the backup
+pub async fn rollback(
+    ses_ops: &SesOps,
+    root: &Path,
+    name: &str,
+    timestamp: Option<String>,
+    assume_yes: bool,
+) {
+    let available = backups(root, name);
+    let Some(timestamp) = timestamp.or(available.last().cloned()) else {
+        println!(
+            "{}\n",
+            format!("There are no backups for '{name}'").red().bold()
+        );
+        return;
+    };
+    let Some(backup) = TemplateFiles::read(&root.join(BACKUP_DIR).join(name).join(&timestamp))
+    else {
+        println!(
+            "{}\n",
+            format!("The backup '{timestamp}' doesn't exist for '{name}'")
+                .red()
+                .bold()
+        );
+        return;
+    };
+    if !ses_ops
+        .list_email_templates()
+        .await
+        .contains(&name.to_string())
+    {
+        ses_ops
+            .create_email_template(name, &backup.subject, &backup.html, backup.text.clone())
+            .await;
+        return;
+    }
+    let remote = TemplateFiles::fetch(ses_ops, name).await;
+    update_with_backup(ses_ops, root, name, &remote, &backup, assume_yes).await;
+}
@@ -0,0 +1,329 @@
+use aws_config::SdkConfig;
+use aws_sdk_sesv2::Client;
+use colored::Colorize;
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet};
+use std::env::var;
+
+// SES template names may only contain letters, numbers, '_' and '-', so the variant 'welcome.de'
+// is stored as 'welcome__de'. The double underscore marks variants explicitly, so templates such
+// as 'welcome_new' or 'invoice_eu' aren't mistaken for locales. Both spellings are accepted
+// wherever a variant name is expected
+const VARIANT_SEPARATOR: &str = "__";
+
+/// Which template data field holds the recipient's locale and which locale to fall back to,
+/// read from 'LOCALE_ATTRIBUTE' (default 'locale') and 'DEFAULT_LOCALE' (default 'en')
+pub struct LocaleSettings {
+    pub attribute: String,
+    pub default_locale: String,
+}
+
+impl LocaleSettings {
+    pub fn from_env() -> Self {
+        dotenv::dotenv().ok();
+        Self {
+            attribute: var("LOCALE_ATTRIBUTE").unwrap_or("locale".into()),
+            default_locale: normalize_locale(&var("DEFAULT_LOCALE").unwrap_or("en".into()))
+                .unwrap_or("en".into()),
+        }
+    }
+}
+
+/// Normalizes 'pt_br', 'PT-BR' or 'pt-BR' to 'pt-BR'; returns None for anything that isn't a locale
+pub fn normalize_locale(locale: &str) -> Option<String> {
+    let locale_pattern = Regex::new(r"^(?i)([a-z]{2,3})(?:[-_]([a-z]{2}|[0-9]{3}))?$").unwrap();
+    let captures = locale_pattern.captures(locale.trim())?;
+    let language = captures[1].to_lowercase();
+    match captures.get(2) {
+        Some(region) => Some(format!("{language}-{}", region.as_str().to_uppercase())),
+        None => Some(language),
+    }
+}
+
+/// The SES name of a variant, e.g. ('welcome', 'de') becomes 'welcome__de'
+pub fn variant_name(family: &str, locale: &str) -> String {
+    format!("{family}{VARIANT_SEPARATOR}{locale}")
+}
+
+/// Splits 'welcome__de' or 'welcome.de' into the family and the locale. A dot can't be part of
+/// an SES name, so it only marks a variant in what the user typed
+pub fn split_variant_name(name: &str) -> Option<(String, String)> {
+    let (family, locale) = name
+        .rsplit_once(VARIANT_SEPARATOR)
+        .or_else(|| name.rsplit_once('.'))?;
+    match family.is_empty() {
+        true => None,
+        false => Some((family.to_string(), normalize_locale(locale)?)),
+    }
+}
+
+pub async fn list_template_names(client: &Client) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut next_token = None;
+    loop {
+        let output = client
+            .list_email_templates()
+            .set_next_token(next_token)
+            .send()
+            .await
+            .expect("Error while listing the email templates\n");
+        output
+            .templates_metadata()
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|template| template.template_name())
+            .for_each(|name| names.push(name.to_string()));
+        next_token = output.next_token().map(|token| token.to_string());
+        if next_token.is_none() {
+            break names;
+        }
+    }
+}
+
+/// The existing template a name refers to, accepting 'welcome.de' for the variant 'welcome__de'
+fn existing_template(template_names: &[String], name: &str) -> Option<String> {
+    let candidate = match split_variant_name(name) {
+        Some((family, locale)) if !template_names.iter().any(|existing| existing == name) => {
+            variant_name(&family, &locale)
+        }
+        _ => name.to_string(),
+    };
+    template_names.contains(&candidate).then_some(candidate)
+}
+
+/// Groups the template names into families, each mapping a locale to the SES template name
+pub fn group_families(names: &[String]) -> BTreeMap<String, BTreeMap<String, String>> {
+    let mut families: BTreeMap<String, BTreeMap<String, String>> = BTreeMap::new();
+    for name in names {
+        if let Some((family, locale)) = split_variant_name(name) {
+            families
+                .entry(family)
+                .or_default()
+                .insert(locale, name.clone());
+        }
+    }
+    families
+}
+
+/// The locale variants of one template family, e.g. {"de": "welcome__de", "en": "welcome__en"}
+pub struct TemplateFamily {
+    pub name: String,
+    pub variants: BTreeMap<String, String>,
+}
+
+impl TemplateFamily {
+    /// Accepts the family name ('welcome') as well as one of its variants ('welcome.de' or 'welcome__de')
+    pub fn find(template_names: &[String], name: &str) -> Option<Self> {
+        let families = group_families(template_names);
+        let family = match families.contains_key(name) {
+            true => name.to_string(),
+            false => split_variant_name(name)?.0,
+        };
+        let variants = families.get(&family)?.clone();
+        Some(Self {
+            name: family,
+            variants,
+        })
+    }
+
+    /// Tries the exact locale, then its language ('de-AT' falls back to 'de', and 'pt' to 'pt-BR'),
+    /// then the default locale the same way and finally the first variant
+    pub fn resolve(&self, locale: Option<&str>, default_locale: &str) -> &str {
+        let variant = locale
+            .and_then(normalize_locale)
+            .and_then(|locale| self.closest(&locale))
+            .or(self.closest(default_locale))
+            .or(self.variants.values().next())
+            .unwrap();
+        variant
+    }
+
+    fn closest(&self, locale: &str) -> Option<&String> {
+        let language = locale.split('-').next().unwrap();
+        self.variants
+            .get(locale)
+            .or(self.variants.get(language))
+            .or(self
+                .variants
+                .iter()
+                .find(|(variant, _)| variant.split('-').next() == Some(language))
+                .map(|(_, name)| name))
+    }
+}
+
+/// Reads the locale field from JSON template data such as '{"Name": "Jo", "locale": "de"}'
+pub fn locale_from_data(template_data: &str, attribute: &str) -> Option<String> {
+    let data: serde_json::Value = serde_json::from_str(template_data).ok()?;
+    data.get(attribute)?
+        .as_str()
+        .map(|locale| locale.to_string())
+}
+
+/// Resolves the template to send for one recipient. A plain template name is returned unchanged,
+/// while a family name picks the variant that matches the locale in the template data
+pub async fn resolve_template(
+    config: &SdkConfig,
+    template_name: &str,
+    template_data: &str,
+) -> String {
+    let client = Client::new(config);
+    let template_names = list_template_names(&client).await;
+    if let Some(existing) = existing_template(&template_names, template_name) {
+        return existing;
+    }
+    let settings = LocaleSettings::from_env();
+    match TemplateFamily::find(&template_names, template_name) {
+        Some(family) => {
+            let locale = locale_from_data(template_data, &settings.attribute);
+            let variant = family.resolve(locale.as_deref(), &settings.default_locale);
+            println!(
+                "Using the variant '{}' of the template family '{}'\n",
+                variant.green().bold(),
+                family.name.green().bold()
+            );
+            variant.to_string()
+        }
+        None => template_name.to_string(),
+    }
+}
+
+/// Reads the template data file and resolves the template for it, returning (template, data)
+pub async fn read_and_resolve_template(
+    config: &SdkConfig,
+    template_name: &str,
+    template_path: &str,
+) -> (String, String) {
+    let template_data = std::fs::read_to_string(template_path)
+        .expect("Error opening the template data file path you specified\n");
+    let template = resolve_template(config, template_name, &template_data).await;
+    (template, template_data)
+}
+
+/// Splits (email, template data) entries into one group per template variant
+pub async fn group_entries_by_variant(
+    client: &Client,
+    template_name: &str,
+    entries: Vec<(String, String)>,
+) -> BTreeMap<String, Vec<(String, String)>> {
+    let template_names = list_template_names(client).await;
+    let existing = existing_template(&template_names, template_name);
+    let family = match existing {
+        Some(_) => None,
+        None => TemplateFamily::find(&template_names, template_name),
+    };
+    let settings = LocaleSettings::from_env();
+    let mut groups: BTreeMap<String, Vec<(String, String)>> = BTreeMap::new();
+    for (email, template_data) in entries {
+        let variant = match &family {
+            Some(family) => {
+                let locale = locale_from_data(&template_data, &settings.attribute);
+                family
+                    .resolve(locale.as_deref(), &settings.default_locale)
+                    .to_string()
+            }
+            None => existing.clone().unwrap_or(template_name.to_string()),
+        };
+        groups
+            .entry(variant)
+            .or_default()
+            .push((email, template_data));
+    }
+    groups
+}
+
+pub async fn print_families(config: &SdkConfig) {
+    let template_names = list_template_names(&Client::new(config)).await;
+    let families = group_families(&template_names);
+    if families.is_empty() {
+        println!(
+            "{}\n",
+            "No template families found; name the variants like 'welcome__en' and 'welcome__de'"
+                .yellow()
+                .bold()
+        );
+    }
+    for (family, variants) in families {
+        let locales = variants.keys().cloned().collect::<Vec<_>>().join(", ");
+        println!(
+            "Family: {}\nLocales: {}\n",
+            family.green().bold(),
+            locales.green().bold()
+        );
+    }
+}
+
+fn template_variables(content: &str) -> BTreeSet<String> {
+    let variable = Regex::new(r"\{\{\{?\s*([A-Za-z_][\w.]*)\s*\}?\}\}").unwrap();
+    variable
+        .captures_iter(content)
+        .map(|captures| captures[1].to_string())
+        .filter(|name| name != "else" && name != "this")
+        .collect()
+}
+
+/// Compares the template variables of every variant with those of the default locale, so a
+/// translation that drops or renames a placeholder is noticed before sending. Returns whether they all match
+pub async fn check_family(config: &SdkConfig, family_name: &str) -> bool {
+    let client = Client::new(config);
+    let template_names = list_template_names(&client).await;
+    let Some(family) = TemplateFamily::find(&template_names, family_name) else {
+        println!(
+            "{}\n",
+            format!("No variants found for the template family '{family_name}'")
+                .red()
+                .bold()
+        );
+        return false;
+    };
+    let mut variables = BTreeMap::new();
+    for (locale, template_name) in &family.variants {
+        let output = client
+            .get_email_template()
+            .template_name(template_name)
+            .send()
+            .await
+            .expect("Error while getting the email template\n");
+        let content = output.template_content();
+        let combined = [
+            content.and_then(|content| content.subject()),
+            content.and_then(|content| content.html()),
+            content.and_then(|content| content.text()),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join("\n");
+        variables.insert(locale.clone(), template_variables(&combined));
+    }
+    let settings = LocaleSettings::from_env();
+    let reference_locale = family
+        .resolve(Some(&settings.default_locale), &settings.default_locale)
+        .to_string();
+    let reference_locale = split_variant_name(&reference_locale).unwrap().1;
+    let reference = &variables[&reference_locale];
+    println!(
+        "Comparing the variants of '{}' with '{}'\n",
+        family.name.green().bold(),
+        reference_locale.green().bold()
+    );
+    let mut consistent = true;
+    for (locale, found) in &variables {
+        let missing = reference.difference(found).cloned().collect::<Vec<_>>();
+        let extra = found.difference(reference).cloned().collect::<Vec<_>>();
+        match (missing.is_empty(), extra.is_empty()) {
+            (true, true) => println!("{}: {}", locale.green().bold(), "ok".green()),
+            _ => {
+                consistent = false;
+                println!("{}:", locale.yellow().bold());
+                if !missing.is_empty() {
+                    println!("    Missing variables: {}", missing.join(", ").red());
+                }
+                if !extra.is_empty() {
+                    println!("    Extra variables: {}", extra.join(", ").yellow());
+                }
+            }
+        }
+    }
+    println!();
+    consistent
+}